@@ -30,7 +30,7 @@ use hal::{
     clocks::{init_clocks_and_plls, Clock},
     gpio::{bank0, FunctionI2C, Pin},
     i2c::{peripheral::I2CPeripheralEventIterator, I2C},
-    pac,
+    pac::{self, interrupt},
     sio::Sio,
     watchdog::Watchdog,
 };
@@ -69,6 +69,12 @@ async fn prph_demo(
     peripheral::run_demo(&mut i2c).await.expect("Demo failed")
 }
 
+// The controller's async operations sleep until the I2C interrupt wakes them
+#[interrupt]
+fn I2C0_IRQ() {
+    hal::i2c::controller::on_i2c0_interrupt();
+}
+
 #[cortex_m_rt::entry]
 fn main() -> ! {
     let mut pac = pac::Peripherals::take().unwrap();
@@ -100,6 +106,9 @@ fn main() -> ! {
         &mut pac.RESETS,
         clocks.system_clock.freq(),
     );
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::I2C0_IRQ);
+    }
 
     let i2c1 = I2C::new_peripheral_event_iterator(
         pac.I2C1,
@@ -107,7 +116,8 @@ fn main() -> ! {
         pins.gpio3.into_mode(),
         &mut pac.RESETS,
         ADDRESS,
-    );
+    )
+    .unwrap();
 
     static EXECUTOR: Forever<Executor> = Forever::new();
     let executor = EXECUTOR.put(Executor::new());
@@ -0,0 +1,129 @@
+//! # ADC DMA Example
+//!
+//! This application demonstrates how to capture ADC samples continuously with
+//! the DMA. The ADC runs free at 100 ksps, writing 8 bit samples from GPIO26
+//! into its FIFO, and a pair of DMA channels take turns to copy them into two
+//! buffers, so that no sample is lost while the CPU checks the last buffer
+//! filled.
+//!
+//! If the captured signal swings by more than a quarter of the ADC range, the
+//! LED on pin 25 is turned on.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Some traits we need
+use embedded_hal::digital::v2::OutputPin;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Our interface to the DMA channels
+use hal::dma::DMAExt;
+
+//// The linker will place this boot block at the start of our program image. We
+//// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Number of samples in each buffer
+const SAMPLES: usize = 1024;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then checks each block of
+/// samples captured in an infinite loop.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::watchdog::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock, and a 48 MHz ADC
+    // clock
+    let _clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::sio::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+    let mut adc_pin_0 = hal::adc::AdcPin::new(pins.gpio26);
+
+    // Enable the ADC, sampling at 48 MHz / 480 = 100 ksps
+    let mut adc = hal::adc::Adc::new(pac.ADC, &mut pac.RESETS);
+    adc.set_clock_divider(479, 0);
+
+    let dma = pac.DMA.split(&mut pac.RESETS);
+
+    // The buffers must live for as long as the transfers may run
+    let first = cortex_m::singleton!(: [u8; SAMPLES] = [0; SAMPLES]).unwrap();
+    let second = cortex_m::singleton!(: [u8; SAMPLES] = [0; SAMPLES]).unwrap();
+
+    // Start sampling into the first buffer, and queue the second one, which
+    // the DMA moves on to as soon as the first one is full
+    let transfer = adc.dma_read_double(&mut adc_pin_0, (dma.ch0, dma.ch1), first);
+    let mut transfer = transfer.write_next(second);
+
+    loop {
+        // Wait for a buffer to fill up, while sampling goes on into the other
+        let (buffer, next) = transfer.wait().unwrap();
+
+        let min = buffer.iter().min().copied().unwrap_or(0);
+        let max = buffer.iter().max().copied().unwrap_or(0);
+        if max - min > 64 {
+            led_pin.set_high().unwrap();
+        } else {
+            led_pin.set_low().unwrap();
+        }
+
+        // Queue the buffer again, once we're done with it
+        transfer = next.write_next(buffer);
+    }
+}
+
+// End of file
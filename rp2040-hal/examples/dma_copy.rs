@@ -0,0 +1,118 @@
+//! # DMA Copy Example
+//!
+//! This application benchmarks copying a 16kB buffer with the DMA against
+//! copying it with the CPU, as a framebuffer blit would.
+//!
+//! The durations, in microseconds, are kept in `dma_us` and `cpu_us` for a
+//! debugger to read. The LED on pin 25 is turned on if the DMA copy was the
+//! fastest.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Some traits we need
+use embedded_hal::digital::v2::OutputPin;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// The DMA channels and the copy and fill helpers
+use hal::dma::{self, DMAExt};
+
+//// The linker will place this boot block at the start of our program image. We
+//// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Number of words copied
+const WORDS: usize = 4096;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then times both copies.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::watchdog::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let _clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::sio::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    let timer = hal::timer::Timer::new(pac.TIMER, &mut pac.RESETS);
+    let dma = pac.DMA.split(&mut pac.RESETS);
+
+    // The buffers must live for as long as the transfers may run
+    let from = cortex_m::singleton!(: [u32; WORDS] = [0; WORDS]).unwrap();
+    let to = cortex_m::singleton!(: [u32; WORDS] = [0; WORDS]).unwrap();
+
+    // Fill the source with a pattern
+    let (ch0, _, from) = dma::fill(dma.ch0, &0x5555_aaaa, from).wait().unwrap();
+
+    // Copy it with the DMA
+    let start = timer.get_counter();
+    let (_ch0, from, to) = dma::copy(ch0, from, to).wait().unwrap();
+    let dma_us = timer.get_counter() - start;
+
+    // And with the CPU
+    let start = timer.get_counter();
+    to.copy_from_slice(from);
+    let cpu_us = timer.get_counter() - start;
+
+    if dma_us < cpu_us {
+        led_pin.set_high().unwrap();
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+// End of file
@@ -0,0 +1,125 @@
+//! # Multicore FIFO Example
+//!
+//! This application runs code on both cores of the RP2040, which talk to each
+//! other through the inter-core FIFO.
+//!
+//! Core 0 sends numbers to core 1, which squares them and sends the results
+//! back. The LED on pin 25 is turned on once all results are checked.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Some traits we need
+use embedded_hal::digital::v2::OutputPin;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Launching code on core 1
+use hal::multicore::{Multicore, Stack};
+
+//// The linker will place this boot block at the start of our program image. We
+//// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// The stack of core 1, which must live for as long as core 1 runs
+static mut CORE1_STACK: Stack<4096> = Stack::new();
+
+/// The code running on core 1: square the numbers received from core 0.
+fn core1_task() -> ! {
+    // Core 0 owns the peripherals, but each core has its own end of the FIFO
+    let pac = unsafe { pac::Peripherals::steal() };
+    let mut sio = hal::sio::Sio::new(pac.SIO);
+
+    loop {
+        let input = sio.fifo.read_blocking();
+        sio.fifo.write_blocking(input * input);
+    }
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then starts core 1 and
+/// talks to it.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::watchdog::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let _clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins and the FIFO
+    let mut sio = hal::sio::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // Start core 1
+    let mut mc = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio.fifo);
+    let cores = mc.cores();
+    let core1 = &mut cores[1];
+    core1
+        .spawn(unsafe { &mut CORE1_STACK.mem }, core1_task)
+        .unwrap();
+
+    // Hand it some work, and check the results
+    let mut ok = true;
+    for input in 0..100 {
+        sio.fifo.write_blocking(input);
+        ok &= sio.fifo.read_blocking() == input * input;
+    }
+
+    if ok {
+        led_pin.set_high().unwrap();
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+// End of file
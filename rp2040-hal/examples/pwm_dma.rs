@@ -0,0 +1,127 @@
+//! # PWM DMA Example
+//!
+//! If you have an LED connected to pin 25, it will fade the LED using the PWM
+//! peripheral, with the duty cycle values streamed in by the DMA.
+//!
+//! The PWM slice raises a DMA request every time its counter wraps, so the DMA
+//! writes exactly one new compare value per PWM period without the CPU being
+//! involved.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Our interface to the DMA channels
+use hal::dma::{single_buffer, DMAExt};
+
+//// The linker will place this boot block at the start of our program image. We
+//// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Number of steps in one fade up and down
+const STEPS: usize = 256;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then restarts the DMA
+/// transfer each time it has finished in an infinite loop.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::watchdog::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock
+    let _clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::sio::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Build a gamma corrected fade up and down. Channel B's compare value
+    // lives in the upper half of the CC register. The buffer must live for as
+    // long as the transfers may run.
+    let mut duty = cortex_m::singleton!(: [u32; STEPS] = [0; STEPS]).unwrap();
+    for (i, value) in duty.iter_mut().enumerate() {
+        let level = (if i < STEPS / 2 { i } else { STEPS - 1 - i }) as u32;
+        *value = (level * level) << 16;
+    }
+
+    // Init PWMs
+    let pwm_slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+
+    // Configure PWM4 to wrap at 16384, at about 30 Hz
+    let mut pwm = pwm_slices.pwm4;
+    pwm.default_config();
+    pwm.set_top(16384);
+    pwm.set_div_int(255);
+
+    // Output channel B on PWM4 to GPIO 25
+    pwm.channel_b.output_to(pins.gpio25);
+
+    pwm.enable();
+
+    let dma = pac.DMA.split(&mut pac.RESETS);
+    let mut ch0 = dma.ch0;
+
+    loop {
+        // Stream one fade into the compare register of the slice, paced by
+        // its DREQ, which it raises as its counter wraps
+        let transfer = single_buffer::Config::new(ch0, duty, pwm).start();
+
+        // Wait for the DMA to finish, and get everything back for the next fade
+        let (ch, from, to) = transfer.wait().unwrap();
+        ch0 = ch;
+        duty = from;
+        pwm = to;
+    }
+}
+
+// End of file
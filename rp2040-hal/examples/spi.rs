@@ -80,11 +80,12 @@ fn main() -> ! {
         &mut pac.RESETS,
     );
 
-    // These are implicitly used by the spi driver if they are in the correct mode
-    let _spi_sclk = pins.gpio6.into_mode::<hal::gpio::FunctionSpi>();
-    let _spi_mosi = pins.gpio7.into_mode::<hal::gpio::FunctionSpi>();
-    let _spi_miso = pins.gpio4.into_mode::<hal::gpio::FunctionSpi>();
-    let spi = hal::spi::Spi::<_, _, 8>::new(pac.SPI0);
+    // The driver takes ownership of the pins, which are checked at compile
+    // time to be usable with SPI0
+    let spi_sclk = pins.gpio6.into_mode::<hal::gpio::FunctionSpi>();
+    let spi_mosi = pins.gpio7.into_mode::<hal::gpio::FunctionSpi>();
+    let spi_miso = pins.gpio4.into_mode::<hal::gpio::FunctionSpi>();
+    let spi = hal::spi::Spi::<_, _, 8, _>::new_with_pins(pac.SPI0, (spi_mosi, spi_miso, spi_sclk));
 
     // Exchange the uninitialised SPI driver for an initialised one
     let mut spi = spi.init(
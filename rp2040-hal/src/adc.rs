@@ -7,19 +7,19 @@
 //! Capture ADC reading from a pin
 //! ```no_run
 //! use embedded_hal::adc::OneShot;
-//! use rp2040_hal::{adc::Adc, gpio::Pins, pac, sio::Sio};
+//! use rp2040_hal::{adc::{Adc, AdcPin}, gpio::Pins, pac, sio::Sio};
 //! let mut peripherals = pac::Peripherals::take().unwrap();
 //! let sio = Sio::new(peripherals.SIO);
 //! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
 //! // Enable adc
 //! let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
 //! // Configure one of the pins as an ADC input
-//! let mut adc_pin_0 = pins.gpio26.into_floating_input();
+//! let mut adc_pin_0 = AdcPin::new(pins.gpio26);
 //! // Read the ADC counts from the ADC channel
 //! let pin_adc_counts: u16 = adc.read(&mut adc_pin_0).unwrap();
 //! ```
 //!
-//! Capture ADC reading from temperature sensor. Note that this needs conversion to be a real-world temperature.
+//! Capture ADC reading from temperature sensor. Note that the raw reading needs conversion to be a real-world temperature.
 //! ```no_run
 //! use embedded_hal::adc::OneShot;
 //! use rp2040_hal::{adc::Adc, gpio::Pins, pac, sio::Sio};
@@ -32,25 +32,109 @@
 //! let mut temperature_sensor = adc.enable_temp_sensor();
 //! // Read the ADC counts from the ADC channel
 //! let temperature_adc_counts: u16 = adc.read(&mut temperature_sensor).unwrap();
+//! // Or let the HAL do the conversion, assuming a 3.3 V reference
+//! let temperature = adc.read_temperature_celsius(&mut temperature_sensor, 3300);
+//! ```
+//!
+//! Sample continuously into the FIFO, alternating between two pins
+//! ```no_run
+//! use rp2040_hal::{adc::Adc, gpio::Pins, pac, sio::Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+//! let mut adc_pin_0 = pins.gpio26.into_floating_disabled();
+//! // 48 MHz / 4800 = 10 ksps, shared between the two channels
+//! adc.set_clock_divider(4799, 0);
+//! adc.set_round_robin(0b0011);
+//! adc.enable_fifo(false, false);
+//! adc.start_free_running(&mut adc_pin_0);
+//! loop {
+//!     if let Some(sample) = adc.read_fifo() {
+//!         // ...
+//!     }
+//! }
+//! ```
+//!
+//! Capture a block of samples with the DMA
+//! ```no_run
+//! use cortex_m::singleton;
+//! use rp2040_hal::{adc::Adc, dma::DMAExt, gpio::Pins, pac, sio::Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//! let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+//! let mut adc_pin_0 = pins.gpio26.into_floating_disabled();
+//! let buffer = singleton!(: [u16; 1024] = [0; 1024]).unwrap();
+//! let transfer = adc.dma_read(&mut adc_pin_0, dma.ch0, buffer);
+//! let (ch0, adc_dma, buffer) = transfer.wait().unwrap();
+//! let adc = adc_dma.stop();
 //! ```
 //!
 //! See [examples/adc.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/adc.rs) and
 //! [pico_explorer_showcase.rs](https://github.com/rp-rs/rp-hal/tree/main/boards/pico_explorer/examples/pico_explorer_showcase.rs) for more complete examples
 
+#[cfg(feature = "embassy-traits")]
+use core::{
+    cell::RefCell,
+    task::{Poll, Waker},
+};
+
+#[cfg(feature = "embassy-traits")]
+use cortex_m::interrupt::{self, Mutex};
 use hal::adc::{Channel, OneShot};
 use pac::{ADC, RESETS};
 
+use core::marker::PhantomData;
+use embedded_dma::WriteBuffer;
+
 use crate::{
+    dma::{double_buffer, single_buffer, ReadTarget, SingleChannel, Word},
     gpio::Pin,
     gpio::{
         bank0::{Gpio26, Gpio27, Gpio28, Gpio29},
-        FloatingInput,
+        FloatingDisabled, PinId, PinMode, ValidPinMode,
     },
     resets::SubsystemReset,
 };
 
 const TEMPERATURE_SENSOR_CHANNEL: u8 = 4;
 
+/// Bit 15 of a FIFO entry flags a failed conversion, if enabled
+const FIFO_ERR: u16 = 1 << 15;
+
+/// The FIFO bit of the INTE register
+#[cfg(feature = "embassy-traits")]
+const INTE_FIFO: u32 = 1 << 0;
+
+/// The task waiting for a sample
+#[cfg(feature = "embassy-traits")]
+static WAKER: Mutex<RefCell<Option<Waker>>> = Mutex::new(RefCell::new(None));
+
+/// Wake the task waiting for a sample. Call this from the `ADC_IRQ_FIFO` handler.
+///
+/// [`Adc::wait_for_sample`] sleeps until the FIFO interrupt fires, so the
+/// interrupt must be unmasked in the NVIC and its handler must call this.
+#[cfg(feature = "embassy-traits")]
+pub fn on_adc_irq() {
+    // Safety: only the FIFO bit of INTE is cleared, through its atomic alias
+    let adc = unsafe { &*ADC::ptr() };
+    // Stop the interrupt from firing again until the task is waiting again
+    unsafe { crate::atomic_register_access::write_bitmask_clear(adc.inte.as_ptr(), INTE_FIFO) };
+    if let Some(waker) = interrupt::free(|cs| WAKER.borrow(cs).borrow_mut().take()) {
+        waker.wake();
+    }
+}
+
+/// Errors that can occur when reading the ADC
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AdcError {
+    /// The conversion failed, and the result is not valid
+    ConversionFailed,
+}
+
 /// Adc
 pub struct Adc {
     device: ADC,
@@ -92,11 +176,471 @@ impl Adc {
     pub fn disable_temp_sensor(&mut self, _: TempSense) {
         self.device.cs.modify(|_, w| w.ts_en().clear_bit());
     }
+
+    /// Read the die temperature in degrees Celsius
+    ///
+    /// `vref_mv` is the ADC reference voltage in millivolts, which is the
+    /// 3.3 V supply on most boards. Measuring it rather than assuming the
+    /// nominal value gives noticeably better results, as the sensor only
+    /// changes by about 1.7 mV per degree.
+    pub fn read_temperature_celsius(&mut self, _sensor: &mut TempSense, vref_mv: u16) -> f32 {
+        let raw = self.convert(TEMPERATURE_SENSOR_CHANNEL);
+        let volts = raw as f32 * vref_mv as f32 / 1000.0 / 4096.0;
+        // See section 4.9.5 of the datasheet
+        27.0 - (volts - 0.706) / 0.001721
+    }
+
+    /// Convert a raw 12 bit reading to millivolts
+    ///
+    /// `vref_mv` is the ADC reference voltage in millivolts, i.e. the voltage
+    /// on the ADC_AVDD pin, which is the 3.3 V supply on most boards.
+    pub fn to_millivolts(&self, raw: u16, vref_mv: u16) -> u16 {
+        ((raw as u32 * vref_mv as u32 + 2048) / 4096) as u16
+    }
+
+    /// Estimate the ADC reference voltage in millivolts from a pin held at a
+    /// known voltage
+    ///
+    /// `known_mv` is the voltage applied to `pin`, e.g. from a precision
+    /// reference. It should be as close to the top of the range as possible
+    /// for the best accuracy. The result can be passed to
+    /// [`to_millivolts`](Self::to_millivolts) for other channels. Returns
+    /// `None` if the pin reads as zero.
+    pub fn estimate_vref_mv<PIN: Channel<Adc, ID = u8>>(
+        &mut self,
+        pin: &mut PIN,
+        known_mv: u16,
+    ) -> Option<u16> {
+        let raw = self.read_oversampled(pin, 2) as u32;
+        if raw == 0 {
+            return None;
+        }
+        // `raw` has 14 bits of resolution
+        let vref = (known_mv as u32 * 16384 + raw / 2) / raw;
+        Some(vref.min(u16::MAX as u32) as u16)
+    }
+
+    /// Set the clock divider, which sets the sample rate in free-running mode
+    ///
+    /// A conversion takes 96 cycles of the 48 MHz ADC clock, so a total period
+    /// of less than 96 cycles (`int` less than 95) runs back to back
+    /// conversions at 500 ksps. Otherwise a new conversion starts every
+    /// `1 + int + frac / 256` cycles.
+    pub fn set_clock_divider(&mut self, int: u16, frac: u8) {
+        self.device
+            .div
+            .write(|w| unsafe { w.int().bits(int).frac().bits(frac) });
+    }
+
+    /// Set the channels to sample in turn in free-running mode
+    ///
+    /// Bit `n` of `mask` selects channel `n`. A mask of 0 disables round
+    /// robin sampling, so only the channel passed to
+    /// [`start_free_running`](Self::start_free_running) is sampled.
+    pub fn set_round_robin(&mut self, mask: u8) {
+        self.device
+            .cs
+            .modify(|_, w| unsafe { w.rrobin().bits(mask & 0x1f) });
+    }
+
+    /// Start converting continuously, beginning with channel `PIN`
+    pub fn start_free_running<PIN: Channel<Adc, ID = u8>>(&mut self, _pin: &mut PIN) {
+        self.device
+            .cs
+            .modify(|_, w| unsafe { w.ainsel().bits(PIN::channel()).start_many().set_bit() });
+    }
+
+    /// Stop converting continuously
+    ///
+    /// A conversion in progress is completed, so wait for the ADC to become
+    /// ready before starting a one-shot conversion.
+    pub fn stop_free_running(&mut self) {
+        self.device.cs.modify(|_, w| w.start_many().clear_bit());
+    }
+
+    /// Is the ADC ready to start a new conversion?
+    pub fn is_ready(&self) -> bool {
+        self.device.cs.read().ready().bit_is_set()
+    }
+
+    /// Write results to the FIFO
+    ///
+    /// If `shift` is set, results are right shifted to 8 bits, which suits
+    /// byte-sized DMA transfers. If `err` is set, bit 15 of each FIFO entry
+    /// flags a conversion error.
+    pub fn enable_fifo(&mut self, shift: bool, err: bool) {
+        self.device
+            .fcs
+            .modify(|_, w| w.en().set_bit().shift().bit(shift).err().bit(err));
+    }
+
+    /// Stop writing results to the FIFO
+    pub fn disable_fifo(&mut self) {
+        self.device.fcs.modify(|_, w| w.en().clear_bit());
+    }
+
+    /// Set the FIFO level at or above which the FIFO interrupt is asserted
+    pub fn set_fifo_threshold(&mut self, threshold: u8) {
+        self.device
+            .fcs
+            .modify(|_, w| unsafe { w.thresh().bits(threshold & 0xf) });
+    }
+
+    /// Enable the ADC_IRQ_FIFO interrupt
+    ///
+    /// The interrupt is asserted while the FIFO level is at or above the
+    /// [threshold](Self::set_fifo_threshold), so the handler must read enough
+    /// samples to bring the level back below it.
+    pub fn enable_fifo_interrupt(&mut self) {
+        self.device.inte.modify(|_, w| w.fifo().set_bit());
+    }
+
+    /// Disable the ADC_IRQ_FIFO interrupt
+    pub fn disable_fifo_interrupt(&mut self) {
+        self.device.inte.modify(|_, w| w.fifo().clear_bit());
+    }
+
+    /// Is the ADC_IRQ_FIFO interrupt asserted?
+    pub fn is_fifo_interrupt_pending(&self) -> bool {
+        self.device.ints.read().fifo().bit_is_set()
+    }
+
+    /// Number of results waiting in the FIFO
+    pub fn fifo_len(&self) -> u8 {
+        self.device.fcs.read().level().bits()
+    }
+
+    /// Read the oldest result from the FIFO, if there is one
+    ///
+    /// The value includes the error flag in bit 15 if it was enabled in
+    /// [`enable_fifo`](Self::enable_fifo).
+    pub fn read_fifo(&mut self) -> Option<u16> {
+        if self.device.fcs.read().empty().bit_is_set() {
+            None
+        } else {
+            Some(self.device.fifo.read().bits() as u16)
+        }
+    }
+
+    /// Did the FIFO overflow and lose results since this was last called?
+    ///
+    /// Clears the overflow flag.
+    pub fn fifo_overflowed(&mut self) -> bool {
+        let over = self.device.fcs.read().over().bit_is_set();
+        if over {
+            self.device.fcs.modify(|_, w| w.over().set_bit());
+        }
+        over
+    }
+
+    /// Raise the ADC DREQ while the FIFO contains data
+    ///
+    /// This lets a DMA channel paced by [`dreq`](Self::dreq) copy samples
+    /// out of [`fifo_address`](Self::fifo_address) as they arrive. The FIFO
+    /// must be enabled too.
+    pub fn enable_dma(&mut self) {
+        self.device.fcs.modify(|_, w| w.dreq_en().set_bit());
+    }
+
+    /// Stop raising the ADC DREQ
+    pub fn disable_dma(&mut self) {
+        self.device.fcs.modify(|_, w| w.dreq_en().clear_bit());
+    }
+
+    /// The DREQ raised by the ADC while its FIFO contains data
+    pub fn dreq(&self) -> u8 {
+        crate::dma::DREQ_ADC
+    }
+
+    /// Address of the FIFO register, to be used as the read address of a DMA
+    /// channel
+    ///
+    /// With [`enable_fifo`](Self::enable_fifo)'s `shift` set, each sample is
+    /// 8 bits and can be transferred as a byte.
+    pub fn fifo_address(&self) -> u32 {
+        self.device.fifo.as_ptr() as u32
+    }
+
+    /// Start sampling freely into the FIFO, from channel `PIN` or the
+    /// [round robin](Self::set_round_robin) channels, and fill `buffer` with
+    /// the samples through the DMA channel `ch`
+    ///
+    /// `u8` samples are shifted to 8 bits by the FIFO. The sample rate is set
+    /// by the [clock divider](Self::set_clock_divider). Once the transfer is
+    /// done, [`AdcDma::stop`] gives the ADC back.
+    pub fn dma_read<CH, W, B, PIN>(
+        self,
+        pin: &mut PIN,
+        ch: CH,
+        buffer: B,
+    ) -> single_buffer::Transfer<CH, AdcDma<W>, B>
+    where
+        CH: SingleChannel,
+        W: AdcSample,
+        B: WriteBuffer<Word = W>,
+        PIN: Channel<Adc, ID = u8>,
+    {
+        single_buffer::Config::new(ch, self.start_dma(pin), buffer).start()
+    }
+
+    /// As [`dma_read`](Self::dma_read), but taking turns on a pair of DMA
+    /// channels, so that sampling goes on into the next buffer queued with
+    /// [`write_next`](double_buffer::Transfer::write_next) while `buffer` is
+    /// processed
+    #[allow(clippy::type_complexity)]
+    pub fn dma_read_double<CH1, CH2, W, B, PIN>(
+        self,
+        pin: &mut PIN,
+        ch: (CH1, CH2),
+        buffer: B,
+    ) -> double_buffer::Transfer<CH1, CH2, AdcDma<W>, B, ()>
+    where
+        CH1: SingleChannel,
+        CH2: SingleChannel,
+        W: AdcSample,
+        B: WriteBuffer<Word = W>,
+        PIN: Channel<Adc, ID = u8>,
+    {
+        double_buffer::Config::new(ch, self.start_dma(pin), buffer).start()
+    }
+
+    fn start_dma<W: AdcSample, PIN: Channel<Adc, ID = u8>>(mut self, pin: &mut PIN) -> AdcDma<W> {
+        self.enable_fifo(W::SHIFT, false);
+        self.clear_fifo();
+        self.enable_dma();
+        self.start_free_running(pin);
+        AdcDma {
+            adc: self,
+            _word: PhantomData,
+        }
+    }
+
+    /// Wait for a result to arrive in the FIFO, without blocking the executor
+    ///
+    /// The ADC must be [free-running](Self::start_free_running) with the FIFO
+    /// enabled. The future sleeps until ADC_IRQ_FIFO fires, whose handler
+    /// must call [`on_adc_irq`]: the interrupt is asserted once the FIFO
+    /// reaches its [threshold](Self::set_fifo_threshold), which should be 1 to
+    /// wake on each sample.
+    #[cfg(feature = "embassy-traits")]
+    pub async fn wait_for_sample(&mut self) -> u16 {
+        futures::future::poll_fn(|cx| {
+            if let Some(value) = self.read_fifo() {
+                return Poll::Ready(value);
+            }
+
+            interrupt::free(|cs| WAKER.borrow(cs).replace(Some(cx.waker().clone())));
+            // The interrupt is level triggered, so it fires right away if a
+            // sample arrived in the meantime
+            unsafe {
+                crate::atomic_register_access::write_bitmask_set(
+                    self.device.inte.as_ptr(),
+                    INTE_FIFO,
+                )
+            };
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Discard all results in the FIFO
+    pub fn clear_fifo(&mut self) {
+        while self.read_fifo().is_some() {}
+    }
+
+    /// Average `samples` back to back conversions of channel `PIN`
+    ///
+    /// The conversions run in free-running mode at the full 500 ksps. The
+    /// clock divider, round robin and FIFO settings are restored afterwards,
+    /// but any results already in the FIFO are discarded.
+    pub fn read_averaged<PIN: Channel<Adc, ID = u8>>(
+        &mut self,
+        _pin: &mut PIN,
+        samples: u8,
+    ) -> u16 {
+        let samples = samples.max(1) as u32;
+        let sum = self.capture_sum(PIN::channel(), samples);
+        ((sum + samples / 2) / samples) as u16
+    }
+
+    /// Oversample channel `PIN` and decimate, to gain `extra_bits` of
+    /// resolution
+    ///
+    /// This takes `4 ^ extra_bits` conversions, and returns a
+    /// `12 + extra_bits` bit result. `extra_bits` is limited to 4, giving a
+    /// 16 bit result from 256 conversions. Oversampling only gains
+    /// resolution if there is some noise on the input, which is normally the
+    /// case. See [`read_averaged`](Self::read_averaged) for how the
+    /// conversions are made.
+    pub fn read_oversampled<PIN: Channel<Adc, ID = u8>>(
+        &mut self,
+        _pin: &mut PIN,
+        extra_bits: u8,
+    ) -> u16 {
+        let extra_bits = extra_bits.min(4);
+        let sum = self.capture_sum(PIN::channel(), 1 << (2 * extra_bits));
+        (sum >> extra_bits) as u16
+    }
+
+    /// Sum `samples` conversions of channel `chan`, made in free-running mode
+    fn capture_sum(&mut self, chan: u8, samples: u32) -> u32 {
+        // FCS.OVER and FCS.UNDER are write-1-to-clear, so mask them out when
+        // restoring the FIFO settings
+        const FCS_FLAGS: u32 = 0b11 << 10;
+        // Likewise CS.ERR_STICKY, which would clear the error reported by
+        // `has_error`, along with the READY and ERR status bits and
+        // START_ONCE, which would start a conversion
+        const CS_FLAGS: u32 = 0b111 << 8 | 1 << 2;
+        let div = self.device.div.read().bits();
+        let fcs = self.device.fcs.read().bits() & !FCS_FLAGS;
+        let cs = self.device.cs.read().bits() & !CS_FLAGS;
+
+        if chan == TEMPERATURE_SENSOR_CHANNEL {
+            self.device.cs.modify(|_, w| w.ts_en().set_bit())
+        }
+        while !self.is_ready() {}
+        self.device.div.write(|w| unsafe { w.bits(0) });
+        self.device.fcs.write(|w| w.en().set_bit());
+        self.clear_fifo();
+        self.device.cs.modify(|_, w| unsafe {
+            w.ainsel()
+                .bits(chan)
+                .rrobin()
+                .bits(0)
+                .start_many()
+                .set_bit()
+        });
+
+        let mut sum = 0;
+        let mut count = 0;
+        while count < samples {
+            if let Some(value) = self.read_fifo() {
+                sum += value as u32;
+                count += 1;
+            }
+        }
+
+        self.stop_free_running();
+        while !self.is_ready() {}
+        self.clear_fifo();
+        self.device.div.write(|w| unsafe { w.bits(div) });
+        self.device.fcs.write(|w| unsafe { w.bits(fcs) });
+        self.device.cs.write(|w| unsafe { w.bits(cs) });
+        sum
+    }
+
+    /// Read a single conversion of channel `PIN`, reporting failed conversions
+    pub fn read_with_status<PIN: Channel<Adc, ID = u8>>(
+        &mut self,
+        _pin: &mut PIN,
+    ) -> Result<u16, AdcError> {
+        let value = self.convert(PIN::channel());
+        if self.device.cs.read().err().bit_is_set() {
+            Err(AdcError::ConversionFailed)
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// Read the oldest result from the FIFO, if there is one, reporting
+    /// failed conversions
+    ///
+    /// The FIFO must have been enabled with `err` set in
+    /// [`enable_fifo`](Self::enable_fifo), otherwise no failure is reported.
+    pub fn read_fifo_with_status(&mut self) -> Option<Result<u16, AdcError>> {
+        self.read_fifo().map(|value| {
+            if value & FIFO_ERR != 0 {
+                Err(AdcError::ConversionFailed)
+            } else {
+                Ok(value)
+            }
+        })
+    }
+
+    /// Has any conversion failed since the flag was last cleared?
+    pub fn has_error(&self) -> bool {
+        self.device.cs.read().err_sticky().bit_is_set()
+    }
+
+    /// Clear the flag reported by [`has_error`](Self::has_error)
+    pub fn clear_error(&mut self) {
+        self.device.cs.modify(|_, w| w.err_sticky().set_bit());
+    }
+
+    /// Perform a single conversion of channel `chan`, blocking until it is done
+    fn convert(&mut self, chan: u8) -> u16 {
+        if chan == TEMPERATURE_SENSOR_CHANNEL {
+            self.device.cs.modify(|_, w| w.ts_en().set_bit())
+        }
+
+        while !self.device.cs.read().ready().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+
+        self.device
+            .cs
+            .modify(|_, w| unsafe { w.ainsel().bits(chan).start_once().set_bit() });
+
+        while !self.device.cs.read().ready().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+
+        self.device.result.read().result().bits()
+    }
+}
+
+/// A pin set up for use as an ADC input
+///
+/// Creating an [`AdcPin`] puts the pin in the [`FloatingDisabled`] mode,
+/// which turns off the digital input buffer and the pulls, as recommended by
+/// the datasheet for accurate readings.
+pub struct AdcPin<I: PinId> {
+    pin: Pin<I, FloatingDisabled>,
+}
+
+impl<I: PinId> AdcPin<I>
+where
+    Pin<I, FloatingDisabled>: Channel<Adc, ID = u8>,
+{
+    /// Set up `pin` as an ADC input
+    pub fn new<M: PinMode + ValidPinMode<I>>(pin: Pin<I, M>) -> Self {
+        Self {
+            pin: pin.into_floating_disabled(),
+        }
+    }
+
+    /// Release the pin, still in the [`FloatingDisabled`] mode
+    pub fn release(self) -> Pin<I, FloatingDisabled> {
+        self.pin
+    }
+}
+
+impl<I: PinId> Channel<Adc> for AdcPin<I>
+where
+    Pin<I, FloatingDisabled>: Channel<Adc, ID = u8>,
+{
+    type ID = u8; // ADC channels are identified numerically
+
+    fn channel() -> u8 {
+        Pin::<I, FloatingDisabled>::channel()
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<I: PinId> eh1_0_alpha::adc::nb::Channel<Adc> for AdcPin<I>
+where
+    Pin<I, FloatingDisabled>: eh1_0_alpha::adc::nb::Channel<Adc, ID = u8>,
+{
+    type ID = u8; // ADC channels are identified numerically
+
+    fn channel(&self) -> u8 {
+        self.pin.channel()
+    }
 }
 
 macro_rules! channel {
     ($pin:ident, $channel:expr) => {
-        impl Channel<Adc> for Pin<$pin, FloatingInput> {
+        impl Channel<Adc> for Pin<$pin, FloatingDisabled> {
             type ID = u8; // ADC channels are identified numerically
 
             fn channel() -> u8 {
@@ -105,7 +649,7 @@ macro_rules! channel {
         }
 
         #[cfg(feature = "eh1_0_alpha")]
-        impl eh1_0_alpha::adc::nb::Channel<Adc> for Pin<$pin, FloatingInput> {
+        impl eh1_0_alpha::adc::nb::Channel<Adc> for Pin<$pin, FloatingDisabled> {
             type ID = u8; // ADC channels are identified numerically
 
             fn channel(&self) -> u8 {
@@ -152,23 +696,7 @@ where
     fn read(&mut self, _pin: &mut PIN) -> nb::Result<WORD, Self::Error> {
         let chan = PIN::channel();
 
-        if chan == 4 {
-            self.device.cs.modify(|_, w| w.ts_en().set_bit())
-        }
-
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
-        }
-
-        self.device
-            .cs
-            .modify(|_, w| unsafe { w.ainsel().bits(chan).start_once().set_bit() });
-
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
-        }
-
-        Ok(self.device.result.read().result().bits().into())
+        Ok(self.convert(chan).into())
     }
 }
 
@@ -183,22 +711,76 @@ where
     fn read(&mut self, pin: &mut PIN) -> nb::Result<WORD, Self::Error> {
         let chan = PIN::channel(pin);
 
-        if chan == 4 {
-            self.device.cs.modify(|_, w| w.ts_en().set_bit())
-        }
+        Ok(self.convert(chan).into())
+    }
+}
 
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
-        }
+/// Samples are read from the FIFO as they arrive, once [`enable_dma`](Adc::enable_dma) is called.
+// Safety: The FIFO register stays valid for as long as the ADC is held by the transfer.
+unsafe impl ReadTarget for Adc {
+    type ReceivedWord = u16;
 
-        self.device
-            .cs
-            .modify(|_, w| unsafe { w.ainsel().bits(chan).start_once().set_bit() });
+    fn rx_treq(&self) -> Option<u8> {
+        Some(self.dreq())
+    }
 
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
-        }
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.fifo_address(), u32::MAX)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
+}
+
+/// Size of the samples moved by the DMA from the ADC FIFO: `u16`, or `u8`
+/// for samples shifted to 8 bits
+pub trait AdcSample: Word {
+    /// Whether the FIFO shifts the samples to 8 bits
+    const SHIFT: bool;
+}
+
+impl AdcSample for u8 {
+    const SHIFT: bool = true;
+}
+
+impl AdcSample for u16 {
+    const SHIFT: bool = false;
+}
+
+/// The ADC sampling freely into its FIFO, as the source of the DMA transfers
+/// started by [`Adc::dma_read`] and [`Adc::dma_read_double`]
+pub struct AdcDma<W: AdcSample> {
+    adc: Adc,
+    _word: PhantomData<W>,
+}
+
+impl<W: AdcSample> AdcDma<W> {
+    /// Stop sampling, and give the ADC back, with its FIFO disabled and
+    /// empty.
+    pub fn stop(mut self) -> Adc {
+        self.adc.stop_free_running();
+        while !self.adc.is_ready() {}
+        self.adc.disable_dma();
+        self.adc.clear_fifo();
+        self.adc.disable_fifo();
+        self.adc
+    }
+}
+
+// Safety: The FIFO register stays valid for as long as the ADC is held by the transfer.
+unsafe impl<W: AdcSample> ReadTarget for AdcDma<W> {
+    type ReceivedWord = W;
+
+    fn rx_treq(&self) -> Option<u8> {
+        Some(self.adc.dreq())
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.adc.fifo_address(), u32::MAX)
+    }
 
-        Ok(self.device.result.read().result().bits().into())
+    fn rx_increment(&self) -> bool {
+        false
     }
 }
@@ -1,3 +1,9 @@
+//! Implementation of `critical-section` for both cores
+//!
+//! A critical section disables the interrupts of the core entering it, and claims spinlock 31,
+//! so that the other core waits for it to end before entering its own. This makes
+//! `critical_section::Mutex` and the crates built on it safe to share between the cores.
+
 use core::sync::atomic::{AtomicU8, Ordering};
 
 struct RpSpinlockCs;
@@ -0,0 +1,208 @@
+//! Full-duplex DMA transfers through a peripheral, on a pair of channels
+//!
+//! On a full-duplex bus such as SPI, each word sent clocks one word in. The first channel feeds
+//! the peripheral from a source, and the second one drains the peripheral into a destination,
+//! both paced by the DREQs of the peripheral. Use [`Repeat`](super::Repeat) as the source to only
+//! receive, and [`Discard`](super::Discard) as the destination to only send.
+//!
+//! ## Usage
+//! ```no_run
+//! use cortex_m::singleton;
+//! use embedded_hal::spi::MODE_0;
+//! use embedded_time::rate::*;
+//! use rp2040_hal::{dma::{bidirectional, DMAExt}, gpio::{FunctionSpi, Pins}, pac, sio::Sio, spi::Spi};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//!
+//! let sck = pins.gpio2.into_mode::<FunctionSpi>();
+//! let tx = pins.gpio3.into_mode::<FunctionSpi>();
+//! let rx = pins.gpio4.into_mode::<FunctionSpi>();
+//! let spi = Spi::<_, _, 8, _>::new_with_pins(peripherals.SPI0, (tx, rx, sck)).init(&mut peripherals.RESETS, 125_000_000u32.Hz(), 16_000_000u32.Hz(), &MODE_0);
+//!
+//! let from = singleton!(: [u8; 16] = [0x42; 16]).unwrap();
+//! let to = singleton!(: [u8; 16] = [0; 16]).unwrap();
+//! let transfer = bidirectional::Config::new((dma.ch0, dma.ch1), from, spi, to).start();
+//! let ((ch0, ch1), from, spi, to) = transfer.wait().unwrap();
+//! ```
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use super::{
+    configure, Ctrl, Pace, Priority, ReadTarget, SingleChannel, TransferError, WriteTarget,
+};
+
+/// Configuration for a full-duplex DMA transfer
+pub struct Config<CH1, CH2, FROM, BIDI, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    BIDI: ReadTarget + WriteTarget,
+    TO: WriteTarget,
+{
+    ch: (CH1, CH2),
+    from: FROM,
+    bidi: BIDI,
+    to: TO,
+    priority: Priority,
+}
+
+impl<CH1, CH2, FROM, BIDI, TO, WORD> Config<CH1, CH2, FROM, BIDI, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    BIDI: ReadTarget<ReceivedWord = WORD> + WriteTarget<TransmittedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Create a new configuration for a transfer from `from` to the peripheral `bidi`, with the
+    /// first channel, and from `bidi` to `to`, with the second one
+    ///
+    /// The transfer moves as many words as the shortest of `from` and `to` holds, in each
+    /// direction.
+    pub fn new(ch: (CH1, CH2), from: FROM, bidi: BIDI, to: TO) -> Self {
+        Config {
+            ch,
+            from,
+            bidi,
+            to,
+            priority: Priority::Normal,
+        }
+    }
+
+    /// Set the priority of both channels in the DMA arbiter.
+    pub fn priority(&mut self, priority: Priority) {
+        self.priority = priority;
+    }
+
+    /// Start the DMA transfer
+    pub fn start(mut self) -> Transfer<CH1, CH2, FROM, BIDI, TO> {
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        let high_priority = self.priority == Priority::High;
+        configure(
+            &self.ch.0,
+            &self.from,
+            &mut self.bidi,
+            Pace::PreferSink,
+            Ctrl {
+                high_priority,
+                ..Ctrl::new::<CH1, WORD>(&self.ch.0)
+            },
+            false,
+        );
+        configure(
+            &self.ch.1,
+            &self.bidi,
+            &mut self.to,
+            Pace::PreferSource,
+            Ctrl {
+                high_priority,
+                ..Ctrl::new::<CH2, WORD>(&self.ch.1)
+            },
+            false,
+        );
+
+        // The peripheral's end is endless, so both directions move the length of the shorter
+        // buffer
+        let count = u32::min(self.from.rx_address_count().1, self.to.tx_address_count().1);
+        self.ch
+            .0
+            .ch()
+            .ch_trans_count
+            .write(|w| unsafe { w.bits(count) });
+        self.ch
+            .1
+            .ch()
+            .ch_trans_count
+            .write(|w| unsafe { w.bits(count) });
+
+        // Safety: MULTI_CHAN_TRIGGER only triggers the channels whose bits are set, which are
+        // owned by the transfer.
+        let dma = unsafe { &*pac::DMA::ptr() };
+        dma.multi_chan_trigger
+            .write(|w| unsafe { w.bits(1 << self.ch.0.id() | 1 << self.ch.1.id()) });
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            bidi: self.bidi,
+            to: self.to,
+        }
+    }
+}
+
+/// Instance of a full-duplex DMA transfer
+pub struct Transfer<CH1, CH2, FROM, BIDI, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    BIDI: ReadTarget + WriteTarget,
+    TO: WriteTarget,
+{
+    ch: (CH1, CH2),
+    from: FROM,
+    bidi: BIDI,
+    to: TO,
+}
+
+impl<CH1, CH2, FROM, BIDI, TO, WORD> Transfer<CH1, CH2, FROM, BIDI, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    BIDI: ReadTarget<ReceivedWord = WORD> + WriteTarget<TransmittedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Check if both directions have completed, or were halted by a bus error.
+    pub fn is_done(&self) -> bool {
+        !self.ch.0.ch().ch_ctrl_trig.read().busy().bit_is_set()
+            && !self.ch.1.ch().ch_ctrl_trig.read().busy().bit_is_set()
+    }
+
+    /// Block until the transfer is complete, returning the channels and the targets.
+    ///
+    /// If a bus error halted the transfer, they are returned in the error.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(
+        mut self,
+    ) -> Result<((CH1, CH2), FROM, BIDI, TO), TransferError<((CH1, CH2), FROM, BIDI, TO)>> {
+        while !self.is_done() {
+            // Once the sending channel is halted by a bus error, the words the receiving one
+            // waits for never come
+            let ctrl = self.ch.0.ch().ch_ctrl_trig.read();
+            if ctrl.busy().bit_is_clear()
+                && (ctrl.read_error().bit_is_set() || ctrl.write_error().bit_is_set())
+            {
+                self.ch.1.abort();
+            }
+        }
+
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        let error = self.ch.0.take_error().or(self.ch.1.take_error());
+        let parts = (self.ch, self.from, self.bidi, self.to);
+        match error {
+            None => Ok(parts),
+            Some(error) => Err(TransferError::new(error, parts)),
+        }
+    }
+
+    /// Abort the transfer, returning the channels and the targets once both channels have
+    /// stopped.
+    pub fn abort(mut self) -> ((CH1, CH2), FROM, BIDI, TO) {
+        self.ch.0.abort();
+        self.ch.1.abort();
+        self.ch.0.take_error();
+        self.ch.1.take_error();
+
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        (self.ch, self.from, self.bidi, self.to)
+    }
+}
@@ -0,0 +1,295 @@
+//! Gather DMA transfers driven by lists of control blocks
+//!
+//! A control channel walks a [`ControlBlockList`] in RAM, and reprograms a data channel with
+//! each of its blocks in turn, through the alias registers of the data channel. The data channel
+//! chains back to the control channel once it has moved a fragment, so that fragments scattered
+//! in memory reach the same destination, a peripheral FIFO or a buffer, without the CPU stitching
+//! them together.
+//!
+//! ## Usage
+//! ```no_run
+//! use cortex_m::singleton;
+//! use rp2040_hal::{dma::{control_block::{Config, ControlBlockList}, DMAExt}, pac};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//!
+//! static HEADER: [u8; 4] = [0xaa, 0x55, 0, 3];
+//! static PAYLOAD: [u8; 3] = [1, 2, 3];
+//! let list = singleton!(: ControlBlockList<u8, 3> = ControlBlockList::new()).unwrap();
+//! list.push(&HEADER).unwrap();
+//! list.push(&PAYLOAD).unwrap();
+//!
+//! let to = singleton!(: [u8; 7] = [0; 7]).unwrap();
+//! let transfer = Config::new((dma.ch0, dma.ch1), list, to).start();
+//! let ((ch0, ch1), list, to) = transfer.wait().unwrap();
+//! ```
+use core::marker::PhantomData;
+use core::mem;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use super::{
+    Ctrl, Priority, SingleChannel, TransferError, Word, WriteTarget, CTRL_ERRORS, TREQ_UNPACED,
+};
+
+/// Transfer count and read address of one fragment, in the order of the data channel's
+/// `AL3_TRANS_COUNT` and `AL3_READ_ADDR_TRIG` registers.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ControlBlock {
+    count: u32,
+    read_addr: u32,
+}
+
+impl ControlBlock {
+    /// The block ending the list: writing a read address of 0 to a trigger register is a null
+    /// trigger, which doesn't start the data channel.
+    const NULL: ControlBlock = ControlBlock {
+        count: 0,
+        read_addr: 0,
+    };
+}
+
+/// List of up to `N - 1` fragments of `W` words, gathered by a [`Transfer`]
+///
+/// The last block of the list always ends it, which is why it holds one fragment less than its
+/// size.
+pub struct ControlBlockList<W: Word, const N: usize> {
+    blocks: [ControlBlock; N],
+    len: usize,
+    _word: PhantomData<&'static [W]>,
+}
+
+impl<W: Word, const N: usize> ControlBlockList<W, N> {
+    /// Create an empty list.
+    pub const fn new() -> Self {
+        ControlBlockList {
+            blocks: [ControlBlock::NULL; N],
+            len: 0,
+            _word: PhantomData,
+        }
+    }
+
+    /// Append a fragment to the list, or return it if the list is full.
+    pub fn push(&mut self, fragment: &'static [W]) -> Result<(), &'static [W]> {
+        // Keep a null block after the last fragment
+        if self.len + 1 >= N {
+            return Err(fragment);
+        }
+        self.blocks[self.len] = ControlBlock {
+            count: fragment.len() as u32,
+            read_addr: fragment.as_ptr() as u32,
+        };
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Remove all fragments from the list.
+    pub fn clear(&mut self) {
+        self.blocks[..self.len].fill(ControlBlock::NULL);
+        self.len = 0;
+    }
+
+    /// The number of fragments in the list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the list holds no fragment.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The total number of words of the fragments.
+    pub fn word_count(&self) -> u32 {
+        self.blocks[..self.len].iter().map(|b| b.count).sum()
+    }
+}
+
+impl<W: Word, const N: usize> Default for ControlBlockList<W, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Configuration for a gather DMA transfer
+pub struct Config<C1, C2, W, TO, const N: usize>
+where
+    C1: SingleChannel,
+    C2: SingleChannel,
+    W: Word,
+    TO: WriteTarget<TransmittedWord = W>,
+{
+    ch: (C1, C2),
+    list: &'static mut ControlBlockList<W, N>,
+    to: TO,
+    priority: Priority,
+}
+
+impl<C1, C2, W, TO, const N: usize> Config<C1, C2, W, TO, N>
+where
+    C1: SingleChannel,
+    C2: SingleChannel,
+    W: Word,
+    TO: WriteTarget<TransmittedWord = W>,
+{
+    /// Create a new configuration, gathering the fragments of `list` into `to`.
+    ///
+    /// The first channel of `ch` walks the list, and the second one moves the fragments, paced by
+    /// the DREQ of `to` if it has one.
+    pub fn new(ch: (C1, C2), list: &'static mut ControlBlockList<W, N>, to: TO) -> Self {
+        Config {
+            ch,
+            list,
+            to,
+            priority: Priority::Normal,
+        }
+    }
+
+    /// Set the priority of both channels in the DMA arbiter.
+    pub fn priority(&mut self, priority: Priority) {
+        self.priority = priority;
+    }
+
+    /// Start the DMA transfer
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to` has room for fewer words than the fragments of the list hold, or if the
+    /// list is of size 0, leaving no room for the block ending it.
+    pub fn start(mut self) -> Transfer<C1, C2, W, TO, N> {
+        assert!(N > 0, "The list has no room for the block ending it");
+        let (control, data) = &self.ch;
+        let (dest, dest_count) = self.to.tx_address_count();
+        assert!(
+            self.list.word_count() <= dest_count,
+            "The destination is too small for the fragments"
+        );
+
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        // The data channel writes all fragments one after the other, and triggers the control
+        // channel once done with each of them. Its interrupt is only raised by the null trigger
+        // ending the list.
+        let treq = self.to.tx_treq().unwrap_or(TREQ_UNPACED);
+        let high_priority = self.priority == Priority::High;
+        let data_ctrl = Ctrl {
+            incr_write: self.to.tx_increment(),
+            chain_to: control.id(),
+            treq,
+            high_priority,
+            irq_quiet: true,
+            ..Ctrl::new::<_, W>(data)
+        };
+        data.ch()
+            .ch_al1_ctrl
+            .write(|w| unsafe { w.bits(data_ctrl.bits()) });
+        data.ch().ch_write_addr.write(|w| unsafe { w.bits(dest) });
+
+        // The control channel copies one block to the data channel's AL3_TRANS_COUNT and
+        // AL3_READ_ADDR_TRIG registers each time it is triggered, wrapping around these 8 bytes.
+        let control_ctrl = Ctrl {
+            ring: Some((3, true)),
+            high_priority,
+            ..Ctrl::new::<_, u32>(control)
+        };
+        control
+            .ch()
+            .ch_al1_ctrl
+            .write(|w| unsafe { w.bits(control_ctrl.bits()) });
+        control
+            .ch()
+            .ch_write_addr
+            .write(|w| unsafe { w.bits(data.ch().ch_al3_trans_count.as_ptr() as u32) });
+        control.ch().ch_trans_count.write(|w| unsafe { w.bits(2) });
+        control
+            .ch()
+            .ch_al3_read_addr_trig
+            .write(|w| unsafe { w.bits(self.list.blocks.as_ptr() as u32) });
+
+        Transfer {
+            ch: self.ch,
+            list: self.list,
+            to: self.to,
+        }
+    }
+}
+
+/// Instance of a gather DMA transfer
+pub struct Transfer<C1, C2, W, TO, const N: usize>
+where
+    C1: SingleChannel,
+    C2: SingleChannel,
+    W: Word,
+    TO: WriteTarget<TransmittedWord = W>,
+{
+    ch: (C1, C2),
+    list: &'static mut ControlBlockList<W, N>,
+    to: TO,
+}
+
+impl<C1, C2, W, TO, const N: usize> Transfer<C1, C2, W, TO, N>
+where
+    C1: SingleChannel,
+    C2: SingleChannel,
+    W: Word,
+    TO: WriteTarget<TransmittedWord = W>,
+{
+    /// Check if the transfer has completed, or was halted by a bus error.
+    pub fn is_done(&self) -> bool {
+        let (control, data) = &self.ch;
+        let control_ctrl = control.ch().ch_ctrl_trig.read();
+        let data_ctrl = data.ch().ch_ctrl_trig.read();
+        if control_ctrl.bits() & CTRL_ERRORS != 0 || data_ctrl.bits() & CTRL_ERRORS != 0 {
+            // Neither channel triggers the other anymore
+            return !control_ctrl.busy().bit_is_set() && !data_ctrl.busy().bit_is_set();
+        }
+        // The control channel has read the null block once its read address is past it
+        let end = self.list.blocks.as_ptr() as u32
+            + ((self.list.len + 1) * mem::size_of::<ControlBlock>()) as u32;
+        control.ch().ch_read_addr.read().bits() == end
+            && !control_ctrl.busy().bit_is_set()
+            && !data_ctrl.busy().bit_is_set()
+    }
+
+    /// Block until the transfer is complete, returning the channels, the list and the
+    /// destination.
+    ///
+    /// If a bus error halted the transfer, they are returned in the error.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(
+        mut self,
+    ) -> Result<
+        ((C1, C2), &'static mut ControlBlockList<W, N>, TO),
+        TransferError<((C1, C2), &'static mut ControlBlockList<W, N>, TO)>,
+    > {
+        while !self.is_done() {}
+
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        // Take the errors of both channels, to leave them clear
+        let data_error = self.ch.1.take_error();
+        let control_error = self.ch.0.take_error();
+        match data_error.or(control_error) {
+            None => Ok((self.ch, self.list, self.to)),
+            Some(error) => Err(TransferError::new(error, (self.ch, self.list, self.to))),
+        }
+    }
+
+    /// Abort the transfer, returning the channels, the list and the destination once both
+    /// channels have stopped.
+    #[allow(clippy::type_complexity)]
+    pub fn abort(mut self) -> ((C1, C2), &'static mut ControlBlockList<W, N>, TO) {
+        // Abort the control channel first, so that it doesn't restart the data channel
+        self.ch.0.abort();
+        self.ch.1.abort();
+        self.ch.0.take_error();
+        self.ch.1.take_error();
+
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        (self.ch, self.list, self.to)
+    }
+}
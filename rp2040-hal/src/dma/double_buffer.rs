@@ -0,0 +1,440 @@
+//! Double-buffered DMA transfers, ping-ponging between a pair of channels
+//!
+//! While one channel moves a buffer, the other one is set up with the next buffer, and chained
+//! to by the first one, so that a continuous stream, such as samples from the ADC, is never
+//! interrupted while the CPU processes the buffer just completed.
+//!
+//! ## Usage
+//! ```no_run
+//! use cortex_m::singleton;
+//! use rp2040_hal::{dma::{double_buffer, DMAExt}, pac};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//!
+//! let from = singleton!(: [u32; 16] = [0x1234_5678; 16]).unwrap();
+//! let to0 = singleton!(: [u32; 16] = [0; 16]).unwrap();
+//! let to1 = singleton!(: [u32; 16] = [0; 16]).unwrap();
+//! let transfer = double_buffer::Config::new((dma.ch0, dma.ch1), from, to0).start();
+//! // Queue the second buffer, which the transfer moves on to once the first one is full
+//! let mut transfer = transfer.write_next(to1);
+//! loop {
+//!     let (full, next) = transfer.wait().unwrap();
+//!     // Process `full` while the other buffer is filled, then queue it again
+//!     transfer = next.write_next(full);
+//! }
+//! ```
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use super::{
+    configure, Ctrl, Pace, Priority, ReadTarget, SingleChannel, TransferError, WriteTarget,
+    CTRL_ERRORS,
+};
+
+/// Settings shared by all the transfers of a double-buffered stream
+#[derive(Clone, Copy)]
+struct Settings {
+    pace: Pace,
+    bswap: bool,
+    high_priority: bool,
+}
+
+impl Settings {
+    /// Configure the channel of `ch` which isn't running, from `from` to `to`, without
+    /// triggering it
+    fn configure_other<CH1, CH2, W, FROM, TO>(
+        self,
+        ch: &(CH1, CH2),
+        second_ch: bool,
+        from: &FROM,
+        to: &mut TO,
+    ) where
+        CH1: SingleChannel,
+        CH2: SingleChannel,
+        FROM: ReadTarget<ReceivedWord = W>,
+        TO: WriteTarget<TransmittedWord = W>,
+        W: super::Word,
+    {
+        if second_ch {
+            let ctrl = self.ctrl(Ctrl::new::<CH1, W>(&ch.0));
+            configure(&ch.0, from, to, self.pace, ctrl, false);
+        } else {
+            let ctrl = self.ctrl(Ctrl::new::<CH2, W>(&ch.1));
+            configure(&ch.1, from, to, self.pace, ctrl, false);
+        }
+    }
+
+    fn ctrl(self, ctrl: Ctrl) -> Ctrl {
+        Ctrl {
+            bswap: self.bswap,
+            high_priority: self.high_priority,
+            ..ctrl
+        }
+    }
+}
+
+/// Configuration for double-buffered DMA transfer
+pub struct Config<CH1, CH2, FROM, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    TO: WriteTarget,
+{
+    ch: (CH1, CH2),
+    from: FROM,
+    to: TO,
+    settings: Settings,
+}
+
+impl<CH1, CH2, FROM, TO, WORD> Config<CH1, CH2, FROM, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+    WORD: super::Word,
+{
+    /// Create a new configuration for double-buffered DMA transfer, starting with `from` and
+    /// `to` on the first channel
+    ///
+    /// Each transfer moves as many words as the shortest of its source and destination holds,
+    /// paced by the DREQ of the source if it has one, or else by the DREQ of the destination.
+    pub fn new(ch: (CH1, CH2), from: FROM, to: TO) -> Self {
+        Config {
+            ch,
+            from,
+            to,
+            settings: Settings {
+                pace: Pace::PreferSource,
+                bswap: false,
+                high_priority: false,
+            },
+        }
+    }
+
+    /// Set the transfer pacing, when both the source and the destination have a DREQ, or to pace
+    /// it with another DREQ.
+    pub fn pace(&mut self, pace: Pace) {
+        self.settings.pace = pace;
+    }
+
+    /// Reverse the order of the bytes of each word moved.
+    pub fn bswap(&mut self, bswap: bool) {
+        self.settings.bswap = bswap;
+    }
+
+    /// Set the priority of both channels in the DMA arbiter.
+    pub fn priority(&mut self, priority: Priority) {
+        self.settings.high_priority = priority == Priority::High;
+    }
+
+    /// Start the first DMA transfer
+    pub fn start(mut self) -> Transfer<CH1, CH2, FROM, TO, ()> {
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        let ctrl = self.settings.ctrl(Ctrl::new::<CH1, WORD>(&self.ch.0));
+        configure(
+            &self.ch.0,
+            &self.from,
+            &mut self.to,
+            self.settings.pace,
+            ctrl,
+            true,
+        );
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            to: self.to,
+            settings: self.settings,
+            second_ch: false,
+            next: (),
+        }
+    }
+}
+
+/// The source of the transfer queued after the running one
+pub struct ReadNext<BUF: ReadTarget>(BUF);
+
+/// The destination of the transfer queued after the running one
+pub struct WriteNext<BUF: WriteTarget>(BUF);
+
+/// Instance of a double-buffered DMA transfer
+///
+/// `NEXT` is `()` while no transfer is queued after this one, or else the [`ReadNext`] or
+/// [`WriteNext`] buffer of the queued transfer.
+pub struct Transfer<CH1, CH2, FROM, TO, NEXT>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    TO: WriteTarget,
+{
+    ch: (CH1, CH2),
+    from: FROM,
+    to: TO,
+    settings: Settings,
+    /// Whether the running transfer is on the second channel
+    second_ch: bool,
+    next: NEXT,
+}
+
+impl<CH1, CH2, FROM, TO, NEXT, WORD> Transfer<CH1, CH2, FROM, TO, NEXT>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Check if the running transfer has completed, or was halted by a bus error.
+    pub fn is_done(&self) -> bool {
+        let current = if self.second_ch {
+            self.ch.1.ch()
+        } else {
+            self.ch.0.ch()
+        };
+        current.ch_ctrl_trig.read().busy().bit_is_clear()
+    }
+
+    /// Chain the running channel to the other one, which was just configured, and trigger the
+    /// other one if the running transfer completed before the chain was set
+    fn chain_to_other(&self) {
+        let (current, other, other_id) = if self.second_ch {
+            (self.ch.1.ch(), self.ch.0.ch(), self.ch.0.id())
+        } else {
+            (self.ch.0.ch(), self.ch.1.ch(), self.ch.1.id())
+        };
+
+        // CHAIN_TO is bits 11 to 14, and the write-one-to-clear error flags are left alone
+        let ctrl = current.ch_ctrl_trig.read().bits() & !(0xf << 11 | CTRL_ERRORS);
+        current
+            .ch_al1_ctrl
+            .write(|w| unsafe { w.bits(ctrl | u32::from(other_id) << 11) });
+
+        // If the running transfer completed before the chain was set, the other channel is
+        // still idle
+        if current.ch_ctrl_trig.read().busy().bit_is_clear()
+            && other.ch_ctrl_trig.read().busy().bit_is_clear()
+        {
+            // Safety: MULTI_CHAN_TRIGGER only triggers the channel whose bit is set, which is
+            // owned by the transfer.
+            let dma = unsafe { &*pac::DMA::ptr() };
+            dma.multi_chan_trigger
+                .write(|w| unsafe { w.bits(1 << other_id) });
+        }
+    }
+}
+
+impl<CH1, CH2, FROM, TO, WORD> Transfer<CH1, CH2, FROM, TO, ()>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+    WORD: super::Word,
+{
+    /// Queue a transfer from `buf` to the same destination, which starts as soon as the running
+    /// one completes.
+    pub fn read_next<BUF: ReadTarget<ReceivedWord = WORD>>(
+        mut self,
+        buf: BUF,
+    ) -> Transfer<CH1, CH2, FROM, TO, ReadNext<BUF>> {
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        self.settings
+            .configure_other(&self.ch, self.second_ch, &buf, &mut self.to);
+        self.chain_to_other();
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            to: self.to,
+            settings: self.settings,
+            second_ch: self.second_ch,
+            next: ReadNext(buf),
+        }
+    }
+
+    /// Queue a transfer from the same source to `buf`, which starts as soon as the running one
+    /// completes.
+    pub fn write_next<BUF: WriteTarget<TransmittedWord = WORD>>(
+        self,
+        mut buf: BUF,
+    ) -> Transfer<CH1, CH2, FROM, TO, WriteNext<BUF>> {
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        self.settings
+            .configure_other(&self.ch, self.second_ch, &self.from, &mut buf);
+        self.chain_to_other();
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            to: self.to,
+            settings: self.settings,
+            second_ch: self.second_ch,
+            next: WriteNext(buf),
+        }
+    }
+
+    /// Block until the transfer is complete, returning the channels and the targets.
+    ///
+    /// If a bus error halted the transfer, they are returned in the error.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(mut self) -> Result<((CH1, CH2), FROM, TO), TransferError<((CH1, CH2), FROM, TO)>> {
+        while !self.is_done() {}
+
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        let error = if self.second_ch {
+            self.ch.1.take_error()
+        } else {
+            self.ch.0.take_error()
+        };
+        match error {
+            None => Ok((self.ch, self.from, self.to)),
+            Some(error) => Err(TransferError::new(error, (self.ch, self.from, self.to))),
+        }
+    }
+
+    /// Abort the transfer, returning the channels and the targets once the channel has stopped.
+    pub fn abort(mut self) -> ((CH1, CH2), FROM, TO) {
+        if self.second_ch {
+            self.ch.1.abort();
+            self.ch.1.take_error();
+        } else {
+            self.ch.0.abort();
+            self.ch.0.take_error();
+        }
+
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        (self.ch, self.from, self.to)
+    }
+}
+
+impl<CH1, CH2, FROM, TO, NEXT> Transfer<CH1, CH2, FROM, TO, NEXT>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    TO: WriteTarget,
+{
+    /// Wait for the running transfer to complete, and take its result out, the queued
+    /// transfer running on
+    ///
+    /// If a bus error halted the running transfer, the queued one is aborted, and the error
+    /// holds everything back.
+    #[allow(clippy::type_complexity)]
+    fn finish(mut self) -> Result<Self, TransferError<((CH1, CH2), FROM, TO, NEXT)>> {
+        loop {
+            let current = if self.second_ch {
+                self.ch.1.ch()
+            } else {
+                self.ch.0.ch()
+            };
+            if current.ch_ctrl_trig.read().busy().bit_is_clear() {
+                break;
+            }
+        }
+
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        let error = if self.second_ch {
+            self.ch.1.take_error()
+        } else {
+            self.ch.0.take_error()
+        };
+        match error {
+            None => Ok(self),
+            Some(error) => {
+                // A halted channel doesn't chain, but make sure the queued one doesn't run
+                if self.second_ch {
+                    self.ch.0.abort();
+                } else {
+                    self.ch.1.abort();
+                }
+                Err(TransferError::new(
+                    error,
+                    (self.ch, self.from, self.to, self.next),
+                ))
+            }
+        }
+    }
+}
+
+impl<CH1, CH2, FROM, TO, BUF, WORD> Transfer<CH1, CH2, FROM, TO, ReadNext<BUF>>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+    BUF: ReadTarget<ReceivedWord = WORD>,
+{
+    /// Block until the running transfer is complete, returning its source and the queued
+    /// transfer, which is now running.
+    ///
+    /// If a bus error halted the running transfer, the queued one is aborted, and everything is
+    /// returned in the error.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(
+        self,
+    ) -> Result<
+        (FROM, Transfer<CH1, CH2, BUF, TO, ()>),
+        TransferError<((CH1, CH2), FROM, TO, ReadNext<BUF>)>,
+    > {
+        let done = self.finish()?;
+        Ok((
+            done.from,
+            Transfer {
+                ch: done.ch,
+                from: done.next.0,
+                to: done.to,
+                settings: done.settings,
+                second_ch: !done.second_ch,
+                next: (),
+            },
+        ))
+    }
+}
+
+impl<CH1, CH2, FROM, TO, BUF, WORD> Transfer<CH1, CH2, FROM, TO, WriteNext<BUF>>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+    BUF: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Block until the running transfer is complete, returning its destination and the queued
+    /// transfer, which is now running.
+    ///
+    /// If a bus error halted the running transfer, the queued one is aborted, and everything is
+    /// returned in the error.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(
+        self,
+    ) -> Result<
+        (TO, Transfer<CH1, CH2, FROM, BUF, ()>),
+        TransferError<((CH1, CH2), FROM, TO, WriteNext<BUF>)>,
+    > {
+        let done = self.finish()?;
+        Ok((
+            done.to,
+            Transfer {
+                ch: done.ch,
+                from: done.from,
+                to: done.next.0,
+                settings: done.settings,
+                second_ch: !done.second_ch,
+                next: (),
+            },
+        ))
+    }
+}
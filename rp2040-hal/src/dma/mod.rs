@@ -0,0 +1,966 @@
+//! # DMA
+//!
+//! The DMA block is split into its 12 channels with [`DMAExt::split`], and each channel runs
+//! transfers configured by [`single_buffer::Config`]. Pairs of channels can also gather
+//! fragments scattered in memory, following lists of control blocks: see [`control_block`],
+//! stream without gaps by taking turns on alternate buffers: see [`double_buffer`], or run both
+//! directions of a full-duplex peripheral: see [`bidirectional`].
+//!
+//! Peripherals the DMA can feed or drain describe themselves with [`ReadTarget`] and
+//! [`WriteTarget`]. They are implemented for the FIFOs of the UART, SPI, I2C, PIO and ADC
+//! peripherals, for the compare registers of the PWM slices, and for `'static` buffers through
+//! [`embedded_dma::ReadBuffer`] and [`embedded_dma::WriteBuffer`].
+//!
+//! All channels share the DMA's bus masters, taking turns as set by their [`Priority`], so that
+//! latency-sensitive streams can pre-empt bulk transfers.
+//!
+//! Instead of polling transfers, channels can raise DMA_IRQ_0 or DMA_IRQ_1 when their transfers
+//! complete: see [`SingleChannel::listen_irq0`]. The interrupt handler then finds the channels
+//! which completed with [`SingleChannel::check_irq0`], typically on transfers shared with it
+//! through a `cortex_m::interrupt::Mutex`, and can signal the tasks waiting for them.
+//!
+//! See [Chapter 2 Section 5](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for
+//! more details.
+use crate::{
+    atomic_register_access::{write_bitmask_clear, write_bitmask_set},
+    resets::SubsystemReset,
+    typelevel::Sealed,
+};
+use core::mem;
+use embedded_dma::{ReadBuffer, WriteBuffer};
+use embedded_time::rate::Hertz;
+
+pub mod bidirectional;
+pub mod control_block;
+pub mod double_buffer;
+pub mod single_buffer;
+
+/// DMA unit.
+pub trait DMAExt: Sealed {
+    /// Splits the DMA unit into its individual channels.
+    fn split(self, resets: &mut pac::RESETS) -> Channels;
+}
+
+impl Sealed for pac::DMA {}
+
+impl DMAExt for pac::DMA {
+    fn split(self, resets: &mut pac::RESETS) -> Channels {
+        self.reset_bring_down(resets);
+        self.reset_bring_up(resets);
+
+        Channels {
+            ch0: Channel::new(),
+            ch1: Channel::new(),
+            ch2: Channel::new(),
+            ch3: Channel::new(),
+            ch4: Channel::new(),
+            ch5: Channel::new(),
+            ch6: Channel::new(),
+            ch7: Channel::new(),
+            ch8: Channel::new(),
+            ch9: Channel::new(),
+            ch10: Channel::new(),
+            ch11: Channel::new(),
+            timer0: PacingTimer::new(),
+            timer1: PacingTimer::new(),
+            timer2: PacingTimer::new(),
+            timer3: PacingTimer::new(),
+            sniffer: Sniffer { _private: () },
+        }
+    }
+}
+
+/// DMA channel identifier.
+pub trait ChannelIndex: Sealed {
+    /// Numerical index of the DMA channel (0..11).
+    fn id() -> u8;
+}
+
+macro_rules! channels {
+    ($($CH:ident: ($ch:ident, $id:expr),)+) => {
+        $(
+            #[doc = concat!("Marker for DMA channel ", stringify!($id))]
+            pub struct $CH;
+
+            impl Sealed for $CH {}
+
+            impl ChannelIndex for $CH {
+                fn id() -> u8 {
+                    $id
+                }
+            }
+        )+
+
+        /// Set of DMA channels, and of the pacing timers and sniffer of the DMA block.
+        pub struct Channels {
+            $(
+                #[doc = concat!("DMA channel ", stringify!($id))]
+                pub $ch: Channel<$CH>,
+            )+
+            /// Pacing timer 0
+            pub timer0: PacingTimer<TIMER0>,
+            /// Pacing timer 1
+            pub timer1: PacingTimer<TIMER1>,
+            /// Pacing timer 2
+            pub timer2: PacingTimer<TIMER2>,
+            /// Pacing timer 3
+            pub timer3: PacingTimer<TIMER3>,
+            /// Sniffer
+            pub sniffer: Sniffer,
+        }
+    };
+}
+
+channels! {
+    CH0: (ch0, 0),
+    CH1: (ch1, 1),
+    CH2: (ch2, 2),
+    CH3: (ch3, 3),
+    CH4: (ch4, 4),
+    CH5: (ch5, 5),
+    CH6: (ch6, 6),
+    CH7: (ch7, 7),
+    CH8: (ch8, 8),
+    CH9: (ch9, 9),
+    CH10: (ch10, 10),
+    CH11: (ch11, 11),
+}
+
+/// DMA channel.
+pub struct Channel<CH: ChannelIndex> {
+    _phantom: core::marker::PhantomData<CH>,
+}
+
+impl<CH: ChannelIndex> Channel<CH> {
+    fn new() -> Self {
+        Channel {
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+/// Trait which is implemented by anything that can be used as a single DMA channel.
+pub trait SingleChannel: Sealed {
+    /// Returns the registers associated with this DMA channel.
+    ///
+    /// In the case of channel pairs, this returns the first channel.
+    fn ch(&self) -> &pac::dma::CH;
+
+    /// Returns the index of the DMA channel.
+    fn id(&self) -> u8;
+
+    /// Enables the DMA_IRQ_0 interrupt for this channel.
+    fn listen_irq0(&mut self) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            write_bitmask_set((*pac::DMA::ptr()).inte0.as_ptr(), 1 << self.id());
+        }
+    }
+
+    /// Disables the DMA_IRQ_0 interrupt for this channel.
+    fn unlisten_irq0(&mut self) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            write_bitmask_clear((*pac::DMA::ptr()).inte0.as_ptr(), 1 << self.id());
+        }
+    }
+
+    /// Checks whether this channel raised DMA_IRQ_0, and clears the interrupt if it did.
+    ///
+    /// This is meant to be called from the DMA_IRQ_0 handler, to find which of the channels
+    /// completed a transfer.
+    fn check_irq0(&mut self) -> bool {
+        // Safety: INTS0 is only read, and the flag of this channel cleared with a
+        // write-one-to-clear.
+        let dma = unsafe { &*pac::DMA::ptr() };
+        let mask = 1 << self.id();
+        if dma.ints0.read().bits() & mask != 0 {
+            dma.ints0.write(|w| unsafe { w.bits(mask) });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Enables the DMA_IRQ_1 interrupt for this channel.
+    fn listen_irq1(&mut self) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            write_bitmask_set((*pac::DMA::ptr()).inte1.as_ptr(), 1 << self.id());
+        }
+    }
+
+    /// Disables the DMA_IRQ_1 interrupt for this channel.
+    fn unlisten_irq1(&mut self) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            write_bitmask_clear((*pac::DMA::ptr()).inte1.as_ptr(), 1 << self.id());
+        }
+    }
+
+    /// Checks whether this channel raised DMA_IRQ_1, and clears the interrupt if it did.
+    ///
+    /// This is meant to be called from the DMA_IRQ_1 handler, to find which of the channels
+    /// completed a transfer.
+    fn check_irq1(&mut self) -> bool {
+        // Safety: INTS1 is only read, and the flag of this channel cleared with a
+        // write-one-to-clear.
+        let dma = unsafe { &*pac::DMA::ptr() };
+        let mask = 1 << self.id();
+        if dma.ints1.read().bits() & mask != 0 {
+            dma.ints1.write(|w| unsafe { w.bits(mask) });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Aborts the transfer in progress on this channel, if any, and waits until the channel has
+    /// stopped.
+    ///
+    /// The interrupts of the channel are masked meanwhile: an aborted channel may still raise
+    /// them (erratum RP2040-E13).
+    fn abort(&mut self) {
+        // Safety: INTE0, INTE1 and CHAN_ABORT are only changed for this channel, through their
+        // atomic aliases or write-one-to-act bits.
+        let dma = unsafe { &*pac::DMA::ptr() };
+        let mask = 1 << self.id();
+        let inte0 = dma.inte0.read().bits() & mask;
+        let inte1 = dma.inte1.read().bits() & mask;
+        unsafe {
+            write_bitmask_clear(dma.inte0.as_ptr(), mask);
+            write_bitmask_clear(dma.inte1.as_ptr(), mask);
+        }
+
+        dma.chan_abort.write(|w| unsafe { w.bits(mask) });
+        while dma.chan_abort.read().bits() & mask != 0 {}
+        while self.ch().ch_ctrl_trig.read().busy().bit_is_set() {}
+
+        dma.ints0.write(|w| unsafe { w.bits(mask) });
+        dma.ints1.write(|w| unsafe { w.bits(mask) });
+        unsafe {
+            write_bitmask_set(dma.inte0.as_ptr(), inte0);
+            write_bitmask_set(dma.inte1.as_ptr(), inte1);
+        }
+    }
+
+    /// Returns the bus error which halted the last transfer of this channel, if any, and clears
+    /// it.
+    fn take_error(&mut self) -> Option<Error> {
+        let ctrl = self.ch().ch_ctrl_trig.read();
+        let error = if ctrl.read_error().bit_is_set() {
+            Error::Read
+        } else if ctrl.write_error().bit_is_set() {
+            Error::Write
+        } else {
+            return None;
+        };
+        // Writing back the flags clears them, and AL1_CTRL doesn't trigger the channel
+        self.ch()
+            .ch_al1_ctrl
+            .write(|w| unsafe { w.bits(ctrl.bits() | CTRL_ERRORS) });
+        Some(error)
+    }
+}
+
+/// Bus error which halted a DMA transfer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading from the source faulted, for instance on an address without memory.
+    Read,
+    /// Writing to the destination faulted, for instance on a read-only address.
+    Write,
+}
+
+/// A DMA transfer halted by a bus error
+///
+/// This holds back the channel and the targets of the transfer, to be recovered with
+/// [`into_parts`](Self::into_parts).
+pub struct TransferError<T> {
+    error: Error,
+    parts: T,
+}
+
+impl<T> TransferError<T> {
+    pub(crate) fn new(error: Error, parts: T) -> Self {
+        TransferError { error, parts }
+    }
+
+    /// The bus error which halted the transfer
+    pub fn error(&self) -> Error {
+        self.error
+    }
+
+    /// The channel and the targets of the transfer
+    pub fn into_parts(self) -> T {
+        self.parts
+    }
+}
+
+impl<T> core::fmt::Debug for TransferError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TransferError")
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl<CH: ChannelIndex> Sealed for Channel<CH> {}
+
+impl<CH: ChannelIndex> SingleChannel for Channel<CH> {
+    fn ch(&self) -> &pac::dma::CH {
+        // Safety: The channel is only accessed through this handle, and the registers shared
+        // between channels are only written atomically.
+        unsafe { &(*pac::DMA::ptr()).ch[CH::id() as usize] }
+    }
+
+    fn id(&self) -> u8 {
+        CH::id()
+    }
+}
+
+/// DMA pacing timer identifier.
+pub trait TimerIndex: Sealed {
+    /// Numerical index of the pacing timer (0..3).
+    fn id() -> u8;
+}
+
+macro_rules! timers {
+    ($($TIMER:ident: $id:expr,)+) => {
+        $(
+            #[doc = concat!("Marker for DMA pacing timer ", stringify!($id))]
+            pub struct $TIMER;
+
+            impl Sealed for $TIMER {}
+
+            impl TimerIndex for $TIMER {
+                fn id() -> u8 {
+                    $id
+                }
+            }
+        )+
+    };
+}
+
+timers! {
+    TIMER0: 0,
+    TIMER1: 1,
+    TIMER2: 2,
+    TIMER3: 3,
+}
+
+/// DMA pacing timer.
+///
+/// A pacing timer raises its DREQ at a fraction `x / y` of the system clock frequency, so that
+/// a transfer paced by it moves its words at a fixed rate, regardless of the readiness of its
+/// source and destination. This streams samples or bytes to a peripheral at an exact rate,
+/// without a timer interrupt.
+pub struct PacingTimer<T: TimerIndex> {
+    _phantom: core::marker::PhantomData<T>,
+}
+
+impl<T: TimerIndex> PacingTimer<T> {
+    fn new() -> Self {
+        PacingTimer {
+            _phantom: core::marker::PhantomData,
+        }
+    }
+
+    /// Set the rate of the timer to `x / y` times the system clock frequency.
+    ///
+    /// A `y` of 0 stops the timer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is greater than `y`.
+    pub fn set_fraction(&mut self, x: u16, y: u16) {
+        assert!(
+            x <= y,
+            "The pacing timer can't run faster than the system clock"
+        );
+        // Safety: The four TIMERn registers are consecutive, and this timer is only written
+        // through this handle.
+        unsafe {
+            let timer0 = (*pac::DMA::ptr()).timer0.as_ptr();
+            core::ptr::write_volatile(
+                timer0.add(T::id() as usize),
+                u32::from(x) << 16 | u32::from(y),
+            );
+        }
+    }
+
+    /// Set the timer to the closest rate to `rate` it can reach, `system_freq` being the
+    /// frequency of the system clock, and return that rate.
+    ///
+    /// Rates above `system_freq` are clamped to it.
+    pub fn set_rate(&mut self, rate: Hertz, system_freq: Hertz) -> Hertz {
+        let (x, y) = if rate.0 >= system_freq.0 {
+            (1, 1)
+        } else {
+            closest_fraction(rate.0, system_freq.0)
+        };
+        self.set_fraction(x, y);
+        Hertz((u64::from(system_freq.0) * u64::from(x) / u64::from(y)) as u32)
+    }
+
+    /// The DREQ value to pace a transfer with this timer, for [`Pace::Dreq`].
+    pub fn dreq(&self) -> u8 {
+        DREQ_TIMER0 + T::id()
+    }
+}
+
+/// The fraction with 16 bit terms closest to `num / den`, which must be below 1, from the
+/// convergents of its continued fraction.
+fn closest_fraction(num: u32, den: u32) -> (u16, u16) {
+    let (mut n, mut d) = (u64::from(num), u64::from(den));
+    // The last two convergents, starting with the conventional 0/1 and 1/0
+    let (mut p0, mut q0, mut p1, mut q1) = (0u64, 1u64, 1u64, 0u64);
+    while d != 0 {
+        let a = n / d;
+        let (p2, q2) = (a * p1 + p0, a * q1 + q0);
+        if p2 > 0xffff || q2 > 0xffff {
+            break;
+        }
+        p0 = p1;
+        q0 = q1;
+        p1 = p2;
+        q1 = q2;
+        let r = n - a * d;
+        n = d;
+        d = r;
+    }
+    (p1 as u16, q1 as u16)
+}
+
+/// Calculation made by the [`Sniffer`] on the data it sees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SniffMode {
+    /// CRC-32, IEEE 802.3 polynomial
+    Crc32,
+    /// CRC-32, IEEE 802.3 polynomial, with the bits of the data reversed
+    Crc32BitReversed,
+    /// CRC-16-CCITT
+    Crc16Ccitt,
+    /// CRC-16-CCITT, with the bits of the data reversed
+    Crc16CcittBitReversed,
+    /// XOR reduction, giving the even parity of the data in bit 0
+    Xor,
+    /// 32 bit sum of the data
+    Sum,
+}
+
+impl SniffMode {
+    fn calc(self) -> u8 {
+        match self {
+            SniffMode::Crc32 => 0x0,
+            SniffMode::Crc32BitReversed => 0x1,
+            SniffMode::Crc16Ccitt => 0x2,
+            SniffMode::Crc16CcittBitReversed => 0x3,
+            SniffMode::Xor => 0xe,
+            SniffMode::Sum => 0xf,
+        }
+    }
+}
+
+/// DMA sniffer.
+///
+/// The sniffer computes a checksum of the data moved by one channel, at the speed of the
+/// transfer. The channel must also be set to feed the sniffer, with
+/// [`single_buffer::Config::sniff`].
+///
+/// ```no_run
+/// use cortex_m::singleton;
+/// use rp2040_hal::{dma::{single_buffer, DMAExt, SniffMode}, pac};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut dma = peripherals.DMA.split(&mut peripherals.RESETS);
+///
+/// let from = singleton!(: [u8; 9] = *b"123456789").unwrap();
+/// let to = singleton!(: [u8; 9] = [0; 9]).unwrap();
+/// // CRC-32 as computed by zlib: the input and output bit reversed, and inverted
+/// dma.sniffer.attach(&dma.ch0, SniffMode::Crc32BitReversed, 0xffff_ffff);
+/// dma.sniffer.set_output_reverse(true);
+/// dma.sniffer.set_output_invert(true);
+/// let mut config = single_buffer::Config::new(dma.ch0, from, to);
+/// config.sniff(true);
+/// let (ch0, from, to) = config.start().wait().unwrap();
+/// // 0xcbf4_3926
+/// let crc = dma.sniffer.result();
+/// ```
+pub struct Sniffer {
+    _private: (),
+}
+
+impl Sniffer {
+    fn dma(&self) -> &pac::dma::RegisterBlock {
+        // Safety: The sniff registers are only accessed through this handle.
+        unsafe { &*pac::DMA::ptr() }
+    }
+
+    /// Attach the sniffer to the channel `ch`, computing `mode` from `seed`.
+    ///
+    /// The output options are left untouched.
+    pub fn attach<CH: SingleChannel>(&mut self, ch: &CH, mode: SniffMode, seed: u32) {
+        self.dma().sniff_data.write(|w| unsafe { w.bits(seed) });
+        self.dma().sniff_ctrl.modify(|_, w| {
+            unsafe {
+                w.dmach().bits(ch.id());
+                w.calc().bits(mode.calc());
+            }
+            w.en().set_bit()
+        });
+    }
+
+    /// Stop the sniffer.
+    pub fn detach(&mut self) {
+        self.dma().sniff_ctrl.modify(|_, w| w.en().clear_bit());
+    }
+
+    /// Reverse the order of the bytes of each word before they are sniffed, which computes the
+    /// checksum of big-endian data from a little-endian transfer.
+    pub fn set_bswap(&mut self, bswap: bool) {
+        self.dma().sniff_ctrl.modify(|_, w| w.bswap().bit(bswap));
+    }
+
+    /// Reverse the bits of the result when it is read.
+    pub fn set_output_reverse(&mut self, reverse: bool) {
+        self.dma()
+            .sniff_ctrl
+            .modify(|_, w| w.out_rev().bit(reverse));
+    }
+
+    /// Invert the bits of the result when it is read.
+    pub fn set_output_invert(&mut self, invert: bool) {
+        self.dma().sniff_ctrl.modify(|_, w| w.out_inv().bit(invert));
+    }
+
+    /// The result of the calculation, over the data moved since the sniffer was attached.
+    pub fn result(&self) -> u32 {
+        self.dma().sniff_data.read().bits()
+    }
+}
+
+/// DREQ value to run a transfer as fast as possible.
+const TREQ_UNPACED: u8 = 0x3f;
+
+/// Start copying `from` into `to` at full speed, as a DMA accelerated
+/// [`copy_from_slice`](slice::copy_from_slice).
+///
+/// Large copies, such as framebuffer blits, are faster this way than with the CPU, which is
+/// free to do other work until the returned transfer is done.
+///
+/// # Panics
+///
+/// Panics if `from` and `to` differ in length.
+pub fn copy<CH, W, FROM, TO>(
+    ch: CH,
+    from: FROM,
+    mut to: TO,
+) -> single_buffer::Transfer<CH, FROM, TO>
+where
+    CH: SingleChannel,
+    W: Word,
+    FROM: ReadBuffer<Word = W>,
+    TO: WriteBuffer<Word = W>,
+{
+    assert_eq!(
+        from.rx_address_count().1,
+        to.tx_address_count().1,
+        "Source and destination differ in length"
+    );
+    single_buffer::Config::new(ch, from, to).start()
+}
+
+/// Start filling `to` with `value` at full speed, as a DMA accelerated
+/// [`fill`](slice::fill).
+///
+/// Constants have a `'static` lifetime, so for instance `fill(ch, &0u32, buffer)` clears a
+/// buffer.
+pub fn fill<CH, W, TO>(
+    ch: CH,
+    value: &'static W,
+    to: TO,
+) -> single_buffer::Transfer<CH, Repeat<W>, TO>
+where
+    CH: SingleChannel,
+    W: Word,
+    TO: WriteBuffer<Word = W>,
+{
+    single_buffer::Config::new(ch, Repeat(value), to).start()
+}
+
+/// Source of a DMA transfer reading the same word over and over, as done by [`fill`].
+pub struct Repeat<W: Word + 'static>(pub &'static W);
+
+// Safety: The word is `'static`.
+unsafe impl<W: Word> ReadTarget for Repeat<W> {
+    type ReceivedWord = W;
+
+    fn rx_treq(&self) -> Option<u8> {
+        None
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.0 as *const W as u32, u32::MAX)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
+}
+
+/// Destination of a DMA transfer dropping the words written to it, such as the words received
+/// while only sending on a full-duplex bus.
+pub struct Discard<W: Word>(core::marker::PhantomData<W>);
+
+impl<W: Word> Discard<W> {
+    /// Create a destination dropping words of type `W`.
+    pub fn new() -> Self {
+        Discard(core::marker::PhantomData)
+    }
+}
+
+impl<W: Word> Default for Discard<W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where the words written to a [`Discard`] go
+static mut DISCARDED: u32 = 0;
+
+// Safety: `DISCARDED` is static, and never read.
+unsafe impl<W: Word> WriteTarget for Discard<W> {
+    type TransmittedWord = W;
+
+    fn tx_treq(&self) -> Option<u8> {
+        None
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        (
+            unsafe { core::ptr::addr_of_mut!(DISCARDED) } as u32,
+            u32::MAX,
+        )
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
+}
+
+/// Pacing of a transfer, when both its source and its destination have a DREQ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pace {
+    /// The DREQ of the source paces the transfer.
+    PreferSource,
+    /// The DREQ of the destination paces the transfer.
+    PreferSink,
+    /// The given DREQ paces the transfer, whatever its source and destination, for instance the
+    /// one of a [`PacingTimer`].
+    Dreq(u8),
+}
+
+/// Priority of a channel in the DMA arbiter
+///
+/// Each cycle, the arbiter grants the bus to one of the channels whose DREQ is asserted, scanning
+/// them in round-robin order. High priority channels are considered first: while any of them
+/// requests transfers, normal priority channels only get one turn per round over the high
+/// priority ones. Within a priority, channels share the bandwidth equally.
+///
+/// This orders the DMA channels among themselves. Their priority on the bus fabric, against the
+/// processors, is set through the BUSCTRL registers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Priority {
+    /// The default priority, for bulk transfers such as flash reads or memory copies.
+    Normal,
+    /// For latency-sensitive streams, such as audio output, which must not be starved by bulk
+    /// transfers.
+    High,
+}
+
+/// Size of the words moved by a DMA transfer: `u8`, `u16` or `u32`.
+pub trait Word: Sealed {}
+
+impl Sealed for u8 {}
+impl Word for u8 {}
+impl Sealed for u16 {}
+impl Word for u16 {}
+impl Sealed for u32 {}
+impl Word for u32 {}
+
+/// Fields of the CTRL register of a channel
+///
+/// They are written through the `AL1_CTRL` alias, which doesn't trigger the channel, and has no
+/// fields of its own.
+#[derive(Clone, Copy)]
+struct Ctrl {
+    data_size: u8,
+    incr_read: bool,
+    incr_write: bool,
+    /// Size of the ring, as a number of address bits, and whether it wraps the write address
+    /// rather than the read address
+    ring: Option<(u8, bool)>,
+    chain_to: u8,
+    treq: u8,
+    high_priority: bool,
+    irq_quiet: bool,
+    bswap: bool,
+    sniff: bool,
+}
+
+/// The write-one-to-clear WRITE_ERROR and READ_ERROR flags of the CTRL register
+const CTRL_ERRORS: u32 = 0b11 << 29;
+
+impl Ctrl {
+    /// An unpaced transfer of `W` words, incrementing both addresses, without chaining
+    fn new<CH: SingleChannel, W: Word>(ch: &CH) -> Self {
+        Ctrl {
+            // 0 for bytes, 1 for half-words, 2 for words
+            data_size: mem::size_of::<W>() as u8 >> 1,
+            incr_read: true,
+            incr_write: true,
+            ring: None,
+            // Chaining to itself disables chaining
+            chain_to: ch.id(),
+            treq: TREQ_UNPACED,
+            high_priority: false,
+            irq_quiet: false,
+            bswap: false,
+            sniff: false,
+        }
+    }
+
+    fn bits(self) -> u32 {
+        let (ring_size, ring_sel) = self.ring.unwrap_or((0, false));
+        1 // EN
+            | u32::from(self.high_priority) << 1
+            | u32::from(self.data_size) << 2
+            | u32::from(self.incr_read) << 4
+            | u32::from(self.incr_write) << 5
+            | u32::from(ring_size) << 6
+            | u32::from(ring_sel) << 10
+            | u32::from(self.chain_to) << 11
+            | u32::from(self.treq) << 15
+            | u32::from(self.irq_quiet) << 21
+            | u32::from(self.bswap) << 22
+            | u32::from(self.sniff) << 23
+    }
+}
+
+/// Configure the channel registers for a transfer from `from` to `to`, triggering it if `start`
+/// is set.
+fn configure<CH, W, FROM, TO>(
+    ch: &CH,
+    from: &FROM,
+    to: &mut TO,
+    pace: Pace,
+    ctrl: Ctrl,
+    start: bool,
+) where
+    CH: SingleChannel,
+    W: Word,
+    FROM: ReadTarget<ReceivedWord = W>,
+    TO: WriteTarget<TransmittedWord = W>,
+{
+    let (src, src_count) = from.rx_address_count();
+    let (dest, dest_count) = to.tx_address_count();
+    let treq = match pace {
+        Pace::PreferSource => from.rx_treq().or_else(|| to.tx_treq()),
+        Pace::PreferSink => to.tx_treq().or_else(|| from.rx_treq()),
+        Pace::Dreq(dreq) => Some(dreq),
+    }
+    .unwrap_or(TREQ_UNPACED);
+
+    let ctrl = Ctrl {
+        incr_read: from.rx_increment(),
+        incr_write: to.tx_increment(),
+        treq,
+        ..ctrl
+    };
+    ch.ch()
+        .ch_al1_ctrl
+        .write(|w| unsafe { w.bits(ctrl.bits()) });
+    ch.ch().ch_read_addr.write(|w| unsafe { w.bits(src) });
+    ch.ch()
+        .ch_trans_count
+        .write(|w| unsafe { w.bits(u32::min(src_count, dest_count)) });
+    if start {
+        ch.ch()
+            .ch_al2_write_addr_trig
+            .write(|w| unsafe { w.bits(dest) });
+    } else {
+        ch.ch().ch_write_addr.write(|w| unsafe { w.bits(dest) });
+    }
+}
+
+/// The DREQ value for PIO0's TX FIFO 0
+pub const DREQ_PIO0_TX0: u8 = 0;
+/// The DREQ value for PIO0's TX FIFO 1
+pub const DREQ_PIO0_TX1: u8 = 1;
+/// The DREQ value for PIO0's TX FIFO 2
+pub const DREQ_PIO0_TX2: u8 = 2;
+/// The DREQ value for PIO0's TX FIFO 3
+pub const DREQ_PIO0_TX3: u8 = 3;
+/// The DREQ value for PIO0's RX FIFO 0
+pub const DREQ_PIO0_RX0: u8 = 4;
+/// The DREQ value for PIO0's RX FIFO 1
+pub const DREQ_PIO0_RX1: u8 = 5;
+/// The DREQ value for PIO0's RX FIFO 2
+pub const DREQ_PIO0_RX2: u8 = 6;
+/// The DREQ value for PIO0's RX FIFO 3
+pub const DREQ_PIO0_RX3: u8 = 7;
+/// The DREQ value for PIO1's TX FIFO 0
+pub const DREQ_PIO1_TX0: u8 = 8;
+/// The DREQ value for PIO1's TX FIFO 1
+pub const DREQ_PIO1_TX1: u8 = 9;
+/// The DREQ value for PIO1's TX FIFO 2
+pub const DREQ_PIO1_TX2: u8 = 10;
+/// The DREQ value for PIO1's TX FIFO 3
+pub const DREQ_PIO1_TX3: u8 = 11;
+/// The DREQ value for PIO1's RX FIFO 0
+pub const DREQ_PIO1_RX0: u8 = 12;
+/// The DREQ value for PIO1's RX FIFO 1
+pub const DREQ_PIO1_RX1: u8 = 13;
+/// The DREQ value for PIO1's RX FIFO 2
+pub const DREQ_PIO1_RX2: u8 = 14;
+/// The DREQ value for PIO1's RX FIFO 3
+pub const DREQ_PIO1_RX3: u8 = 15;
+/// The DREQ value for SPI0's TX FIFO
+pub const DREQ_SPI0_TX: u8 = 16;
+/// The DREQ value for SPI0's RX FIFO
+pub const DREQ_SPI0_RX: u8 = 17;
+/// The DREQ value for SPI1's TX FIFO
+pub const DREQ_SPI1_TX: u8 = 18;
+/// The DREQ value for SPI1's RX FIFO
+pub const DREQ_SPI1_RX: u8 = 19;
+/// The DREQ value for UART0's TX FIFO
+pub const DREQ_UART0_TX: u8 = 20;
+/// The DREQ value for UART0's RX FIFO
+pub const DREQ_UART0_RX: u8 = 21;
+/// The DREQ value for UART1's TX FIFO
+pub const DREQ_UART1_TX: u8 = 22;
+/// The DREQ value for UART1's RX FIFO
+pub const DREQ_UART1_RX: u8 = 23;
+/// The DREQ value for PWM Counter 0's Wrap Value
+pub const DREQ_PWM_WRAP0: u8 = 24;
+/// The DREQ value for PWM Counter 1's Wrap Value
+pub const DREQ_PWM_WRAP1: u8 = 25;
+/// The DREQ value for PWM Counter 2's Wrap Value
+pub const DREQ_PWM_WRAP2: u8 = 26;
+/// The DREQ value for PWM Counter 3's Wrap Value
+pub const DREQ_PWM_WRAP3: u8 = 27;
+/// The DREQ value for PWM Counter 4's Wrap Value
+pub const DREQ_PWM_WRAP4: u8 = 28;
+/// The DREQ value for PWM Counter 5's Wrap Value
+pub const DREQ_PWM_WRAP5: u8 = 29;
+/// The DREQ value for PWM Counter 6's Wrap Value
+pub const DREQ_PWM_WRAP6: u8 = 30;
+/// The DREQ value for PWM Counter 7's Wrap Value
+pub const DREQ_PWM_WRAP7: u8 = 31;
+/// The DREQ value for I2C0's TX FIFO
+pub const DREQ_I2C0_TX: u8 = 32;
+/// The DREQ value for I2C0's RX FIFO
+pub const DREQ_I2C0_RX: u8 = 33;
+/// The DREQ value for I2C1's TX FIFO
+pub const DREQ_I2C1_TX: u8 = 34;
+/// The DREQ value for I2C1's RX FIFO
+pub const DREQ_I2C1_RX: u8 = 35;
+/// The DREQ value for the ADC
+pub const DREQ_ADC: u8 = 36;
+/// The DREQ value for the XIP Streaming FIFO
+pub const DREQ_XIP_STREAM: u8 = 37;
+/// The DREQ value for the XIP SSI TX FIFO
+pub const DREQ_XIP_SSITX: u8 = 38;
+/// The DREQ value for the XIP SSI RX FIFO
+pub const DREQ_XIP_SSIRX: u8 = 39;
+/// The DREQ value for DMA pacing timer 0
+pub const DREQ_TIMER0: u8 = 0x3b;
+/// The DREQ value for DMA pacing timer 1
+pub const DREQ_TIMER1: u8 = 0x3c;
+/// The DREQ value for DMA pacing timer 2
+pub const DREQ_TIMER2: u8 = 0x3d;
+/// The DREQ value for DMA pacing timer 3
+pub const DREQ_TIMER3: u8 = 0x3e;
+
+/// Source of a DMA transfer.
+///
+/// # Safety
+///
+/// The address and count returned by [`rx_address_count`](Self::rx_address_count) must stay valid
+/// to read from for as long as the transfer runs.
+pub unsafe trait ReadTarget {
+    /// Type of the words read from the source.
+    type ReceivedWord: Word;
+
+    /// DREQ pacing the reads, or `None` to read as fast as possible.
+    fn rx_treq(&self) -> Option<u8>;
+
+    /// Address of the first word and number of words to read.
+    fn rx_address_count(&self) -> (u32, u32);
+
+    /// Whether the read address moves on after each word, which is not the case for FIFOs.
+    fn rx_increment(&self) -> bool;
+}
+
+/// Destination of a DMA transfer.
+///
+/// # Safety
+///
+/// The address and count returned by [`tx_address_count`](Self::tx_address_count) must stay valid
+/// to write to for as long as the transfer runs.
+pub unsafe trait WriteTarget {
+    /// Type of the words written to the destination.
+    type TransmittedWord: Word;
+
+    /// DREQ pacing the writes, or `None` to write as fast as possible.
+    fn tx_treq(&self) -> Option<u8>;
+
+    /// Address of the first word and number of words to write.
+    fn tx_address_count(&mut self) -> (u32, u32);
+
+    /// Whether the write address moves on after each word, which is not the case for FIFOs.
+    fn tx_increment(&self) -> bool;
+}
+
+// Safety: `ReadBuffer` guarantees that the buffer stays valid and unmoved until it is dropped.
+unsafe impl<B: ReadBuffer> ReadTarget for B
+where
+    B::Word: Word,
+{
+    type ReceivedWord = B::Word;
+
+    fn rx_treq(&self) -> Option<u8> {
+        None
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        let (ptr, len) = unsafe { self.read_buffer() };
+        (ptr as u32, len as u32)
+    }
+
+    fn rx_increment(&self) -> bool {
+        true
+    }
+}
+
+// Safety: `WriteBuffer` guarantees that the buffer stays valid and unmoved until it is dropped.
+unsafe impl<B: WriteBuffer> WriteTarget for B
+where
+    B::Word: Word,
+{
+    type TransmittedWord = B::Word;
+
+    fn tx_treq(&self) -> Option<u8> {
+        None
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        let (ptr, len) = unsafe { self.write_buffer() };
+        (ptr as u32, len as u32)
+    }
+
+    fn tx_increment(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,164 @@
+//! Single-buffered or peripheral-peripheral DMA Transfers
+//!
+//! ## Usage
+//! ```no_run
+//! use cortex_m::singleton;
+//! use rp2040_hal::{dma::{single_buffer, DMAExt}, pac};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//!
+//! let from = singleton!(: [u32; 16] = [0x1234_5678; 16]).unwrap();
+//! let to = singleton!(: [u32; 16] = [0; 16]).unwrap();
+//! let transfer = single_buffer::Config::new(dma.ch0, from, to).start();
+//! // Do other work while the transfer runs, and get the buffers back once it is done
+//! let (ch0, from, to) = transfer.wait().unwrap();
+//! ```
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use super::{
+    configure, Ctrl, Pace, Priority, ReadTarget, SingleChannel, TransferError, WriteTarget,
+};
+
+/// Configuration for single-buffered DMA transfer
+pub struct Config<CH: SingleChannel, FROM: ReadTarget, TO: WriteTarget> {
+    ch: CH,
+    from: FROM,
+    to: TO,
+    pace: Pace,
+    bswap: bool,
+    sniff: bool,
+    priority: Priority,
+}
+
+impl<CH, FROM, TO, WORD> Config<CH, FROM, TO>
+where
+    CH: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Create a new configuration for single-buffered DMA transfer
+    ///
+    /// The transfer moves as many words as the shortest of `from` and `to` holds, paced by the
+    /// DREQ of the source if it has one, or else by the DREQ of the destination.
+    pub fn new(ch: CH, from: FROM, to: TO) -> Config<CH, FROM, TO> {
+        Config {
+            ch,
+            from,
+            to,
+            pace: Pace::PreferSource,
+            bswap: false,
+            sniff: false,
+            priority: Priority::Normal,
+        }
+    }
+
+    /// Set the transfer pacing, when both the source and the destination have a DREQ, or to pace
+    /// it with another DREQ.
+    pub fn pace(&mut self, pace: Pace) {
+        self.pace = pace;
+    }
+
+    /// Reverse the order of the bytes of each word moved.
+    pub fn bswap(&mut self, bswap: bool) {
+        self.bswap = bswap;
+    }
+
+    /// Feed the data moved to the [`Sniffer`](super::Sniffer), which must be attached to the
+    /// channel.
+    pub fn sniff(&mut self, sniff: bool) {
+        self.sniff = sniff;
+    }
+
+    /// Set the priority of the channel in the DMA arbiter.
+    pub fn priority(&mut self, priority: Priority) {
+        self.priority = priority;
+    }
+
+    /// Start the DMA transfer
+    pub fn start(mut self) -> Transfer<CH, FROM, TO> {
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        configure(
+            &self.ch,
+            &self.from,
+            &mut self.to,
+            self.pace,
+            Ctrl {
+                bswap: self.bswap,
+                sniff: self.sniff,
+                high_priority: self.priority == Priority::High,
+                ..Ctrl::new::<CH, WORD>(&self.ch)
+            },
+            true,
+        );
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            to: self.to,
+        }
+    }
+}
+
+/// Instance of a single-buffered DMA transfer
+pub struct Transfer<CH: SingleChannel, FROM: ReadTarget, TO: WriteTarget> {
+    ch: CH,
+    from: FROM,
+    to: TO,
+}
+
+impl<CH, FROM, TO, WORD> Transfer<CH, FROM, TO>
+where
+    CH: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Checks whether the channel of the transfer raised DMA_IRQ_0, and clears the interrupt if
+    /// it did.
+    ///
+    /// With [`SingleChannel::listen_irq0`] called on the channel before starting the transfer,
+    /// the DMA_IRQ_0 handler can tell when the transfer is done, and [`wait`](Self::wait) for
+    /// it without blocking.
+    pub fn check_irq0(&mut self) -> bool {
+        self.ch.check_irq0()
+    }
+
+    /// Checks whether the channel of the transfer raised DMA_IRQ_1, and clears the interrupt if
+    /// it did.
+    pub fn check_irq1(&mut self) -> bool {
+        self.ch.check_irq1()
+    }
+
+    /// Check if the transfer has completed, or was halted by a bus error.
+    pub fn is_done(&self) -> bool {
+        !self.ch.ch().ch_ctrl_trig.read().busy().bit_is_set()
+    }
+
+    /// Block until the transfer is complete, returning the channel and the targets.
+    ///
+    /// If a bus error halted the transfer, they are returned in the error.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(mut self) -> Result<(CH, FROM, TO), TransferError<(CH, FROM, TO)>> {
+        while !self.is_done() {}
+
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        match self.ch.take_error() {
+            None => Ok((self.ch, self.from, self.to)),
+            Some(error) => Err(TransferError::new(error, (self.ch, self.from, self.to))),
+        }
+    }
+
+    /// Abort the transfer, returning the channel and the targets once the channel has stopped.
+    pub fn abort(mut self) -> (CH, FROM, TO) {
+        self.ch.abort();
+        self.ch.take_error();
+
+        // Make sure that memory contents reflect what the user intended.
+        compiler_fence(Ordering::SeqCst);
+
+        (self.ch, self.from, self.to)
+    }
+}
@@ -0,0 +1,528 @@
+//! Programming the external flash
+//!
+//! The code of the application is usually executed in place from the external QSPI flash, which
+//! can't be read while it is erased or programmed. These functions run the bootrom flash
+//! functions from RAM, with the interrupts of the core disabled, and restore the execute-in-place
+//! (XIP) mode of the flash once done, with a copy of the second stage bootloader (boot2) of the
+//! application. [`get_unique_id`] and [`get_jedec_id`] send their commands to the flash in the
+//! same way.
+//!
+//! The other core must not access the flash meanwhile either: if it runs, these functions park it
+//! with [`multicore::with_other_core_parked`](crate::multicore::with_other_core_parked), so it
+//! must pass the words it reads from its FIFO to
+//! [`multicore::park_on_request`](crate::multicore::park_on_request). Core 1 is only parked once
+//! spawned with [`multicore::Core::spawn`](crate::multicore::Core::spawn).
+//!
+//! ## Usage
+//! ```no_run
+//! use rp2040_hal::flash;
+//!
+//! // The last sector of a 2MB flash, which the application doesn't use
+//! const SETTINGS: u32 = 2 * 1024 * 1024 - flash::SECTOR_SIZE;
+//! let mut page = [0xff; flash::PAGE_SIZE as usize];
+//! page[..4].copy_from_slice(&1234u32.to_le_bytes());
+//!
+//! unsafe {
+//!     flash::flash_range_erase(SETTINGS, flash::SECTOR_SIZE);
+//!     flash::flash_range_program(SETTINGS, &page);
+//! }
+//! ```
+//!
+//! ## Storage
+//!
+//! [`FlashStorage`] implements the [`embedded_storage::nor_flash`] traits over a region of the
+//! flash, for the crates storing data on NOR flash:
+//! ```no_run
+//! use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+//! use rp2040_hal::flash::{FlashStorage, SECTOR_SIZE};
+//!
+//! // The last 64kB of a 2MB flash, which the application doesn't use
+//! let mut storage = unsafe { FlashStorage::new(2 * 1024 * 1024 - 16 * SECTOR_SIZE, 16 * SECTOR_SIZE) };
+//! storage.erase(0, SECTOR_SIZE).unwrap();
+//! storage.write(0, &[0x42; 256]).unwrap();
+//! let mut buf = [0; 4];
+//! storage.read(0, &mut buf).unwrap();
+//! ```
+//!
+//! ## Streaming
+//!
+//! [`FlashStream`] reads a region of the flash through the streaming FIFO of the XIP block, as
+//! the source of a DMA transfer. Unlike reads through the XIP cache, the words streamed don't
+//! evict the code and data in the cache, which suits large sequential reads such as audio samples:
+//! ```no_run
+//! use rp2040_hal::{dma::{single_buffer, DMAExt}, flash::FlashStream, pac};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//!
+//! static mut SAMPLES: [u32; 1024] = [0; 1024];
+//! // Stream the 4kB of samples at offset 1MB of the flash
+//! let stream = FlashStream::new(peripherals.XIP_CTRL, 1024 * 1024, 1024);
+//! let transfer = single_buffer::Config::new(dma.ch0, stream, unsafe { &mut SAMPLES }).start();
+//! let (ch0, stream, samples) = transfer.wait().unwrap();
+//! let xip_ctrl = stream.free();
+//! ```
+
+use core::ptr;
+
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+
+use crate::dma::{ReadTarget, DREQ_XIP_STREAM};
+use crate::multicore;
+use crate::ram_func;
+use crate::rom_data;
+use pac::XIP_CTRL;
+
+/// The address of the flash in the XIP address space
+pub const XIP_BASE: u32 = 0x1000_0000;
+
+/// The size of the smallest area of flash which can be erased
+pub const SECTOR_SIZE: u32 = 4096;
+
+/// The size of the smallest area of flash which can be programmed
+pub const PAGE_SIZE: u32 = 256;
+
+/// The size of the area of flash erased by `BLOCK_ERASE_CMD`, used to erase faster
+const BLOCK_SIZE: u32 = 65536;
+
+/// The 64kB block erase command of most flash chips
+const BLOCK_ERASE_CMD: u8 = 0xd8;
+
+/// The size of each of the four aliases of the flash in the XIP address space
+const XIP_SIZE: u32 = 16 * 1024 * 1024;
+
+/// The size of boot2, at the start of the flash
+const BOOT2_SIZE_WORDS: usize = 64;
+
+/// Copy of boot2 in RAM, to restore the XIP mode of the flash with
+static mut BOOT2_COPY: [u32; BOOT2_SIZE_WORDS] = [0; BOOT2_SIZE_WORDS];
+
+/// The bootrom functions used while the flash is not accessible, looked up beforehand
+struct RomFuncs {
+    connect_internal_flash: unsafe extern "C" fn(),
+    flash_exit_xip: unsafe extern "C" fn(),
+    flash_range_erase: unsafe extern "C" fn(u32, usize, u32, u8),
+    flash_range_program: unsafe extern "C" fn(u32, *const u8, usize),
+    flash_flush_cache: unsafe extern "C" fn(),
+}
+
+impl RomFuncs {
+    fn lookup() -> Self {
+        // Safety: the bootrom provides these functions with these signatures
+        unsafe {
+            RomFuncs {
+                connect_internal_flash: core::mem::transmute(rom_data::rom_func_lookup(*b"IF")),
+                flash_exit_xip: core::mem::transmute(rom_data::rom_func_lookup(*b"EX")),
+                flash_range_erase: core::mem::transmute(rom_data::rom_func_lookup(*b"RE")),
+                flash_range_program: core::mem::transmute(rom_data::rom_func_lookup(*b"RP")),
+                flash_flush_cache: core::mem::transmute(rom_data::rom_func_lookup(*b"FC")),
+            }
+        }
+    }
+}
+
+/// An operation on the flash, done from RAM
+struct Operation {
+    /// The offset from the start of the flash of the area to erase or program
+    addr: u32,
+    /// The number of bytes to erase, or 0
+    erase_len: u32,
+    /// The data to program, of `program_len` bytes
+    program_data: *const u8,
+    program_len: usize,
+    /// The command to send, of `cmd_len` bytes, replaced with the bytes received meanwhile
+    cmd: *mut u8,
+    cmd_len: usize,
+}
+
+impl Operation {
+    /// An operation doing nothing, to build the others from
+    const NONE: Operation = Operation {
+        addr: 0,
+        erase_len: 0,
+        program_data: ptr::null(),
+        program_len: 0,
+        cmd: ptr::null_mut(),
+        cmd_len: 0,
+    };
+}
+
+/// Erase `len` bytes of flash, starting `addr` bytes from its start.
+///
+/// # Panics
+///
+/// Panics if `addr` and `len` are not multiples of [`SECTOR_SIZE`], or if the range is past the
+/// end of the flash address space.
+///
+/// # Safety
+///
+/// Nothing may access the flash meanwhile, including DMA channels and the code running from flash
+/// on the other core if it isn't parked. The range must not hold code or data in use, such as the
+/// application itself.
+pub unsafe fn flash_range_erase(addr: u32, len: u32) {
+    assert!(
+        addr % SECTOR_SIZE == 0 && len % SECTOR_SIZE == 0,
+        "The range isn't aligned to sectors"
+    );
+    check_range(addr, len);
+    run(Operation {
+        addr,
+        erase_len: len,
+        ..Operation::NONE
+    });
+}
+
+/// Program `data` to the flash, `addr` bytes from its start.
+///
+/// The area must have been erased beforehand: programming only clears bits.
+///
+/// # Panics
+///
+/// Panics if `addr` and the length of `data` are not multiples of [`PAGE_SIZE`], if the range
+/// is past the end of the flash address space, or if `data` is in the flash itself.
+///
+/// # Safety
+///
+/// As for [`flash_range_erase`].
+pub unsafe fn flash_range_program(addr: u32, data: &[u8]) {
+    assert!(
+        addr % PAGE_SIZE == 0 && data.len() as u32 % PAGE_SIZE == 0,
+        "The range isn't aligned to pages"
+    );
+    check_range(addr, data.len() as u32);
+    let data_addr = data.as_ptr() as u32;
+    assert!(
+        !(XIP_BASE..XIP_BASE + 4 * XIP_SIZE).contains(&data_addr),
+        "The data is in the flash, which is not accessible while it is programmed"
+    );
+    run(Operation {
+        addr,
+        program_data: data.as_ptr(),
+        program_len: data.len(),
+        ..Operation::NONE
+    });
+}
+
+/// Read the 64-bit unique ID of the flash chip, with the RUID (0x4b) command.
+///
+/// The ID of the flash chip is often used as the serial number of the board, which the RP2040
+/// lacks.
+///
+/// # Safety
+///
+/// Nothing may access the flash meanwhile, as for [`flash_range_erase`].
+pub unsafe fn get_unique_id() -> [u8; 8] {
+    // The command is followed by 4 dummy bytes, and the ID
+    let mut cmd = [0u8; 1 + 4 + 8];
+    cmd[0] = 0x4b;
+    run(Operation {
+        cmd: cmd.as_mut_ptr(),
+        cmd_len: cmd.len(),
+        ..Operation::NONE
+    });
+    let mut id = [0; 8];
+    id.copy_from_slice(&cmd[5..]);
+    id
+}
+
+/// Read the JEDEC ID of the flash chip, with the JEDEC ID (0x9f) command.
+///
+/// The 24 bits of the ID are the manufacturer ID, the memory type and the capacity, from the most
+/// to the least significant byte. The capacity is usually the base 2 logarithm of the size of the
+/// flash in bytes.
+///
+/// # Safety
+///
+/// As for [`get_unique_id`].
+pub unsafe fn get_jedec_id() -> u32 {
+    let mut cmd = [0x9f, 0, 0, 0];
+    run(Operation {
+        cmd: cmd.as_mut_ptr(),
+        cmd_len: cmd.len(),
+        ..Operation::NONE
+    });
+    u32::from_be_bytes(cmd) & 0x00ff_ffff
+}
+
+fn check_range(addr: u32, len: u32) {
+    assert!(
+        addr.checked_add(len).map_or(false, |end| end <= XIP_SIZE),
+        "The range is past the end of the flash"
+    );
+}
+
+/// Run `op` from RAM, with the interrupts of this core disabled and the other core parked.
+unsafe fn run(op: Operation) {
+    let funcs = RomFuncs::lookup();
+    // boot2 can only be copied while the flash is accessible
+    let boot2 = &mut *ptr::addr_of_mut!(BOOT2_COPY);
+    ptr::copy_nonoverlapping(XIP_BASE as *const u32, boot2.as_mut_ptr(), BOOT2_SIZE_WORDS);
+    let boot2 = boot2.as_ptr();
+
+    multicore::with_other_core_parked_if_running(|| {
+        cortex_m::interrupt::free(|_| {
+            // Make sure that the data to program or send is in memory before the flash is taken
+            // away
+            core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+            run_from_ram(&op, &funcs, boot2);
+            core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+        })
+    });
+}
+
+/// Take the flash out of XIP mode, run `op`, and restore the XIP mode with boot2.
+///
+/// This must only call the bootrom functions and boot2, and access memory through the
+/// [`ram_func`] accessors, so that nothing is fetched from flash while it is not accessible.
+///
+/// # Safety
+///
+/// `boot2` must point to a copy of boot2 in RAM, and interrupts must be disabled.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn run_from_ram(op: &Operation, funcs: &RomFuncs, boot2: *const u32) {
+    (funcs.connect_internal_flash)();
+    (funcs.flash_exit_xip)();
+    if op.erase_len != 0 {
+        (funcs.flash_range_erase)(op.addr, op.erase_len as usize, BLOCK_SIZE, BLOCK_ERASE_CMD);
+    }
+    if op.program_len != 0 {
+        (funcs.flash_range_program)(op.addr, op.program_data, op.program_len);
+    }
+    if op.cmd_len != 0 {
+        do_cmd(op.cmd, op.cmd_len);
+    }
+    // Flush the XIP cache, which holds the previous contents of the flash, and release the chip
+    // select forced by the bootrom
+    (funcs.flash_flush_cache)();
+    // boot2 restores the fast XIP mode of the flash, and returns when called like this
+    let boot2: extern "C" fn() = core::mem::transmute(boot2 as usize + 1);
+    boot2();
+}
+
+/// Errors of [`FlashStorage`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The offset or length is not a multiple of the write or erase size.
+    NotAligned,
+    /// The range is past the end of the storage.
+    OutOfBounds,
+}
+
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::NotAligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+        }
+    }
+}
+
+/// A region of the flash, implementing the [`embedded_storage::nor_flash`] traits
+///
+/// It is erased in sectors of [`SECTOR_SIZE`], and programmed in pages of [`PAGE_SIZE`]. The
+/// offsets taken by the traits are relative to the start of the region.
+pub struct FlashStorage {
+    /// The offset of the region from the start of the flash
+    offset: u32,
+    capacity: u32,
+}
+
+impl FlashStorage {
+    /// Use the `capacity` bytes of flash starting `offset` bytes from its start as storage.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` and `capacity` are not multiples of [`SECTOR_SIZE`], or if the region is
+    /// past the end of the flash address space.
+    ///
+    /// # Safety
+    ///
+    /// The region must not hold code or data in use, such as the application itself, and nothing
+    /// may access the flash while it is erased or written, as for [`flash_range_erase`].
+    pub unsafe fn new(offset: u32, capacity: u32) -> Self {
+        assert!(
+            offset % SECTOR_SIZE == 0 && capacity % SECTOR_SIZE == 0,
+            "The region isn't aligned to sectors"
+        );
+        check_range(offset, capacity);
+        FlashStorage { offset, capacity }
+    }
+
+    fn check(&self, offset: u32, len: usize, align: u32) -> Result<(), Error> {
+        let len = len as u32;
+        if offset % align != 0 || len % align != 0 {
+            return Err(Error::NotAligned);
+        }
+        match offset.checked_add(len) {
+            Some(end) if end <= self.capacity => Ok(()),
+            _ => Err(Error::OutOfBounds),
+        }
+    }
+
+    /// Run `f`, keeping the other core parked across its flash operations.
+    fn with_flash<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        multicore::with_other_core_parked_if_running(f)
+    }
+}
+
+impl ErrorType for FlashStorage {
+    type Error = Error;
+}
+
+impl ReadNorFlash for FlashStorage {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.check(offset, bytes.len(), 1)?;
+        let src = (XIP_BASE + self.offset + offset) as *const u8;
+        // Safety: the range is in the flash, which is only written through `&mut self`
+        unsafe { ptr::copy_nonoverlapping(src, bytes.as_mut_ptr(), bytes.len()) };
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+}
+
+impl NorFlash for FlashStorage {
+    const WRITE_SIZE: usize = PAGE_SIZE as usize;
+
+    const ERASE_SIZE: usize = SECTOR_SIZE as usize;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let len = to.checked_sub(from).ok_or(Error::OutOfBounds)?;
+        self.check(from, len as usize, SECTOR_SIZE)?;
+        let addr = self.offset + from;
+        // Safety: the range is in the region, which the caller of `new` handed over
+        self.with_flash(|| unsafe { flash_range_erase(addr, len) });
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.check(offset, bytes.len(), PAGE_SIZE)?;
+        let addr = self.offset + offset;
+        self.with_flash(|| {
+            // `bytes` may be in the flash, so program it through a copy in RAM
+            let mut page = [0u8; PAGE_SIZE as usize];
+            for (i, chunk) in bytes.chunks(PAGE_SIZE as usize).enumerate() {
+                page.copy_from_slice(chunk);
+                // Safety: as in `erase`
+                unsafe { flash_range_program(addr + i as u32 * PAGE_SIZE, &page) };
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Send the `len` bytes at `buf` to the flash, replacing them with the bytes received meanwhile.
+///
+/// # Safety
+///
+/// As for [`run_from_ram`], which this is inlined in, and the flash must be out of XIP mode.
+/// Like it, this must only access memory through the [`ram_func`] accessors, and computes the
+/// addresses of the bytes with integer arithmetic, which is never outlined.
+#[inline(always)]
+unsafe fn do_cmd(buf: *mut u8, len: usize) {
+    // The chip select of the flash, in IO_QSPI
+    const GPIO_QSPI_SS_CTRL: *mut u32 = 0x4001_800c as _;
+    const OUTOVER_MASK: u32 = 0x3 << 8;
+    const OUTOVER_LOW: u32 = 0x2 << 8;
+    const OUTOVER_HIGH: u32 = 0x3 << 8;
+    // The registers of the SSI
+    const SSI_SR: *const u32 = 0x1800_0028 as _;
+    const SSI_DR0: *mut u32 = 0x1800_0060 as _;
+    const SR_TFNF: u32 = 1 << 1;
+    const SR_RFNE: u32 = 1 << 3;
+    // Keep room in the 16 entries of the RX FIFO for the bytes in flight
+    const MAX_IN_FLIGHT: usize = 16 - 2;
+
+    let ctrl = ram_func::read_u32(GPIO_QSPI_SS_CTRL) & !OUTOVER_MASK;
+    ram_func::write_u32(GPIO_QSPI_SS_CTRL, ctrl | OUTOVER_LOW);
+
+    let buf = buf as usize;
+    let (mut tx, mut rx) = (0, 0);
+    while rx < len {
+        let status = ram_func::read_u32(SSI_SR);
+        if status & SR_TFNF != 0 && tx < len && tx - rx < MAX_IN_FLIGHT {
+            let byte = ram_func::read_u8((buf + tx) as *const u8);
+            ram_func::write_u32(SSI_DR0, byte as u32);
+            tx += 1;
+        }
+        if status & SR_RFNE != 0 {
+            let byte = ram_func::read_u32(SSI_DR0) as u8;
+            ram_func::write_u8((buf + rx) as *mut u8, byte);
+            rx += 1;
+        }
+    }
+
+    ram_func::write_u32(GPIO_QSPI_SS_CTRL, ctrl | OUTOVER_HIGH);
+}
+
+/// The address of the streaming FIFO of the XIP block on the fast AHB-Lite bus
+const XIP_AUX_BASE: u32 = 0x5040_0000;
+
+/// A region of the flash, streamed through the streaming FIFO of the XIP block
+///
+/// It is the source of a DMA transfer of the words of the region, paced by the FIFO, and must not
+/// be erased or programmed while it is streamed.
+pub struct FlashStream {
+    xip_ctrl: XIP_CTRL,
+    words: u32,
+}
+
+impl FlashStream {
+    /// Start streaming `words` words of flash, starting `offset` bytes from its start.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is not a multiple of 4, if `words` is not less than 2^22, or if the
+    /// region is past the end of the flash address space.
+    pub fn new(xip_ctrl: XIP_CTRL, offset: u32, words: u32) -> Self {
+        assert!(offset % 4 == 0, "The offset isn't aligned to words");
+        assert!(words < 1 << 22, "STREAM_CTR holds less than 2^22 words");
+        check_range(offset, words * 4);
+        // Safety: the region is in the flash, and STREAM_CTR holds the number of words
+        xip_ctrl
+            .stream_addr
+            .write(|w| unsafe { w.bits(XIP_BASE + offset) });
+        xip_ctrl.stream_ctr.write(|w| unsafe { w.bits(words) });
+        FlashStream { xip_ctrl, words }
+    }
+
+    /// Whether all the words of the region were read from the flash.
+    ///
+    /// The last of them may still wait in the FIFO for the DMA.
+    pub fn is_done(&self) -> bool {
+        self.xip_ctrl.stream_ctr.read().bits() == 0
+    }
+
+    /// Stop streaming, and release the XIP block.
+    pub fn free(self) -> XIP_CTRL {
+        // Aborting the stream lets the words in flight reach the FIFO, which is then drained
+        self.xip_ctrl.stream_ctr.write(|w| unsafe { w.bits(0) });
+        while self.xip_ctrl.stat.read().fifo_empty().bit_is_clear() {
+            let _ = self.xip_ctrl.stream_fifo.read();
+        }
+        self.xip_ctrl
+    }
+}
+
+// Safety: the FIFO is always mapped, and only read by the DMA while the stream exists.
+unsafe impl ReadTarget for FlashStream {
+    type ReceivedWord = u32;
+
+    fn rx_treq(&self) -> Option<u8> {
+        Some(DREQ_XIP_STREAM)
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (XIP_AUX_BASE, self.words)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
+}
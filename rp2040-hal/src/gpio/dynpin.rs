@@ -76,6 +76,10 @@
 //! [`InvalidPinType`](Error::InvalidPinType).
 use super::pin::{Pin, PinId, PinMode, ValidPinMode};
 use super::reg::RegisterInterface;
+use super::{
+    InputOverride, Interrupt, InterruptOverride, OutputDriveStrength, OutputEnableOverride,
+    OutputOverride, OutputSlewRate,
+};
 use core::convert::TryFrom;
 
 #[cfg(feature = "eh1_0_alpha")]
@@ -87,7 +91,7 @@ use hal::digital::v2::{InputPin, OutputPin, StatefulOutputPin, ToggleableOutputP
 //==============================================================================
 
 /// Value-level `enum` for disabled configurations
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 #[allow(missing_docs)]
 pub enum DynDisabled {
     Floating,
@@ -97,7 +101,7 @@ pub enum DynDisabled {
 }
 
 /// Value-level `enum` for input configurations
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 #[allow(missing_docs)]
 pub enum DynInput {
     Floating,
@@ -107,7 +111,7 @@ pub enum DynInput {
 }
 
 /// Value-level `enum` for output configurations
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 #[allow(missing_docs)]
 pub enum DynOutput {
     PushPull,
@@ -115,7 +119,7 @@ pub enum DynOutput {
 }
 
 /// Value-level `enum` for output configurations
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 #[allow(missing_docs)]
 pub enum DynFunction {
     Spi,
@@ -134,7 +138,7 @@ pub enum DynFunction {
 //==============================================================================
 
 /// Value-level `enum` representing pin modes
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 #[allow(missing_docs)]
 pub enum DynPinMode {
     Disabled(DynDisabled),
@@ -175,6 +179,8 @@ pub const DYN_FLOATING_DISABLED: DynPinMode = DynPinMode::Disabled(DynDisabled::
 pub const DYN_PULL_DOWN_DISABLED: DynPinMode = DynPinMode::Disabled(DynDisabled::PullDown);
 /// Value-level variant of [`DynPinMode`] for pull-up disabled mode
 pub const DYN_PULL_UP_DISABLED: DynPinMode = DynPinMode::Disabled(DynDisabled::PullUp);
+/// Value-level variant of [`DynPinMode`] for bus keep disabled mode
+pub const DYN_BUS_KEEP_DISABLED: DynPinMode = DynPinMode::Disabled(DynDisabled::BusKeep);
 
 /// Value-level variant of [`DynPinMode`] for floating input mode
 pub const DYN_FLOATING_INPUT: DynPinMode = DynPinMode::Input(DynInput::Floating);
@@ -182,6 +188,8 @@ pub const DYN_FLOATING_INPUT: DynPinMode = DynPinMode::Input(DynInput::Floating)
 pub const DYN_PULL_DOWN_INPUT: DynPinMode = DynPinMode::Input(DynInput::PullDown);
 /// Value-level variant of [`DynPinMode`] for pull-up input mode
 pub const DYN_PULL_UP_INPUT: DynPinMode = DynPinMode::Input(DynInput::PullUp);
+/// Value-level variant of [`DynPinMode`] for bus keep input mode
+pub const DYN_BUS_KEEP_INPUT: DynPinMode = DynPinMode::Input(DynInput::BusKeep);
 
 /// Value-level variant of [`DynPinMode`] for push-pull output mode
 pub const DYN_PUSH_PULL_OUTPUT: DynPinMode = DynPinMode::Output(DynOutput::PushPull);
@@ -210,7 +218,7 @@ dyn_function!(Spi, Xip, Uart, I2C, Pwm, Pio0, Pio1, Clock, UsbAux);
 //==============================================================================
 
 /// Value-level `enum` for pin groups
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum DynGroup {
     /// .
     Bank0,
@@ -219,7 +227,7 @@ pub enum DynGroup {
 }
 
 /// Value-level `struct` representing pin IDs
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub struct DynPinId {
     /// .
     pub group: DynGroup,
@@ -321,7 +329,6 @@ impl DynPin {
     /// Convert the pin to the requested [`DynPinMode`]
     #[inline]
     pub fn try_into_mode(&mut self, mode: DynPinMode) -> Result<(), Error> {
-        // FIXME: check valid modes
         // Only modify registers if we are actually changing pin mode
         if mode.valid_for(self.regs.id) {
             if mode != self.mode {
@@ -355,6 +362,13 @@ impl DynPin {
         self.try_into_mode(DYN_PULL_UP_DISABLED).unwrap(); // always valid
     }
 
+    /// Disable the pin and set it to bus keep
+    #[inline]
+    #[allow(clippy::wrong_self_convention)] // matches pin api
+    pub fn into_bus_keep_disabled(&mut self) {
+        self.try_into_mode(DYN_BUS_KEEP_DISABLED).unwrap(); // always valid
+    }
+
     /// Configure the pin to operate as a floating input
     #[inline]
     #[allow(clippy::wrong_self_convention)] // matches pin api
@@ -376,6 +390,13 @@ impl DynPin {
         self.try_into_mode(DYN_PULL_UP_INPUT).unwrap(); // always valid
     }
 
+    /// Configure the pin to operate as a bus keep input
+    #[inline]
+    #[allow(clippy::wrong_self_convention)] // matches pin api
+    pub fn into_bus_keep_input(&mut self) {
+        self.try_into_mode(DYN_BUS_KEEP_INPUT).unwrap(); // always valid
+    }
+
     /// Configure the pin to operate as a push-pull output
     #[inline]
     #[allow(clippy::wrong_self_convention)] // matches pin api
@@ -390,6 +411,184 @@ impl DynPin {
         self.try_into_mode(DYN_READABLE_OUTPUT).unwrap(); // always valid
     }
 
+    /// Read the current drive strength of the pin.
+    #[inline]
+    pub fn get_drive_strength(&self) -> OutputDriveStrength {
+        self.regs.read_drive_strength()
+    }
+
+    /// Set the drive strength for the pin.
+    #[inline]
+    pub fn set_drive_strength(&mut self, strength: OutputDriveStrength) {
+        self.regs.write_drive_strength(strength);
+    }
+
+    /// Get the slew rate for the pin.
+    #[inline]
+    pub fn get_slew_rate(&self) -> OutputSlewRate {
+        self.regs.read_slew_rate()
+    }
+
+    /// Set the slew rate for the pin.
+    #[inline]
+    pub fn set_slew_rate(&mut self, rate: OutputSlewRate) {
+        self.regs.write_slew_rate(rate)
+    }
+
+    /// Get whether the schmitt trigger (hysteresis) is enabled for the pin.
+    #[inline]
+    pub fn get_schmitt_enabled(&self) -> bool {
+        self.regs.read_schmitt_enabled()
+    }
+
+    /// Enable or disable the schmitt trigger (hysteresis) for the pin.
+    #[inline]
+    pub fn set_schmitt_enabled(&mut self, enabled: bool) {
+        self.regs.write_schmitt_enabled(enabled)
+    }
+
+    /// Get whether the input buffer is enabled for the pin.
+    #[inline]
+    pub fn get_input_enable(&self) -> bool {
+        self.regs.read_input_enable()
+    }
+
+    /// Enable or disable the input buffer for the pin.
+    ///
+    /// The input buffer is configured as part of every mode change, so this
+    /// setting only persists until the next mode change. Disabling the input
+    /// buffer on an unused or analog pin reduces power consumption.
+    #[inline]
+    pub fn set_input_enable(&mut self, enabled: bool) {
+        self.regs.write_input_enable(enabled)
+    }
+
+    /// Get whether the input synchronizer is bypassed for the pin.
+    #[inline]
+    pub fn get_sync_bypass(&self) -> bool {
+        self.regs.read_sync_bypass()
+    }
+
+    /// Bypass the input synchronizer for the pin.
+    ///
+    /// By default, the processors sample GPIO inputs through a 2-flip-flop
+    /// synchronizer, which adds two cycles of latency. Bypassing it removes
+    /// that latency, but the input must then be synchronous to the system
+    /// clock: an asynchronous signal may be sampled while it is changing,
+    /// and the resulting metastability can propagate into software. Only
+    /// bypass pins driven by logic clocked from `clk_sys`, such as a PIO
+    /// state machine on the same chip.
+    ///
+    /// This only affects the processors' view of the pin, not peripherals.
+    #[inline]
+    pub fn set_sync_bypass(&mut self, bypass: bool) {
+        self.regs.write_sync_bypass(bypass)
+    }
+
+    /// Clear interrupt.
+    #[inline]
+    pub fn clear_interrupt(&mut self, interrupt: Interrupt) {
+        self.regs.clear_interrupt(interrupt);
+    }
+
+    /// Interrupt status.
+    #[inline]
+    pub fn interrupt_status(&self, interrupt: Interrupt) -> bool {
+        self.regs.interrupt_status(interrupt)
+    }
+
+    /// Is interrupt enabled.
+    #[inline]
+    pub fn is_interrupt_enabled(&self, interrupt: Interrupt) -> bool {
+        self.regs.is_interrupt_enabled(interrupt)
+    }
+
+    /// Enable or disable interrupt.
+    #[inline]
+    pub fn set_interrupt_enabled(&self, interrupt: Interrupt, enabled: bool) {
+        self.regs.set_interrupt_enabled(interrupt, enabled);
+    }
+
+    /// Is interrupt forced.
+    #[inline]
+    pub fn is_interrupt_forced(&self, interrupt: Interrupt) -> bool {
+        self.regs.is_interrupt_forced(interrupt)
+    }
+
+    /// Force or release interrupt.
+    #[inline]
+    pub fn set_interrupt_forced(&self, interrupt: Interrupt, forced: bool) {
+        self.regs.set_interrupt_forced(interrupt, forced);
+    }
+
+    /// Dormant wake status.
+    #[inline]
+    pub fn dormant_wake_status(&self, interrupt: Interrupt) -> bool {
+        self.regs.dormant_wake_status(interrupt)
+    }
+
+    /// Is dormant wake enabled.
+    #[inline]
+    pub fn is_dormant_wake_enabled(&self, interrupt: Interrupt) -> bool {
+        self.regs.is_dormant_wake_enabled(interrupt)
+    }
+
+    /// Enable or disable dormant wake.
+    ///
+    /// When enabled, the given edge or level on this pin wakes the chip from
+    /// dormant mode. Edge events are latched and must be cleared with
+    /// [`clear_dormant_wake`](Self::clear_dormant_wake) before going dormant
+    /// again.
+    #[inline]
+    pub fn set_dormant_wake_enabled(&self, interrupt: Interrupt, enabled: bool) {
+        self.regs.set_dormant_wake_enabled(interrupt, enabled);
+    }
+
+    /// Is dormant wake forced.
+    #[inline]
+    pub fn is_dormant_wake_forced(&self, interrupt: Interrupt) -> bool {
+        self.regs.is_dormant_wake_forced(interrupt)
+    }
+
+    /// Force or release dormant wake.
+    #[inline]
+    pub fn set_dormant_wake_forced(&self, interrupt: Interrupt, forced: bool) {
+        self.regs.set_dormant_wake_forced(interrupt, forced);
+    }
+
+    /// Clear a latched dormant wake edge.
+    ///
+    /// The raw edge status is shared with the processor interrupts, so this
+    /// also clears the corresponding edge interrupt.
+    #[inline]
+    pub fn clear_dormant_wake(&mut self, interrupt: Interrupt) {
+        self.regs.clear_interrupt(interrupt);
+    }
+
+    /// Set the interrupt override.
+    #[inline]
+    pub fn set_interrupt_override(&mut self, override_value: InterruptOverride) {
+        self.regs.set_interrupt_override(override_value);
+    }
+
+    /// Set the input override.
+    #[inline]
+    pub fn set_input_override(&mut self, override_value: InputOverride) {
+        self.regs.set_input_override(override_value);
+    }
+
+    /// Set the output enable override.
+    #[inline]
+    pub fn set_output_enable_override(&mut self, override_value: OutputEnableOverride) {
+        self.regs.set_output_enable_override(override_value);
+    }
+
+    /// Set the output override.
+    #[inline]
+    pub fn set_output_override(&mut self, override_value: OutputOverride) {
+        self.regs.set_output_override(override_value);
+    }
+
     #[inline]
     fn _read(&self) -> Result<bool, Error> {
         match self.mode {
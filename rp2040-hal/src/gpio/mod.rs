@@ -39,6 +39,9 @@ pub use pin::*;
 pub mod dynpin;
 pub use dynpin::*;
 
+pub mod pin_group;
+pub use pin_group::*;
+
 mod reg;
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
@@ -98,6 +98,7 @@ use super::{
     OutputOverride, OutputSlewRate,
 };
 use crate::gpio::reg::RegisterInterface;
+use crate::ram_func;
 use crate::typelevel::{Is, NoneT, Sealed};
 use core::convert::Infallible;
 use core::marker::PhantomData;
@@ -539,6 +540,19 @@ where
         self.into_mode()
     }
 
+    /// Configure the pin to operate as the given alternate peripheral function
+    ///
+    /// This is a shorthand for `into_mode::<Function<F>>()`, e.g.
+    /// `pin.into_function::<Uart>()`.
+    #[inline]
+    pub fn into_function<F>(self) -> Pin<I, Function<F>>
+    where
+        F: FunctionConfig,
+        Function<F>: ValidPinMode<I>,
+    {
+        self.into_mode()
+    }
+
     /// Read the current drive strength of the pin.
     #[inline]
     pub fn get_drive_strength(&self) -> OutputDriveStrength {
@@ -563,6 +577,56 @@ where
         self.regs.write_slew_rate(rate)
     }
 
+    /// Get whether the schmitt trigger (hysteresis) is enabled for the pin.
+    #[inline]
+    pub fn get_schmitt_enabled(&self) -> bool {
+        self.regs.read_schmitt_enabled()
+    }
+
+    /// Enable or disable the schmitt trigger (hysteresis) for the pin.
+    #[inline]
+    pub fn set_schmitt_enabled(&mut self, enabled: bool) {
+        self.regs.write_schmitt_enabled(enabled)
+    }
+
+    /// Get whether the input buffer is enabled for the pin.
+    #[inline]
+    pub fn get_input_enable(&self) -> bool {
+        self.regs.read_input_enable()
+    }
+
+    /// Enable or disable the input buffer for the pin.
+    ///
+    /// The input buffer is configured as part of every mode change, so this
+    /// setting only persists until the next mode change. Disabling the input
+    /// buffer on an unused or analog pin reduces power consumption.
+    #[inline]
+    pub fn set_input_enable(&mut self, enabled: bool) {
+        self.regs.write_input_enable(enabled)
+    }
+
+    /// Get whether the input synchronizer is bypassed for the pin.
+    #[inline]
+    pub fn get_sync_bypass(&self) -> bool {
+        self.regs.read_sync_bypass()
+    }
+
+    /// Bypass the input synchronizer for the pin.
+    ///
+    /// By default, the processors sample GPIO inputs through a 2-flip-flop
+    /// synchronizer, which adds two cycles of latency. Bypassing it removes
+    /// that latency, but the input must then be synchronous to the system
+    /// clock: an asynchronous signal may be sampled while it is changing,
+    /// and the resulting metastability can propagate into software. Only
+    /// bypass pins driven by logic clocked from `clk_sys`, such as a PIO
+    /// state machine on the same chip.
+    ///
+    /// This only affects the processors' view of the pin, not peripherals.
+    #[inline]
+    pub fn set_sync_bypass(&mut self, bypass: bool) {
+        self.regs.write_sync_bypass(bypass)
+    }
+
     /// Clear interrupt.
     #[inline]
     pub fn clear_interrupt(&mut self, interrupt: Interrupt) {
@@ -599,6 +663,50 @@ where
         self.regs.set_interrupt_forced(interrupt, forced);
     }
 
+    /// Dormant wake status.
+    #[inline]
+    pub fn dormant_wake_status(&self, interrupt: Interrupt) -> bool {
+        self.regs.dormant_wake_status(interrupt)
+    }
+
+    /// Is dormant wake enabled.
+    #[inline]
+    pub fn is_dormant_wake_enabled(&self, interrupt: Interrupt) -> bool {
+        self.regs.is_dormant_wake_enabled(interrupt)
+    }
+
+    /// Enable or disable dormant wake.
+    ///
+    /// When enabled, the given edge or level on this pin wakes the chip from
+    /// dormant mode. Edge events are latched and must be cleared with
+    /// [`clear_dormant_wake`](Self::clear_dormant_wake) before going dormant
+    /// again.
+    #[inline]
+    pub fn set_dormant_wake_enabled(&self, interrupt: Interrupt, enabled: bool) {
+        self.regs.set_dormant_wake_enabled(interrupt, enabled);
+    }
+
+    /// Is dormant wake forced.
+    #[inline]
+    pub fn is_dormant_wake_forced(&self, interrupt: Interrupt) -> bool {
+        self.regs.is_dormant_wake_forced(interrupt)
+    }
+
+    /// Force or release dormant wake.
+    #[inline]
+    pub fn set_dormant_wake_forced(&self, interrupt: Interrupt, forced: bool) {
+        self.regs.set_dormant_wake_forced(interrupt, forced);
+    }
+
+    /// Clear a latched dormant wake edge.
+    ///
+    /// The raw edge status is shared with the processor interrupts, so this
+    /// also clears the corresponding edge interrupt.
+    #[inline]
+    pub fn clear_dormant_wake(&mut self, interrupt: Interrupt) {
+        self.regs.clear_interrupt(interrupt);
+    }
+
     /// Set the interrupt override.
     #[inline]
     pub fn set_interrupt_override(&mut self, override_value: InterruptOverride) {
@@ -618,6 +726,19 @@ where
     }
 
     /// Set the output override.
+    ///
+    /// This is applied after the peripheral selected by the pin function, so it
+    /// can be used to, for example, invert the TX line of a UART for RS-232
+    /// style signalling without external hardware:
+    ///
+    /// ```no_run
+    /// # use rp2040_hal::{pac, gpio::{Pins, OutputOverride}, sio::Sio};
+    /// # let mut peripherals = pac::Peripherals::take().unwrap();
+    /// # let sio = Sio::new(peripherals.SIO);
+    /// # let pins = Pins::new(peripherals.IO_BANK0,peripherals.PADS_BANK0,sio.gpio_bank0, &mut peripherals.RESETS);
+    /// let mut tx = pins.gpio0.into_mode::<rp2040_hal::gpio::FunctionUart>();
+    /// tx.set_output_override(OutputOverride::Invert);
+    /// ```
     #[inline]
     pub fn set_output_override(&mut self, override_value: OutputOverride) {
         self.regs.set_output_override(override_value);
@@ -931,7 +1052,7 @@ where
 //==============================================================================
 
 macro_rules! gpio {
-    ($Group:ident, [ $($Func:ident),+ ], [
+    ($Group:ident, $reset_bank:literal, [ $($Func:ident),+ ], [
         $($PXi:ident: ($i:expr, $is:expr, $reset:ident $(, [ $($PinFunc:ident),+ ])? )),+
     ]) => {
         $crate::paste::paste! {
@@ -947,7 +1068,7 @@ macro_rules! gpio {
 
                 // FIXME: Somehow just import what we need
                 #[allow(unused_imports)]
-                use super::{PullDownDisabled,PullUpDisabled,FloatingDisabled,BusKeepDisabled};
+                use super::{PullDownDisabled,PullUpDisabled,FloatingDisabled,BusKeepDisabled,FunctionXip};
 
                 use super::{Pin,PinId};
                 use crate::resets::SubsystemReset;
@@ -973,11 +1094,13 @@ macro_rules! gpio {
                 impl Pins {
                     /// Take ownership of the PAC peripherals and SIO slice and split it into discrete [`Pin`]s
                     pub fn new(io : [<IO_ $Group:upper>], pads: [<PADS_ $Group:upper>], sio: [<SioGpio $Group>], reset : &mut pac::RESETS) -> Self {
-                        pads.reset_bring_down(reset);
-                        io.reset_bring_down(reset);
+                        if $reset_bank {
+                            pads.reset_bring_down(reset);
+                            io.reset_bring_down(reset);
 
-                        io.reset_bring_up(reset);
-                        pads.reset_bring_up(reset);
+                            io.reset_bring_up(reset);
+                            pads.reset_bring_up(reset);
+                        }
                         unsafe {
                             Self {
                                 _io: io,
@@ -998,7 +1121,7 @@ macro_rules! gpio {
 }
 
 gpio!(
-    Bank0, [ Spi, Uart, I2C, Pwm, Pio0, Pio1, UsbAux ], [
+    Bank0, true, [ Spi, Uart, I2C, Pwm, Pio0, Pio1, UsbAux ], [
         Gpio0: (0, "0", PullDownDisabled),
         Gpio1: (1, "1", PullDownDisabled),
         Gpio2: (2, "2", PullDownDisabled),
@@ -1034,17 +1157,75 @@ gpio!(
 
 pub use bank0::Pins; // this is probably the default everyone is going to want
 
+// The QSPI bank is not reset, as the processor is most likely executing code
+// from flash through it. The pins are handed over in the state boot2 leaves
+// them in, i.e. connected to the XIP peripheral.
 gpio!(
-    Qspi, [ Xip ], [
-        Sck: (0, "sck", PullDownDisabled),
-        Cs: (1, "cs", PullUpDisabled),
-        Sd0: (2, "sd0", FloatingDisabled),
-        Sd1: (3, "sd1", FloatingDisabled),
-        Sd2: (4, "sd2", FloatingDisabled),
-        Sd3: (5, "sd3", FloatingDisabled)
+    Qspi, false, [ Xip ], [
+        Sck: (0, "sck", FunctionXip),
+        Cs: (1, "cs", FunctionXip),
+        Sd0: (2, "sd0", FunctionXip),
+        Sd1: (3, "sd1", FunctionXip),
+        Sd2: (4, "sd2", FunctionXip),
+        Sd3: (5, "sd3", FunctionXip)
     ]
 );
 
+//==============================================================================
+//  BOOTSEL button
+//==============================================================================
+
+impl<M: PinMode + ValidPinMode<qspi::Cs>> Pin<qspi::Cs, M> {
+    /// Read the state of the BOOTSEL button, as found on boards such as the
+    /// Raspberry Pi Pico.
+    ///
+    /// The button is connected to the flash chip select line, so reading it
+    /// requires the chip select to be briefly released while no code is being
+    /// fetched from flash. Interrupts are disabled for the duration of the
+    /// check and the sequence itself runs from RAM.
+    ///
+    /// The other core must not be executing from flash (or touching the QSPI
+    /// bank) while this is running.
+    pub fn is_bootsel_pressed(&mut self) -> bool {
+        let ctrl = unsafe { &(*pac::IO_QSPI::ptr()) }
+            .gpio_qspiss
+            .gpio_ctrl
+            .as_ptr();
+        let gpio_hi_in = unsafe { &(*pac::SIO::ptr()) }.gpio_hi_in.as_ptr();
+        cortex_m::interrupt::free(|_| unsafe { read_bootsel_from_ram(ctrl, gpio_hi_in) })
+    }
+}
+
+/// Release the flash chip select and sample it.
+///
+/// This must only touch registers through the accessors of [`ram_func`], so
+/// that nothing is fetched from flash while chip select is overridden.
+///
+/// # Safety
+///
+/// `ctrl` must point to the `GPIO_QSPI_SS_CTRL` register, `gpio_hi_in` to the
+/// SIO `GPIO_HI_IN` register, and interrupts must be disabled.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn read_bootsel_from_ram(ctrl: *mut u32, gpio_hi_in: *const u32) -> bool {
+    const OEOVER_SHIFT: u32 = 12;
+    const OEOVER_MASK: u32 = 0b11 << OEOVER_SHIFT;
+    const OEOVER_DISABLE: u32 = (OutputEnableOverride::Disable as u32) << OEOVER_SHIFT;
+    const CS_MASK: u32 = 1 << (qspi::Cs::DYN.num);
+
+    let original = ram_func::read_u32(ctrl);
+    ram_func::write_u32(ctrl, (original & !OEOVER_MASK) | OEOVER_DISABLE);
+
+    // Give the pull-up time to charge the line
+    ram_func::delay(1000);
+
+    // The button pulls chip select low when pressed
+    let pressed = ram_func::read_u32(gpio_hi_in) & CS_MASK == 0;
+
+    ram_func::write_u32(ctrl, original);
+    pressed
+}
+
 //==============================================================================
 //  bsp_pins
 //==============================================================================
@@ -1060,8 +1241,32 @@ gpio!(
 /// When specifying pin aliases, be sure to use a [`PinMode`]. See
 /// [here](self#types) for a list of the available [`PinMode`] type aliases.
 ///
+/// Each alias generates a type alias for the [`Pin`] in the given mode, along
+/// with `<ALIAS>_ID` and `<ALIAS>_MODE` constants for use with
+/// [`DynPin`](super::DynPin). A pin moved out of the new `Pins` struct can be
+/// converted to its alias with [`into_mode`](Pin::into_mode), as the target
+/// type is inferred.
+///
 /// # Example
-/// ...
+/// ```ignore
+/// rp2040_hal::bsp_pins!(
+///     Gpio0 {
+///         /// UART transmit pin
+///         name: tx,
+///         aliases: { FunctionUart: UartTx }
+///     },
+///     Gpio1 {
+///         name: rx,
+///         aliases: { FunctionUart: UartRx }
+///     },
+///     Gpio25 { name: led },
+/// );
+///
+/// let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+/// let tx: UartTx = pins.tx.into_mode();
+/// let rx: UartRx = pins.rx.into_mode();
+/// let led = pins.led.into_push_pull_output();
+/// ```
 #[macro_export]
 macro_rules! bsp_pins {
     (
@@ -1100,8 +1305,8 @@ macro_rules! bsp_pins {
             }
 
             impl Pins {
-                /// Take ownership of the PAC [`PORT`] and split it into
-                /// discrete [`Pin`]s.
+                /// Take ownership of the PAC peripherals and SIO slice and
+                /// split it into discrete [`Pin`]s.
                 ///
                 /// This struct serves as a replacement for the HAL [`Pins`]
                 /// struct. It is intended to provide more meaningful names for
@@ -0,0 +1,117 @@
+//! # Groups of output pins driven together
+//!
+//! Writing to several [`Pin`]s one after another introduces skew between
+//! them, which is a problem for parallel buses such as 8080-style LCD
+//! interfaces or resistor ladder DACs. A [`PinGroup`] collects several bank 0
+//! output pins and drives all of them with a single masked SIO write, so every
+//! pin in the group changes on the same clock cycle.
+//!
+//! Values are expressed in GPIO bit positions, i.e. bit `n` of the value
+//! corresponds to `GPIOn`. Bits for pins which are not part of the group are
+//! ignored.
+//!
+//! ```no_run
+//! # use rp2040_hal::{pac, gpio::{Pins, PinGroup}, sio::Sio};
+//! # let mut peripherals = pac::Peripherals::take().unwrap();
+//! # let sio = Sio::new(peripherals.SIO);
+//! # let pins = Pins::new(peripherals.IO_BANK0,peripherals.PADS_BANK0,sio.gpio_bank0, &mut peripherals.RESETS);
+//! let mut group = PinGroup::new()
+//!     .add_pin(pins.gpio2.into_push_pull_output())
+//!     .add_pin(pins.gpio3.into_push_pull_output())
+//!     .add_pin(pins.gpio4.into_push_pull_output());
+//! // Drive GPIO2 and GPIO4 high and GPIO3 low, all at once
+//! group.write(0b10100);
+//! ```
+use super::pin::{bank0::BankPinId, Output, OutputConfig, Pin, PinId};
+use crate::pac;
+
+/// A set of bank 0 output pins which are written together
+///
+/// The pins added to the group are consumed. Pins are zero-sized, so nothing
+/// but the mask of the pins is stored.
+pub struct PinGroup {
+    mask: u32,
+}
+
+impl PinGroup {
+    /// Create an empty [`PinGroup`]
+    #[inline]
+    pub fn new() -> Self {
+        PinGroup { mask: 0 }
+    }
+
+    /// Add an output pin to the group
+    #[inline]
+    pub fn add_pin<I, C>(self, _pin: Pin<I, Output<C>>) -> Self
+    where
+        I: PinId + BankPinId,
+        C: OutputConfig,
+    {
+        PinGroup {
+            mask: self.mask | (1 << I::DYN.num),
+        }
+    }
+
+    /// Return the mask of the pins in this group, in GPIO bit positions
+    #[inline]
+    pub fn mask(&self) -> u32 {
+        self.mask
+    }
+
+    /// Drive every pin in the group high
+    #[inline]
+    pub fn set_high(&mut self) {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.gpio_out_set.write(|w| unsafe { w.bits(self.mask) });
+    }
+
+    /// Drive every pin in the group low
+    #[inline]
+    pub fn set_low(&mut self) {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.gpio_out_clr.write(|w| unsafe { w.bits(self.mask) });
+    }
+
+    /// Toggle every pin in the group
+    #[inline]
+    pub fn toggle(&mut self) {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.gpio_out_xor.write(|w| unsafe { w.bits(self.mask) });
+    }
+
+    /// Drive the pins in the group to the levels given by `value`
+    ///
+    /// All pins change on the same cycle: the difference between the current
+    /// and requested levels is applied with a single write to the SIO XOR
+    /// alias.
+    #[inline]
+    pub fn write(&mut self, value: u32) {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        let diff = (sio.gpio_out.read().bits() ^ value) & self.mask;
+        sio.gpio_out_xor.write(|w| unsafe { w.bits(diff) });
+    }
+
+    /// Read back the levels the pins in the group are being driven to
+    #[inline]
+    pub fn read_out(&self) -> u32 {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.gpio_out.read().bits() & self.mask
+    }
+
+    /// Read the input levels of the pins in the group
+    ///
+    /// This only reflects the pad state if the pins were configured as
+    /// [`Readable`](super::Readable) outputs.
+    #[inline]
+    pub fn read(&self) -> u32 {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.gpio_in.read().bits() & self.mask
+    }
+}
+
+impl Default for PinGroup {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
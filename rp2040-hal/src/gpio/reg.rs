@@ -7,6 +7,7 @@ use super::{
 use crate::atomic_register_access::{write_bitmask_clear, write_bitmask_set};
 use crate::gpio::dynpin::{DynDisabled, DynFunction, DynInput, DynOutput, DynPinMode};
 use crate::pac;
+use core::ptr::read_volatile;
 
 //==============================================================================
 //  ModeFields
@@ -253,6 +254,79 @@ pub(super) unsafe trait RegisterInterface {
         };
     }
 
+    #[inline]
+    fn read_schmitt_enabled(&self) -> bool {
+        let num = self.id().num as usize;
+        match self.id().group {
+            DynGroup::Bank0 => unsafe { &(*pac::PADS_BANK0::ptr()) }.gpio[num]
+                .read()
+                .schmitt()
+                .bit_is_set(),
+            DynGroup::Qspi => qspi_read_schmitt(num),
+        }
+    }
+
+    #[inline]
+    fn write_schmitt_enabled(&self, enabled: bool) {
+        let num = self.id().num as usize;
+        match self.id().group {
+            DynGroup::Bank0 => unsafe { &(*pac::PADS_BANK0::ptr()) }.gpio[num]
+                .modify(|_, w| w.schmitt().bit(enabled)),
+            DynGroup::Qspi => qspi_write_schmitt(num, enabled),
+        };
+    }
+
+    #[inline]
+    fn read_input_enable(&self) -> bool {
+        let num = self.id().num as usize;
+        match self.id().group {
+            DynGroup::Bank0 => unsafe { &(*pac::PADS_BANK0::ptr()) }.gpio[num]
+                .read()
+                .ie()
+                .bit_is_set(),
+            DynGroup::Qspi => qspi_read_input_enable(num),
+        }
+    }
+
+    #[inline]
+    fn write_input_enable(&self, enabled: bool) {
+        let num = self.id().num as usize;
+        match self.id().group {
+            DynGroup::Bank0 => {
+                unsafe { &(*pac::PADS_BANK0::ptr()) }.gpio[num].modify(|_, w| w.ie().bit(enabled))
+            }
+            DynGroup::Qspi => qspi_write_input_enable(num, enabled),
+        };
+    }
+
+    #[inline]
+    fn read_sync_bypass(&self) -> bool {
+        let mask = self.mask_32();
+        let syscfg = unsafe { &(*pac::SYSCFG::ptr()) };
+        (match self.id().group {
+            DynGroup::Bank0 => syscfg.proc_in_sync_bypass.read().bits(),
+            DynGroup::Qspi => syscfg.proc_in_sync_bypass_hi.read().bits(),
+        }) & mask
+            != 0
+    }
+
+    #[inline]
+    fn write_sync_bypass(&self, bypass: bool) {
+        let mask = self.mask_32();
+        let syscfg = unsafe { &(*pac::SYSCFG::ptr()) };
+        let reg = match self.id().group {
+            DynGroup::Bank0 => syscfg.proc_in_sync_bypass.as_ptr(),
+            DynGroup::Qspi => syscfg.proc_in_sync_bypass_hi.as_ptr(),
+        };
+        unsafe {
+            if bypass {
+                write_bitmask_set(reg, mask);
+            } else {
+                write_bitmask_clear(reg, mask);
+            }
+        }
+    }
+
     // We have to duplicate code, maybe a fix in the HAL layer can prevent this
     #[inline]
     fn do_change_mode(&self, mode: DynPinMode) {
@@ -267,13 +341,19 @@ pub(super) unsafe trait RegisterInterface {
     #[inline]
     fn clear_interrupt(&self, interrupt: Interrupt) {
         let num = self.id().num as usize;
+        // There are four bits for each GPIO pin (one for each enumerator
+        // in the `Interrupt` enum). There are therefore eight pins per
+        // 32-bit register, and four registers in total for bank 0.
+        let bit_in_reg = num % 8 * 4 + interrupt as usize;
         unsafe {
-            let io = &(*pac::IO_BANK0::ptr());
-            // There are four bits for each GPIO pin (one for each enumerator
-            // in the `Interrupt` enum). There are therefore eight pins per
-            // 32-bit register, and four registers in total.
-            let bit_in_reg = num % 8 * 4 + interrupt as usize;
-            io.intr[num >> 3].write(|w| w.bits(1 << bit_in_reg));
+            match self.id().group {
+                DynGroup::Bank0 => {
+                    (*pac::IO_BANK0::ptr()).intr[num >> 3].write(|w| w.bits(1 << bit_in_reg))
+                }
+                DynGroup::Qspi => (*pac::IO_QSPI::ptr())
+                    .intr
+                    .write(|w| w.bits(1 << bit_in_reg)),
+            }
         }
     }
 
@@ -281,56 +361,27 @@ pub(super) unsafe trait RegisterInterface {
     #[inline]
     fn interrupt_status(&self, interrupt: Interrupt) -> bool {
         let num = self.id().num as usize;
-        unsafe {
-            let io = &(*pac::IO_BANK0::ptr());
-            let cpuid = *(pac::SIO::ptr() as *const u32);
-            // There are four bits for each GPIO pin (one for each enumerator
-            // in the `Interrupt` enum). There are therefore eight pins per
-            // 32-bit register, and four registers per CPU.
-            let bit_in_reg = ((num % 8) * 4) + (interrupt as usize);
-            if cpuid == 0 {
-                (io.proc0_ints[num >> 3].read().bits() & (1 << bit_in_reg)) != 0
-            } else {
-                (io.proc1_ints[num >> 3].read().bits() & (1 << bit_in_reg)) != 0
-            }
-        }
+        let reg = proc_irq_reg(self.id(), IrqReg::Status);
+        let bit_in_reg = num % 8 * 4 + interrupt as usize;
+        (unsafe { read_volatile(reg) } & (1 << bit_in_reg)) != 0
     }
 
     /// Is interrupt enabled.
     #[inline]
     fn is_interrupt_enabled(&self, interrupt: Interrupt) -> bool {
         let num = self.id().num as usize;
-        unsafe {
-            let io = &(*pac::IO_BANK0::ptr());
-            let cpuid = *(pac::SIO::ptr() as *const u32);
-            // There are four bits for each GPIO pin (one for each enumerator
-            // in the `Interrupt` enum). There are therefore eight pins per
-            // 32-bit register, and four registers per CPU.
-            let bit_in_reg = num % 8 * 4 + interrupt as usize;
-            if cpuid == 0 {
-                (io.proc0_inte[num >> 3].read().bits() & (1 << bit_in_reg)) != 0
-            } else {
-                (io.proc1_inte[num >> 3].read().bits() & (1 << bit_in_reg)) != 0
-            }
-        }
+        let reg = proc_irq_reg(self.id(), IrqReg::Enable);
+        let bit_in_reg = num % 8 * 4 + interrupt as usize;
+        (unsafe { read_volatile(reg) } & (1 << bit_in_reg)) != 0
     }
 
     /// Enable or disable interrupt.
     #[inline]
     fn set_interrupt_enabled(&self, interrupt: Interrupt, enabled: bool) {
         let num = self.id().num as usize;
+        let reg = proc_irq_reg(self.id(), IrqReg::Enable);
+        let bit_in_reg = num % 8 * 4 + interrupt as usize;
         unsafe {
-            let cpuid = *(pac::SIO::ptr() as *const u32);
-            let io = &(*pac::IO_BANK0::ptr());
-            // There are four bits for each GPIO pin (one for each enumerator
-            // in the `Interrupt` enum). There are therefore eight pins per
-            // 32-bit register, and four registers per CPU.
-            let reg = if cpuid == 0 {
-                io.proc0_inte[num >> 3].as_ptr()
-            } else {
-                io.proc1_inte[num >> 3].as_ptr()
-            };
-            let bit_in_reg = num % 8 * 4 + interrupt as usize;
             if enabled {
                 write_bitmask_set(reg, 1 << bit_in_reg);
             } else {
@@ -343,37 +394,75 @@ pub(super) unsafe trait RegisterInterface {
     #[inline]
     fn is_interrupt_forced(&self, interrupt: Interrupt) -> bool {
         let num = self.id().num as usize;
+        let reg = proc_irq_reg(self.id(), IrqReg::Force);
+        let bit_in_reg = num % 8 * 4 + interrupt as usize;
+        (unsafe { read_volatile(reg) } & (1 << bit_in_reg)) != 0
+    }
+
+    /// Force or release interrupt.
+    #[inline]
+    fn set_interrupt_forced(&self, interrupt: Interrupt, forced: bool) {
+        let num = self.id().num as usize;
+        let reg = proc_irq_reg(self.id(), IrqReg::Force);
+        let bit_in_reg = num % 8 * 4 + interrupt as usize;
         unsafe {
-            let cpuid = *(pac::SIO::ptr() as *const u32);
-            let io = &(*pac::IO_BANK0::ptr());
-            // There are four bits for each GPIO pin (one for each enumerator
-            // in the `Interrupt` enum). There are therefore eight pins per
-            // 32-bit register, and four registers per CPU.
-            let bit_in_reg = num % 8 * 4 + interrupt as usize;
-            if cpuid == 0 {
-                (io.proc0_intf[num >> 3].read().bits() & (1 << bit_in_reg)) != 0
+            if forced {
+                write_bitmask_set(reg, 1 << bit_in_reg);
             } else {
-                (io.proc1_intf[num >> 3].read().bits() & (1 << bit_in_reg)) != 0
+                write_bitmask_clear(reg, 1 << bit_in_reg);
             }
         }
     }
 
-    /// Force or release interrupt.
+    /// Dormant wake status.
     #[inline]
-    fn set_interrupt_forced(&self, interrupt: Interrupt, forced: bool) {
+    fn dormant_wake_status(&self, interrupt: Interrupt) -> bool {
+        let num = self.id().num as usize;
+        let reg = proc_irq_reg(self.id(), IrqReg::DormantWakeStatus);
+        let bit_in_reg = num % 8 * 4 + interrupt as usize;
+        (unsafe { read_volatile(reg) } & (1 << bit_in_reg)) != 0
+    }
+
+    /// Is dormant wake enabled.
+    #[inline]
+    fn is_dormant_wake_enabled(&self, interrupt: Interrupt) -> bool {
         let num = self.id().num as usize;
+        let reg = proc_irq_reg(self.id(), IrqReg::DormantWakeEnable);
+        let bit_in_reg = num % 8 * 4 + interrupt as usize;
+        (unsafe { read_volatile(reg) } & (1 << bit_in_reg)) != 0
+    }
+
+    /// Enable or disable dormant wake.
+    #[inline]
+    fn set_dormant_wake_enabled(&self, interrupt: Interrupt, enabled: bool) {
+        let num = self.id().num as usize;
+        let reg = proc_irq_reg(self.id(), IrqReg::DormantWakeEnable);
+        let bit_in_reg = num % 8 * 4 + interrupt as usize;
         unsafe {
-            let cpuid = *(pac::SIO::ptr() as *const u32);
-            let io = &(*pac::IO_BANK0::ptr());
-            // There are four bits for each GPIO pin (one for each enumerator
-            // in the `Interrupt` enum). There are therefore eight pins per
-            // 32-bit register, and four registers per CPU.
-            let reg = if cpuid == 0 {
-                io.proc0_intf[num >> 3].as_ptr()
+            if enabled {
+                write_bitmask_set(reg, 1 << bit_in_reg);
             } else {
-                io.proc1_intf[num >> 3].as_ptr()
-            };
-            let bit_in_reg = num % 8 * 4 + interrupt as usize;
+                write_bitmask_clear(reg, 1 << bit_in_reg);
+            }
+        }
+    }
+
+    /// Is dormant wake forced.
+    #[inline]
+    fn is_dormant_wake_forced(&self, interrupt: Interrupt) -> bool {
+        let num = self.id().num as usize;
+        let reg = proc_irq_reg(self.id(), IrqReg::DormantWakeForce);
+        let bit_in_reg = num % 8 * 4 + interrupt as usize;
+        (unsafe { read_volatile(reg) } & (1 << bit_in_reg)) != 0
+    }
+
+    /// Force or release dormant wake.
+    #[inline]
+    fn set_dormant_wake_forced(&self, interrupt: Interrupt, forced: bool) {
+        let num = self.id().num as usize;
+        let reg = proc_irq_reg(self.id(), IrqReg::DormantWakeForce);
+        let bit_in_reg = num % 8 * 4 + interrupt as usize;
+        unsafe {
             if forced {
                 write_bitmask_set(reg, 1 << bit_in_reg);
             } else {
@@ -386,36 +475,105 @@ pub(super) unsafe trait RegisterInterface {
     #[inline]
     fn set_interrupt_override(&self, override_value: InterruptOverride) {
         let num = self.id().num as usize;
-        unsafe { &(*pac::IO_BANK0::ptr()) }.gpio[num]
-            .gpio_ctrl
-            .modify(|_, w| w.irqover().bits(override_value as u8));
+        match self.id().group {
+            DynGroup::Bank0 => unsafe { &(*pac::IO_BANK0::ptr()) }.gpio[num]
+                .gpio_ctrl
+                .modify(|_, w| w.irqover().bits(override_value as u8)),
+            DynGroup::Qspi => qspi_write_irqover(num, override_value as u8),
+        };
     }
 
     /// Set the input override.
     #[inline]
     fn set_input_override(&self, override_value: InputOverride) {
         let num = self.id().num as usize;
-        unsafe { &(*pac::IO_BANK0::ptr()) }.gpio[num]
-            .gpio_ctrl
-            .modify(|_, w| w.inover().bits(override_value as u8));
+        match self.id().group {
+            DynGroup::Bank0 => unsafe { &(*pac::IO_BANK0::ptr()) }.gpio[num]
+                .gpio_ctrl
+                .modify(|_, w| w.inover().bits(override_value as u8)),
+            DynGroup::Qspi => qspi_write_inover(num, override_value as u8),
+        };
     }
 
     /// Set the output enable override.
     #[inline]
     fn set_output_enable_override(&self, override_value: OutputEnableOverride) {
         let num = self.id().num as usize;
-        unsafe { &(*pac::IO_BANK0::ptr()) }.gpio[num]
-            .gpio_ctrl
-            .modify(|_, w| w.oeover().bits(override_value as u8));
+        match self.id().group {
+            DynGroup::Bank0 => unsafe { &(*pac::IO_BANK0::ptr()) }.gpio[num]
+                .gpio_ctrl
+                .modify(|_, w| w.oeover().bits(override_value as u8)),
+            DynGroup::Qspi => qspi_write_oeover(num, override_value as u8),
+        };
     }
 
     /// Set the output override.
     #[inline]
     fn set_output_override(&self, override_value: OutputOverride) {
         let num = self.id().num as usize;
-        unsafe { &(*pac::IO_BANK0::ptr()) }.gpio[num]
-            .gpio_ctrl
-            .modify(|_, w| w.outover().bits(override_value as u8));
+        match self.id().group {
+            DynGroup::Bank0 => unsafe { &(*pac::IO_BANK0::ptr()) }.gpio[num]
+                .gpio_ctrl
+                .modify(|_, w| w.outover().bits(override_value as u8)),
+            DynGroup::Qspi => qspi_write_outover(num, override_value as u8),
+        };
+    }
+}
+
+/// Interrupt registers of an IO bank
+#[derive(Clone, Copy)]
+enum IrqReg {
+    Enable,
+    Force,
+    Status,
+    DormantWakeEnable,
+    DormantWakeForce,
+    DormantWakeStatus,
+}
+
+/// Return a pointer to the register of the given kind which holds the
+/// interrupt bits of the pin `id`.
+///
+/// The processor interrupt registers are those of the current core.
+#[inline]
+fn proc_irq_reg(id: DynPinId, kind: IrqReg) -> *mut u32 {
+    let num = id.num as usize;
+    unsafe {
+        let cpuid = *(pac::SIO::ptr() as *const u32);
+        match id.group {
+            DynGroup::Bank0 => {
+                // There are four bits for each GPIO pin (one for each enumerator
+                // in the `Interrupt` enum). There are therefore eight pins per
+                // 32-bit register, and four registers per CPU.
+                let io = &(*pac::IO_BANK0::ptr());
+                match (cpuid, kind) {
+                    (_, IrqReg::DormantWakeEnable) => io.dormant_wake_inte[num >> 3].as_ptr(),
+                    (_, IrqReg::DormantWakeForce) => io.dormant_wake_intf[num >> 3].as_ptr(),
+                    (_, IrqReg::DormantWakeStatus) => io.dormant_wake_ints[num >> 3].as_ptr(),
+                    (0, IrqReg::Enable) => io.proc0_inte[num >> 3].as_ptr(),
+                    (0, IrqReg::Force) => io.proc0_intf[num >> 3].as_ptr(),
+                    (0, IrqReg::Status) => io.proc0_ints[num >> 3].as_ptr(),
+                    (_, IrqReg::Enable) => io.proc1_inte[num >> 3].as_ptr(),
+                    (_, IrqReg::Force) => io.proc1_intf[num >> 3].as_ptr(),
+                    (_, IrqReg::Status) => io.proc1_ints[num >> 3].as_ptr(),
+                }
+            }
+            DynGroup::Qspi => {
+                // Only six pins, so a single register per CPU is enough.
+                let io = &(*pac::IO_QSPI::ptr());
+                match (cpuid, kind) {
+                    (_, IrqReg::DormantWakeEnable) => io.dormant_wake_inte.as_ptr(),
+                    (_, IrqReg::DormantWakeForce) => io.dormant_wake_intf.as_ptr(),
+                    (_, IrqReg::DormantWakeStatus) => io.dormant_wake_ints.as_ptr(),
+                    (0, IrqReg::Enable) => io.proc0_inte.as_ptr(),
+                    (0, IrqReg::Force) => io.proc0_intf.as_ptr(),
+                    (0, IrqReg::Status) => io.proc0_ints.as_ptr(),
+                    (_, IrqReg::Enable) => io.proc1_inte.as_ptr(),
+                    (_, IrqReg::Force) => io.proc1_intf.as_ptr(),
+                    (_, IrqReg::Status) => io.proc1_ints.as_ptr(),
+                }
+            }
+        }
     }
 }
 
@@ -490,6 +648,70 @@ macro_rules! qspi_bits {
                 }
             }
 
+            #[inline]
+            fn qspi_read_schmitt(num: usize) -> bool {
+                match num {
+                    $($num => unsafe { &(*pac::PADS_QSPI::ptr()).[<gpio_qspi_ $suffix>] }.read().schmitt().bit_is_set(), )+
+                    _ => unreachable!("invalid ID for QSPI pin")
+                }
+            }
+
+            #[inline]
+            fn qspi_write_schmitt(num: usize, enabled : bool) {
+                match num {
+                    $($num => unsafe { &(*pac::PADS_QSPI::ptr()).[<gpio_qspi_ $suffix>] }.modify(|_,w| w.schmitt().bit(enabled) ), )+
+                    _ => unreachable!("invalid ID for QSPI pin")
+                }
+            }
+
+            #[inline]
+            fn qspi_read_input_enable(num: usize) -> bool {
+                match num {
+                    $($num => unsafe { &(*pac::PADS_QSPI::ptr()).[<gpio_qspi_ $suffix>] }.read().ie().bit_is_set(), )+
+                    _ => unreachable!("invalid ID for QSPI pin")
+                }
+            }
+
+            #[inline]
+            fn qspi_write_input_enable(num: usize, enabled : bool) {
+                match num {
+                    $($num => unsafe { &(*pac::PADS_QSPI::ptr()).[<gpio_qspi_ $suffix>] }.modify(|_,w| w.ie().bit(enabled) ), )+
+                    _ => unreachable!("invalid ID for QSPI pin")
+                }
+            }
+
+            #[inline]
+            fn qspi_write_irqover(num: usize, val : u8) {
+                match num {
+                    $($num => unsafe { &(*pac::IO_QSPI::ptr()).[<gpio_qspi $suffix>] }.gpio_ctrl.modify(|_,w| w.irqover().bits(val) ), )+
+                    _ => unreachable!("invalid ID for QSPI pin")
+                }
+            }
+
+            #[inline]
+            fn qspi_write_inover(num: usize, val : u8) {
+                match num {
+                    $($num => unsafe { &(*pac::IO_QSPI::ptr()).[<gpio_qspi $suffix>] }.gpio_ctrl.modify(|_,w| w.inover().bits(val) ), )+
+                    _ => unreachable!("invalid ID for QSPI pin")
+                }
+            }
+
+            #[inline]
+            fn qspi_write_oeover(num: usize, val : u8) {
+                match num {
+                    $($num => unsafe { &(*pac::IO_QSPI::ptr()).[<gpio_qspi $suffix>] }.gpio_ctrl.modify(|_,w| w.oeover().bits(val) ), )+
+                    _ => unreachable!("invalid ID for QSPI pin")
+                }
+            }
+
+            #[inline]
+            fn qspi_write_outover(num: usize, val : u8) {
+                match num {
+                    $($num => unsafe { &(*pac::IO_QSPI::ptr()).[<gpio_qspi $suffix>] }.gpio_ctrl.modify(|_,w| w.outover().bits(val) ), )+
+                    _ => unreachable!("invalid ID for QSPI pin")
+                }
+            }
+
             #[inline]
             fn qspi_change_mode(num: usize, mode: DynPinMode) {
                 let fields : ModeFields = mode.into();
@@ -43,18 +43,22 @@
 //! See [examples/i2c.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/i2c.rs)
 //! for a complete example
 
-use core::{marker::PhantomData, ops::Deref};
+use core::{convert::TryFrom, marker::PhantomData, ops::Deref};
 
 use crate::{
+    dma::{ReadTarget, WriteTarget, DREQ_I2C0_RX, DREQ_I2C0_TX, DREQ_I2C1_RX, DREQ_I2C1_TX},
     gpio::pin::bank0::{
         BankPinId, Gpio0, Gpio1, Gpio10, Gpio11, Gpio12, Gpio13, Gpio14, Gpio15, Gpio16, Gpio17,
         Gpio18, Gpio19, Gpio2, Gpio20, Gpio21, Gpio26, Gpio27, Gpio3, Gpio4, Gpio5, Gpio6, Gpio7,
         Gpio8, Gpio9,
     },
-    gpio::pin::{FunctionI2C, Pin, PinId},
+    gpio::pin::{FunctionI2C, Pin, PinId, PinMode, PullUpInput, ValidPinMode},
+    gpio::{DynPin, OutputEnableOverride, OutputOverride},
     resets::SubsystemReset,
     typelevel::Sealed,
 };
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::InputPin;
 use embedded_time::rate::Hertz;
 use pac::{i2c0::RegisterBlock as I2CBlock, I2C0, I2C1, RESETS};
 
@@ -68,7 +72,19 @@ pub mod peripheral;
 #[derive(Debug)]
 pub enum Error {
     /// I2C abort with error
+    ///
+    /// This holds the abort sources, for the aborts not covered by the other
+    /// variants.
     Abort(u32),
+    /// The address was not acknowledged
+    AddressNack,
+    /// A data byte was not acknowledged
+    DataNack,
+    /// Another controller won arbitration of the bus
+    ArbitrationLoss,
+    /// The operation did not complete in time, see
+    /// [`I2C::set_timeout`](I2C::set_timeout)
+    Timeout,
     /// User passed in a read buffer that was 0 length
     InvalidReadBufferLength,
     /// User passed in a write buffer that was 0 length
@@ -77,12 +93,40 @@ pub enum Error {
     AddressOutOfRange(u16),
     /// Target i2c address is reserved
     AddressReserved(u16),
+    /// The requested bus frequency or timing can't be reached with the system
+    /// clock
+    TimingOutOfRange,
+}
+
+impl Error {
+    /// Map the abort sources in `IC_TX_ABRT_SOURCE` to an error
+    fn from_abort_reason(reason: u32) -> Self {
+        if reason & 1 << 12 != 0 {
+            // ARB_LOST
+            Error::ArbitrationLoss
+        } else if reason & (1 << 2 | 1 << 1 | 1 << 0) != 0 {
+            // ABRT_10ADDR2_NOACK, ABRT_10ADDR1_NOACK or ABRT_7B_ADDR_NOACK
+            Error::AddressNack
+        } else if reason & 1 << 3 != 0 {
+            // ABRT_TXDATA_NOACK
+            Error::DataNack
+        } else {
+            Error::Abort(reason)
+        }
+    }
 }
 
 #[cfg(feature = "eh1_0_alpha")]
 impl eh1_0_alpha::i2c::Error for Error {
     fn kind(&self) -> eh1_0_alpha::i2c::ErrorKind {
         match &self {
+            Error::ArbitrationLoss => eh1_0_alpha::i2c::ErrorKind::ArbitrationLoss,
+            Error::AddressNack => eh1_0_alpha::i2c::ErrorKind::NoAcknowledge(
+                eh1_0_alpha::i2c::NoAcknowledgeSource::Address,
+            ),
+            Error::DataNack => eh1_0_alpha::i2c::ErrorKind::NoAcknowledge(
+                eh1_0_alpha::i2c::NoAcknowledgeSource::Data,
+            ),
             Error::Abort(v) if v & 1<<12 != 0 // ARB_LOST
                 => eh1_0_alpha::i2c::ErrorKind::ArbitrationLoss,
             Error::Abort(v) if v & 1<<7 != 0 // ABRT_SBYTE_ACKDET
@@ -169,12 +213,13 @@ pub struct I2C<I2C, Pins, Mode = Controller> {
     i2c: I2C,
     pins: Pins,
     mode: PhantomData<Mode>,
+    timeout_us: Option<u32>,
 }
 
 const TX_FIFO_SIZE: u8 = 16;
 const RX_FIFO_SIZE: u8 = 16;
 
-fn i2c_reserved_addr(addr: u16) -> bool {
+pub(crate) fn i2c_reserved_addr(addr: u16) -> bool {
     (addr & 0x78) == 0 || (addr & 0x78) == 0x78
 }
 
@@ -264,3 +309,114 @@ hal! {
     I2C0: (i2c0),
     I2C1: (i2c1),
 }
+
+/// Free a bus left in the middle of a transfer, then hand the pins over to
+/// the I2C peripherals.
+///
+/// If a device was reset mid transfer, or the controller was, the device can
+/// be left holding SDA low while it waits for clock pulses. This clocks SCL
+/// up to 9 times, at about 100kHz, until SDA is released, then sends a STOP.
+pub fn force_bus_release<Scl, Sda, SclMode, SdaMode, D>(
+    scl_pin: Pin<Scl, SclMode>,
+    sda_pin: Pin<Sda, SdaMode>,
+    delay: &mut D,
+) -> (Pin<Scl, FunctionI2C>, Pin<Sda, FunctionI2C>)
+where
+    Scl: PinId + BankPinId,
+    Sda: PinId + BankPinId,
+    SclMode: PinMode + ValidPinMode<Scl>,
+    SdaMode: PinMode + ValidPinMode<Sda>,
+    D: DelayUs<u32>,
+{
+    // Emulate open drain outputs: the pins stay inputs with a pull-up, whose
+    // output is forced low before it is ever enabled, so that enabling it
+    // pulls the line low and disabling it releases the line, without ever
+    // driving it high
+    let mut scl: DynPin = scl_pin.into_pull_up_input().into();
+    let mut sda: DynPin = sda_pin.into_pull_up_input().into();
+    scl.set_output_override(OutputOverride::AlwaysLow);
+    sda.set_output_override(OutputOverride::AlwaysLow);
+    let drive_low = |pin: &mut DynPin| pin.set_output_enable_override(OutputEnableOverride::Enable);
+    let release = |pin: &mut DynPin| pin.set_output_enable_override(OutputEnableOverride::Disable);
+    delay.delay_us(5);
+
+    for _ in 0..9 {
+        if sda.is_high().unwrap_or(false) {
+            break;
+        }
+        drive_low(&mut scl);
+        delay.delay_us(5);
+        release(&mut scl);
+        delay.delay_us(5);
+    }
+
+    // STOP: SDA rises while SCL is high
+    drive_low(&mut scl);
+    delay.delay_us(5);
+    drive_low(&mut sda);
+    delay.delay_us(5);
+    release(&mut scl);
+    delay.delay_us(5);
+    release(&mut sda);
+    delay.delay_us(5);
+
+    for pin in [&mut scl, &mut sda].iter_mut() {
+        pin.set_output_enable_override(OutputEnableOverride::DontInvert);
+        pin.set_output_override(OutputOverride::DontInvert);
+    }
+
+    // The pins go from pull-up inputs to the I2C function
+    let scl = Pin::<Scl, PullUpInput>::try_from(scl).unwrap();
+    let sda = Pin::<Sda, PullUpInput>::try_from(sda).unwrap();
+    (scl.into_mode(), sda.into_mode())
+}
+
+macro_rules! dma_targets {
+    ($($I2CX:ident: ($tx_dreq:expr, $rx_dreq:expr),)+) => {
+        $(
+            // Safety: The data register stays valid for as long as the I2C is held by the
+            // transfer.
+            unsafe impl<PINS> ReadTarget for I2C<$I2CX, PINS, Controller> {
+                type ReceivedWord = u8;
+
+                fn rx_treq(&self) -> Option<u8> {
+                    Some($rx_dreq)
+                }
+
+                fn rx_address_count(&self) -> (u32, u32) {
+                    (self.i2c.ic_data_cmd.as_ptr() as u32, u32::MAX)
+                }
+
+                fn rx_increment(&self) -> bool {
+                    false
+                }
+            }
+
+            /// Commands written by DMA hold the data byte in their lower 8 bits, and the read
+            /// (bit 8), stop (bit 9) and restart (bit 10) flags of `IC_DATA_CMD`. The target
+            /// address must be set beforehand.
+            // Safety: The data register stays valid for as long as the I2C is held by the
+            // transfer.
+            unsafe impl<PINS> WriteTarget for I2C<$I2CX, PINS, Controller> {
+                type TransmittedWord = u16;
+
+                fn tx_treq(&self) -> Option<u8> {
+                    Some($tx_dreq)
+                }
+
+                fn tx_address_count(&mut self) -> (u32, u32) {
+                    (self.i2c.ic_data_cmd.as_ptr() as u32, u32::MAX)
+                }
+
+                fn tx_increment(&self) -> bool {
+                    false
+                }
+            }
+        )+
+    };
+}
+
+dma_targets! {
+    I2C0: (DREQ_I2C0_TX, DREQ_I2C0_RX),
+    I2C1: (DREQ_I2C1_TX, DREQ_I2C1_RX),
+}
@@ -1,11 +1,14 @@
-use core::{marker::PhantomData, ops::Deref};
+use core::{convert::TryFrom, marker::PhantomData, ops::Deref};
 
 use crate::{
     gpio::pin::bank0::BankPinId,
     gpio::pin::{FunctionI2C, Pin, PinId},
+    gpio::{OutputEnableOverride, OutputOverride},
     resets::SubsystemReset,
 };
+use embedded_time::duration::{Microseconds, Nanoseconds};
 use embedded_time::rate::Hertz;
+use hal::blocking::delay::DelayUs;
 use hal::blocking::i2c::{Read, Write, WriteRead};
 use pac::{i2c0::RegisterBlock as Block, RESETS};
 
@@ -16,6 +19,8 @@ use super::{i2c_reserved_addr, Controller, Error, SclPin, SdaPin, I2C};
 
 #[cfg(feature = "embassy-traits")]
 mod embassy_support;
+#[cfg(feature = "embassy-traits")]
+pub use embassy_support::{on_i2c0_interrupt, on_i2c1_interrupt};
 
 impl<T: SubsystemReset + Deref<Target = Block>, Sda: PinId + BankPinId, Scl: PinId + BankPinId>
     I2C<T, (Pin<Sda, FunctionI2C>, Pin<Scl, FunctionI2C>), Controller>
@@ -58,52 +63,16 @@ impl<T: SubsystemReset + Deref<Target = Block>, Sda: PinId + BankPinId, Scl: Pin
         i2c.ic_tx_tl.write(|w| unsafe { w.tx_tl().bits(0) });
         i2c.ic_rx_tl.write(|w| unsafe { w.rx_tl().bits(0) });
 
-        let freq_in = system_clock.into().0;
-
-        // There are some subtleties to I2C timing which we are completely ignoring here
-        // See: https://github.com/raspberrypi/pico-sdk/blob/bfcbefafc5d2a210551a4d9d80b4303d4ae0adf7/src/rp2_common/hardware_i2c/i2c.c#L69
-        let period = (freq_in + freq / 2) / freq;
-        let lcnt = period * 3 / 5; // spend 3/5 (60%) of the period low
-        let hcnt = period - lcnt; // and 2/5 (40%) of the period high
-
-        // Check for out-of-range divisors:
-        assert!(hcnt <= 0xffff);
-        assert!(lcnt <= 0xffff);
-        assert!(hcnt >= 8);
-        assert!(lcnt >= 8);
-
-        // Per I2C-bus specification a device in standard or fast mode must
-        // internally provide a hold time of at least 300ns for the SDA signal to
-        // bridge the undefined region of the falling edge of SCL. A smaller hold
-        // time of 120ns is used for fast mode plus.
-        let sda_tx_hold_count = if freq < 1000000 {
-            // sda_tx_hold_count = freq_in [cycles/s] * 300ns * (1s / 1e9ns)
-            // Reduce 300/1e9 to 3/1e7 to avoid numbers that don't fit in uint.
-            // Add 1 to avoid division truncation.
-            ((freq_in * 3) / 10000000) + 1
-        } else {
-            // fast mode plus requires a clk_in > 32MHz
-            assert!(freq_in >= 32_000_000);
+        // Always enable DREQ signals -- harmless if DMA is not listening
+        i2c.ic_dma_cr
+            .write(|w| w.tdmae().set_bit().rdmae().set_bit());
 
-            // sda_tx_hold_count = freq_in [cycles/s] * 120ns * (1s / 1e9ns)
-            // Reduce 120/1e9 to 3/25e6 to avoid numbers that don't fit in uint.
-            // Add 1 to avoid division truncation.
-            ((freq_in * 3) / 25000000) + 1
+        let freq_in = system_clock.into().0;
+        let timings = match scl_timings(freq_in, freq, &Timing::default()) {
+            Ok(timings) => timings,
+            Err(_) => panic!("I2C frequency not achievable with this system clock"),
         };
-        assert!(sda_tx_hold_count <= lcnt - 2);
-
-        unsafe {
-            i2c.ic_fs_scl_hcnt
-                .write(|w| w.ic_fs_scl_hcnt().bits(hcnt as u16));
-            i2c.ic_fs_scl_lcnt
-                .write(|w| w.ic_fs_scl_lcnt().bits(lcnt as u16));
-            i2c.ic_fs_spklen.write(|w| {
-                w.ic_fs_spklen()
-                    .bits(if lcnt < 16 { 1 } else { (lcnt / 16) as u8 })
-            });
-            i2c.ic_sda_hold
-                .modify(|_r, w| w.ic_sda_tx_hold().bits(sda_tx_hold_count as u16));
-        }
+        write_timings(&i2c, &timings);
 
         // Enable I2C block
         i2c.ic_enable.write(|w| w.enable().enabled());
@@ -112,10 +81,329 @@ impl<T: SubsystemReset + Deref<Target = Block>, Sda: PinId + BankPinId, Scl: Pin
             i2c,
             pins: (sda_pin, scl_pin),
             mode: PhantomData,
+            timeout_us: None,
+        }
+    }
+
+    /// Send an SMBus quick command to the device at `addr`: the address and
+    /// the R/W bit given by `read`, with no data.
+    ///
+    /// The I2C block of the RP2040 can't end a transfer right after the
+    /// address, and lacks the SMBus extensions of its IP, so the command is
+    /// bit-banged on the pins at about 100kHz, using overrides of the pin
+    /// outputs. This fails with [`Error::AddressNack`] when no device
+    /// acknowledges the address.
+    pub fn quick_command<D: DelayUs<u32>>(
+        &mut self,
+        addr: u8,
+        read: bool,
+        delay: &mut D,
+    ) -> Result<(), Error> {
+        let addr: u16 = addr.into();
+        Self::validate(addr, None, None)?;
+        let deadline = Deadline::new(self.timeout_us);
+
+        self.i2c.ic_enable.write(|w| w.enable().disabled());
+        // Drive the pins low only when their output is enabled, like open
+        // drain outputs
+        let (sda, scl) = &mut self.pins;
+        sda.set_output_override(OutputOverride::AlwaysLow);
+        scl.set_output_override(OutputOverride::AlwaysLow);
+        sda.set_output_enable_override(OutputEnableOverride::Disable);
+        scl.set_output_enable_override(OutputEnableOverride::Disable);
+
+        let result =
+            bit_bang_quick_command(sda, scl, (addr as u8) << 1 | read as u8, delay, &deadline);
+
+        sda.set_output_enable_override(OutputEnableOverride::DontInvert);
+        scl.set_output_enable_override(OutputEnableOverride::DontInvert);
+        sda.set_output_override(OutputOverride::DontInvert);
+        scl.set_output_override(OutputOverride::DontInvert);
+        self.i2c.ic_enable.write(|w| w.enable().enabled());
+
+        result
+    }
+}
+
+/// Drive a pin low if `low`, and release it otherwise
+fn drive<I: PinId + BankPinId>(pin: &mut Pin<I, FunctionI2C>, low: bool) {
+    pin.set_output_enable_override(if low {
+        OutputEnableOverride::Enable
+    } else {
+        OutputEnableOverride::Disable
+    });
+}
+
+/// Bit-bang a START, the address byte `byte`, and a STOP, returning whether
+/// the address was acknowledged
+fn bit_bang_quick_command<Sda, Scl, D>(
+    sda: &mut Pin<Sda, FunctionI2C>,
+    scl: &mut Pin<Scl, FunctionI2C>,
+    byte: u8,
+    delay: &mut D,
+    deadline: &Deadline,
+) -> Result<(), Error>
+where
+    Sda: PinId + BankPinId,
+    Scl: PinId + BankPinId,
+    D: DelayUs<u32>,
+{
+    // Release SCL, and wait for devices stretching the clock
+    let clock_high = |scl: &mut Pin<Scl, FunctionI2C>, delay: &mut D| {
+        drive(scl, false);
+        while scl._is_low() {
+            if deadline.expired() {
+                return Err(Error::Timeout);
+            }
+        }
+        delay.delay_us(5);
+        Ok(())
+    };
+
+    // START: SDA falls while SCL is high
+    if sda._is_low() || scl._is_low() {
+        return Err(Error::ArbitrationLoss);
+    }
+    drive(sda, true);
+    delay.delay_us(5);
+    drive(scl, true);
+
+    for bit in (0..8).rev() {
+        drive(sda, byte & (1 << bit) == 0);
+        delay.delay_us(5);
+        clock_high(scl, delay)?;
+        drive(scl, true);
+    }
+
+    // ACK: the device holds SDA low during the ninth clock
+    drive(sda, false);
+    delay.delay_us(5);
+    clock_high(scl, delay)?;
+    let acked = sda._is_low();
+    drive(scl, true);
+
+    // STOP: SDA rises while SCL is high
+    drive(sda, true);
+    delay.delay_us(5);
+    clock_high(scl, delay)?;
+    drive(sda, false);
+    delay.delay_us(5);
+
+    if acked {
+        Ok(())
+    } else {
+        Err(Error::AddressNack)
+    }
+}
+
+/// Timing parameters of the bus, on top of the SCL frequency
+///
+/// Parameters left to `None` use the minimum required by the I2C-bus
+/// specification for the speed of the bus: standard mode up to 100kHz, fast
+/// mode up to 400kHz and fast mode plus up to 1MHz.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timing {
+    /// How long SDA is held after the falling edge of SCL. Defaults to 300ns,
+    /// or 120ns in fast mode plus.
+    pub sda_hold: Option<Nanoseconds<u32>>,
+    /// How long SDA is set up before the rising edge of SCL when transmitting
+    /// data as a peripheral. Defaults to 250ns in standard mode, 100ns in fast
+    /// mode and 50ns in fast mode plus.
+    pub sda_setup: Option<Nanoseconds<u32>>,
+    /// Length of the longest spike filtered out of SCL and SDA. Defaults to
+    /// 50ns.
+    pub spike_length: Option<Nanoseconds<u32>>,
+}
+
+/// Register values setting the SCL clock
+struct SclTimings {
+    hcnt: u16,
+    lcnt: u16,
+    spklen: u8,
+    sda_tx_hold: u16,
+    sda_setup: u8,
+    period: u32,
+}
+
+/// Number of cycles of a `freq_in` clock covering `ns` nanoseconds, rounded up
+fn cycles(freq_in: u32, ns: u32) -> u32 {
+    ((freq_in as u64 * ns as u64 + 999_999_999) / 1_000_000_000) as u32
+}
+
+/// Compute the SCL timings for a bus frequency of `freq`, from a peripheral
+/// clock of `freq_in`.
+///
+/// Fails with [`Error::TimingOutOfRange`] if the registers can't hold the
+/// counts, or if the bus would run slower than `freq`.
+fn scl_timings(freq_in: u32, freq: u32, timing: &Timing) -> Result<SclTimings, Error> {
+    if freq == 0 || freq > 1_000_000 {
+        return Err(Error::TimingOutOfRange);
+    }
+    let fast_mode_plus = freq > 400_000;
+    // fast mode plus requires a clk_in > 32MHz
+    if fast_mode_plus && freq_in < 32_000_000 {
+        return Err(Error::TimingOutOfRange);
+    }
+
+    let period = (freq_in + freq / 2) / freq;
+    // Spend 3/5 (60%) of the period low and 2/5 (40%) high, which meets the
+    // minimum low and high times of the I2C-bus specification in all modes
+    let low = period * 3 / 5;
+    let high = period - low;
+
+    // Suppress spikes of up to 50ns, as required in fast mode and fast mode
+    // plus. Round up to be sure to cover them.
+    let spike_length = timing.spike_length.map_or(50, |t| t.0);
+    let spklen = core::cmp::max(1, cycles(freq_in, spike_length));
+
+    // The controller stretches the counts: SCL is low for LCNT + 1 cycles, and
+    // high for HCNT + SPKLEN + 7 cycles. The hardware doesn't allow an HCNT
+    // below 6, so a peripheral clock that is too slow can't reach `freq`.
+    let lcnt = low.saturating_sub(1);
+    let hcnt = match high.checked_sub(spklen + 7) {
+        Some(hcnt) if hcnt >= 6 => hcnt,
+        _ => return Err(Error::TimingOutOfRange),
+    };
+    if hcnt > 0xffff || lcnt > 0xffff || lcnt < 8 || spklen > 0xff {
+        return Err(Error::TimingOutOfRange);
+    }
+
+    // Per I2C-bus specification a device in standard or fast mode must
+    // internally provide a hold time of at least 300ns for the SDA signal to
+    // bridge the undefined region of the falling edge of SCL. A smaller hold
+    // time of 120ns is used for fast mode plus.
+    let sda_hold = timing
+        .sda_hold
+        .map_or(if fast_mode_plus { 120 } else { 300 }, |t| t.0);
+    let sda_tx_hold = cycles(freq_in, sda_hold);
+    if sda_tx_hold > lcnt - 2 {
+        return Err(Error::TimingOutOfRange);
+    }
+
+    // The setup count is one more than the setup time, and at least 2
+    let sda_setup = timing.sda_setup.map_or(
+        if freq <= 100_000 {
+            250
+        } else if !fast_mode_plus {
+            100
+        } else {
+            50
+        },
+        |t| t.0,
+    );
+    let sda_setup = core::cmp::max(2, cycles(freq_in, sda_setup) + 1);
+    if sda_setup > 0xff {
+        return Err(Error::TimingOutOfRange);
+    }
+
+    Ok(SclTimings {
+        hcnt: hcnt as u16,
+        lcnt: lcnt as u16,
+        spklen: spklen as u8,
+        sda_tx_hold: sda_tx_hold as u16,
+        sda_setup: sda_setup as u8,
+        period: (lcnt + 1) + (hcnt + spklen + 7),
+    })
+}
+
+/// Write the SCL timings, while the block is disabled
+fn write_timings(i2c: &Block, timings: &SclTimings) {
+    unsafe {
+        i2c.ic_fs_scl_hcnt
+            .write(|w| w.ic_fs_scl_hcnt().bits(timings.hcnt));
+        i2c.ic_fs_scl_lcnt
+            .write(|w| w.ic_fs_scl_lcnt().bits(timings.lcnt));
+        i2c.ic_fs_spklen
+            .write(|w| w.ic_fs_spklen().bits(timings.spklen));
+        i2c.ic_sda_hold
+            .modify(|_r, w| w.ic_sda_tx_hold().bits(timings.sda_tx_hold));
+        i2c.ic_sda_setup
+            .write(|w| w.sda_setup().bits(timings.sda_setup));
+    }
+}
+
+/// Point in time after which a blocking operation gives up
+pub(crate) struct Deadline {
+    start: u32,
+    timeout_us: Option<u32>,
+}
+
+impl Deadline {
+    pub(crate) fn new(timeout_us: Option<u32>) -> Self {
+        Self {
+            start: timer_now(),
+            timeout_us,
+        }
+    }
+
+    pub(crate) fn expired(&self) -> bool {
+        match self.timeout_us {
+            Some(timeout_us) => timer_now().wrapping_sub(self.start) > timeout_us,
+            None => false,
         }
     }
 }
+
+/// Low word of the microsecond timer
+fn timer_now() -> u32 {
+    // Reading the raw counter has no side effects, so this doesn't need to own
+    // the timer
+    unsafe { (*pac::TIMER::ptr()).timerawl.read().bits() }
+}
+
 impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
+    /// Set how long a blocking operation may take before giving up with
+    /// [`Error::Timeout`], or `None` to wait forever, which is the default.
+    ///
+    /// This protects against a device holding SCL or SDA low. Time is
+    /// measured with the TIMER peripheral, which must be running, for
+    /// instance by creating a [`Timer`](crate::timer::Timer).
+    pub fn set_timeout(&mut self, timeout: Option<Microseconds>) {
+        self.timeout_us = timeout.map(|timeout| timeout.0);
+    }
+
+    /// Change the SCL frequency and the bus timing
+    ///
+    /// `system_clock` must be the current frequency of the system clock, from
+    /// which the timings are derived. This returns the actual SCL frequency,
+    /// which may be slightly above `freq` due to rounding, or
+    /// [`Error::TimingOutOfRange`] if `freq` and `timing` can't be reached
+    /// from `system_clock`, in which case the timing is left unchanged.
+    ///
+    /// Frequencies above 400kHz use fast mode plus, which needs a system clock
+    /// of at least 32MHz.
+    pub fn set_timing<F, SystemF>(
+        &mut self,
+        freq: F,
+        timing: Timing,
+        system_clock: SystemF,
+    ) -> Result<Hertz, Error>
+    where
+        F: Into<Hertz<u64>>,
+        SystemF: Into<Hertz<u32>>,
+    {
+        let freq_in = system_clock.into().0;
+        let freq = u32::try_from(freq.into().0).map_err(|_| Error::TimingOutOfRange)?;
+        let timings = scl_timings(freq_in, freq, &timing)?;
+
+        self.i2c.ic_enable.write(|w| w.enable().disabled());
+        write_timings(&self.i2c, &timings);
+        self.i2c.ic_enable.write(|w| w.enable().enabled());
+
+        Ok(Hertz(freq_in / timings.period))
+    }
+
+    /// Give up on the transfer if `deadline` has passed
+    fn check_deadline(&mut self, deadline: &Deadline) -> Result<(), Error> {
+        if deadline.expired() {
+            // Let the controller end the transfer with a STOP, once it can
+            self.i2c.ic_enable.modify(|_, w| w.abort().set_bit());
+            Err(Error::Timeout)
+        } else {
+            Ok(())
+        }
+    }
+
     fn validate(
         addr: u16,
         opt_tx_empty: Option<bool>,
@@ -141,6 +429,28 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
         }
     }
 
+    /// Write `bytes` to all the devices on the bus with a general call
+    ///
+    /// The devices which take part in general calls must acknowledge the
+    /// general call address, or this fails with an
+    /// [`Error::Abort`](Error::Abort) reporting `ABRT_GCALL_NOACK`.
+    pub fn general_call(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        // The general call address is reserved, so it can't be validated
+        if bytes.is_empty() {
+            return Err(Error::InvalidWriteBufferLength);
+        }
+        let deadline = Deadline::new(self.timeout_us);
+
+        self.i2c.ic_enable.write(|w| w.enable().disabled());
+        self.i2c.ic_tar.write(|w| {
+            w.special().enabled();
+            w.gc_or_start().general_call()
+        });
+        self.i2c.ic_enable.write(|w| w.enable().enabled());
+
+        self.write_internal(bytes, true, &deadline)
+    }
+
     fn setup(&mut self, addr: u16) {
         self.i2c.ic_enable.write(|w| w.enable().disabled());
         self.i2c.ic_tar.write(|w| unsafe { w.ic_tar().bits(addr) });
@@ -160,14 +470,16 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
         }
     }
 
-    fn read_internal(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+    fn read_internal(&mut self, buffer: &mut [u8], deadline: &Deadline) -> Result<(), Error> {
         let lastindex = buffer.len() - 1;
         for (i, byte) in buffer.iter_mut().enumerate() {
             let first = i == 0;
             let last = i == lastindex;
 
             // wait until there is space in the FIFO to write the next byte
-            while self.tx_fifo_full() {}
+            while self.tx_fifo_full() {
+                self.check_deadline(deadline)?;
+            }
 
             self.i2c.ic_data_cmd.write(|w| {
                 if first {
@@ -187,8 +499,9 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
 
             while self.i2c.ic_rxflr.read().bits() == 0 {
                 if let Some(abort_reason) = self.read_and_clear_abort_reason() {
-                    return Err(Error::Abort(abort_reason));
+                    return Err(Error::from_abort_reason(abort_reason));
                 }
+                self.check_deadline(deadline)?;
             }
 
             *byte = self.i2c.ic_data_cmd.read().dat().bits();
@@ -197,7 +510,12 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
         Ok(())
     }
 
-    fn write_internal(&mut self, bytes: &[u8], do_stop: bool) -> Result<(), Error> {
+    fn write_internal(
+        &mut self,
+        bytes: &[u8],
+        do_stop: bool,
+        deadline: &Deadline,
+    ) -> Result<(), Error> {
         for (i, byte) in bytes.iter().enumerate() {
             let last = i == bytes.len() - 1;
 
@@ -214,7 +532,9 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
             // shift register has completed. For this to function correctly, the
             // TX_EMPTY_CTRL flag in IC_CON must be set. The TX_EMPTY_CTRL flag
             // was set in i2c_init.
-            while self.i2c.ic_raw_intr_stat.read().tx_empty().is_inactive() {}
+            while self.i2c.ic_raw_intr_stat.read().tx_empty().is_inactive() {
+                self.check_deadline(deadline)?;
+            }
 
             let abort_reason = self.read_and_clear_abort_reason();
 
@@ -222,7 +542,9 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
                 // If the transaction was aborted or if it completed
                 // successfully wait until the STOP condition has occured.
 
-                while self.i2c.ic_raw_intr_stat.read().stop_det().is_inactive() {}
+                while self.i2c.ic_raw_intr_stat.read().stop_det().is_inactive() {
+                    self.check_deadline(deadline)?;
+                }
 
                 self.i2c.ic_clr_stop_det.read().clr_stop_det();
             }
@@ -231,7 +553,7 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
             // Note also the hardware clears RX FIFO as well as TX on abort,
             // ecause we set hwparam IC_AVOID_RX_FIFO_FLUSH_ON_TX_ABRT to 0.
             if let Some(abort_reason) = abort_reason {
-                return Err(Error::Abort(abort_reason));
+                return Err(Error::from_abort_reason(abort_reason));
             }
         }
         Ok(())
@@ -245,8 +567,9 @@ impl<T: Deref<Target = Block>, PINS> Read for I2C<T, PINS, Controller> {
 
         Self::validate(addr, None, Some(buffer.is_empty()))?;
 
+        let deadline = Deadline::new(self.timeout_us);
         self.setup(addr);
-        self.read_internal(buffer)
+        self.read_internal(buffer, &deadline)
     }
 }
 impl<T: Deref<Target = Block>, PINS> WriteRead for I2C<T, PINS, Controller> {
@@ -256,10 +579,11 @@ impl<T: Deref<Target = Block>, PINS> WriteRead for I2C<T, PINS, Controller> {
         let addr: u16 = addr.into();
 
         Self::validate(addr, Some(tx.is_empty()), Some(rx.is_empty()))?;
+        let deadline = Deadline::new(self.timeout_us);
         self.setup(addr);
 
-        self.write_internal(tx, false)?;
-        self.read_internal(rx)
+        self.write_internal(tx, false, &deadline)?;
+        self.read_internal(rx, &deadline)
     }
 }
 impl<T: Deref<Target = Block>, PINS> Write for I2C<T, PINS, Controller> {
@@ -268,9 +592,10 @@ impl<T: Deref<Target = Block>, PINS> Write for I2C<T, PINS, Controller> {
     fn write(&mut self, addr: u8, tx: &[u8]) -> Result<(), Error> {
         let addr: u16 = addr.into();
         Self::validate(addr, Some(tx.is_empty()), None)?;
+        let deadline = Deadline::new(self.timeout_us);
         self.setup(addr);
 
-        self.write_internal(tx, true)
+        self.write_internal(tx, true, &deadline)
     }
 }
 
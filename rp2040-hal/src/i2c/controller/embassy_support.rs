@@ -1,8 +1,76 @@
-use core::{future::Future, iter::Peekable, ops::Deref, task::Poll};
+use core::{
+    cell::RefCell,
+    future::Future,
+    iter::Peekable,
+    ops::Deref,
+    task::{Poll, Waker},
+};
+
+use cortex_m::interrupt::{self, Mutex};
 
 use super::{Block, Controller, Error, I2C};
 
+// Interrupt mask bits
+const RX_FULL: u32 = 1 << 2;
+const TX_EMPTY: u32 = 1 << 4;
+const TX_ABRT: u32 = 1 << 6;
+const STOP_DET: u32 = 1 << 9;
+
+/// The tasks waiting on I2C0 and I2C1
+static WAKERS: [Mutex<RefCell<Option<Waker>>>; 2] = [
+    Mutex::new(RefCell::new(None)),
+    Mutex::new(RefCell::new(None)),
+];
+
+/// Wake the task waiting on I2C0. Call this from the `I2C0_IRQ` handler.
+///
+/// The async operations sleep until an I2C interrupt fires, so the interrupt
+/// must be unmasked in the NVIC and its handler must call this.
+pub fn on_i2c0_interrupt() {
+    on_interrupt(unsafe { &*pac::I2C0::ptr() }, 0);
+}
+
+/// Wake the task waiting on I2C1. Call this from the `I2C1_IRQ` handler.
+///
+/// See [`on_i2c0_interrupt`].
+pub fn on_i2c1_interrupt() {
+    on_interrupt(unsafe { &*pac::I2C1::ptr() }, 1);
+}
+
+fn on_interrupt(block: &Block, index: usize) {
+    // Stop the interrupt from firing again until the task is waiting again
+    block.ic_intr_mask.write(|w| unsafe { w.bits(0) });
+    if let Some(waker) = interrupt::free(|cs| WAKERS[index].borrow(cs).borrow_mut().take()) {
+        waker.wake();
+    }
+}
+
 impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
+    /// Wait until `f` is ready, sleeping until one of `interrupts` fires
+    /// between checks.
+    async fn wait_for<R>(&mut self, interrupts: u32, mut f: impl FnMut(&mut Self) -> Poll<R>) -> R {
+        let index = if &*self.i2c as *const Block == pac::I2C0::ptr() {
+            0
+        } else {
+            1
+        };
+
+        futures::future::poll_fn(|cx| {
+            if let Poll::Ready(result) = f(self) {
+                return Poll::Ready(result);
+            }
+
+            interrupt::free(|cs| WAKERS[index].borrow(cs).replace(Some(cx.waker().clone())));
+            // The interrupts are level triggered, or latched until cleared,
+            // so one fires right away if the condition was met in the meantime
+            self.i2c
+                .ic_intr_mask
+                .write(|w| unsafe { w.bits(interrupts) });
+            Poll::Pending
+        })
+        .await
+    }
+
     async fn non_blocking_read_internal<'a, U: Iterator<Item = &'a mut u8> + 'a>(
         &mut self,
         mut buffer: Peekable<U>,
@@ -12,8 +80,8 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
             let last = buffer.peek().is_none();
 
             // wait until there is space in the FIFO to write the next byte
-            block_on(|| {
-                if self.tx_fifo_full() {
+            self.wait_for(TX_EMPTY, |i2c| {
+                if i2c.tx_fifo_full() {
                     Poll::Pending
                 } else {
                     Poll::Ready(())
@@ -37,10 +105,10 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
                 w.cmd().read()
             });
 
-            block_on(|| {
-                if let Some(abort_reason) = self.read_and_clear_abort_reason() {
-                    Poll::Ready(Err(Error::Abort(abort_reason)))
-                } else if self.i2c.ic_rxflr.read().bits() != 0 {
+            self.wait_for(RX_FULL | TX_ABRT, |i2c| {
+                if let Some(abort_reason) = i2c.read_and_clear_abort_reason() {
+                    Poll::Ready(Err(Error::from_abort_reason(abort_reason)))
+                } else if i2c.i2c.ic_rxflr.read().bits() != 0 {
                     Poll::Ready(Ok(()))
                 } else {
                     Poll::Pending
@@ -76,8 +144,8 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
             // shift register has completed. For this to function correctly, the
             // TX_EMPTY_CTRL flag in IC_CON must be set. The TX_EMPTY_CTRL flag
             // was set in i2c_init.
-            block_on(|| {
-                if self.i2c.ic_raw_intr_stat.read().tx_empty().is_inactive() {
+            self.wait_for(TX_EMPTY, |i2c| {
+                if i2c.i2c.ic_raw_intr_stat.read().tx_empty().is_inactive() {
                     Poll::Pending
                 } else {
                     Poll::Ready(())
@@ -91,8 +159,8 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
                 // If the transaction was aborted or if it completed
                 // successfully wait until the STOP condition has occured.
 
-                block_on(|| {
-                    if self.i2c.ic_raw_intr_stat.read().stop_det().is_inactive() {
+                self.wait_for(STOP_DET, |i2c| {
+                    if i2c.i2c.ic_raw_intr_stat.read().stop_det().is_inactive() {
                         Poll::Pending
                     } else {
                         Poll::Ready(())
@@ -107,22 +175,13 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
             // Note also the hardware clears RX FIFO as well as TX on abort,
             // ecause we set hwparam IC_AVOID_RX_FIFO_FLUSH_ON_TX_ABRT to 0.
             if let Some(abort_reason) = abort_reason {
-                return Err(Error::Abort(abort_reason));
+                return Err(Error::from_abort_reason(abort_reason));
             }
         }
 
         Ok(())
     }
 }
-async fn block_on<F: FnMut() -> Poll<T>, T>(mut f: F) -> T {
-    futures::future::poll_fn(|cx| {
-        // always ready to scan
-        cx.waker().wake_by_ref();
-
-        f()
-    })
-    .await
-}
 
 impl<T, PINS, A> embassy_traits::i2c::I2c<A> for I2C<T, PINS, Controller>
 where
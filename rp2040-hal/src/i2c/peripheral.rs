@@ -1,3 +1,37 @@
+//! # I2C Peripheral (target) mode
+//!
+//! The I2C block answers at an address of its own, and reports what the
+//! controller does as a stream of [`I2CEvent`]s:
+//!
+//! ```no_run
+//! use rp2040_hal::{i2c::{peripheral::I2CEvent, I2C}, gpio::Pins, pac, sio::Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//!
+//! let mut i2c = I2C::new_peripheral_event_iterator(
+//!     peripherals.I2C0,
+//!     pins.gpio0.into_mode(), // sda
+//!     pins.gpio1.into_mode(), // scl
+//!     &mut peripherals.RESETS,
+//!     0x55,
+//! )
+//! .unwrap();
+//!
+//! let mut register = [0u8; 1];
+//! loop {
+//!     match i2c.next() {
+//!         Some(I2CEvent::TransferWrite) => {
+//!             i2c.read(&mut register);
+//!         }
+//!         Some(I2CEvent::TransferRead) => {
+//!             i2c.write(&[0x42]);
+//!         }
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
 use core::{marker::PhantomData, ops::Deref};
 
 use crate::{
@@ -7,7 +41,7 @@ use crate::{
 };
 use pac::{i2c0::RegisterBlock as I2CBlock, RESETS};
 
-use super::{Peripheral, SclPin, SdaPin, I2C};
+use super::{i2c_reserved_addr, Error, Peripheral, SclPin, SdaPin, I2C};
 
 /// I2C bus events
 #[derive(Debug, PartialEq, Eq)]
@@ -32,6 +66,9 @@ enum State {
 }
 
 /// Provides Async features to I2C peripheral.
+///
+/// Polling the iterator never blocks: it returns `None` when nothing has
+/// happened on the bus since the last event.
 pub struct I2CPeripheralEventIterator<Block, Pins> {
     i2c: I2C<Block, Pins, Peripheral>,
     state: State,
@@ -45,6 +82,10 @@ where
 {
     /// Configures the I2C peripheral to work in peripheral mode
     ///
+    /// Addresses above 0x7F are 10 bit addresses. Addresses above 0x3FF fail
+    /// with [`Error::AddressOutOfRange`] and reserved 7 bit addresses with
+    /// [`Error::AddressReserved`], before the peripheral is touched.
+    ///
     /// The bus *MUST* be idle when this method is called.
     #[allow(clippy::type_complexity)]
     pub fn new_peripheral_event_iterator(
@@ -53,23 +94,30 @@ where
         scl_pin: Pin<Scl, FunctionI2C>,
         resets: &mut RESETS,
         addr: u16,
-    ) -> I2CPeripheralEventIterator<T, (Pin<Sda, FunctionI2C>, Pin<Scl, FunctionI2C>)>
+    ) -> Result<I2CPeripheralEventIterator<T, (Pin<Sda, FunctionI2C>, Pin<Scl, FunctionI2C>)>, Error>
     where
         Sda: SdaPin<T>,
         Scl: SclPin<T>,
     {
+        if addr >= 0x400 {
+            return Err(Error::AddressOutOfRange(addr));
+        }
+        let ten_bit = addr >= 0x80;
+        if !ten_bit && i2c_reserved_addr(addr) {
+            return Err(Error::AddressReserved(addr));
+        }
+
         i2c.reset_bring_down(resets);
         i2c.reset_bring_up(resets);
 
         i2c.ic_enable.write(|w| w.enable().disabled());
 
-        // TODO: rp2040 supports 10bits addressing
-        //i2c_reserved_addr(addr)
         i2c.ic_sar.write(|w| unsafe { w.ic_sar().bits(addr) });
         // select peripheral mode & speed
         i2c.ic_con.modify(|_, w| {
             // run in fast mode
             w.speed().fast();
+            w.ic_10bitaddr_slave().bit(ten_bit);
             // setup slave mode
             w.master_mode().disabled();
             w.ic_slave_disable().slave_enabled();
@@ -86,14 +134,15 @@ where
         // Enable I2C block
         i2c.ic_enable.write(|w| w.enable().enabled());
 
-        I2CPeripheralEventIterator {
+        Ok(I2CPeripheralEventIterator {
             i2c: Self {
                 i2c,
                 pins: (sda_pin, scl_pin),
                 mode: PhantomData,
+                timeout_us: None,
             },
             state: State::Idle,
-        }
+        })
     }
 }
 
@@ -0,0 +1,601 @@
+//! I2C controller running on a PIO state machine
+//!
+//! This provides more I2C buses than the two I2C peripherals, on any pair of
+//! pins, following the I2C example of the pico-sdk. Devices may stretch the
+//! clock. The bus runs at the requested frequency, up to 400kHz.
+//!
+//! The program takes 18 instructions of the 32 of a PIO block, so each PIO
+//! block can drive a single bus.
+//!
+//! A device stretching the clock stalls the state machine: set a
+//! [timeout](I2C::set_timeout) to give up on a bus held low.
+//!
+//! ## Usage
+//! ```no_run
+//! use embedded_time::rate::Extensions;
+//! use rp2040_hal::{gpio::{FunctionPio0, Pin, Pins}, i2c_pio::I2C, pac, pio::PIOExt, sio::Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let (mut pio, sm0, _, _, _) = peripherals.PIO0.split(&mut peripherals.RESETS);
+//!
+//! let sda: Pin<_, FunctionPio0> = pins.gpio2.into_mode();
+//! let scl: Pin<_, FunctionPio0> = pins.gpio7.into_mode();
+//! let mut i2c = I2C::new(&mut pio, sda, scl, sm0, 100.kHz(), 125_000_000.Hz()).unwrap();
+//!
+//! use embedded_hal::prelude::_embedded_hal_blocking_i2c_Write;
+//! i2c.write(0x2c, &[1, 2, 3]).unwrap();
+//! ```
+use crate::{
+    gpio::pin::{Function, FunctionConfig, Pin, PinId, ValidPinMode},
+    i2c::{controller::Deadline, i2c_reserved_addr, Error},
+    pio::{
+        InstallError, PIOBuilder, PIOExt, PinDir, PinState, Running, Rx, ShiftDirection,
+        StateMachine, StateMachineIndex, Tx, UninitStateMachine, PIO,
+    },
+};
+use embedded_time::duration::Microseconds;
+use embedded_time::rate::Hertz;
+use hal::blocking::i2c::{Read, Write, WriteRead};
+
+#[cfg(feature = "eh1_0_alpha")]
+use eh1_0_alpha::i2c::blocking as eh1;
+
+// Layout of the words sent to the state machine:
+// | 15:10 | 9     | 8:1  | 0   |
+// | Instr | Final | Data | NAK |
+const ICOUNT_LSB: u16 = 10;
+const FINAL_LSB: u16 = 9;
+const DATA_LSB: u16 = 1;
+const NAK_LSB: u16 = 0;
+
+/// Errors of [`I2C::new`]
+#[derive(Debug)]
+pub enum InitError {
+    /// There was not enough space for the program in the PIO block.
+    Install(InstallError),
+    /// The bus frequency is 0, or above 400kHz.
+    BusFrequencyOutOfRange,
+    /// The state machine, which runs 32 cycles per bit, can't be clocked from the system clock
+    /// at the bus frequency: the clock divisor would be below 1, or not below 65536.
+    ClockDivisorOutOfRange,
+}
+
+impl From<InstallError> for InitError {
+    fn from(e: InstallError) -> Self {
+        InitError::Install(e)
+    }
+}
+
+/// Why the state machine didn't run a transfer to its end
+enum Abort {
+    /// It stopped on a NAK, after shifting in this many bytes, the address included.
+    Nak(usize),
+    /// It stalled for longer than the timeout, most likely on a device holding SCL low.
+    Timeout,
+}
+
+/// Instructions driving SCL with side-set and SDA with `set pindirs`, to
+/// generate START and STOP conditions. A pin direction of 0 pulls the line
+/// low.
+struct LineState {
+    scl: u8,
+    sda: u8,
+}
+
+impl LineState {
+    const SC0_SD0: Self = Self { scl: 0, sda: 0 };
+    const SC0_SD1: Self = Self { scl: 0, sda: 1 };
+    const SC1_SD0: Self = Self { scl: 1, sda: 0 };
+    const SC1_SD1: Self = Self { scl: 1, sda: 1 };
+
+    fn instruction(&self) -> u16 {
+        pio::Instruction {
+            operands: pio::InstructionOperands::SET {
+                destination: pio::SetDestination::PINDIRS,
+                data: self.sda,
+            },
+            delay: 7,
+            side_set: Some(self.scl),
+        }
+        .encode(pio::SideSet::new(true, 1, true))
+    }
+}
+
+/// Assemble the I2C program, waiting for the clock to be released on `scl`
+fn program(scl: u8) -> pio::Program<{ pio::RP2040_MAX_PROGRAM_SIZE }> {
+    use pio::{InSource, JmpCondition, MovDestination, MovOperation, MovSource, OutDestination};
+
+    let mut a = pio::Assembler::<{ pio::RP2040_MAX_PROGRAM_SIZE }>::new_with_side_set(
+        pio::SideSet::new(true, 1, true),
+    );
+    let mut do_nack = a.label();
+    let mut do_byte = a.label();
+    let mut bitloop = a.label();
+    let mut do_exec = a.label();
+    let mut wrap_target = a.label();
+    let mut wrap_source = a.label();
+
+    a.bind(&mut do_nack);
+    // Continue if NAK was expected
+    a.jmp(JmpCondition::YDecNonZero, &mut wrap_target);
+    // Otherwise stop, and raise the IRQ flag of the state machine
+    a.irq(false, true, 0, true);
+
+    a.bind(&mut do_byte);
+    a.set(pio::SetDestination::X, 7);
+    a.bind(&mut bitloop);
+    // Serialise write data (all-ones if reading)
+    a.out_with_delay(OutDestination::PINDIRS, 1, 7);
+    // SCL rising edge
+    a.mov_with_delay_and_side_set(MovDestination::Y, MovOperation::None, MovSource::Y, 2, 1);
+    // Allow clock to be stretched
+    a.wait_with_delay(1, pio::WaitSource::GPIO, scl, 4);
+    // Sample read data in middle of SCL pulse
+    a.in_with_delay(InSource::PINS, 1, 7);
+    // SCL falling edge
+    a.jmp_with_delay_and_side_set(JmpCondition::XDecNonZero, &mut bitloop, 7, 0);
+
+    // On reads, we provide the ACK
+    a.out_with_delay(OutDestination::PINDIRS, 1, 7);
+    // SCL rising edge
+    a.mov_with_delay_and_side_set(MovDestination::Y, MovOperation::None, MovSource::Y, 7, 1);
+    // Allow clock to be stretched
+    a.wait_with_delay(1, pio::WaitSource::GPIO, scl, 7);
+    // Test SDA for ACK/NAK, fall through if ACK
+    a.jmp_with_delay_and_side_set(JmpCondition::PinHigh, &mut do_nack, 2, 0);
+
+    a.bind(&mut wrap_target);
+    // Unpack Instr count
+    a.out(OutDestination::X, 6);
+    // Unpack the NAK ignore bit
+    a.out(OutDestination::Y, 1);
+    // Instr == 0, this is a data record
+    a.jmp(JmpCondition::XIsZero, &mut do_byte);
+    // Instr > 0, remainder of this OSR is invalid
+    a.out(OutDestination::NULL, 32);
+    a.bind(&mut do_exec);
+    // Execute one instruction per FIFO word
+    a.out(OutDestination::EXEC, 16);
+    // Repeat n + 1 times
+    a.jmp(JmpCondition::XDecNonZero, &mut do_exec);
+    a.bind(&mut wrap_source);
+
+    a.assemble_with_wrap(wrap_source, wrap_target)
+}
+
+/// I2C controller on a PIO state machine
+pub struct I2C<'pio, P, SM, Sda, Scl>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    Sda: PinId,
+    Scl: PinId,
+    Function<P>: ValidPinMode<Sda> + ValidPinMode<Scl>,
+{
+    pio: &'pio mut PIO<P>,
+    sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+    tx: Tx<(P, SM)>,
+    entry_point: u8,
+    sda: Pin<Sda, Function<P>>,
+    scl: Pin<Scl, Function<P>>,
+    timeout_us: Option<u32>,
+    /// The deadline of the transfer running
+    deadline: Deadline,
+}
+
+impl<'pio, P, SM, Sda, Scl> I2C<'pio, P, SM, Sda, Scl>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    Sda: PinId,
+    Scl: PinId,
+    Function<P>: ValidPinMode<Sda> + ValidPinMode<Scl>,
+{
+    /// Install the I2C program in `pio`, and run it on `sm` to drive a bus at
+    /// `bus_freq` on the `sda` and `scl` pins.
+    ///
+    /// The pull-ups of the pins are enabled, though most buses need stronger
+    /// external pull-ups.
+    ///
+    /// Nothing is installed if `bus_freq` is out of range, or can't be reached
+    /// from `system_freq`.
+    pub fn new<BusF, SystemF>(
+        pio: &'pio mut PIO<P>,
+        mut sda: Pin<Sda, Function<P>>,
+        mut scl: Pin<Scl, Function<P>>,
+        sm: UninitStateMachine<(P, SM)>,
+        bus_freq: BusF,
+        system_freq: SystemF,
+    ) -> Result<Self, InitError>
+    where
+        BusF: Into<Hertz<u32>>,
+        SystemF: Into<Hertz<u32>>,
+    {
+        let bus_freq = bus_freq.into().0;
+        let system_freq = system_freq.into().0;
+        if bus_freq == 0 || bus_freq > 400_000 {
+            return Err(InitError::BusFrequencyOutOfRange);
+        }
+        // Each bit takes 32 cycles of the state machine
+        let clock_divisor = system_freq as f32 / (32 * bus_freq) as f32;
+        if !(1.0..65536.0).contains(&clock_divisor) {
+            return Err(InitError::ClockDivisorOutOfRange);
+        }
+
+        let sda_id = Sda::DYN.num;
+        let scl_id = Scl::DYN.num;
+        let installed = pio.install(&program(scl_id))?;
+        let entry_point = installed.wrap_target();
+
+        // The config was checked above, so this doesn't panic
+        let (mut sm, rx, tx) = PIOBuilder::from_program(installed)
+            .set_pins(sda_id, 1)
+            .out_pins(sda_id, 1)
+            .in_pin_base(sda_id)
+            .side_set_pin_base(scl_id)
+            .jmp_pin(sda_id)
+            .out_shift_direction(ShiftDirection::Left)
+            .autopull(true)
+            .pull_threshold(16)
+            .in_shift_direction(ShiftDirection::Left)
+            .autopush(true)
+            .push_threshold(8)
+            .clock_divisor(clock_divisor)
+            .build(sm);
+
+        // Avoid glitching the bus while taking over the pins: release them,
+        // then invert the output enables so that a pin direction of 0 pulls
+        // the line low, and a direction of 1 lets the pull-ups raise it.
+        set_pull_up(sda_id);
+        set_pull_up(scl_id);
+        sm.set_pins([(sda_id, PinState::High), (scl_id, PinState::High)]);
+        sm.set_pindirs([(sda_id, PinDir::Output), (scl_id, PinDir::Output)]);
+        sda.set_output_enable_override(crate::gpio::OutputEnableOverride::Invert);
+        scl.set_output_enable_override(crate::gpio::OutputEnableOverride::Invert);
+        sm.set_pins([(sda_id, PinState::Low), (scl_id, PinState::Low)]);
+
+        // The IRQ flag reports NAKs, without raising an interrupt
+        pio.clear_irq(1 << SM::id());
+
+        sm.exec_instruction(
+            pio::InstructionOperands::JMP {
+                condition: pio::JmpCondition::Always,
+                address: entry_point,
+            }
+            .encode(),
+        );
+        let sm = sm.start();
+
+        Ok(Self {
+            pio,
+            sm,
+            rx,
+            tx,
+            entry_point,
+            sda,
+            scl,
+            timeout_us: None,
+            deadline: Deadline::new(None),
+        })
+    }
+
+    /// Set how long a transfer may take before giving up with
+    /// [`Error::Timeout`], or `None` to wait forever, which is the default.
+    ///
+    /// This protects against a device stretching the clock forever. Time is
+    /// measured with the TIMER peripheral, which must be running, for
+    /// instance by creating a [`Timer`](crate::timer::Timer).
+    pub fn set_timeout(&mut self, timeout: Option<Microseconds>) {
+        self.timeout_us = timeout.map(|timeout| timeout.0);
+    }
+
+    /// Stop the state machine and uninstall the program, and return the state
+    /// machine and the pins
+    #[allow(clippy::type_complexity)]
+    pub fn free(
+        mut self,
+    ) -> (
+        UninitStateMachine<(P, SM)>,
+        Pin<Sda, Function<P>>,
+        Pin<Scl, Function<P>>,
+    ) {
+        self.sda
+            .set_output_enable_override(crate::gpio::OutputEnableOverride::DontInvert);
+        self.scl
+            .set_output_enable_override(crate::gpio::OutputEnableOverride::DontInvert);
+        let (sm, installed) = self.sm.uninit(self.rx, self.tx);
+        self.pio.uninstall(installed);
+        (sm, self.sda, self.scl)
+    }
+
+    fn has_errored(&self) -> bool {
+        self.pio.get_irq_raw() & (1 << SM::id()) != 0
+    }
+
+    /// Whether the state machine stopped on a NAK, or the transfer timed out
+    fn has_aborted(&self) -> bool {
+        self.has_errored() || self.deadline.expired()
+    }
+
+    /// Queue `data` for the state machine, unless the transfer has aborted
+    fn put(&mut self, data: u16) {
+        while self.tx.is_full() {
+            if self.has_aborted() {
+                return;
+            }
+        }
+        if !self.has_aborted() {
+            self.tx.write(data);
+        }
+    }
+
+    fn start(&mut self) {
+        // Escape code for a 2 instruction sequence
+        self.put(1 << ICOUNT_LSB);
+        // We are already in idle state, just pull SDA low
+        self.put(LineState::SC1_SD0.instruction());
+        // Also pull clock low so we can present data
+        self.put(LineState::SC0_SD0.instruction());
+    }
+
+    fn repstart(&mut self) {
+        self.put(3 << ICOUNT_LSB);
+        self.put(LineState::SC0_SD1.instruction());
+        self.put(LineState::SC1_SD1.instruction());
+        self.put(LineState::SC1_SD0.instruction());
+        self.put(LineState::SC0_SD0.instruction());
+    }
+
+    fn stop(&mut self) {
+        self.put(2 << ICOUNT_LSB);
+        // SDA is unknown; pull it down
+        self.put(LineState::SC0_SD0.instruction());
+        // Release clock
+        self.put(LineState::SC1_SD0.instruction());
+        // Release SDA to return to idle state
+        self.put(LineState::SC1_SD1.instruction());
+    }
+
+    /// Count the bytes the state machine shifted in, storing them in `buffer`
+    /// if given
+    fn drain_rx(&mut self, received: &mut usize, buffer: Option<&mut [u8]>) {
+        let mut buffer = buffer;
+        while let Some(byte) = self.rx.read() {
+            if let Some(buffer) = buffer.as_deref_mut() {
+                // The address byte comes first
+                if let Some(slot) = received.checked_sub(1).and_then(|i| buffer.get_mut(i)) {
+                    *slot = byte as u8;
+                }
+            }
+            *received += 1;
+        }
+    }
+
+    /// Send the address byte and the bytes of `tx`, after a START or repeated
+    /// START.
+    ///
+    /// Returns how the transfer aborted, if it did.
+    fn write_internal(&mut self, addr: u8, tx: &[u8]) -> Result<(), Abort> {
+        let mut received = 0;
+        self.put(u16::from(addr) << 2 | 1 << NAK_LSB);
+        for byte in tx {
+            while self.tx.is_full() && !self.has_aborted() {
+                self.drain_rx(&mut received, None);
+            }
+            self.put(u16::from(*byte) << DATA_LSB | 1 << NAK_LSB);
+        }
+        self.wait_idle(&mut received, None)
+    }
+
+    /// Send the address byte, then clock in the bytes of `rx`, after a START
+    /// or repeated START
+    fn read_internal(&mut self, addr: u8, rx: &mut [u8]) -> Result<(), Abort> {
+        let mut received = 0;
+        self.put(u16::from(addr) << 2 | 1 << DATA_LSB | 1 << NAK_LSB);
+        for i in 0..rx.len() {
+            while self.tx.is_full() && !self.has_aborted() {
+                self.drain_rx(&mut received, Some(&mut *rx));
+            }
+            // Stuff 0xff bytes in to get clocks, and NAK the last byte
+            let last = i == rx.len() - 1;
+            self.put(0xff << DATA_LSB | u16::from(last) << FINAL_LSB | u16::from(last) << NAK_LSB);
+        }
+        self.wait_idle(&mut received, Some(rx))
+    }
+
+    /// Wait for the state machine to run out of work, to stop on a NAK, or
+    /// for the transfer to time out
+    fn wait_idle(&mut self, received: &mut usize, mut rx: Option<&mut [u8]>) -> Result<(), Abort> {
+        self.tx.clear_stalled_flag();
+        while !self.tx.has_stalled() && !self.has_aborted() {
+            self.drain_rx(received, rx.as_deref_mut());
+        }
+        self.drain_rx(received, rx);
+        if self.has_errored() {
+            Err(Abort::Nak(*received))
+        } else if !self.tx.has_stalled() {
+            Err(Abort::Timeout)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Resume after a NAK or a timeout, and release the bus
+    ///
+    /// The jump forced to the entry point also takes the state machine out of
+    /// a `wait` for SCL.
+    fn recover(&mut self, abort: Abort) -> Error {
+        self.tx.drain_fifo();
+        self.sm.exec_instruction(
+            pio::InstructionOperands::JMP {
+                condition: pio::JmpCondition::Always,
+                address: self.entry_point,
+            }
+            .encode(),
+        );
+        self.pio.clear_irq(1 << SM::id());
+        // The STOP only runs forced instructions, which don't wait for SCL
+        self.deadline = Deadline::new(self.timeout_us);
+        self.stop();
+        let mut dropped = 0;
+        let _ = self.wait_idle(&mut dropped, None);
+
+        match abort {
+            Abort::Nak(received) if received <= 1 => Error::AddressNack,
+            Abort::Nak(_) => Error::DataNack,
+            Abort::Timeout => Error::Timeout,
+        }
+    }
+
+    /// Run `f` between a START and a STOP, and turn NAKs and timeouts into
+    /// errors
+    fn transaction(&mut self, f: impl FnOnce(&mut Self) -> Result<(), Abort>) -> Result<(), Error> {
+        self.deadline = Deadline::new(self.timeout_us);
+        let mut dropped = 0;
+        self.drain_rx(&mut dropped, None);
+        self.start();
+        let result = f(self);
+        match result {
+            Ok(()) => {
+                self.stop();
+                self.wait_idle(&mut dropped, None)
+                    .map_err(|abort| self.recover(abort))
+            }
+            Err(abort) => Err(self.recover(abort)),
+        }
+    }
+
+    fn validate(addr: u8) -> Result<(), Error> {
+        let addr = u16::from(addr);
+        if addr >= 0x80 {
+            Err(Error::AddressOutOfRange(addr))
+        } else if i2c_reserved_addr(addr) {
+            Err(Error::AddressReserved(addr))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Enable the pull-up of the pad of GPIO `id`
+fn set_pull_up(id: u8) {
+    // The pin is owned by the caller, so its pad can be changed
+    unsafe {
+        (*pac::PADS_BANK0::ptr()).gpio[id as usize]
+            .modify(|_, w| w.pue().set_bit().pde().clear_bit());
+    }
+}
+
+impl<'pio, P, SM, Sda, Scl> Read for I2C<'pio, P, SM, Sda, Scl>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    Sda: PinId,
+    Scl: PinId,
+    Function<P>: ValidPinMode<Sda> + ValidPinMode<Scl>,
+{
+    type Error = Error;
+
+    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        Self::validate(addr)?;
+        if buffer.is_empty() {
+            return Err(Error::InvalidReadBufferLength);
+        }
+        self.transaction(|i2c| i2c.read_internal(addr, buffer))
+    }
+}
+
+impl<'pio, P, SM, Sda, Scl> Write for I2C<'pio, P, SM, Sda, Scl>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    Sda: PinId,
+    Scl: PinId,
+    Function<P>: ValidPinMode<Sda> + ValidPinMode<Scl>,
+{
+    type Error = Error;
+
+    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+        Self::validate(addr)?;
+        if bytes.is_empty() {
+            return Err(Error::InvalidWriteBufferLength);
+        }
+        self.transaction(|i2c| i2c.write_internal(addr, bytes))
+    }
+}
+
+impl<'pio, P, SM, Sda, Scl> WriteRead for I2C<'pio, P, SM, Sda, Scl>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    Sda: PinId,
+    Scl: PinId,
+    Function<P>: ValidPinMode<Sda> + ValidPinMode<Scl>,
+{
+    type Error = Error;
+
+    fn write_read(&mut self, addr: u8, tx: &[u8], rx: &mut [u8]) -> Result<(), Error> {
+        Self::validate(addr)?;
+        if tx.is_empty() {
+            return Err(Error::InvalidWriteBufferLength);
+        }
+        if rx.is_empty() {
+            return Err(Error::InvalidReadBufferLength);
+        }
+        self.transaction(|i2c| {
+            i2c.write_internal(addr, tx)?;
+            i2c.repstart();
+            i2c.read_internal(addr, rx)
+        })
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<'pio, P, SM, Sda, Scl> eh1::Write for I2C<'pio, P, SM, Sda, Scl>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    Sda: PinId,
+    Scl: PinId,
+    Function<P>: ValidPinMode<Sda> + ValidPinMode<Scl>,
+{
+    type Error = Error;
+
+    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        Write::write(self, addr, bytes)
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<'pio, P, SM, Sda, Scl> eh1::WriteRead for I2C<'pio, P, SM, Sda, Scl>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    Sda: PinId,
+    Scl: PinId,
+    Function<P>: ValidPinMode<Sda> + ValidPinMode<Scl>,
+{
+    type Error = Error;
+
+    fn write_read(&mut self, addr: u8, tx: &[u8], rx: &mut [u8]) -> Result<(), Self::Error> {
+        WriteRead::write_read(self, addr, tx, rx)
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<'pio, P, SM, Sda, Scl> eh1::Read for I2C<'pio, P, SM, Sda, Scl>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    Sda: PinId,
+    Scl: PinId,
+    Function<P>: ValidPinMode<Sda> + ValidPinMode<Scl>,
+{
+    type Error = Error;
+
+    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        Read::read(self, addr, buffer)
+    }
+}
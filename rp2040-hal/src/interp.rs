@@ -0,0 +1,312 @@
+//! Interpolators (INTERP0 and INTERP1)
+//!
+//! Each core has two interpolators in its SIO, which compute addresses and blends of values in a
+//! single cycle. An interpolator has two lanes, each of which shifts and masks its accumulator,
+//! and adds its base to the result. Popping the results writes the result of each lane back to
+//! its accumulator, ready for the next step. See section 2.3.1.6 of the [RP2040 datasheet](https://datasheets.raspberrypi.org/rp2040/rp2040-datasheet.pdf)
+//! for more details.
+//!
+//! The interpolators are taken from the [`Sio`](crate::sio::Sio).
+//!
+//! ## Texture mapping
+//!
+//! Stepping through a 2D texture of 64x64 bytes with fixed-point coordinates, the address of
+//! each texel is popped from the interpolator:
+//! ```no_run
+//! use rp2040_hal::{interp::LaneCtrl, pac, sio::Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut sio = Sio::new(peripherals.SIO);
+//! let interp = &mut sio.interp0;
+//!
+//! static TEXTURE: [u8; 64 * 64] = [0; 64 * 64];
+//! const UV_FRACTIONAL_BITS: u8 = 16;
+//! const WIDTH_BITS: u8 = 6;
+//! const HEIGHT_BITS: u8 = 6;
+//!
+//! // Lane 0 takes the integer part of u as the column
+//! interp.lane0().configure(LaneCtrl {
+//!     shift: UV_FRACTIONAL_BITS,
+//!     mask_lsb: 0,
+//!     mask_msb: WIDTH_BITS - 1,
+//!     add_raw: true,
+//!     ..LaneCtrl::new()
+//! });
+//! // Lane 1 takes the integer part of v as the row, times the width
+//! interp.lane1().configure(LaneCtrl {
+//!     shift: UV_FRACTIONAL_BITS - WIDTH_BITS,
+//!     mask_lsb: WIDTH_BITS,
+//!     mask_msb: WIDTH_BITS + HEIGHT_BITS - 1,
+//!     add_raw: true,
+//!     ..LaneCtrl::new()
+//! });
+//! interp.set_base2(TEXTURE.as_ptr() as u32);
+//!
+//! // Start at (0.5, 2.25), and step by (1.5, 0.25)
+//! interp.lane0().set_accum(1 << 15);
+//! interp.lane1().set_accum(9 << 14);
+//! interp.lane0().set_base(3 << 15);
+//! interp.lane1().set_base(1 << 14);
+//!
+//! let mut line = [0u8; 32];
+//! for texel in line.iter_mut() {
+//!     *texel = unsafe { *(interp.pop() as *const u8) };
+//! }
+//! ```
+//!
+//! ## Linear blending
+//!
+//! In blend mode, which only INTERP0 has, lane 1 blends the bases of the lanes by the 8 low bits
+//! of its accumulator:
+//! ```no_run
+//! use rp2040_hal::{interp::LaneCtrl, pac, sio::Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut sio = Sio::new(peripherals.SIO);
+//! let interp = &mut sio.interp0;
+//!
+//! interp.lane0().configure(LaneCtrl {
+//!     blend: true,
+//!     ..LaneCtrl::new()
+//! });
+//! interp.lane1().configure(LaneCtrl::new());
+//!
+//! interp.lane0().set_base(1000);
+//! interp.lane1().set_base(2000);
+//! // A quarter of the way from 1000 to 2000: 1250
+//! interp.lane1().set_accum(64);
+//! let blended = interp.lane1().peek();
+//! ```
+use core::marker::PhantomData;
+use core::ptr;
+
+use crate::pac;
+
+/// Offset of the registers of INTERP0 in the SIO
+const INTERP0_OFFSET: usize = 0x080;
+/// Offset of the registers of INTERP1 in the SIO
+const INTERP1_OFFSET: usize = 0x0c0;
+
+// Offsets of the registers of an interpolator
+const ACCUM0: usize = 0x00;
+const BASE0: usize = 0x08;
+const BASE2: usize = 0x10;
+const POP_LANE0: usize = 0x14;
+const POP_FULL: usize = 0x1c;
+const PEEK_LANE0: usize = 0x20;
+const PEEK_FULL: usize = 0x28;
+const CTRL_LANE0: usize = 0x2c;
+const ACCUM0_ADD: usize = 0x34;
+const BASE_1AND0: usize = 0x3c;
+
+/// Configuration of a lane of an interpolator
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LaneCtrl {
+    /// Right rotation applied to the accumulator, from 0 to 31
+    pub shift: u8,
+    /// The least significant bit kept by the mask, from 0 to 31
+    pub mask_lsb: u8,
+    /// The most significant bit kept by the mask, from 0 to 31, and no less than `mask_lsb`
+    pub mask_msb: u8,
+    /// Sign-extend the masked value from `mask_msb`, to add it to the base as a signed value
+    pub signed: bool,
+    /// Shift and mask the accumulator of the other lane instead
+    pub cross_input: bool,
+    /// Write the result of the other lane back to the accumulator of this one when popping
+    pub cross_result: bool,
+    /// Add the accumulator to the base without shifting or masking it, in the result of the lane
+    ///
+    /// Popping then adds the base to the accumulator. The full result still adds the shifted and
+    /// masked accumulator.
+    pub add_raw: bool,
+    /// ORed into bits 29:28 of the result, to turn it into an address in a given memory region
+    pub force_msb: u8,
+    /// Blend mode, only available on lane 0 of INTERP0
+    ///
+    /// Lane 1 then returns the linear interpolation of the bases of lane 0 and lane 1, by the 8
+    /// low bits of its accumulator, as a fraction of 256.
+    pub blend: bool,
+    /// Clamp mode, only available on lane 0 of INTERP1
+    ///
+    /// The result of the lane is then clamped between its base and the base of lane 1.
+    pub clamp: bool,
+}
+
+impl LaneCtrl {
+    /// The configuration after reset: no shift, and the whole accumulator kept by the mask.
+    pub const fn new() -> Self {
+        LaneCtrl {
+            shift: 0,
+            mask_lsb: 0,
+            mask_msb: 31,
+            signed: false,
+            cross_input: false,
+            cross_result: false,
+            add_raw: false,
+            force_msb: 0,
+            blend: false,
+            clamp: false,
+        }
+    }
+
+    /// The value of the CTRL_LANE register
+    fn bits(&self) -> u32 {
+        u32::from(self.shift & 0x1f)
+            | u32::from(self.mask_lsb & 0x1f) << 5
+            | u32::from(self.mask_msb & 0x1f) << 10
+            | u32::from(self.signed) << 15
+            | u32::from(self.cross_input) << 16
+            | u32::from(self.cross_result) << 17
+            | u32::from(self.add_raw) << 18
+            | u32::from(self.force_msb & 0x3) << 19
+            | u32::from(self.blend) << 21
+            | u32::from(self.clamp) << 22
+    }
+}
+
+impl Default for LaneCtrl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Interpolator `N` of the core executing the code
+pub struct Interp<const N: usize> {
+    _private: PhantomData<()>,
+}
+
+/// INTERP0, which has the blend mode
+pub type Interp0 = Interp<0>;
+
+/// INTERP1, which has the clamp mode
+pub type Interp1 = Interp<1>;
+
+impl<const N: usize> Interp<N> {
+    pub(crate) fn new() -> Self {
+        Interp {
+            _private: PhantomData,
+        }
+    }
+
+    fn reg(&self, offset: usize) -> *mut u32 {
+        let base = if N == 0 {
+            INTERP0_OFFSET
+        } else {
+            INTERP1_OFFSET
+        };
+        (pac::SIO::ptr() as usize + base + offset) as *mut u32
+    }
+
+    fn read(&self, offset: usize) -> u32 {
+        // Safety: the registers of the interpolators of each core are only accessed through
+        // these handles, which the core owns
+        unsafe { ptr::read_volatile(self.reg(offset)) }
+    }
+
+    fn write(&mut self, offset: usize, value: u32) {
+        // Safety: as in `read`
+        unsafe { ptr::write_volatile(self.reg(offset), value) }
+    }
+
+    /// Lane 0 of the interpolator
+    pub fn lane0(&mut self) -> Lane<'_, N> {
+        Lane {
+            interp: self,
+            lane: 0,
+        }
+    }
+
+    /// Lane 1 of the interpolator
+    pub fn lane1(&mut self) -> Lane<'_, N> {
+        Lane {
+            interp: self,
+            lane: 1,
+        }
+    }
+
+    /// Set the base added to the full result.
+    pub fn set_base2(&mut self, value: u32) {
+        self.write(BASE2, value);
+    }
+
+    /// The base added to the full result.
+    pub fn base2(&self) -> u32 {
+        self.read(BASE2)
+    }
+
+    /// Set the bases of both lanes at once: the 16 low bits of `value` are the base of lane 0 and
+    /// the 16 high bits the base of lane 1, each sign-extended if their lane is signed.
+    pub fn set_base_1and0(&mut self, value: u32) {
+        self.write(BASE_1AND0, value);
+    }
+
+    /// Read the full result, which is the sum of the results of both lanes and of base 2, and
+    /// write the result of each lane back to its accumulator.
+    pub fn pop(&mut self) -> u32 {
+        self.read(POP_FULL)
+    }
+
+    /// Read the full result, without changing the accumulators.
+    pub fn peek(&self) -> u32 {
+        self.read(PEEK_FULL)
+    }
+}
+
+/// A lane of an [`Interp`]
+pub struct Lane<'a, const N: usize> {
+    interp: &'a mut Interp<N>,
+    lane: usize,
+}
+
+impl<'a, const N: usize> Lane<'a, N> {
+    /// Configure the lane.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ctrl` sets the blend mode on a lane other than lane 0 of INTERP0, or the clamp
+    /// mode on a lane other than lane 0 of INTERP1.
+    pub fn configure(&mut self, ctrl: LaneCtrl) {
+        assert!(
+            !ctrl.blend || (N == 0 && self.lane == 0),
+            "Only lane 0 of INTERP0 has the blend mode"
+        );
+        assert!(
+            !ctrl.clamp || (N == 1 && self.lane == 0),
+            "Only lane 0 of INTERP1 has the clamp mode"
+        );
+        self.interp.write(CTRL_LANE0 + 4 * self.lane, ctrl.bits());
+    }
+
+    /// Set the accumulator of the lane.
+    pub fn set_accum(&mut self, value: u32) {
+        self.interp.write(ACCUM0 + 4 * self.lane, value);
+    }
+
+    /// The accumulator of the lane.
+    pub fn accum(&self) -> u32 {
+        self.interp.read(ACCUM0 + 4 * self.lane)
+    }
+
+    /// Add `value` to the accumulator of the lane, in a single write.
+    pub fn add_accum(&mut self, value: u32) {
+        self.interp.write(ACCUM0_ADD + 4 * self.lane, value);
+    }
+
+    /// Set the base of the lane, added to its result.
+    pub fn set_base(&mut self, value: u32) {
+        self.interp.write(BASE0 + 4 * self.lane, value);
+    }
+
+    /// The base of the lane.
+    pub fn base(&self) -> u32 {
+        self.interp.read(BASE0 + 4 * self.lane)
+    }
+
+    /// Read the result of the lane, and write the result of each lane back to its accumulator.
+    pub fn pop(&mut self) -> u32 {
+        self.interp.read(POP_LANE0 + 4 * self.lane)
+    }
+
+    /// Read the result of the lane, without changing the accumulators.
+    pub fn peek(&self) -> u32 {
+        self.interp.read(PEEK_LANE0 + 4 * self.lane)
+    }
+}
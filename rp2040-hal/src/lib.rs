@@ -18,15 +18,21 @@ pub extern crate rp2040_pac as pac;
 pub mod adc;
 pub(crate) mod atomic_register_access;
 pub mod clocks;
+#[cfg(feature = "critical-section-impl")]
 mod critical_section_impl;
 pub mod dma;
+pub mod flash;
 pub mod gpio;
 pub mod i2c;
+#[cfg(feature = "i2c-pio")]
+pub mod i2c_pio;
+pub mod interp;
 pub mod multicore;
 pub mod pio;
 pub mod pll;
 pub mod prelude;
 pub mod pwm;
+pub(crate) mod ram_func;
 pub mod resets;
 pub mod rom_data;
 pub mod rosc;
@@ -34,6 +40,7 @@ pub mod rtc;
 pub mod sio;
 pub mod spi;
 pub mod ssi;
+pub mod sysinfo;
 pub mod timer;
 pub mod typelevel;
 pub mod uart;
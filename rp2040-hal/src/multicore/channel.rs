@@ -0,0 +1,213 @@
+//! Bounded channel between the cores
+//!
+//! A [`Channel`] is a queue in RAM with a single producer and a single consumer, which may run on
+//! different cores. Unlike the inter-core FIFO, it carries values of any type, and holds as many
+//! of them as it is sized for.
+//!
+//! It only relies on atomic loads and stores, which the Cortex-M0+ supports, and wakes the other
+//! core up with an event once it has sent or received a value.
+//!
+//! ## Usage
+//! ```no_run
+//! use rp2040_hal::multicore::Channel;
+//!
+//! static SAMPLES: Channel<u16, 16> = Channel::new();
+//!
+//! fn core1_task() -> ! {
+//!     // Safety: core 1 is the only producer
+//!     let mut tx = unsafe { SAMPLES.sender() };
+//!     let mut sample = 0;
+//!     loop {
+//!         tx.send_blocking(sample);
+//!         sample = sample.wrapping_add(1);
+//!     }
+//! }
+//!
+//! // On core 0, once core 1 runs `core1_task`
+//! // Safety: core 0 is the only consumer
+//! let mut rx = unsafe { SAMPLES.receiver() };
+//! let sample = rx.recv_blocking();
+//! ```
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Queue of up to `N - 1` values of `T`, sent from one core to the other
+///
+/// One slot always stays empty, to tell a full queue from an empty one, which is why it holds one
+/// value less than its size.
+pub struct Channel<T, const N: usize> {
+    buffer: [UnsafeCell<MaybeUninit<T>>; N],
+    /// Index of the next value to receive, only written by the receiver
+    head: AtomicUsize,
+    /// Index of the next value to send, only written by the sender
+    tail: AtomicUsize,
+}
+
+// Safety: the sender and the receiver never access the same slot at the same time
+unsafe impl<T: Send, const N: usize> Sync for Channel<T, N> {}
+
+impl<T, const N: usize> Channel<T, N> {
+    const EMPTY: UnsafeCell<MaybeUninit<T>> = UnsafeCell::new(MaybeUninit::uninit());
+
+    /// Create an empty channel.
+    pub const fn new() -> Self {
+        Channel {
+            buffer: [Self::EMPTY; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Split the channel into its sending and receiving halves.
+    pub fn split(&'static mut self) -> (Sender<T, N>, Receiver<T, N>) {
+        let channel: &'static Self = self;
+        (Sender { channel }, Receiver { channel })
+    }
+
+    /// Get the sending half of the channel.
+    ///
+    /// # Safety
+    ///
+    /// There must be no other `Sender` of this channel.
+    pub unsafe fn sender(&'static self) -> Sender<T, N> {
+        Sender { channel: self }
+    }
+
+    /// Get the receiving half of the channel.
+    ///
+    /// # Safety
+    ///
+    /// There must be no other `Receiver` of this channel.
+    pub unsafe fn receiver(&'static self) -> Receiver<T, N> {
+        Receiver { channel: self }
+    }
+
+    /// The number of values the channel can hold.
+    pub fn capacity(&self) -> usize {
+        N.saturating_sub(1)
+    }
+
+    /// The number of values waiting in the channel.
+    pub fn len(&self) -> usize {
+        if N == 0 {
+            return 0;
+        }
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        (tail + N - head) % N
+    }
+
+    /// Whether no value is waiting in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Only to be called by the sender
+    fn enqueue(&self, value: T) -> Result<(), T> {
+        if N == 0 {
+            return Err(value);
+        }
+        let tail = self.tail.load(Ordering::Relaxed);
+        let next = (tail + 1) % N;
+        if next == self.head.load(Ordering::Acquire) {
+            return Err(value);
+        }
+        // Safety: the receiver doesn't read this slot until `tail` moves past it
+        unsafe { (*self.buffer[tail].get()).as_mut_ptr().write(value) };
+        self.tail.store(next, Ordering::Release);
+        // Wake the receiver up, in case it waits for a value
+        cortex_m::asm::sev();
+        Ok(())
+    }
+
+    /// Only to be called by the receiver
+    fn dequeue(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head == self.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        // Safety: the sender wrote this slot before moving `tail` past it, and doesn't write it
+        // again until `head` moves past it
+        let value = unsafe { (*self.buffer[head].get()).as_ptr().read() };
+        self.head.store((head + 1) % N, Ordering::Release);
+        // Wake the sender up, in case it waits for room
+        cortex_m::asm::sev();
+        Some(value)
+    }
+}
+
+impl<T, const N: usize> Default for Channel<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for Channel<T, N> {
+    fn drop(&mut self) {
+        while self.dequeue().is_some() {}
+    }
+}
+
+/// The sending half of a [`Channel`]
+pub struct Sender<T: 'static, const N: usize> {
+    channel: &'static Channel<T, N>,
+}
+
+// Safety: only one `Sender` of a channel exists, so it can be moved to the other core
+unsafe impl<T: Send, const N: usize> Send for Sender<T, N> {}
+
+impl<T, const N: usize> Sender<T, N> {
+    /// Send a value, or return it if the channel is full.
+    pub fn send(&mut self, value: T) -> Result<(), T> {
+        self.channel.enqueue(value)
+    }
+
+    /// Send a value, sleeping until the channel has room for it.
+    pub fn send_blocking(&mut self, mut value: T) {
+        loop {
+            match self.channel.enqueue(value) {
+                Ok(()) => return,
+                Err(v) => value = v,
+            }
+            // The receiver raises an event once it makes room
+            cortex_m::asm::wfe();
+        }
+    }
+
+    /// Whether the channel is full.
+    pub fn is_full(&self) -> bool {
+        self.channel.len() == self.channel.capacity()
+    }
+}
+
+/// The receiving half of a [`Channel`]
+pub struct Receiver<T: 'static, const N: usize> {
+    channel: &'static Channel<T, N>,
+}
+
+// Safety: only one `Receiver` of a channel exists, so it can be moved to the other core
+unsafe impl<T: Send, const N: usize> Send for Receiver<T, N> {}
+
+impl<T, const N: usize> Receiver<T, N> {
+    /// Receive a value, or `None` if the channel is empty.
+    pub fn recv(&mut self) -> Option<T> {
+        self.channel.dequeue()
+    }
+
+    /// Receive a value, sleeping until one is sent.
+    pub fn recv_blocking(&mut self) -> T {
+        loop {
+            if let Some(value) = self.channel.dequeue() {
+                return value;
+            }
+            // The sender raises an event once it sends a value
+            cortex_m::asm::wfe();
+        }
+    }
+
+    /// Whether the channel is empty.
+    pub fn is_empty(&self) -> bool {
+        self.channel.is_empty()
+    }
+}
@@ -0,0 +1,489 @@
+//! Multicore support
+//!
+//! Core 1 sleeps in the bootrom after reset, until core 0 hands it a vector table, a stack
+//! pointer and an entry point through the inter-core FIFO. [`Core::spawn`] goes through this
+//! handshake, described in section 2.8.2 of the [RP2040 datasheet](https://datasheets.raspberrypi.org/rp2040/rp2040-datasheet.pdf).
+//!
+//! ## Usage
+//! ```no_run
+//! use rp2040_hal::{multicore::{Multicore, Stack}, pac, sio::Sio};
+//!
+//! static mut CORE1_STACK: Stack<4096> = Stack::new();
+//!
+//! fn core1_task() -> ! {
+//!     loop {}
+//! }
+//!
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut sio = Sio::new(peripherals.SIO);
+//! let mut mc = Multicore::new(&mut peripherals.PSM, &mut peripherals.PPB, &mut sio.fifo);
+//! let cores = mc.cores();
+//! let core1 = &mut cores[1];
+//! core1.spawn(unsafe { &mut CORE1_STACK.mem }, core1_task).unwrap();
+//! ```
+//!
+//! The cores can then share data through a [`Channel`] or a [`CoreMutex`], or core 0 can hand
+//! function calls to core 1 with [`call_on_core1`].
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::pac;
+use crate::ram_func;
+use crate::sio::{CoreId, Sio, SioFifo};
+
+pub mod channel;
+pub use channel::{Channel, Receiver, Sender};
+
+pub mod mutex;
+pub use mutex::{CoreMutex, CoreMutexGuard};
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+/// Errors for multicore operations.
+#[derive(Debug)]
+pub enum Error {
+    /// Operation is invalid on this core.
+    InvalidCore,
+    /// Core was unresposive to commands.
+    Unresponsive,
+    /// The stack is too small to hold the stack guard and the launch parameters.
+    StackTooSmall,
+}
+
+/// The number of words of the stack which are not usable by the code running on core 1: the
+/// stack guard may cover up to 63 bytes at the bottom of the stack, and the launch parameters
+/// take 3 words at its top.
+const STACK_OVERHEAD: usize = 16 + 3;
+
+/// A stack for core 1
+///
+/// It is aligned so that the stack guard covers exactly its first 32 bytes. Declare it as a
+/// `static mut`, so that it lives for as long as core 1 may run.
+#[repr(C, align(32))]
+pub struct Stack<const SIZE: usize> {
+    /// Memory of the stack, to hand to [`Core::spawn`]
+    pub mem: [usize; SIZE],
+}
+
+impl<const SIZE: usize> Stack<SIZE> {
+    /// Create a new stack, of `SIZE` words.
+    pub const fn new() -> Stack<SIZE> {
+        Stack { mem: [0; SIZE] }
+    }
+}
+
+impl<const SIZE: usize> Default for Stack<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// We pass data to cores via the stack, so we read
+// the data off the stack and into parameters that
+// rust can read here. Ideally this would be a
+// #[naked] function but that is not stable yet.
+static MULTICORE_TRAMPOLINE: [u16; 2] = [
+    0xbd03, // pop {r0, r1, pc} - call wrapper (pc) with r0 and r1
+    0x46c0, // nop - pad this out to 32 bits long
+];
+
+#[inline(always)]
+fn install_stack_guard(stack_bottom: *mut usize) {
+    let core = unsafe { pac::CorePeripherals::steal() };
+
+    // Trap if MPU is already configured
+    if core.MPU.ctrl.read() != 0 {
+        cortex_m::asm::udf();
+    }
+
+    // The minimum we can protect is 32 bytes on a 32 byte boundary, so round up which will
+    // just shorten the valid stack range a tad.
+    let addr = (stack_bottom as u32 + 31) & !31;
+    // Mask is 1 bit per 32 bytes of the 256 byte range... clear the bit for the segment we want
+    let subregion_select = 0xff ^ (1 << ((addr >> 5) & 7));
+    unsafe {
+        core.MPU.ctrl.write(5); // enable mpu with background default map
+        core.MPU.rbar.write((addr & !0xff) | 0x8);
+        core.MPU.rasr.write(
+            1 // enable region
+               | (0x7 << 1) // size 2^(7 + 1) = 256
+               | (subregion_select << 8)
+               | 0x10000000, // XN = disable instruction fetch; no other bits means no permissions
+        );
+    }
+}
+
+/// Whether core 1 runs code spawned by [`Core::spawn`], and must be parked while the flash is not
+/// accessible
+static CORE1_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Whether [`with_other_core_parked`] holds the other core parked
+static OTHER_CORE_PARKED: AtomicBool = AtomicBool::new(false);
+
+/// Hold core 1 in reset, then release it, so that it waits for code to run in the bootrom.
+fn reset_core1(psm: &mut pac::PSM) {
+    CORE1_RUNNING.store(false, Ordering::SeqCst);
+    psm.frce_off.modify(|_, w| w.proc1().set_bit());
+    while !psm.frce_off.read().proc1().bit_is_set() {
+        cortex_m::asm::nop();
+    }
+    psm.frce_off.modify(|_, w| w.proc1().clear_bit());
+}
+
+#[inline(always)]
+fn core1_setup(stack_bottom: *mut usize) {
+    install_stack_guard(stack_bottom);
+    // TODO: irq priorities
+}
+
+/// Multicore execution management.
+pub struct Multicore<'p> {
+    cores: [Core<'p>; 2],
+}
+
+impl<'p> Multicore<'p> {
+    /// Create a new |Multicore| instance.
+    pub fn new(psm: &'p mut pac::PSM, ppb: &'p mut pac::PPB, fifo: &'p mut SioFifo) -> Self {
+        Self {
+            cores: [
+                Core {
+                    inner: None,
+                    stack: None,
+                },
+                Core {
+                    inner: Some((psm, ppb, fifo)),
+                    stack: None,
+                },
+            ],
+        }
+    }
+
+    /// Get the available |Core| instances.
+    pub fn cores(&mut self) -> &mut [Core<'p>] {
+        &mut self.cores
+    }
+}
+
+/// A handle for controlling a logical core.
+pub struct Core<'p> {
+    inner: Option<(&'p mut pac::PSM, &'p mut pac::PPB, &'p mut SioFifo)>,
+    /// The stack handed to the core by [`spawn`](Self::spawn), which the core owns until it is
+    /// reset
+    stack: Option<(*mut usize, usize)>,
+}
+
+impl<'p> Core<'p> {
+    /// Get the id of this core.
+    pub fn id(&self) -> u8 {
+        match self.inner {
+            None => 0,
+            Some(..) => 1,
+        }
+    }
+
+    /// Reset this core, stopping the code it runs.
+    ///
+    /// The core goes back to waiting for code to run in the bootrom. If it was spawned through
+    /// this handle, the stack it was given is returned, as it isn't in use anymore.
+    pub fn reset(&mut self) -> Result<Option<&'static mut [usize]>, Error> {
+        if let Some((psm, _, _)) = self.inner.as_mut() {
+            reset_core1(psm);
+            // Safety: the stack was given to us as a `&'static mut`, and the core which used it
+            // is stopped
+            Ok(self
+                .stack
+                .take()
+                .map(|(ptr, len)| unsafe { core::slice::from_raw_parts_mut(ptr, len) }))
+        } else {
+            Err(Error::InvalidCore)
+        }
+    }
+
+    fn inner_spawn(
+        &mut self,
+        wrapper: *mut (),
+        entry: *mut (),
+        stack: &'static mut [usize],
+    ) -> Result<(), Error> {
+        if let Some((psm, ppb, fifo)) = self.inner.as_mut() {
+            if stack.len() < STACK_OVERHEAD {
+                return Err(Error::StackTooSmall);
+            }
+
+            // Reset the core, which stops it from using the stack it may have been given before
+            reset_core1(psm);
+
+            // Set up the stack, whose top must be aligned to 8 bytes as the ABI requires
+            let stack_bottom = stack.as_mut_ptr();
+            let stack_len = stack.len();
+            let mut stack_ptr = unsafe { stack_bottom.add(stack_len) };
+            if stack_ptr as usize % 8 != 0 {
+                stack_ptr = unsafe { stack_ptr.sub(1) };
+            }
+
+            let mut push = |v: usize| unsafe {
+                stack_ptr = stack_ptr.sub(1);
+                stack_ptr.write(v);
+            };
+
+            push(wrapper as usize);
+            push(stack_bottom as usize);
+            push(entry as usize);
+
+            let vector_table = ppb.vtor.read().bits();
+
+            // After reset, core 1 is waiting to receive commands over FIFO.
+            // This is the sequence to have it jump to some code.
+            let cmd_seq = [
+                0,
+                0,
+                1,
+                vector_table as usize,
+                stack_ptr as usize,
+                MULTICORE_TRAMPOLINE.as_ptr() as usize + 1,
+            ];
+
+            let mut seq = 0;
+            let mut fails = 0;
+            loop {
+                let cmd = cmd_seq[seq] as u32;
+                if cmd == 0 {
+                    fifo.drain();
+                    cortex_m::asm::sev();
+                }
+                fifo.write_blocking(cmd);
+                let response = fifo.read_blocking();
+                if cmd == response {
+                    seq += 1;
+                } else {
+                    seq = 0;
+                    fails += 1;
+                    if fails > 16 {
+                        return Err(Error::Unresponsive);
+                    }
+                }
+                if seq >= cmd_seq.len() {
+                    break;
+                }
+            }
+
+            self.stack = Some((stack_bottom, stack_len));
+            CORE1_RUNNING.store(true, Ordering::SeqCst);
+            Ok(())
+        } else {
+            Err(Error::InvalidCore)
+        }
+    }
+
+    /// Spawn a function on this core.
+    #[cfg(not(feature = "alloc"))]
+    ///
+    /// The core is reset first, so spawning a function on a core which already runs one replaces
+    /// it. The core owns `stack` until it is [`reset`](Self::reset).
+    pub fn spawn(&mut self, stack: &'static mut [usize], entry: fn() -> !) -> Result<(), Error> {
+        #[allow(improper_ctypes_definitions)]
+        extern "C" fn core1_no_alloc(entry: fn() -> !, stack_bottom: *mut usize) -> ! {
+            core1_setup(stack_bottom);
+            entry();
+        }
+
+        self.inner_spawn(core1_no_alloc as _, entry as _, stack)
+    }
+
+    /// Spawn a function on this core.
+    #[cfg(feature = "alloc")]
+    ///
+    /// The core is reset first, so spawning a function on a core which already runs one replaces
+    /// it. The core owns `stack` until it is [`reset`](Self::reset).
+    pub fn spawn<F>(&mut self, stack: &'static mut [usize], entry: F) -> Result<(), Error>
+    where
+        F: FnOnce() -> bad::Never,
+        F: Send + 'static,
+    {
+        use alloc::boxed::Box;
+
+        let main: Box<dyn FnOnce() -> bad::Never> = Box::new(move || entry());
+        let p = Box::into_raw(Box::new(main));
+
+        extern "C" fn core1_alloc(entry: *mut (), stack_bottom: *mut usize) -> ! {
+            core1_setup(stack_bottom);
+            let main = unsafe { Box::from_raw(entry as *mut Box<dyn FnOnce() -> bad::Never>) };
+            main();
+        }
+
+        self.inner_spawn(core1_alloc as _, p as _, stack)
+    }
+}
+
+/// Run `f(arg)` on core 1, and return its result.
+///
+/// Core 1 must run [`serve_calls`], to which the call is handed through the inter-core FIFO:
+/// nothing else may use the FIFO while core 1 serves calls.
+///
+/// ```no_run
+/// use rp2040_hal::{multicore::{call_on_core1, serve_calls, Multicore, Stack}, pac, sio::Sio};
+///
+/// static mut CORE1_STACK: Stack<4096> = Stack::new();
+///
+/// fn checksum(len: u32) -> u32 {
+///     (0..len).fold(0, |sum, i| sum ^ i)
+/// }
+///
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut sio = Sio::new(peripherals.SIO);
+/// let mut mc = Multicore::new(&mut peripherals.PSM, &mut peripherals.PPB, &mut sio.fifo);
+/// let cores = mc.cores();
+/// cores[1].spawn(unsafe { &mut CORE1_STACK.mem }, serve_calls).unwrap();
+///
+/// let sum = call_on_core1(&mut sio.fifo, checksum, 1000);
+/// ```
+pub fn call_on_core1(fifo: &mut SioFifo, f: fn(u32) -> u32, arg: u32) -> u32 {
+    fifo.write_blocking(f as usize as u32);
+    fifo.write_blocking(arg);
+    fifo.read_blocking()
+}
+
+/// Run the calls made with [`call_on_core1`], forever.
+///
+/// Spawn it on core 1, to use it as a coprocessor. It also parks core 1 on the requests of
+/// [`with_other_core_parked`], so that core 0 can write to the flash.
+pub fn serve_calls() -> ! {
+    // Safety: core 1 doesn't use its end of the FIFO for anything else
+    let mut fifo = unsafe { SioFifo::steal() };
+    loop {
+        let f = fifo.read_blocking();
+        if park_on_request(f) {
+            continue;
+        }
+        let arg = fifo.read_blocking();
+        // Safety: core 0 sent a function pointer, with `call_on_core1`
+        let f: fn(u32) -> u32 = unsafe { core::mem::transmute(f as usize) };
+        fifo.write_blocking(f(arg));
+    }
+}
+
+/// Sent through the FIFO by [`with_other_core_parked`], to ask the other core to park
+const PARK_REQUEST: u32 = 0x7061_726b;
+/// Sent back by the core once it is parked
+const PARK_ACK: u32 = 0x7061_726c;
+/// Sent through the FIFO by [`with_other_core_parked`], to release the parked core
+const PARK_RELEASE: u32 = 0x7061_726d;
+
+/// Park the other core while `f` runs.
+///
+/// The parked core runs a loop in RAM with its interrupts disabled, so that `f` can for instance
+/// write to the flash, which the other core can't execute code from meanwhile.
+///
+/// The other core must pass the words it reads from its end of the FIFO to [`park_on_request`],
+/// typically in the handler of its FIFO interrupt, as [`serve_calls`] does: this blocks until it
+/// parks. Words other than its acknowledgement which it sends meanwhile are dropped.
+///
+/// The [`flash`](crate::flash) functions call this themselves when core 1 was spawned, so it is
+/// only needed to keep the other core parked across several of them.
+pub fn with_other_core_parked<R>(fifo: &mut SioFifo, f: impl FnOnce() -> R) -> R {
+    if OTHER_CORE_PARKED.load(Ordering::SeqCst) {
+        return f();
+    }
+
+    fifo.write_blocking(PARK_REQUEST);
+    // Spin rather than wait for an event, as the other core doesn't raise one once parked
+    while fifo.read() != Some(PARK_ACK) {}
+    OTHER_CORE_PARKED.store(true, Ordering::SeqCst);
+
+    let result = f();
+
+    OTHER_CORE_PARKED.store(false, Ordering::SeqCst);
+    fifo.write_blocking(PARK_RELEASE);
+    result
+}
+
+/// Park the other core while `f` runs if it runs code, as for [`with_other_core_parked`].
+///
+/// Core 1 is only known to run once spawned by [`Core::spawn`], while core 0 always runs.
+pub(crate) fn with_other_core_parked_if_running<R>(f: impl FnOnce() -> R) -> R {
+    let other_core_running = match Sio::core() {
+        CoreId::Core0 => CORE1_RUNNING.load(Ordering::SeqCst),
+        CoreId::Core1 => true,
+    };
+    if other_core_running {
+        // Safety: the FIFO is only used for the park handshake meanwhile, as the caller blocks
+        // until `f` returns
+        let mut fifo = unsafe { SioFifo::steal() };
+        with_other_core_parked(&mut fifo, f)
+    } else {
+        f()
+    }
+}
+
+/// Park the core executing this if `word`, read from its end of the FIFO, is a request of
+/// [`with_other_core_parked`] running on the other core, until the other core releases it.
+///
+/// Returns whether `word` was such a request.
+///
+/// ```no_run
+/// use rp2040_hal::{multicore::park_on_request, sio::SioFifo};
+/// // In the handler of the FIFO interrupt of core 1
+/// # fn handler(fifo: &mut SioFifo) {
+/// while let Some(word) = fifo.read() {
+///     if !park_on_request(word) {
+///         // Handle the word
+///     }
+/// }
+/// # }
+/// ```
+pub fn park_on_request(word: u32) -> bool {
+    if word != PARK_REQUEST {
+        return false;
+    }
+    // Safety: we're only computing the addresses of registers
+    let sio = unsafe { &*pac::SIO::ptr() };
+    let (fifo_st, fifo_wr, fifo_rd) = (
+        sio.fifo_st.as_ptr(),
+        sio.fifo_wr.as_ptr(),
+        sio.fifo_rd.as_ptr(),
+    );
+    cortex_m::interrupt::free(|_| unsafe { park_in_ram(fifo_st, fifo_wr, fifo_rd) });
+    true
+}
+
+/// Acknowledge the park request, and spin until the other core releases this one.
+///
+/// This must only touch registers through the [`ram_func`] accessors, so that nothing is fetched
+/// from flash while the other core may be writing to it.
+///
+/// # Safety
+///
+/// The pointers must point to the `FIFO_ST`, `FIFO_WR` and `FIFO_RD` registers of the SIO, and
+/// interrupts must be disabled.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn park_in_ram(fifo_st: *const u32, fifo_wr: *mut u32, fifo_rd: *const u32) {
+    const VLD: u32 = 1 << 0;
+    const RDY: u32 = 1 << 1;
+
+    while ram_func::read_u32(fifo_st) & RDY == 0 {}
+    ram_func::write_u32(fifo_wr, PARK_ACK);
+
+    loop {
+        if ram_func::read_u32(fifo_st) & VLD != 0 && ram_func::read_u32(fifo_rd) == PARK_RELEASE {
+            break;
+        }
+    }
+}
+
+// https://github.com/nvzqz/bad-rs/blob/master/src/never.rs
+#[cfg(feature = "alloc")]
+mod bad {
+    pub(crate) type Never = <F as HasOutput>::Output;
+
+    pub trait HasOutput {
+        type Output;
+    }
+
+    impl<O> HasOutput for fn() -> O {
+        type Output = O;
+    }
+
+    type F = fn() -> !;
+}
@@ -0,0 +1,118 @@
+//! Data shared between the cores, guarded by a hardware spinlock
+//!
+//! ## Usage
+//! ```no_run
+//! use rp2040_hal::multicore::CoreMutex;
+//!
+//! // Guarded by spinlock 3
+//! static COUNTERS: CoreMutex<[u32; 4], 3> = CoreMutex::new([0; 4]);
+//!
+//! // On either core
+//! COUNTERS.lock()[1] += 1;
+//! ```
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use crate::sio::{Spinlock, SpinlockValid};
+
+/// Data of type `T`, which either core can access after claiming spinlock `N`
+///
+/// The spinlock excludes the other core, but not the interrupt handlers of the core holding it:
+/// locking the data from an interrupt handler while the code it interrupted holds it is a
+/// deadlock. Lock it within a [`critical_section`] if interrupt handlers lock it too.
+///
+/// Like [`Spinlock`], it is not re-entrant.
+pub struct CoreMutex<T, const N: usize> {
+    data: UnsafeCell<T>,
+}
+
+// Safety: the data is only accessed while the spinlock is held
+unsafe impl<T: Send, const N: usize> Sync for CoreMutex<T, N> {}
+
+impl<T, const N: usize> CoreMutex<T, N> {
+    /// Create a mutex holding `value`.
+    pub const fn new(value: T) -> Self {
+        CoreMutex {
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Access the data without locking it, which is safe as this borrows the mutex mutably.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Return the data held by the mutex.
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T, const N: usize> CoreMutex<T, N>
+where
+    Spinlock<N>: SpinlockValid,
+{
+    /// Lock the data, spinning until the other core releases it.
+    ///
+    /// It is released when the returned guard is dropped.
+    pub fn lock(&self) -> CoreMutexGuard<'_, T, N> {
+        self.guard(Spinlock::<N>::claim())
+    }
+
+    /// Lock the data, or return `None` if it is locked already.
+    pub fn try_lock(&self) -> Option<CoreMutexGuard<'_, T, N>> {
+        Spinlock::<N>::try_claim().map(|lock| self.guard(lock))
+    }
+
+    fn guard(&self, lock: Spinlock<N>) -> CoreMutexGuard<'_, T, N> {
+        // Don't let the accesses to the data move before the spinlock is claimed
+        compiler_fence(Ordering::SeqCst);
+        CoreMutexGuard {
+            mutex: self,
+            _lock: lock,
+        }
+    }
+}
+
+/// Access to the data of a locked [`CoreMutex`]
+pub struct CoreMutexGuard<'a, T, const N: usize>
+where
+    Spinlock<N>: SpinlockValid,
+{
+    mutex: &'a CoreMutex<T, N>,
+    // Released after `drop` runs
+    _lock: Spinlock<N>,
+}
+
+impl<'a, T, const N: usize> Deref for CoreMutexGuard<'a, T, N>
+where
+    Spinlock<N>: SpinlockValid,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: the spinlock is held
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<'a, T, const N: usize> DerefMut for CoreMutexGuard<'a, T, N>
+where
+    Spinlock<N>: SpinlockValid,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: the spinlock is held
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<'a, T, const N: usize> Drop for CoreMutexGuard<'a, T, N>
+where
+    Spinlock<N>: SpinlockValid,
+{
+    fn drop(&mut self) {
+        // Don't let the accesses to the data move after the spinlock is released
+        compiler_fence(Ordering::SeqCst);
+    }
+}
@@ -2,11 +2,20 @@
 //! See [Chapter 3 of the datasheet](https://rptl.io/rp2040-datasheet#section_pio) for more details.
 use crate::{
     atomic_register_access::{write_bitmask_clear, write_bitmask_set},
+    dma::{ReadTarget, WriteTarget},
     resets::SubsystemReset,
 };
+use embedded_time::rate::Hertz;
 use pio::{Program, SideSet, Wrap};
 use rp2040_pac::{PIO0, PIO1};
 
+#[cfg(feature = "pio-drivers")]
+pub mod quadrature;
+#[cfg(feature = "pio-drivers")]
+pub mod uart;
+#[cfg(feature = "pio-drivers")]
+pub mod ws2812;
+
 const PIO_INSTRUCTION_COUNT: usize = 32;
 
 /// PIO Instance
@@ -222,10 +231,26 @@ impl<P: PIOExt> PIO<P> {
     }
 
     /// Removes the specified program from instruction memory, freeing the allocated space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the space of the program is not allocated, for instance when a program shared
+    /// with [`InstalledProgram::share`] is uninstalled twice.
     pub fn uninstall(&mut self, p: InstalledProgram<P>) {
         let instr_mask = ((1 << p.length as u32) - 1) << p.offset as u32;
+        assert!(
+            self.used_instruction_space & instr_mask == instr_mask,
+            "Uninstalling a program which is not installed"
+        );
         self.used_instruction_space &= !instr_mask;
     }
+
+    /// Number of instructions left in instruction memory.
+    ///
+    /// The space may be fragmented, so a program this long may still not fit.
+    pub fn free_instruction_count(&self) -> u32 {
+        PIO_INSTRUCTION_COUNT as u32 - self.used_instruction_space.count_ones()
+    }
 }
 
 /// Handle to a program that was placed in the PIO's instruction memory.
@@ -293,6 +318,14 @@ impl<P: PIOExt> InstalledProgram<P> {
         self.offset + self.wrap.target
     }
 
+    /// Get the address of the first instruction of the installed program.
+    ///
+    /// Add this to the position of a label in the program to get the address the label was
+    /// relocated to, for instance to jump to an alternative entry point.
+    pub fn offset(&self) -> u8 {
+        self.offset
+    }
+
     /// Clones this program handle so that it can be executed by two state machines at the same
     /// time.
     ///
@@ -406,6 +439,25 @@ pub enum PinDir {
     Output,
 }
 
+/// Compute the clock divisor, as the `(int, frac)` pair taken by
+/// [`StateMachine::set_clock_divisor`], running a state machine at `cycles_per_unit` times `rate`.
+///
+/// For instance, a UART program taking 8 cycles per bit runs at 115200 bauds with
+/// `clock_divisor_for_rate(115_200.Hz(), 8, system_freq)`, `system_freq` being the frequency of
+/// `clk_sys`. The divisor is rounded to the nearest 1/256, and clamped to the range supported by
+/// the state machines, 1 to 65536.
+pub fn clock_divisor_for_rate(rate: Hertz, cycles_per_unit: u32, system_freq: Hertz) -> (u16, u8) {
+    let cycles_freq = u64::from(rate.0) * u64::from(cycles_per_unit);
+    let divisor = if cycles_freq == 0 {
+        u64::MAX
+    } else {
+        (u64::from(system_freq.0) * 256 + cycles_freq / 2) / cycles_freq
+    };
+    // 65536 is encoded as an integer part of 0
+    let divisor = divisor.max(1 << 8).min(1 << 24);
+    ((divisor >> 8) as u16, divisor as u8)
+}
+
 /// PIO State Machine (uninitialized, without a program).
 #[derive(Debug)]
 pub struct UninitStateMachine<SM: ValidStateMachine> {
@@ -457,12 +509,16 @@ impl<SM: ValidStateMachine> UninitStateMachine<SM> {
         }
     }
 
-    // Safety: The Send trait assumes this is the only write to sm_clkdiv
     fn set_clock_divisor(&self, divisor: f32) {
         // sm frequency = clock freq / (CLKDIV_INT + CLKDIV_FRAC / 256)
         let int = divisor as u16;
         let frac = ((divisor - int as f32) * 256.0) as u8;
 
+        self.set_clock_divisor_fixed_point(int, frac);
+    }
+
+    // Safety: The Send trait assumes this is the only write to sm_clkdiv
+    fn set_clock_divisor_fixed_point(&self, int: u16, frac: u8) {
         self.sm().sm_clkdiv.write(|w| {
             unsafe {
                 w.int().bits(int);
@@ -514,6 +570,25 @@ impl<SM: ValidStateMachine, State> StateMachine<SM, State> {
         (self.sm, self.program)
     }
 
+    /// Change the clock divisor of the state machine to `int + frac / 256`.
+    ///
+    /// An `int` of 0 stands for 65536. This can be called while the state machine is running:
+    /// the clock divider is restarted, so that the new rate applies from the next cycle.
+    ///
+    /// See [`clock_divisor_for_rate`] to derive the divisor from a target rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `int` is 0 and `frac` is not.
+    pub fn set_clock_divisor(&mut self, int: u16, frac: u8) {
+        assert!(
+            int != 0 || frac == 0,
+            "The fractional part of a clock divisor of 65536 must be 0"
+        );
+        self.sm.set_clock_divisor_fixed_point(int, frac);
+        self.sm.reset_clock();
+    }
+
     /// The address of the instruction currently being executed.
     pub fn instruction_address(&self) -> u32 {
         self.sm.sm().sm_addr.read().bits()
@@ -535,6 +610,15 @@ impl<SM: ValidStateMachine, State> StateMachine<SM, State> {
         self.sm.exec_instruction(instruction);
     }
 
+    /// Execute the instruction immediately, and wait for it to complete.
+    ///
+    /// This is for instructions which may stall, like a `WAIT` or a blocking `PULL`, while the
+    /// state machine is running. Any delay of the instruction is not waited for.
+    pub fn exec_instruction_blocking(&mut self, instruction: u16) {
+        self.sm.exec_instruction(instruction);
+        while self.stalled() {}
+    }
+
     /// Check if the current instruction is stalled.
     pub fn stalled(&self) -> bool {
         self.sm.sm().sm_execctrl.read().exec_stalled().bits()
@@ -616,6 +700,22 @@ impl<SM: ValidStateMachine> StateMachine<SM, Stopped> {
             .sm_pinctrl
             .write(|w| unsafe { w.bits(saved_ctrl.bits()) });
     }
+
+    /// Resets the state machine to the start of its program.
+    ///
+    /// This clears the shift registers and any pending delay or stall, restarts the clock
+    /// divider, and jumps to the wrap target of the program. The FIFOs are left untouched.
+    pub fn restart(&mut self) {
+        self.sm.exec_instruction(
+            pio::InstructionOperands::JMP {
+                condition: pio::JmpCondition::Always,
+                address: self.program.wrap_target(),
+            }
+            .encode(),
+        );
+        self.sm.restart();
+        self.sm.reset_clock();
+    }
 }
 
 impl<P: PIOExt, SM: StateMachineIndex> StateMachine<(P, SM), Stopped> {
@@ -672,10 +772,143 @@ impl<'sm, SM: ValidStateMachine> Drop for Synchronize<'sm, SM> {
     }
 }
 
+impl<SM: ValidStateMachine, State> StateMachine<SM, State> {
+    fn into_state<NewState>(self) -> StateMachine<SM, NewState> {
+        StateMachine {
+            sm: self.sm,
+            program: self.program,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> StateMachine<(P, SM), Stopped> {
+    /// Groups this state machine with another one of the same PIO block, to start them in the
+    /// same cycle.
+    ///
+    /// Unlike [`Self::synchronize_with`], this also enables the state machines together, so they
+    /// run in lockstep from their first instruction.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let group = sm0.with(sm1).with(sm2).start();
+    /// let (sm0, sm1, sm2) = group.stop().free();
+    /// ```
+    pub fn with<SM2: StateMachineIndex>(
+        self,
+        other: StateMachine<(P, SM2), Stopped>,
+    ) -> StateMachineGroup2<P, SM, SM2, Stopped> {
+        StateMachineGroup2 {
+            sm1: self,
+            sm2: other,
+        }
+    }
+}
+
+macro_rules! state_machine_group {
+    ($Group:ident, $doc:literal, $first:ident: $First:ident, $($sm:ident: $SM:ident),+) => {
+        #[doc = concat!("Group of ", $doc, " state machines of a PIO block, started and stopped together.")]
+        ///
+        /// See [`StateMachine::with`].
+        pub struct $Group<P: PIOExt, $First: StateMachineIndex, $($SM: StateMachineIndex,)+ State> {
+            $first: StateMachine<(P, $First), State>,
+            $($sm: StateMachine<(P, $SM), State>,)+
+        }
+
+        impl<P: PIOExt, $First: StateMachineIndex, $($SM: StateMachineIndex,)+ State>
+            $Group<P, $First, $($SM,)+ State>
+        {
+            /// Splits the group into its state machines.
+            pub fn free(self) -> (StateMachine<(P, $First), State>, $(StateMachine<(P, $SM), State>,)+) {
+                (self.$first, $(self.$sm,)+)
+            }
+
+            fn sm_mask() -> u32 {
+                1 << $First::id() $(| 1 << $SM::id())+
+            }
+
+            // Safety: All ctrl accesses go through the atomic aliases.
+            fn set_ctrl_bits(&mut self, bits: u32) {
+                self.$first.sm.set_ctrl_bits(bits);
+            }
+
+            fn clear_ctrl_bits(&mut self, bits: u32) {
+                self.$first.sm.clear_ctrl_bits(bits);
+            }
+        }
+
+        impl<P: PIOExt, $First: StateMachineIndex, $($SM: StateMachineIndex,)+>
+            $Group<P, $First, $($SM,)+ Stopped>
+        {
+            /// Restarts the clock dividers and starts all the state machines in the same cycle.
+            pub fn start(mut self) -> $Group<P, $First, $($SM,)+ Running> {
+                // Bits 3:0 are SM_ENABLE, and bits 11:8 are CLKDIV_RESTART.
+                let mask = Self::sm_mask();
+                self.set_ctrl_bits(mask | mask << 8);
+                $Group {
+                    $first: self.$first.into_state(),
+                    $($sm: self.$sm.into_state(),)+
+                }
+            }
+        }
+
+        impl<P: PIOExt, $First: StateMachineIndex, $($SM: StateMachineIndex,)+>
+            $Group<P, $First, $($SM,)+ Running>
+        {
+            /// Stops all the state machines in the same cycle.
+            pub fn stop(mut self) -> $Group<P, $First, $($SM,)+ Stopped> {
+                self.clear_ctrl_bits(Self::sm_mask());
+                $Group {
+                    $first: self.$first.into_state(),
+                    $($sm: self.$sm.into_state(),)+
+                }
+            }
+        }
+    };
+}
+
+state_machine_group!(StateMachineGroup2, "two", sm1: SM1, sm2: SM2);
+state_machine_group!(StateMachineGroup3, "three", sm1: SM1, sm2: SM2, sm3: SM3);
+state_machine_group!(StateMachineGroup4, "four", sm1: SM1, sm2: SM2, sm3: SM3, sm4: SM4);
+
+impl<P: PIOExt, SM1: StateMachineIndex, SM2: StateMachineIndex>
+    StateMachineGroup2<P, SM1, SM2, Stopped>
+{
+    /// Adds another state machine to the group.
+    pub fn with<SM3: StateMachineIndex>(
+        self,
+        other: StateMachine<(P, SM3), Stopped>,
+    ) -> StateMachineGroup3<P, SM1, SM2, SM3, Stopped> {
+        StateMachineGroup3 {
+            sm1: self.sm1,
+            sm2: self.sm2,
+            sm3: other,
+        }
+    }
+}
+
+impl<P: PIOExt, SM1: StateMachineIndex, SM2: StateMachineIndex, SM3: StateMachineIndex>
+    StateMachineGroup3<P, SM1, SM2, SM3, Stopped>
+{
+    /// Adds another state machine to the group.
+    pub fn with<SM4: StateMachineIndex>(
+        self,
+        other: StateMachine<(P, SM4), Stopped>,
+    ) -> StateMachineGroup4<P, SM1, SM2, SM3, SM4, Stopped> {
+        StateMachineGroup4 {
+            sm1: self.sm1,
+            sm2: self.sm2,
+            sm3: self.sm3,
+            sm4: other,
+        }
+    }
+}
+
 impl<SM: ValidStateMachine> StateMachine<SM, Running> {
     /// Stops execution of the selected program.
     pub fn stop(mut self) -> StateMachine<SM, Stopped> {
-        // Enable SM
+        // Disable SM
         self.sm.set_enabled(false);
 
         StateMachine {
@@ -699,6 +932,8 @@ impl<SM: ValidStateMachine> StateMachine<SM, Running> {
         );
         // clear osr/isr
         self.sm.restart();
+        // restart the clock divider, so that the program runs in phase with the clock
+        self.sm.reset_clock();
         // unpause the state machine
         self.sm.set_enabled(true);
     }
@@ -763,10 +998,42 @@ impl<SM: ValidStateMachine> Rx<SM> {
             .modify(|_, w| w.autopush().bit(enable))
     }
 
-    /// Indicate if the tx FIFO is full
+    /// Indicate if the rx FIFO is empty
     pub fn is_empty(&self) -> bool {
         self.register_block().fstat.read().rxempty().bits() & (1 << SM::id()) != 0
     }
+
+    /// Indicate if the rx FIFO is full
+    pub fn is_full(&self) -> bool {
+        self.register_block().fstat.read().rxfull().bits() & (1 << SM::id()) != 0
+    }
+
+    /// Number of words waiting in the rx FIFO.
+    ///
+    /// This is at most 4, or 8 when the FIFOs are joined with [`Buffers::OnlyRx`].
+    pub fn level(&self) -> u8 {
+        // FLEVEL holds a TX and an RX nibble per state machine, and the RX nibble comes second.
+        let shift = SM::id() * 8 + 4;
+        (self.register_block().flevel.read().bits() >> shift) as u8 & 0xf
+    }
+}
+
+// Safety: The FIFO register stays valid for as long as this handle exists.
+unsafe impl<SM: ValidStateMachine> ReadTarget for Rx<SM> {
+    type ReceivedWord = u32;
+
+    fn rx_treq(&self) -> Option<u8> {
+        Some(self.dreq_value())
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        // A FIFO never runs out of words
+        (self.fifo_address() as u32, u32::MAX)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
 }
 
 /// PIO TX FIFO handle.
@@ -856,6 +1123,14 @@ impl<SM: ValidStateMachine> Tx<SM> {
         self.register_block().fstat.read().txfull().bits() & (1 << SM::id()) != 0
     }
 
+    /// Number of words waiting in the tx FIFO.
+    ///
+    /// This is at most 4, or 8 when the FIFOs are joined with [`Buffers::OnlyTx`].
+    pub fn level(&self) -> u8 {
+        let shift = SM::id() * 8;
+        (self.register_block().flevel.read().bits() >> shift) as u8 & 0xf
+    }
+
     /// Drain Tx fifo.
     pub fn drain_fifo(&mut self) {
         // According to the datasheet 3.5.4.2 Page 358:
@@ -893,6 +1168,24 @@ impl<SM: ValidStateMachine> Tx<SM> {
     }
 }
 
+// Safety: The FIFO register stays valid for as long as this handle exists.
+unsafe impl<SM: ValidStateMachine> WriteTarget for Tx<SM> {
+    type TransmittedWord = u32;
+
+    fn tx_treq(&self) -> Option<u8> {
+        Some(self.dreq_value())
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        // A FIFO never runs out of room
+        (self.fifo_address() as u32, u32::MAX)
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
+}
+
 /// PIO Interrupt controller.
 #[derive(Debug)]
 pub struct Interrupt<P: PIOExt> {
@@ -1079,6 +1372,36 @@ impl<P: PIOExt> Interrupt<P> {
         }
     }
 
+    /// Stop forcing a state machine interrupt.
+    ///
+    /// See [`Self::enable_sm_interrupt`] for info about the index.
+    pub fn unforce_sm_interrupt(&self, id: u8) {
+        self.clear_forced(8, id);
+    }
+
+    /// Stop forcing a TX FIFO not full interrupt.
+    ///
+    /// See [`Self::enable_tx_not_full_interrupt`] for info about the index.
+    pub fn unforce_tx_not_full_interrupt(&self, id: u8) {
+        self.clear_forced(4, id);
+    }
+
+    /// Stop forcing a RX FIFO not empty interrupt.
+    ///
+    /// See [`Self::enable_rx_not_empty_interrupt`] for info about the index.
+    pub fn unforce_rx_not_empty_interrupt(&self, id: u8) {
+        self.clear_forced(0, id);
+    }
+
+    fn clear_forced(&self, base: u8, id: u8) {
+        assert!(id < 4, "invalid state machine interrupt number");
+        let mask = 1 << (base + id);
+        // Safety: only the bit of this interrupt is cleared, atomically
+        unsafe {
+            write_bitmask_clear(self.irq().irq_intf.as_ptr(), mask);
+        }
+    }
+
     /// Get the raw interrupt state.
     ///
     /// This is the state of the interrupts without interrupt masking and forcing.
@@ -1227,6 +1550,29 @@ pub enum InstallError {
     NoSpace,
 }
 
+/// Errors that occurred during [`PIOBuilder::try_build`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BuildError {
+    /// The clock divisor is below 1, or not below 65536.
+    ClockDivisorOutOfRange,
+    /// A push or pull threshold is 0, or above 32.
+    ThresholdOutOfRange,
+    /// The inline `OUT` enable bit is not one of the 32 bits of the OSR.
+    InlineOutOutOfRange,
+    /// The FIFO level compared by `mov x, status` is above 15.
+    MovStatusOutOfRange,
+    /// A pin number is above 31.
+    PinOutOfRange,
+    /// The side-set of the program takes more than the 5 bits of an instruction shared with
+    /// the delay, counting its enable bit if it is optional.
+    SideSetTooWide,
+    /// The side-set of the program is optional or drives pin directions, but has no pins.
+    SideSetWithoutPins,
+    /// The wrap source or target of the program is past its end.
+    WrapOutOfRange,
+}
+
 impl<P: PIOExt> PIOBuilder<P> {
     /// Set config settings based on information from the given [`pio::Program`].
     /// Additional configuration may be needed in addition to this.
@@ -1239,8 +1585,8 @@ impl<P: PIOExt> PIOBuilder<P> {
             inline_out: None,
             mov_status: MovStatusConfig::Tx(0),
             fifo_join: Buffers::RxTx,
-            pull_threshold: 0,
-            push_threshold: 0,
+            pull_threshold: 32,
+            push_threshold: 32,
             out_shiftdir: ShiftDirection::Left,
             in_shiftdir: ShiftDirection::Left,
             autopull: false,
@@ -1338,6 +1684,12 @@ impl<P: PIOExt> PIOBuilder<P> {
         self
     }
 
+    /// Set the comparison used by `mov x, status` instruction.
+    pub fn mov_status(mut self, mov_status: MovStatusConfig) -> Self {
+        self.mov_status = mov_status;
+        self
+    }
+
     /// Set the autopush state.
     ///
     /// When autopush is enabled, the `IN` instruction automatically pushes the data once the number of bits reaches
@@ -1348,6 +1700,8 @@ impl<P: PIOExt> PIOBuilder<P> {
     }
 
     /// Set the number of bits pushed into ISR before autopush or conditional push will take place.
+    ///
+    /// The threshold is between 1 and 32, the default.
     pub fn push_threshold(mut self, threshold: u8) -> Self {
         self.push_threshold = threshold;
         self
@@ -1363,6 +1717,8 @@ impl<P: PIOExt> PIOBuilder<P> {
     }
 
     /// Set the number of bits pulled from out of OSR before autopull or conditional pull will take place.
+    ///
+    /// The threshold is between 1 and 32, the default.
     pub fn pull_threshold(mut self, threshold: u8) -> Self {
         self.pull_threshold = threshold;
         self
@@ -1385,11 +1741,72 @@ impl<P: PIOExt> PIOBuilder<P> {
     }
 
     /// Build the config and deploy it to a StateMachine.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the config is invalid, see [`Self::try_build`].
     #[allow(clippy::type_complexity)] // The return type cannot really be simplified.
     pub fn build<SM: StateMachineIndex>(
         self,
-        mut sm: UninitStateMachine<(P, SM)>,
+        sm: UninitStateMachine<(P, SM)>,
     ) -> (StateMachine<(P, SM), Stopped>, Rx<(P, SM)>, Tx<(P, SM)>) {
+        match self.try_build(sm) {
+            Ok(built) => built,
+            Err(e) => panic!("Invalid PIO state machine config: {:?}", e),
+        }
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        if !(1.0..65536.0).contains(&self.clock_divisor) {
+            return Err(BuildError::ClockDivisorOutOfRange);
+        }
+        if !(1..=32).contains(&self.pull_threshold) || !(1..=32).contains(&self.push_threshold) {
+            return Err(BuildError::ThresholdOutOfRange);
+        }
+        if matches!(self.inline_out, Some(bit) if bit >= 32) {
+            return Err(BuildError::InlineOutOutOfRange);
+        }
+        if let MovStatusConfig::Tx(n) | MovStatusConfig::Rx(n) = self.mov_status {
+            if n >= 16 {
+                return Err(BuildError::MovStatusOutOfRange);
+            }
+        }
+        let pins = [
+            self.jmp_pin,
+            self.in_base,
+            self.side_set_base,
+            self.set_base,
+            self.out_base,
+        ];
+        if pins.iter().any(|&pin| pin >= 32) {
+            return Err(BuildError::PinOutOfRange);
+        }
+
+        // Check the config against the program, whose side-set configures EXECCTRL and PINCTRL
+        let side_set = self.program.side_set;
+        if side_set.bits() > 5 {
+            return Err(BuildError::SideSetTooWide);
+        }
+        let side_set_pins = side_set.bits() - side_set.optional() as u8;
+        if side_set_pins == 0 && (side_set.optional() || side_set.pindirs()) {
+            return Err(BuildError::SideSetWithoutPins);
+        }
+        let wrap = self.program.wrap;
+        if wrap.source >= self.program.length || wrap.target >= self.program.length {
+            return Err(BuildError::WrapOutOfRange);
+        }
+        Ok(())
+    }
+
+    /// Build the config and deploy it to a StateMachine, if the config is valid.
+    ///
+    /// The state machine is left untouched if the config is invalid.
+    #[allow(clippy::type_complexity)] // The return type cannot really be simplified.
+    pub fn try_build<SM: StateMachineIndex>(
+        self,
+        mut sm: UninitStateMachine<(P, SM)>,
+    ) -> Result<(StateMachine<(P, SM), Stopped>, Rx<(P, SM)>, Tx<(P, SM)>), BuildError> {
+        self.validate()?;
         let offset = self.program.offset;
 
         // Stop the SM
@@ -1450,9 +1867,9 @@ impl<P: PIOExt> PIOBuilder<P> {
             w.fjoin_tx().bit(fjoin_tx);
 
             unsafe {
-                // TODO: Encode 32 as zero, and error on 0
-                w.pull_thresh().bits(self.pull_threshold);
-                w.push_thresh().bits(self.push_threshold);
+                // A threshold of 32 is encoded as 0
+                w.pull_thresh().bits(self.pull_threshold % 32);
+                w.push_thresh().bits(self.push_threshold % 32);
             }
 
             w.out_shiftdir().bit(self.out_shiftdir.bit());
@@ -1501,7 +1918,7 @@ impl<P: PIOExt> PIOBuilder<P> {
             block: sm.block,
             _phantom: core::marker::PhantomData,
         };
-        (
+        Ok((
             StateMachine {
                 sm,
                 program: self.program,
@@ -1509,6 +1926,6 @@ impl<P: PIOExt> PIOBuilder<P> {
             },
             rx,
             tx,
-        )
+        ))
     }
 }
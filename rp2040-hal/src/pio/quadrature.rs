@@ -0,0 +1,180 @@
+//! Quadrature encoder decoded by a PIO state machine
+//!
+//! This follows the quadrature encoder example of the pico-examples: the
+//! state machine samples the A and B phases, keeps the position in its Y
+//! register, and continuously pushes it to the RX FIFO, so no step is missed
+//! however rarely the position is read.
+//!
+//! The program has to be installed at the start of the instruction memory,
+//! and takes 24 of its 32 instructions.
+//!
+//! ## Usage
+//! ```no_run
+//! use rp2040_hal::{gpio::{FunctionPio0, Pin, Pins}, pac, pio::{quadrature::QuadratureEncoder, PIOExt}, sio::Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let (mut pio, sm0, _, _, _) = peripherals.PIO0.split(&mut peripherals.RESETS);
+//!
+//! let a: Pin<_, FunctionPio0> = pins.gpio10.into_mode();
+//! let b: Pin<_, FunctionPio0> = pins.gpio11.into_mode();
+//! let mut encoder = QuadratureEncoder::new(&mut pio, a, b, sm0, None).unwrap();
+//! let position = encoder.position();
+//! ```
+use super::{
+    Buffers, InstallError, InstalledProgram, PIOBuilder, PIOExt, Running, Rx, ShiftDirection,
+    StateMachine, StateMachineIndex, Tx, UninitStateMachine, PIO,
+};
+use crate::gpio::pin::{Function, FunctionConfig, Pin, PinId, ValidPinMode};
+use embedded_time::rate::Hertz;
+
+fn program() -> pio::Program<{ pio::RP2040_MAX_PROGRAM_SIZE }> {
+    use pio::{InSource, JmpCondition, MovDestination, MovOperation, MovSource, OutDestination};
+
+    let mut a = pio::Assembler::<{ pio::RP2040_MAX_PROGRAM_SIZE }>::new();
+    let mut update = a.label();
+    let mut decrement = a.label();
+    let mut increment = a.label();
+    let mut increment_cont = a.label();
+    let mut wrap_source = a.label();
+
+    // Jump table, indexed by the previous state of the pins in bits 3:2, and
+    // the new one in bits 1:0. The last 2 entries are the start of the
+    // program itself.
+    const U: u8 = 0;
+    const D: u8 = 1;
+    const I: u8 = 2;
+    for target in [U, D, I, U, I, U, U, D, D, U, U, I, U, I] {
+        let label = match target {
+            D => &mut decrement,
+            I => &mut increment,
+            _ => &mut update,
+        };
+        a.jmp(JmpCondition::Always, label);
+    }
+    a.bind(&mut decrement);
+    // Jumping to the next instruction makes this a pure decrement of Y
+    a.jmp(JmpCondition::YDecNonZero, &mut update);
+
+    // Main loop
+    a.bind(&mut update);
+    a.mov(MovDestination::ISR, MovOperation::None, MovSource::Y);
+    a.push(false, false);
+    // Shift the previous state of the pins, kept in OSR, and their new state
+    // into ISR, to form the index in the jump table. The PUSH above and the
+    // OUT below clear the other bits of ISR.
+    a.out(OutDestination::ISR, 2);
+    a.r#in(InSource::PINS, 2);
+    // Keep the state in OSR, to free ISR
+    a.mov(MovDestination::OSR, MovOperation::None, MovSource::ISR);
+    a.mov(MovDestination::PC, MovOperation::None, MovSource::ISR);
+
+    // There is no increment instruction, so negate, decrement, and negate
+    a.bind(&mut increment);
+    a.mov(MovDestination::Y, MovOperation::Invert, MovSource::Y);
+    a.jmp(JmpCondition::YDecNonZero, &mut increment_cont);
+    a.bind(&mut increment_cont);
+    a.mov(MovDestination::Y, MovOperation::Invert, MovSource::Y);
+    a.bind(&mut wrap_source);
+
+    let mut program = a.assemble_with_wrap(wrap_source, update);
+    // The jump table is addressed with absolute addresses
+    program.origin = Some(0);
+    program
+}
+
+/// A quadrature encoder decoded by a PIO state machine
+pub struct QuadratureEncoder<P, SM, A, B>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    A: PinId,
+    B: PinId,
+    Function<P>: ValidPinMode<A> + ValidPinMode<B>,
+{
+    sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+    tx: Tx<(P, SM)>,
+    pins: (Pin<A, Function<P>>, Pin<B, Function<P>>),
+}
+
+impl<P, SM, A, B> QuadratureEncoder<P, SM, A, B>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    A: PinId,
+    B: PinId,
+    Function<P>: ValidPinMode<A> + ValidPinMode<B>,
+{
+    /// Install the decoder program in `pio`, and run it on `sm` to follow the
+    /// encoder on the `a` and `b` pins, which must be consecutive.
+    ///
+    /// The state machine runs at full speed, taking 10 cycles per step, unless
+    /// slowed down to `Some((max_step_rate, system_freq))` steps per second,
+    /// which saves power.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `b` doesn't follow `a`.
+    pub fn new(
+        pio: &mut PIO<P>,
+        a: Pin<A, Function<P>>,
+        b: Pin<B, Function<P>>,
+        sm: UninitStateMachine<(P, SM)>,
+        max_step_rate: Option<(Hertz, Hertz)>,
+    ) -> Result<Self, InstallError> {
+        let a_id = A::DYN.num;
+        assert_eq!(B::DYN.num, a_id + 1, "the B pin must follow the A pin");
+
+        let installed = pio.install(&program())?;
+        let divisor = match max_step_rate {
+            Some((max_step_rate, system_freq)) => {
+                system_freq.0 as f32 / (10 * max_step_rate.0) as f32
+            }
+            None => 1.0,
+        };
+        let (sm, rx, tx) = PIOBuilder::from_program(installed)
+            .in_pin_base(a_id)
+            .in_shift_direction(ShiftDirection::Left)
+            .out_shift_direction(ShiftDirection::Right)
+            .buffers(Buffers::OnlyRx)
+            .clock_divisor(divisor)
+            .build(sm);
+
+        Ok(Self {
+            sm: sm.start(),
+            rx,
+            tx,
+            pins: (a, b),
+        })
+    }
+
+    /// The number of steps counted since the decoder started, forward steps
+    /// counting up and backward steps down.
+    pub fn position(&mut self) -> i32 {
+        // The FIFO holds past positions: drain them, and wait for a position
+        // which was pushed after this call started
+        let mut n = self.rx.level() + 1;
+        let mut position = 0;
+        while n > 0 {
+            if let Some(value) = self.rx.read() {
+                position = value as i32;
+                n -= 1;
+            }
+        }
+        position
+    }
+
+    /// Stop the state machine, and return it with its program and the pins
+    #[allow(clippy::type_complexity)]
+    pub fn free(
+        self,
+    ) -> (
+        UninitStateMachine<(P, SM)>,
+        InstalledProgram<P>,
+        (Pin<A, Function<P>>, Pin<B, Function<P>>),
+    ) {
+        let (sm, installed) = self.sm.uninit(self.rx, self.tx);
+        (sm, installed, self.pins)
+    }
+}
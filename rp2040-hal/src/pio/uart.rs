@@ -0,0 +1,294 @@
+//! UART transmitter and receiver on PIO state machines
+//!
+//! This follows the UART examples of the pico-sdk, sending and receiving 8
+//! data bits, no parity and 1 stop bit, on any pin. Each direction takes a
+//! state machine, and can be used without the other one.
+//!
+//! ## Usage
+//! ```no_run
+//! use embedded_hal::serial::{Read, Write};
+//! use embedded_time::rate::Extensions;
+//! use rp2040_hal::{gpio::{FunctionPio0, Pin, Pins}, pac, pio::{uart::{UartRx, UartTx}, PIOExt}, sio::Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let (mut pio, sm0, sm1, _, _) = peripherals.PIO0.split(&mut peripherals.RESETS);
+//!
+//! let tx_pin: Pin<_, FunctionPio0> = pins.gpio2.into_mode();
+//! let rx_pin: Pin<_, FunctionPio0> = pins.gpio3.into_mode();
+//! let mut tx = UartTx::new(&mut pio, tx_pin, sm0, 115_200.Bd(), 125_000_000.Hz()).unwrap();
+//! let mut rx = UartRx::new(&mut pio, rx_pin, sm1, 115_200.Bd(), 125_000_000.Hz()).unwrap();
+//! // Echo back what is received
+//! let byte = nb::block!(rx.read()).unwrap();
+//! nb::block!(tx.write(byte)).unwrap();
+//! ```
+use super::{
+    clock_divisor_for_rate, Buffers, InstallError, InstalledProgram, PIOBuilder, PIOExt, PinDir,
+    PinState, Running, Rx, ShiftDirection, StateMachine, StateMachineIndex, Tx, UninitStateMachine,
+    PIO,
+};
+use crate::gpio::pin::{Function, FunctionConfig, Pin, PinId, ValidPinMode};
+use core::convert::Infallible;
+use embedded_time::rate::{Baud, Hertz};
+
+/// State machine cycles per bit, in both directions
+const CYCLES_PER_BIT: u32 = 8;
+
+fn clock_divisor(baudrate: Baud, system_freq: Hertz) -> f32 {
+    system_freq.0 as f32 / (CYCLES_PER_BIT * baudrate.0) as f32
+}
+
+fn tx_program() -> pio::Program<{ pio::RP2040_MAX_PROGRAM_SIZE }> {
+    let mut a = pio::Assembler::<{ pio::RP2040_MAX_PROGRAM_SIZE }>::new_with_side_set(
+        pio::SideSet::new(true, 1, false),
+    );
+    let mut wrap_target = a.label();
+    let mut wrap_source = a.label();
+    let mut bitloop = a.label();
+
+    a.bind(&mut wrap_target);
+    // Keep the line high, as a stop bit, until there is data to send
+    a.pull_with_delay_and_side_set(false, true, 7, 1);
+    // Start bit
+    a.set_with_delay_and_side_set(pio::SetDestination::X, 7, 7, 0);
+    a.bind(&mut bitloop);
+    // Data bits, least significant first
+    a.out(pio::OutDestination::PINS, 1);
+    a.jmp_with_delay(pio::JmpCondition::XDecNonZero, &mut bitloop, 6);
+    a.bind(&mut wrap_source);
+
+    a.assemble_with_wrap(wrap_source, wrap_target)
+}
+
+fn rx_program() -> pio::Program<{ pio::RP2040_MAX_PROGRAM_SIZE }> {
+    let mut a = pio::Assembler::<{ pio::RP2040_MAX_PROGRAM_SIZE }>::new();
+    let mut wrap_target = a.label();
+    let mut wrap_source = a.label();
+    let mut bitloop = a.label();
+    let mut good_stop = a.label();
+
+    a.bind(&mut wrap_target);
+    // Wait for the start bit, then to the middle of the first data bit
+    a.wait(0, pio::WaitSource::PIN, 0);
+    a.set_with_delay(pio::SetDestination::X, 7, 10);
+    a.bind(&mut bitloop);
+    // Sample the data bits, in the middle of each of them
+    a.r#in(pio::InSource::PINS, 1);
+    a.jmp_with_delay(pio::JmpCondition::XDecNonZero, &mut bitloop, 6);
+    a.jmp(pio::JmpCondition::PinHigh, &mut good_stop);
+    // Framing error, or break: drop the byte and wait for the line to idle
+    a.wait(1, pio::WaitSource::PIN, 0);
+    a.jmp(pio::JmpCondition::Always, &mut wrap_target);
+    a.bind(&mut good_stop);
+    a.push(false, true);
+    a.bind(&mut wrap_source);
+
+    a.assemble_with_wrap(wrap_source, wrap_target)
+}
+
+/// The sending half of a UART, running on a PIO state machine
+pub struct UartTx<P, SM, I>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    I: PinId,
+    Function<P>: ValidPinMode<I>,
+{
+    sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+    tx: Tx<(P, SM)>,
+    pin: Pin<I, Function<P>>,
+}
+
+impl<P, SM, I> UartTx<P, SM, I>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    I: PinId,
+    Function<P>: ValidPinMode<I>,
+{
+    /// Install the transmitter program in `pio`, and run it on `sm` to send
+    /// data on `pin`.
+    ///
+    /// The bit timing is derived from `system_freq`, the frequency of the
+    /// system clock.
+    pub fn new(
+        pio: &mut PIO<P>,
+        pin: Pin<I, Function<P>>,
+        sm: UninitStateMachine<(P, SM)>,
+        baudrate: Baud,
+        system_freq: Hertz,
+    ) -> Result<Self, InstallError> {
+        let installed = pio.install(&tx_program())?;
+        let pin_id = I::DYN.num;
+
+        let (mut sm, rx, tx) = PIOBuilder::from_program(installed)
+            .out_pins(pin_id, 1)
+            .side_set_pin_base(pin_id)
+            .out_shift_direction(ShiftDirection::Right)
+            .buffers(Buffers::OnlyTx)
+            .clock_divisor(clock_divisor(baudrate, system_freq))
+            .build(sm);
+        // Idle high, without a glitch when the pin becomes an output
+        sm.set_pins([(pin_id, PinState::High)]);
+        sm.set_pindirs([(pin_id, PinDir::Output)]);
+
+        Ok(Self {
+            sm: sm.start(),
+            rx,
+            tx,
+            pin,
+        })
+    }
+
+    /// Change the baudrate, `system_freq` being the frequency of the system clock.
+    ///
+    /// A byte being sent is garbled: [`flush`](embedded_hal::serial::Write::flush) beforehand.
+    pub fn set_baudrate(&mut self, baudrate: Baud, system_freq: Hertz) {
+        let (int, frac) = clock_divisor_for_rate(Hertz(baudrate.0), CYCLES_PER_BIT, system_freq);
+        self.sm.set_clock_divisor(int, frac);
+    }
+
+    /// Stop the state machine, and return it with its program and the pin
+    pub fn free(
+        self,
+    ) -> (
+        UninitStateMachine<(P, SM)>,
+        InstalledProgram<P>,
+        Pin<I, Function<P>>,
+    ) {
+        let (sm, installed) = self.sm.uninit(self.rx, self.tx);
+        (sm, installed, self.pin)
+    }
+}
+
+impl<P, SM, I> embedded_hal::serial::Write<u8> for UartTx<P, SM, I>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    I: PinId,
+    Function<P>: ValidPinMode<I>,
+{
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        if self.tx.write(u32::from(word)) {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        // Once the FIFO is empty, the state machine stalls on the PULL after
+        // the stop bit of the last byte
+        if self.tx.is_empty() && self.sm.stalled() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<P, SM, I> embedded_hal::blocking::serial::write::Default<u8> for UartTx<P, SM, I>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    I: PinId,
+    Function<P>: ValidPinMode<I>,
+{
+}
+
+/// The receiving half of a UART, running on a PIO state machine
+///
+/// Bytes without a valid stop bit are dropped.
+pub struct UartRx<P, SM, I>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    I: PinId,
+    Function<P>: ValidPinMode<I>,
+{
+    sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+    tx: Tx<(P, SM)>,
+    pin: Pin<I, Function<P>>,
+}
+
+impl<P, SM, I> UartRx<P, SM, I>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    I: PinId,
+    Function<P>: ValidPinMode<I>,
+{
+    /// Install the receiver program in `pio`, and run it on `sm` to receive
+    /// data on `pin`.
+    ///
+    /// The bit timing is derived from `system_freq`, the frequency of the
+    /// system clock.
+    pub fn new(
+        pio: &mut PIO<P>,
+        pin: Pin<I, Function<P>>,
+        sm: UninitStateMachine<(P, SM)>,
+        baudrate: Baud,
+        system_freq: Hertz,
+    ) -> Result<Self, InstallError> {
+        let installed = pio.install(&rx_program())?;
+        let pin_id = I::DYN.num;
+
+        let (mut sm, rx, tx) = PIOBuilder::from_program(installed)
+            .in_pin_base(pin_id)
+            .jmp_pin(pin_id)
+            .in_shift_direction(ShiftDirection::Right)
+            .buffers(Buffers::OnlyRx)
+            .clock_divisor(clock_divisor(baudrate, system_freq))
+            .build(sm);
+        sm.set_pindirs([(pin_id, PinDir::Input)]);
+
+        Ok(Self {
+            sm: sm.start(),
+            rx,
+            tx,
+            pin,
+        })
+    }
+
+    /// Change the baudrate, `system_freq` being the frequency of the system clock.
+    ///
+    /// A byte being received is garbled.
+    pub fn set_baudrate(&mut self, baudrate: Baud, system_freq: Hertz) {
+        let (int, frac) = clock_divisor_for_rate(Hertz(baudrate.0), CYCLES_PER_BIT, system_freq);
+        self.sm.set_clock_divisor(int, frac);
+    }
+
+    /// Stop the state machine, and return it with its program and the pin
+    pub fn free(
+        self,
+    ) -> (
+        UninitStateMachine<(P, SM)>,
+        InstalledProgram<P>,
+        Pin<I, Function<P>>,
+    ) {
+        let (sm, installed) = self.sm.uninit(self.rx, self.tx);
+        (sm, installed, self.pin)
+    }
+}
+
+impl<P, SM, I> embedded_hal::serial::Read<u8> for UartRx<P, SM, I>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    I: PinId,
+    Function<P>: ValidPinMode<I>,
+{
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        // The 8 data bits are shifted in from the left of the ISR
+        match self.rx.read() {
+            Some(word) => Ok((word >> 24) as u8),
+            None => Err(nb::Error::WouldBlock),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! WS2812 addressable LEDs driven by a PIO state machine
+//!
+//! This follows the WS2812 example of the pico-sdk, sending 24 bit colors at
+//! 800kHz.
+//!
+//! ## Usage
+//! ```no_run
+//! use embedded_time::rate::Extensions;
+//! use rp2040_hal::{gpio::{FunctionPio0, Pin, Pins}, pac, pio::{ws2812::Ws2812, PIOExt}, sio::Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let (mut pio, sm0, _, _, _) = peripherals.PIO0.split(&mut peripherals.RESETS);
+//!
+//! let data: Pin<_, FunctionPio0> = pins.gpio16.into_mode();
+//! let mut leds = Ws2812::new(&mut pio, data, sm0, 125_000_000.Hz()).unwrap();
+//! // Light up the first LED red, and the second one blue
+//! leds.write([[0xff, 0, 0], [0, 0, 0xff]]);
+//! ```
+use super::{
+    Buffers, InstallError, InstalledProgram, PIOBuilder, PIOExt, PinDir, Running, Rx,
+    ShiftDirection, StateMachine, StateMachineIndex, Tx, UninitStateMachine, PIO,
+};
+use crate::gpio::pin::{Function, FunctionConfig, Pin, PinId, ValidPinMode};
+use embedded_time::rate::Hertz;
+
+/// Cycles of the high pulse starting each bit
+const T1: u8 = 2;
+/// Cycles of the pulse telling a one from a zero
+const T2: u8 = 5;
+/// Cycles of the low time ending each bit
+const T3: u8 = 3;
+
+/// Frequency of the bits sent to the LEDs
+const BIT_FREQ: u32 = 800_000;
+
+fn program() -> pio::Program<{ pio::RP2040_MAX_PROGRAM_SIZE }> {
+    let mut a = pio::Assembler::<{ pio::RP2040_MAX_PROGRAM_SIZE }>::new_with_side_set(
+        pio::SideSet::new(false, 1, false),
+    );
+    let mut wrap_target = a.label();
+    let mut wrap_source = a.label();
+    let mut do_zero = a.label();
+
+    a.bind(&mut wrap_target);
+    // Shift out the next bit. The side-set takes place when the instruction
+    // stalls, keeping the line low between colors.
+    a.out_with_delay_and_side_set(pio::OutDestination::X, 1, T3 - 1, 0);
+    // Positive pulse, then branch on the bit
+    a.jmp_with_delay_and_side_set(pio::JmpCondition::XIsZero, &mut do_zero, T1 - 1, 1);
+    // Continue driving high, for a long pulse
+    a.jmp_with_delay_and_side_set(pio::JmpCondition::Always, &mut wrap_target, T2 - 1, 1);
+    a.bind(&mut do_zero);
+    // Or drive low, for a short pulse
+    a.mov_with_delay_and_side_set(
+        pio::MovDestination::Y,
+        pio::MovOperation::None,
+        pio::MovSource::Y,
+        T2 - 1,
+        0,
+    );
+    a.bind(&mut wrap_source);
+
+    a.assemble_with_wrap(wrap_source, wrap_target)
+}
+
+/// A chain of WS2812 LEDs, driven by a PIO state machine
+pub struct Ws2812<P, SM, I>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    I: PinId,
+    Function<P>: ValidPinMode<I>,
+{
+    sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+    tx: Tx<(P, SM)>,
+    pin: Pin<I, Function<P>>,
+}
+
+impl<P, SM, I> Ws2812<P, SM, I>
+where
+    P: PIOExt + FunctionConfig,
+    SM: StateMachineIndex,
+    I: PinId,
+    Function<P>: ValidPinMode<I>,
+{
+    /// Install the WS2812 program in `pio`, and run it on `sm` to drive the
+    /// data line of the LEDs on `pin`.
+    ///
+    /// The timing is derived from `system_freq`, the frequency of the system
+    /// clock.
+    pub fn new<SystemF: Into<Hertz<u32>>>(
+        pio: &mut PIO<P>,
+        pin: Pin<I, Function<P>>,
+        sm: UninitStateMachine<(P, SM)>,
+        system_freq: SystemF,
+    ) -> Result<Self, InstallError> {
+        let installed = pio.install(&program())?;
+        let pin_id = I::DYN.num;
+        let cycles_per_bit = u32::from(T1 + T2 + T3);
+
+        // Each color takes the 24 most significant bits of a word
+        let (mut sm, rx, tx) = PIOBuilder::from_program(installed)
+            .side_set_pin_base(pin_id)
+            .out_shift_direction(ShiftDirection::Left)
+            .autopull(true)
+            .pull_threshold(24)
+            .buffers(Buffers::OnlyTx)
+            .clock_divisor(system_freq.into().0 as f32 / (BIT_FREQ * cycles_per_bit) as f32)
+            .build(sm);
+        sm.set_pindirs([(pin_id, PinDir::Output)]);
+
+        Ok(Self {
+            sm: sm.start(),
+            rx,
+            tx,
+            pin,
+        })
+    }
+
+    /// Queue a color in GRB order, in the 24 least significant bits of `grb`,
+    /// waiting for room in the FIFO.
+    pub fn write_grb(&mut self, grb: u32) {
+        while !self.tx.write(grb << 8) {}
+    }
+
+    /// Send the RGB colors of consecutive LEDs, starting from the first one.
+    ///
+    /// This returns once the last color is queued. The LEDs latch the colors
+    /// when the line stays low for 50µs, so wait that long after the last
+    /// color has been sent before writing the next frame.
+    pub fn write<C: IntoIterator<Item = [u8; 3]>>(&mut self, colors: C) {
+        for [r, g, b] in colors {
+            self.write_grb(u32::from(g) << 16 | u32::from(r) << 8 | u32::from(b));
+        }
+    }
+
+    /// Stop the state machine, and return it with its program and the pin
+    pub fn free(
+        self,
+    ) -> (
+        UninitStateMachine<(P, SM)>,
+        InstalledProgram<P>,
+        Pin<I, Function<P>>,
+    ) {
+        let (sm, installed) = self.sm.uninit(self.rx, self.tx);
+        (sm, installed, self.pin)
+    }
+}
@@ -0,0 +1,101 @@
+//! Edge counting
+//!
+//! A slice in [`CountRisingEdge`](super::CountRisingEdge) or
+//! [`CountFallingEdge`](super::CountFallingEdge) mode advances its counter
+//! once per edge on its B pin, and outputs nothing. [`Counter`] configures a
+//! slice as a free running 16-bit event counter on that pin, which is a cheap
+//! alternative to a PIO state machine for counting pulses.
+//!
+//! ```no_run
+//! # use rp2040_hal::{pac, gpio::Pins, sio::Sio, pwm::{CountRisingEdge, Counter, Slices}};
+//! # let mut pac = pac::Peripherals::take().unwrap();
+//! # let sio = Sio::new(pac.SIO);
+//! # let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+//! let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+//! let slice = pwm_slices.pwm2.into_mode::<CountRisingEdge>();
+//! let mut counter = Counter::new(slice, pins.gpio5);
+//! // ... some time later
+//! let pulses = counter.read_count();
+//! counter.reset_count();
+//! ```
+use super::{reg::RegisterInterface, PwmPinToken, Slice, SliceId, SliceMode, ValidPwmInputPin};
+use super::{ValidSliceInputMode, ValidSliceMode};
+use crate::gpio::{bank0::BankPinId, Pin, PinId, PinMode, ValidPinMode};
+
+/// A 16-bit counter of the edges on the B pin of a PWM slice
+pub struct Counter<S, M, G>
+where
+    S: SliceId,
+    M: SliceMode + ValidSliceMode<S>,
+    G: PinId + BankPinId,
+{
+    slice: Slice<S, M>,
+    pin: PwmPinToken<G>,
+}
+
+impl<S, M, G> Counter<S, M, G>
+where
+    S: SliceId,
+    M: SliceMode + ValidSliceInputMode<S>,
+    G: PinId + BankPinId + ValidPwmInputPin<S>,
+{
+    /// Configure `slice` to count on `pin`, starting from zero
+    ///
+    /// The counter wraps from 65535 back to 0, raising the slice's wrap
+    /// interrupt flag.
+    pub fn new<PM>(mut slice: Slice<S, M>, pin: Pin<G, PM>) -> Self
+    where
+        PM: PinMode + ValidPinMode<G>,
+    {
+        slice.default_config();
+        let pin = slice.input_from(pin);
+        slice.regs.clear_raw_wrap();
+        slice.enable();
+        Counter { slice, pin }
+    }
+
+    /// Read the number of edges counted since the last reset, modulo 65536
+    #[inline]
+    pub fn read_count(&self) -> u16 {
+        self.slice.get_counter()
+    }
+
+    /// Restart counting from zero, and clear the overflow flag
+    #[inline]
+    pub fn reset_count(&mut self) {
+        self.slice.set_counter(0);
+        self.slice.regs.clear_raw_wrap();
+    }
+
+    /// Has the counter wrapped since the overflow flag was last cleared?
+    ///
+    /// This works whether or not the overflow interrupt is enabled.
+    #[inline]
+    pub fn has_overflowed(&self) -> bool {
+        self.slice.regs.read_raw_wrap()
+    }
+
+    /// Clear the overflow flag, and the interrupt if it is enabled
+    #[inline]
+    pub fn clear_overflow(&mut self) {
+        self.slice.regs.clear_raw_wrap();
+    }
+
+    /// Raise PWM_IRQ_WRAP when the counter overflows
+    #[inline]
+    pub fn enable_overflow_interrupt(&mut self) {
+        self.slice.enable_interrupt();
+    }
+
+    /// Stop raising PWM_IRQ_WRAP when the counter overflows
+    #[inline]
+    pub fn disable_overflow_interrupt(&mut self) {
+        self.slice.disable_interrupt();
+    }
+
+    /// Stop counting and release the slice and the pin
+    pub fn free(mut self) -> (Slice<S, M>, PwmPinToken<G>) {
+        self.slice.disable();
+        (self.slice, self.pin)
+    }
+}
@@ -1,7 +1,7 @@
 //! Semi-internal enums mostly used in typelevel magic
 
 /// Value-level `struct` representing slice IDs
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub struct DynSliceId {
     /// Slice id
     pub num: u8,
@@ -21,7 +21,7 @@ pub enum DynSliceMode {
 }
 
 /// Channel ids
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum DynChannelId {
     /// Channel A
     A,
@@ -52,6 +52,8 @@
 //! channel_a.get_duty();
 //! channel_a.set_inverted(); // Invert the output
 //! channel_a.clr_inverted(); // Don't invert the output
+//! channel_a.force(false); // Hold the output low
+//! channel_a.release(); // Restore the duty cycle
 //! ```
 //!
 //! The following configuration options are also available:
@@ -72,6 +74,23 @@
 //!
 //! ```
 //!
+//! Several slices can be started on the same cycle, and then shifted relative
+//! to each other:
+//!
+//! ```no_run
+//! # use rp2040_hal::{prelude::*, pwm::Slices};
+//! # let mut pac = rp2040_pac::Peripherals::take().unwrap();
+//! let mut pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+//! pwm_slices.pwm0.set_div_int(2);
+//! pwm_slices.pwm1.set_div_int(2);
+//! pwm_slices.enable_simultaneous(0b11);
+//! // Shift slice 1 by one count relative to slice 0
+//! pwm_slices.pwm1.advance_phase().unwrap();
+//! ```
+//!
+//! The B channel input can also be used to measure an external signal, see
+//! [`Slice::capture_duty_cycle`], or to count pulses, see [`Counter`].
+//!
 //! default_config() sets ph_correct to false, the clock divider to 1, does not invert the output, sets top to 65535, and resets the counter.
 //! min_config() leaves those registers in the state they were before it was called (Careful, this can lead to unexpected behavior)
 //! It's recommended to only call min_config() after calling default_config() on a pin that shares a PWM block.
@@ -79,6 +98,7 @@
 use core::marker::PhantomData;
 
 use crate::{
+    dma::WriteTarget,
     gpio::{
         bank0::*, FunctionClock, FunctionI2C, FunctionPio0, FunctionPio1, FunctionPwm, FunctionSpi,
         FunctionUart, FunctionUsbAux, FunctionXip, Input, InputConfig, Output, OutputConfig, Pin,
@@ -89,7 +109,11 @@ use crate::{
 };
 #[cfg(feature = "eh1_0_alpha")]
 use eh1_0_alpha::pwm::blocking as eh1;
+use embedded_hal::blocking::delay::DelayUs;
 use embedded_hal::PwmPin;
+use embedded_time::duration::Nanoseconds;
+use embedded_time::fixed_point::FixedPoint;
+use embedded_time::rate::Hertz;
 use pac::PWM;
 
 use crate::atomic_register_access::{write_bitmask_clear, write_bitmask_set};
@@ -97,6 +121,17 @@ use crate::atomic_register_access::{write_bitmask_clear, write_bitmask_set};
 pub mod dyn_slice;
 pub use dyn_slice::*;
 
+mod counter;
+pub use counter::Counter;
+
+mod single_channel;
+
+mod servo;
+pub use servo::Servo;
+
+mod tone;
+pub use tone::Tone;
+
 mod reg;
 
 use reg::RegisterInterface;
@@ -239,6 +274,16 @@ impl<I: SliceId> Registers<I> {
     }
 }
 
+/// Why the phase of a slice can't be shifted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhaseError {
+    /// The slice is disabled, so its counter doesn't run.
+    Disabled,
+    /// The counter runs at full speed, with a clock divider of 1, so it can't advance any
+    /// faster.
+    FullSpeed,
+}
+
 /// Pwm slice
 pub struct Slice<I, M>
 where
@@ -298,22 +343,6 @@ where
         self.regs.write_cc_b(0); //Default duty cycle of 0%
     }
 
-    /// Advance the phase with one count
-    ///
-    /// Counter must be running at less than full speed (div_int + div_frac / 16 > 1)
-    #[inline]
-    pub fn advance_phase(&mut self) {
-        self.regs.advance_phase()
-    }
-
-    /// Retard the phase with one count
-    ///
-    /// Counter must be running at less than full speed (div_int + div_frac / 16 > 1)
-    #[inline]
-    pub fn retard_phase(&mut self) {
-        self.regs.retard_phase()
-    }
-
     /// Enable phase correct mode
     #[inline]
     pub fn set_ph_correct(&mut self) {
@@ -338,6 +367,12 @@ where
         self.regs.write_enable(false)
     }
 
+    /// Is the slice enabled
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.regs.read_enable()
+    }
+
     /// Sets the integer part of the clock divider
     #[inline]
     pub fn set_div_int(&mut self, value: u8) {
@@ -368,12 +403,48 @@ where
         self.regs.read_top()
     }
 
+    /// Set the duty cycle of both channels with a single write
+    ///
+    /// The compare register is double buffered, so both channels change on
+    /// the same wrap of the counter. This avoids the glitch where one channel
+    /// has been updated and the other hasn't, e.g. overlapping on-times in an
+    /// H-bridge.
+    #[inline]
+    pub fn set_duty_a_b(&mut self, a: u16, b: u16) {
+        self.regs.write_cc(a, b)
+    }
+
     /// Sets the top register value
     #[inline]
     pub fn set_top(&mut self, value: u16) {
         self.regs.write_top(value)
     }
 
+    /// The DREQ raised by this slice each time its counter wraps
+    ///
+    /// A DMA channel paced by this DREQ can stream a new compare value into
+    /// the [`cc_address`](Self::cc_address) register every period.
+    #[inline]
+    pub fn dreq(&self) -> u8 {
+        crate::dma::DREQ_PWM_WRAP0 + I::DYN.num
+    }
+
+    /// Address of the CC (compare) register of this slice
+    ///
+    /// Channel A's compare value is held in the low half-word and channel B's
+    /// in the high half-word. The register is latched on each wrap, so a
+    /// write lands on the next period.
+    #[inline]
+    pub fn cc_address(&self) -> u32 {
+        self.regs.ch().cc.as_ptr() as u32
+    }
+
+    /// Address of the TOP register of this slice
+    #[inline]
+    pub fn top_address(&self) -> u32 {
+        self.regs.ch().top.as_ptr() as u32
+    }
+
     /// Create the interrupt bitmask corresponding to this slice
     #[inline]
     fn bitmask(&self) -> u32 {
@@ -400,6 +471,13 @@ where
         };
     }
 
+    /// Is the PWM_IRQ_WRAP interrupt enabled for this slice?
+    #[inline]
+    pub fn is_interrupt_enabled(&self) -> bool {
+        let mask = self.bitmask();
+        unsafe { (*pac::PWM::ptr()).inte.read().bits() & mask == mask }
+    }
+
     /// Did this slice trigger an overflow interrupt?
     #[inline]
     pub fn has_overflown(&self) -> bool {
@@ -436,6 +514,61 @@ where
     }
 }
 
+impl<I: SliceId> Slice<I, FreeRunning> {
+    /// Advance the phase with one count
+    ///
+    /// Counter must be running at less than full speed (div_int + div_frac / 16 > 1),
+    /// and the slice must be enabled, as the counter would never advance otherwise.
+    /// Only free-running slices can be shifted, as a gated or edge-counting
+    /// counter may not step again until its input changes.
+    #[inline]
+    pub fn advance_phase(&mut self) -> Result<(), PhaseError> {
+        if !self.regs.read_enable() {
+            return Err(PhaseError::Disabled);
+        }
+        if self.regs.read_div_int() == 1 && self.regs.read_div_frac() == 0 {
+            return Err(PhaseError::FullSpeed);
+        }
+        self.regs.advance_phase();
+        Ok(())
+    }
+
+    /// Retard the phase with one count
+    ///
+    /// The slice must be enabled, as the counter would never retard otherwise.
+    #[inline]
+    pub fn retard_phase(&mut self) -> Result<(), PhaseError> {
+        if !self.regs.read_enable() {
+            return Err(PhaseError::Disabled);
+        }
+        self.regs.retard_phase();
+        Ok(())
+    }
+}
+
+/// Each word written holds the compare values of channel A in its low half-word and of
+/// channel B in its high half-word, and is applied on the next wrap of the counter.
+// Safety: The CC register stays valid for as long as the slice is held by the transfer.
+unsafe impl<I, M> WriteTarget for Slice<I, M>
+where
+    I: SliceId,
+    M: SliceMode + ValidSliceMode<I>,
+{
+    type TransmittedWord = u32;
+
+    fn tx_treq(&self) -> Option<u8> {
+        Some(self.dreq())
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        (self.cc_address(), u32::MAX)
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
+}
+
 macro_rules! pwm {
     ($PWMX:ident, [
         $($SXi:ident: ($slice:literal, [$($pin_a:ident, $pin_b:ident),*], $i:expr)),+
@@ -492,6 +625,269 @@ pwm! {
     ]
 }
 
+/// Compute the integer clock divider and TOP value giving a period of
+/// `frequency` from a `sys_frequency` clock, keeping TOP as large as possible
+/// for the best duty cycle resolution.
+pub(crate) fn period_config(sys_frequency: u32, frequency: u32) -> (u8, u16) {
+    ticks_config(sys_frequency / frequency)
+}
+
+/// Compute the integer clock divider and TOP value giving a period of `ticks`
+/// system clock cycles.
+fn ticks_config(ticks: u32) -> (u8, u16) {
+    let div = (ticks / 0x1_0000 + 1).min(255);
+    let top = (ticks / div).clamp(1, 0x1_0000) - 1;
+    (div as u8, top as u16)
+}
+
+/// A [`Slice`] which knows the frequency of the clock driving it
+///
+/// Created with [`Slice::with_clock`]. Knowing the clock frequency allows the
+/// period to be expressed in time units, so this type implements the
+/// multi-channel [`embedded_hal::Pwm`] trait.
+pub struct ClockedSlice<S: SliceId, M: SliceMode + ValidSliceMode<S>> {
+    slice: Slice<S, M>,
+    sys_hz: u32,
+}
+
+impl<S: SliceId, M: SliceMode + ValidSliceMode<S>> Slice<S, M> {
+    /// Attach the frequency of the system clock, which feeds the PWM block, to
+    /// this slice
+    pub fn with_clock<F: Into<Hertz<u32>>>(self, sys_frequency: F) -> ClockedSlice<S, M> {
+        ClockedSlice {
+            slice: self,
+            sys_hz: sys_frequency.into().integer(),
+        }
+    }
+}
+
+impl<S: SliceId, M: SliceMode + ValidSliceMode<S>> ClockedSlice<S, M> {
+    /// Access the underlying slice
+    pub fn slice(&mut self) -> &mut Slice<S, M> {
+        &mut self.slice
+    }
+
+    /// Release the underlying slice
+    pub fn free(self) -> Slice<S, M> {
+        self.slice
+    }
+}
+
+impl<S: SliceId, M: SliceMode + ValidSliceMode<S>> embedded_hal::Pwm for ClockedSlice<S, M> {
+    type Channel = DynChannelId;
+    type Time = Nanoseconds<u32>;
+    type Duty = u16;
+
+    fn disable(&mut self, channel: DynChannelId) {
+        match channel {
+            DynChannelId::A => self.slice.channel_a.disable(),
+            DynChannelId::B => self.slice.channel_b.disable(),
+        }
+    }
+
+    fn enable(&mut self, channel: DynChannelId) {
+        match channel {
+            DynChannelId::A => self.slice.channel_a.enable(),
+            DynChannelId::B => self.slice.channel_b.enable(),
+        }
+    }
+
+    fn get_period(&self) -> Nanoseconds<u32> {
+        let regs = &self.slice.regs;
+        let div16 = ((regs.read_div_int() as u64) << 4) | regs.read_div_frac() as u64;
+        let mut ticks = (regs.read_top() as u64 + 1) * div16;
+        if regs.read_ph_correct() {
+            ticks *= 2;
+        }
+        Nanoseconds((ticks * 1_000_000_000 / 16 / self.sys_hz as u64) as u32)
+    }
+
+    fn get_duty(&self, channel: DynChannelId) -> u16 {
+        match channel {
+            DynChannelId::A => self.slice.channel_a.get_duty(),
+            DynChannelId::B => self.slice.channel_b.get_duty(),
+        }
+    }
+
+    fn get_max_duty(&self) -> u16 {
+        self.slice.get_top()
+    }
+
+    fn set_duty(&mut self, channel: DynChannelId, duty: u16) {
+        match channel {
+            DynChannelId::A => self.slice.channel_a.set_duty(duty),
+            DynChannelId::B => self.slice.channel_b.set_duty(duty),
+        }
+    }
+
+    /// Set the period, using an integer clock divider
+    ///
+    /// The duty cycle range, given by [`get_max_duty`](Self::get_max_duty),
+    /// changes with the period, so duties should be set again afterwards.
+    fn set_period<P>(&mut self, period: P)
+    where
+        P: Into<Nanoseconds<u32>>,
+    {
+        let mut ticks = period.into().integer() as u64 * self.sys_hz as u64 / 1_000_000_000;
+        if self.slice.regs.read_ph_correct() {
+            ticks /= 2;
+        }
+        let (div, top) = ticks_config(ticks.min(u32::MAX as u64) as u32);
+        self.slice.set_div_int(div);
+        self.slice.set_div_frac(0);
+        self.slice.set_top(top);
+    }
+}
+
+/// Iterator over the slices with a pending PWM_IRQ_WRAP interrupt
+///
+/// Created by [`pending_interrupts`]. The pending state is sampled once, when
+/// the iterator is created, and interrupts are not cleared by iterating.
+#[derive(Clone, Copy)]
+pub struct PendingInterrupts {
+    bits: u8,
+}
+
+impl Iterator for PendingInterrupts {
+    type Item = DynSliceId;
+
+    fn next(&mut self) -> Option<DynSliceId> {
+        if self.bits == 0 {
+            return None;
+        }
+        let num = self.bits.trailing_zeros() as u8;
+        self.bits &= !(1 << num);
+        Some(DynSliceId { num })
+    }
+}
+
+/// Return the slices with a pending PWM_IRQ_WRAP interrupt
+///
+/// This is intended for use in the interrupt handler, where the individual
+/// [`Slice`]s may not be available:
+///
+/// ```no_run
+/// # use rp2040_hal::pwm;
+/// for slice in pwm::pending_interrupts() {
+///     // Update the waveform of `slice`...
+///     pwm::clear_interrupt(slice);
+/// }
+/// ```
+#[inline]
+pub fn pending_interrupts() -> PendingInterrupts {
+    PendingInterrupts {
+        bits: unsafe { (*pac::PWM::ptr()).ints.read().bits() } as u8,
+    }
+}
+
+/// Mark the PWM_IRQ_WRAP interrupt of the given slice as handled
+#[inline]
+pub fn clear_interrupt(slice: DynSliceId) {
+    unsafe { (*pac::PWM::ptr()).intr.write(|w| w.bits(1 << slice.num)) };
+}
+
+/// Result of a [`capture_duty_cycle`](Slice::capture_duty_cycle) measurement
+#[derive(Clone, Copy, Debug)]
+pub struct Capture {
+    /// Frequency of the signal on the B pin
+    pub frequency: Hertz,
+    /// Fraction of the time the B pin was high, from `0.0` to `1.0`
+    pub duty_cycle: f32,
+}
+
+impl<S: SliceId, M: SliceMode + ValidSliceMode<S>> Slice<S, M> {
+    /// Measure the frequency and duty cycle of the signal on the B pin
+    ///
+    /// The slice is used to count rising edges for `gate_time_us`
+    /// microseconds, and then to count `clk_sys` cycles while the pin is high
+    /// for another `gate_time_us`. The slice is disabled while measuring, and
+    /// counts up to 65535 in both steps, without phase correction: its mode,
+    /// clock divider, TOP, phase correction and enable state are restored
+    /// afterwards. The counter and the raw wrap interrupt flag are clobbered.
+    ///
+    /// `sys_frequency` must be the frequency of the system clock, which feeds
+    /// the PWM block. Returns `None` if `gate_time_us` is zero, if more than
+    /// 65535 edges occurred during the gate time, or if the gate time is too
+    /// long for the high-time count to fit in the counter.
+    pub fn capture_duty_cycle<G, D, F>(
+        &mut self,
+        _pin: &PwmPinToken<G>,
+        delay: &mut D,
+        sys_frequency: F,
+        gate_time_us: u32,
+    ) -> Option<Capture>
+    where
+        G: PinId + BankPinId + ValidPwmInputPin<S>,
+        D: DelayUs<u32>,
+        F: Into<Hertz<u32>>,
+    {
+        if gate_time_us == 0 {
+            return None;
+        }
+        let sys_frequency = sys_frequency.into().integer();
+        let was_enabled = self.regs.read_enable();
+        let div_int = self.regs.read_div_int();
+        let div_frac = self.regs.read_div_frac();
+        let top = self.regs.read_top();
+        let ph_correct = self.regs.read_ph_correct();
+
+        self.regs.write_enable(false);
+        // A wrap then only happens when the count doesn't fit in the counter
+        self.regs.write_top(0xFFFF);
+        self.regs.write_ph_correct(false);
+        self.regs.write_div_int(1);
+        self.regs.write_div_frac(0);
+        let edges = self.count_for(DynSliceMode::CountRisingEdge, delay, gate_time_us);
+
+        // Divide clk_sys so the high time of a full gate period fits in the
+        // counter.
+        let gate_cycles = sys_frequency as u64 * gate_time_us as u64 / 1_000_000;
+        let div = (gate_cycles / 0x1_0000 + 1).min(255) as u8;
+        self.regs.write_div_int(div);
+        let high = self.count_for(DynSliceMode::InputHighRunning, delay, gate_time_us);
+
+        self.regs.do_change_mode(M::DYN);
+        self.regs.write_div_int(div_int);
+        self.regs.write_div_frac(div_frac);
+        self.regs.write_top(top);
+        self.regs.write_ph_correct(ph_correct);
+        self.regs.write_enable(was_enabled);
+
+        let edges = edges?;
+        let high = high?;
+        let total = gate_cycles / div as u64;
+        Some(Capture {
+            frequency: Hertz((edges as u64 * 1_000_000 / gate_time_us as u64) as u32),
+            duty_cycle: if total == 0 {
+                0.0
+            } else {
+                (high as f32 / total as f32).min(1.0)
+            },
+        })
+    }
+
+    /// Run the slice in `mode` for `time_us` and return the count, or `None`
+    /// if the counter wrapped.
+    fn count_for<D: DelayUs<u32>>(
+        &mut self,
+        mode: DynSliceMode,
+        delay: &mut D,
+        time_us: u32,
+    ) -> Option<u16> {
+        self.regs.do_change_mode(mode);
+        self.regs.write_ctr(0);
+        self.regs.clear_raw_wrap();
+        self.regs.write_enable(true);
+        delay.delay_us(time_us);
+        self.regs.write_enable(false);
+        if self.regs.read_raw_wrap() {
+            None
+        } else {
+            Some(self.regs.read_ctr())
+        }
+    }
+}
+
 /// Marker trait for valid output pins
 pub trait ValidPwmInputPin<S: SliceId>: Sealed {}
 /// Marker trait for valid input pins (Channel B only)
@@ -532,17 +928,29 @@ impl Slices {
         self._pwm
     }
 
-    //     /// Enable multiple slices at the same time to make their counters sync up.
-    //     ///
-    //     /// You still need to call `slice` to get an actual slice
-    //     pub fn enable_simultaneous<S: SliceId>(&mut self, bits: u8) {
-    //         // Enable all slices at the same time
-    //         unsafe {
-    //             &(*pac::PWM::ptr())
-    //                 .en
-    //                 .modify(|r, w| w.bits(((r.bits() as u8) | bits) as u32));
-    //         }
-    //     }
+    /// Enable multiple slices at the same time to make their counters sync up.
+    ///
+    /// Bit `n` of `bits` corresponds to slice `n`. Slices whose bit is clear
+    /// are left untouched. Combined with [`Slice::set_counter`] or
+    /// [`Slice::advance_phase`]/[`Slice::retard_phase`], this allows slices
+    /// to run with a fixed phase offset from each other.
+    pub fn enable_simultaneous(&mut self, bits: u8) {
+        unsafe {
+            let reg = (*pac::PWM::ptr()).en.as_ptr();
+            write_bitmask_set(reg, bits as u32);
+        }
+    }
+
+    /// Disable multiple slices at the same time.
+    ///
+    /// Bit `n` of `bits` corresponds to slice `n`. Slices whose bit is clear
+    /// are left untouched.
+    pub fn disable_simultaneous(&mut self, bits: u8) {
+        unsafe {
+            let reg = (*pac::PWM::ptr()).en.as_ptr();
+            write_bitmask_clear(reg, bits as u32);
+        }
+    }
 
     // /// Get pwm slice based on gpio pin
     // pub fn borrow_mut_from_pin<
@@ -574,6 +982,8 @@ pub struct Channel<S: SliceId, M: SliceMode, C: ChannelId> {
     slice_mode: PhantomData<M>,
     channel_id: PhantomData<C>,
     duty_cycle: u16,
+    /// The duty cycle and polarity to restore once released from [`force`](Self::force)
+    forced_from: Option<(u16, bool)>,
 }
 
 impl<S: SliceId, M: SliceMode, C: ChannelId> Channel<S, M, C> {
@@ -583,8 +993,89 @@ impl<S: SliceId, M: SliceMode, C: ChannelId> Channel<S, M, C> {
             slice_mode: PhantomData,
             channel_id: PhantomData,
             duty_cycle,
+            forced_from: None,
         }
     }
+
+    #[inline]
+    fn read_cc(&self) -> u16 {
+        match C::DYN {
+            DynChannelId::A => self.regs.read_cc_a(),
+            DynChannelId::B => self.regs.read_cc_b(),
+        }
+    }
+
+    #[inline]
+    fn write_cc(&mut self, value: u16) {
+        match C::DYN {
+            DynChannelId::A => self.regs.write_cc_a(value),
+            DynChannelId::B => self.regs.write_cc_b(value),
+        }
+    }
+
+    #[inline]
+    fn write_inv(&mut self, value: bool) {
+        match C::DYN {
+            DynChannelId::A => self.regs.write_inv_a(value),
+            DynChannelId::B => self.regs.write_inv_b(value),
+        }
+    }
+
+    /// Is the channel output inverted?
+    #[inline]
+    pub fn is_inverted(&self) -> bool {
+        match C::DYN {
+            DynChannelId::A => self.regs.read_inv_a(),
+            DynChannelId::B => self.regs.read_inv_b(),
+        }
+    }
+
+    /// Hold the channel output at a constant level, regardless of the counter
+    ///
+    /// The pin stays connected to the PWM, so this can be used to park an
+    /// output in a safe state, e.g. to switch off both sides of a
+    /// complementary driver pair on a fault. The duty cycle and polarity in
+    /// effect are restored by [`release`](Self::release).
+    ///
+    /// The level takes effect at the next wrap of the counter, as the
+    /// compare register is double buffered. The exception is forcing the
+    /// active level of the channel while TOP is 0xFFFF, where no compare value
+    /// lasts the whole period: the polarity is then inverted, which takes
+    /// effect at once, so the output may glitch until the next wrap.
+    pub fn force(&mut self, high: bool) {
+        if self.forced_from.is_none() {
+            self.forced_from = Some((self.read_cc(), self.is_inverted()));
+        }
+        let inverted = self.forced_from.map_or(false, |(_, inverted)| inverted);
+        // The output is active while the counter is below the compare value
+        let active = high != inverted;
+        let top = self.regs.read_top();
+        if !active {
+            self.write_inv(inverted);
+            self.write_cc(0);
+        } else if top < u16::MAX {
+            self.write_inv(inverted);
+            self.write_cc(top + 1);
+        } else {
+            self.write_cc(0);
+            self.write_inv(!inverted);
+        }
+    }
+
+    /// Stop forcing the output level, restoring the previous duty cycle and
+    /// polarity
+    pub fn release(&mut self) {
+        if let Some((duty_cycle, inverted)) = self.forced_from.take() {
+            self.write_inv(inverted);
+            self.write_cc(duty_cycle);
+        }
+    }
+
+    /// Is the output being held at a constant level by [`force`](Self::force)?
+    #[inline]
+    pub fn is_forced(&self) -> bool {
+        self.forced_from.is_some()
+    }
 }
 
 impl<S: SliceId, M: SliceMode, C: ChannelId> Sealed for Channel<S, M, C> {}
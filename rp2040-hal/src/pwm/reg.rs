@@ -20,12 +20,16 @@ pub(super) unsafe trait RegisterInterface {
 
     #[inline]
     fn advance_phase(&mut self) {
-        self.ch().csr.modify(|_, w| w.ph_adv().set_bit())
+        self.ch().csr.modify(|_, w| w.ph_adv().set_bit());
+        // The bit is cleared by hardware once the advance has happened
+        while self.ch().csr.read().ph_adv().bit_is_set() {}
     }
 
     #[inline]
     fn retard_phase(&mut self) {
-        self.ch().csr.modify(|_, w| w.ph_ret().set_bit())
+        self.ch().csr.modify(|_, w| w.ph_ret().set_bit());
+        // The bit is cleared by hardware once the retard has happened
+        while self.ch().csr.read().ph_ret().bit_is_set() {}
     }
 
     #[inline]
@@ -48,6 +52,21 @@ pub(super) unsafe trait RegisterInterface {
         self.ch().csr.modify(|_, w| w.b_inv().bit(value));
     }
 
+    #[inline]
+    fn read_inv_a(&self) -> bool {
+        self.ch().csr.read().a_inv().bit_is_set()
+    }
+
+    #[inline]
+    fn read_inv_b(&self) -> bool {
+        self.ch().csr.read().b_inv().bit_is_set()
+    }
+
+    #[inline]
+    fn read_ph_correct(&self) -> bool {
+        self.ch().csr.read().ph_correct().bit_is_set()
+    }
+
     #[inline]
     fn write_ph_correct(&mut self, value: bool) {
         self.ch().csr.modify(|_, w| w.ph_correct().bit(value));
@@ -58,6 +77,21 @@ pub(super) unsafe trait RegisterInterface {
         self.ch().csr.modify(|_, w| w.en().bit(value));
     }
 
+    #[inline]
+    fn read_enable(&self) -> bool {
+        self.ch().csr.read().en().bit_is_set()
+    }
+
+    #[inline]
+    fn read_div_int(&self) -> u8 {
+        self.ch().div.read().int().bits()
+    }
+
+    #[inline]
+    fn read_div_frac(&self) -> u8 {
+        self.ch().div.read().frac().bits()
+    }
+
     #[inline]
     fn write_div_int(&mut self, value: u8) {
         self.ch().div.modify(|_, w| unsafe { w.int().bits(value) });
@@ -94,6 +128,13 @@ pub(super) unsafe trait RegisterInterface {
     fn read_cc_b(&self) -> u16 {
         self.ch().cc.read().b().bits()
     }
+
+    #[inline]
+    fn write_cc(&mut self, a: u16, b: u16) {
+        self.ch()
+            .cc
+            .write(|w| unsafe { w.bits(((b as u32) << 16) | a as u32) });
+    }
     #[inline]
     fn write_top(&mut self, value: u16) {
         self.ch().top.write(|w| unsafe { w.top().bits(value) });
@@ -102,4 +143,17 @@ pub(super) unsafe trait RegisterInterface {
     fn read_top(&self) -> u16 {
         self.ch().top.read().top().bits()
     }
+
+    /// Raw (unmasked) wrap interrupt flag of this slice
+    #[inline]
+    fn read_raw_wrap(&self) -> bool {
+        let mask = 1 << self.id().num;
+        unsafe { (*pac::PWM::ptr()).intr.read().bits() & mask == mask }
+    }
+
+    #[inline]
+    fn clear_raw_wrap(&mut self) {
+        let mask = 1 << self.id().num;
+        unsafe { (*pac::PWM::ptr()).intr.write(|w| w.bits(mask)) };
+    }
 }
@@ -0,0 +1,94 @@
+//! Hobby servo control
+//!
+//! Hobby servos expect a pulse every 20 ms, with the width of the pulse (1 to
+//! 2 ms for most servos) selecting the position. [`Servo`] configures a slice
+//! for a 50 Hz period and sets the pulse width in microseconds.
+//!
+//! ```no_run
+//! # use rp2040_hal::{pac, gpio::Pins, sio::Sio, pwm::{Servo, Slices}};
+//! # let mut pac = pac::Peripherals::take().unwrap();
+//! # let sio = Sio::new(pac.SIO);
+//! # let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+//! use embedded_time::rate::Extensions;
+//!
+//! let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+//! let mut servo = Servo::new(pwm_slices.pwm0, pins.gpio0, 125_000_000u32.Hz());
+//! // Calibrate for a servo which needs 0.5 ms to 2.5 ms pulses
+//! servo.set_range_us(500, 2500);
+//! servo.set_angle(90);
+//! ```
+use super::single_channel::SingleChannel;
+use super::{ChannelId, FreeRunning, PwmPinToken, Slice, SliceId, ValidPwmOutputPin};
+use crate::gpio::{bank0::BankPinId, Pin, PinId, PinMode, ValidPinMode};
+use embedded_time::fixed_point::FixedPoint;
+use embedded_time::rate::Hertz;
+
+/// Servo update frequency
+const SERVO_FREQUENCY_HZ: u32 = 50;
+
+/// A hobby servo driven by one channel of a PWM slice
+pub struct Servo<S: SliceId, C: ChannelId, G: PinId + BankPinId> {
+    channel: SingleChannel<S, C, G>,
+    min_us: u16,
+    max_us: u16,
+}
+
+impl<S, C, G> Servo<S, C, G>
+where
+    S: SliceId,
+    C: ChannelId,
+    G: PinId + BankPinId + ValidPwmOutputPin<S, C>,
+{
+    /// Configure `slice` for a 50 Hz period and output it to `pin`
+    ///
+    /// `sys_frequency` must be the frequency of the system clock, which feeds
+    /// the PWM block. The pulse width range defaults to 1000 to 2000 µs, and
+    /// the output is held low until a position is set.
+    pub fn new<PM, F>(slice: Slice<S, FreeRunning>, pin: Pin<G, PM>, sys_frequency: F) -> Self
+    where
+        PM: PinMode + ValidPinMode<G>,
+        F: Into<Hertz<u32>>,
+    {
+        let mut channel = SingleChannel::new(slice, pin, sys_frequency.into().integer());
+        channel.set_frequency(SERVO_FREQUENCY_HZ);
+        channel.slice.enable();
+        Servo {
+            channel,
+            min_us: 1000,
+            max_us: 2000,
+        }
+    }
+
+    /// Set the pulse widths corresponding to 0 and 180 degrees
+    pub fn set_range_us(&mut self, min_us: u16, max_us: u16) {
+        self.min_us = min_us;
+        self.max_us = max_us;
+    }
+
+    /// Set the width of the pulse in microseconds
+    ///
+    /// Widths longer than the 20 ms period keep the output high.
+    pub fn set_pulse_width_us(&mut self, width_us: u16) {
+        self.channel.set_duty_us(width_us as u32);
+    }
+
+    /// Move the servo to `degrees`, between 0 and 180
+    ///
+    /// Larger values are clamped to 180 degrees.
+    pub fn set_angle(&mut self, degrees: u8) {
+        let degrees = degrees.min(180) as i32;
+        let span = self.max_us as i32 - self.min_us as i32;
+        let width = self.min_us as i32 + span * degrees / 180;
+        self.set_pulse_width_us(width as u16);
+    }
+
+    /// Stop sending pulses, letting the servo go limp
+    pub fn stop(&mut self) {
+        self.set_pulse_width_us(0);
+    }
+
+    /// Release the slice and the pin
+    pub fn free(self) -> (Slice<S, FreeRunning>, PwmPinToken<G>) {
+        self.channel.free()
+    }
+}
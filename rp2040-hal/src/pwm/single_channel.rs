@@ -0,0 +1,82 @@
+//! One channel of a slice driven on its own, with a fixed period
+//!
+//! This is what [`Servo`](super::Servo) and [`Tone`](super::Tone) share: they
+//! only differ in how they pick the period and the duty cycle.
+use super::{
+    period_config, ChannelId, DynChannelId, FreeRunning, PwmPinToken, Slice, SliceId,
+    ValidPwmOutputPin,
+};
+use crate::gpio::{bank0::BankPinId, Pin, PinId, PinMode, ValidPinMode};
+use core::marker::PhantomData;
+use embedded_hal::PwmPin;
+
+pub(super) struct SingleChannel<S: SliceId, C: ChannelId, G: PinId + BankPinId> {
+    pub(super) slice: Slice<S, FreeRunning>,
+    pin: PwmPinToken<G>,
+    channel: PhantomData<C>,
+    sys_hz: u32,
+    /// Frequency of the counter, once the clock divider is applied
+    tick_hz: u32,
+}
+
+impl<S, C, G> SingleChannel<S, C, G>
+where
+    S: SliceId,
+    C: ChannelId,
+    G: PinId + BankPinId + ValidPwmOutputPin<S, C>,
+{
+    /// Take `slice`, in its default configuration, and output channel `C` to
+    /// `pin`
+    pub(super) fn new<PM>(mut slice: Slice<S, FreeRunning>, pin: Pin<G, PM>, sys_hz: u32) -> Self
+    where
+        PM: PinMode + ValidPinMode<G>,
+    {
+        slice.default_config();
+        let pin = slice.output_to::<G, PM, C>(pin);
+        SingleChannel {
+            slice,
+            pin,
+            channel: PhantomData,
+            sys_hz,
+            tick_hz: sys_hz,
+        }
+    }
+
+    /// Set the period to the closest one to `frequency`, and return the
+    /// frequency achieved
+    ///
+    /// The duty cycle must be set again afterwards, as TOP changes.
+    pub(super) fn set_frequency(&mut self, frequency: u32) -> u32 {
+        let (div, top) = period_config(self.sys_hz, frequency.max(1));
+        self.slice.set_div_int(div);
+        self.slice.set_top(top);
+        self.tick_hz = self.sys_hz / div as u32;
+        self.tick_hz / (top as u32 + 1)
+    }
+
+    /// Set the time the output is high in each period, in counter ticks,
+    /// clamped to TOP
+    pub(super) fn set_duty_ticks(&mut self, ticks: u32) {
+        let duty = ticks.min(self.slice.get_top() as u32) as u16;
+        match C::DYN {
+            DynChannelId::A => self.slice.channel_a.set_duty(duty),
+            DynChannelId::B => self.slice.channel_b.set_duty(duty),
+        }
+    }
+
+    /// Set the time the output is high in each period, in microseconds,
+    /// clamped to TOP
+    pub(super) fn set_duty_us(&mut self, us: u32) {
+        let ticks = us as u64 * self.tick_hz as u64 / 1_000_000;
+        self.set_duty_ticks(ticks.min(u32::MAX as u64) as u32);
+    }
+
+    /// Half of the period, in counter ticks
+    pub(super) fn half_period_ticks(&self) -> u32 {
+        (self.slice.get_top() as u32 + 1) / 2
+    }
+
+    pub(super) fn free(self) -> (Slice<S, FreeRunning>, PwmPinToken<G>) {
+        (self.slice, self.pin)
+    }
+}
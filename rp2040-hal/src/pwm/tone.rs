@@ -0,0 +1,93 @@
+//! Square wave tone generation
+//!
+//! [`Tone`] plays a square wave of a given frequency on one channel of a
+//! slice, e.g. to drive a piezo buzzer.
+//!
+//! ```no_run
+//! # use rp2040_hal::{pac, gpio::Pins, sio::Sio, pwm::{Slices, Tone}};
+//! # let mut pac = pac::Peripherals::take().unwrap();
+//! # let sio = Sio::new(pac.SIO);
+//! # let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+//! use embedded_time::rate::Extensions;
+//!
+//! let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+//! let mut buzzer = Tone::new(pwm_slices.pwm2, pins.gpio4, 125_000_000u32.Hz());
+//! buzzer.play(440u32.Hz());
+//! // ...
+//! buzzer.stop();
+//! ```
+use super::single_channel::SingleChannel;
+use super::{ChannelId, FreeRunning, PwmPinToken, Slice, SliceId, ValidPwmOutputPin};
+use crate::gpio::{bank0::BankPinId, Pin, PinId, PinMode, ValidPinMode};
+use embedded_time::fixed_point::FixedPoint;
+use embedded_time::rate::Hertz;
+
+/// A square wave generator driven by one channel of a PWM slice
+pub struct Tone<S: SliceId, C: ChannelId, G: PinId + BankPinId> {
+    channel: SingleChannel<S, C, G>,
+}
+
+impl<S, C, G> Tone<S, C, G>
+where
+    S: SliceId,
+    C: ChannelId,
+    G: PinId + BankPinId + ValidPwmOutputPin<S, C>,
+{
+    /// Take `slice` and output it to `pin`, initially silent
+    ///
+    /// `sys_frequency` must be the frequency of the system clock, which feeds
+    /// the PWM block.
+    pub fn new<PM, F>(slice: Slice<S, FreeRunning>, pin: Pin<G, PM>, sys_frequency: F) -> Self
+    where
+        PM: PinMode + ValidPinMode<G>,
+        F: Into<Hertz<u32>>,
+    {
+        Tone {
+            channel: SingleChannel::new(slice, pin, sys_frequency.into().integer()),
+        }
+    }
+
+    /// Play a square wave of the given frequency, and return the frequency
+    /// actually achieved
+    ///
+    /// The lowest frequency which can be produced is about 8 Hz with a
+    /// 125 MHz system clock.
+    pub fn play<F: Into<Hertz<u32>>>(&mut self, frequency: F) -> Hertz {
+        let achieved = self.channel.set_frequency(frequency.into().integer());
+        let half_period = self.channel.half_period_ticks();
+        self.channel.set_duty_ticks(half_period);
+        self.channel.slice.enable();
+        Hertz(achieved)
+    }
+
+    /// Stop playing, leaving the output low
+    ///
+    /// Blocks for up to one period of the tone, until the compare value of 0
+    /// is latched.
+    pub fn stop(&mut self) {
+        self.channel.set_duty_ticks(0);
+        // With a TOP of 0, the counter wraps on every cycle, so the new
+        // compare value is already latched.
+        if self.channel.slice.is_enabled() && self.channel.slice.get_top() > 0 {
+            // The compare value only takes effect when the counter wraps, so
+            // the output could be left high if the slice was disabled now.
+            // Free-running without phase correction, the counter only goes
+            // down when it wraps.
+            let mut last = self.channel.slice.get_counter();
+            loop {
+                let ctr = self.channel.slice.get_counter();
+                if ctr < last {
+                    break;
+                }
+                last = ctr;
+            }
+        }
+        self.channel.slice.disable();
+        self.channel.slice.set_counter(0);
+    }
+
+    /// Release the slice and the pin
+    pub fn free(self) -> (Slice<S, FreeRunning>, PwmPinToken<G>) {
+        self.channel.free()
+    }
+}
@@ -0,0 +1,88 @@
+//! Memory accesses for the functions running from RAM while the flash is not accessible
+//!
+//! The functions placed in `.data.ram_func` must not call anything living in flash.
+//! `core::ptr::read_volatile` and `write_volatile` are only `#[inline]`, so they are outlined in
+//! debug builds, and fetched from flash. These accessors are inline assembly instead, which is
+//! always inlined.
+
+#[cfg(target_arch = "arm")]
+use core::arch::asm;
+
+/// Read the word at `ptr`.
+///
+/// # Safety
+///
+/// As for [`core::ptr::read_volatile`].
+#[inline(always)]
+pub(crate) unsafe fn read_u32(ptr: *const u32) -> u32 {
+    #[cfg(target_arch = "arm")]
+    {
+        let value;
+        asm!("ldr {0}, [{1}]", out(reg) value, in(reg) ptr, options(nostack, preserves_flags, readonly));
+        value
+    }
+    #[cfg(not(target_arch = "arm"))]
+    core::ptr::read_volatile(ptr)
+}
+
+/// Write `value` to the word at `ptr`.
+///
+/// # Safety
+///
+/// As for [`core::ptr::write_volatile`].
+#[inline(always)]
+pub(crate) unsafe fn write_u32(ptr: *mut u32, value: u32) {
+    #[cfg(target_arch = "arm")]
+    asm!("str {0}, [{1}]", in(reg) value, in(reg) ptr, options(nostack, preserves_flags));
+    #[cfg(not(target_arch = "arm"))]
+    core::ptr::write_volatile(ptr, value)
+}
+
+/// Read the byte at `ptr`.
+///
+/// # Safety
+///
+/// As for [`core::ptr::read_volatile`].
+#[inline(always)]
+pub(crate) unsafe fn read_u8(ptr: *const u8) -> u8 {
+    #[cfg(target_arch = "arm")]
+    {
+        let value: u32;
+        asm!("ldrb {0}, [{1}]", out(reg) value, in(reg) ptr, options(nostack, preserves_flags, readonly));
+        value as u8
+    }
+    #[cfg(not(target_arch = "arm"))]
+    core::ptr::read_volatile(ptr)
+}
+
+/// Write `value` to the byte at `ptr`.
+///
+/// # Safety
+///
+/// As for [`core::ptr::write_volatile`].
+#[inline(always)]
+pub(crate) unsafe fn write_u8(ptr: *mut u8, value: u8) {
+    #[cfg(target_arch = "arm")]
+    asm!("strb {0}, [{1}]", in(reg) value as u32, in(reg) ptr, options(nostack, preserves_flags));
+    #[cfg(not(target_arch = "arm"))]
+    core::ptr::write_volatile(ptr, value)
+}
+
+/// Spin for about `3 * cycles` cycles.
+#[inline(always)]
+pub(crate) fn delay(cycles: u32) {
+    #[cfg(target_arch = "arm")]
+    unsafe {
+        asm!(
+            "1:",
+            "subs {0}, #1",
+            "bne 1b",
+            inout(reg) if cycles == 0 { 1 } else { cycles } => _,
+            options(nomem, nostack),
+        );
+    }
+    #[cfg(not(target_arch = "arm"))]
+    for _ in 0..cycles {
+        core::hint::spin_loop();
+    }
+}
@@ -7,6 +7,19 @@
 //! > on the device, as well as highly optimized versions of certain key
 //! > functionality that would otherwise have to take up space in most user
 //! > binaries.
+//!
+//! ## Features
+//!
+//! With the `rom-func-cache` feature, each function or table is only looked up in the bootrom the
+//! first time it is used, and its address is kept in RAM for the next calls.
+//!
+//! With the `rom-v2-intrinsics` feature, the single and double-precision additions,
+//! subtractions, multiplications, divisions and conversions which the compiler turns into calls
+//! to its soft-float library run on the optimized bootrom functions instead. The double-precision
+//! functions are only present in the V2 bootrom, of B1 chips and later, which this feature
+//! requires.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 /// A bootrom function table code.
 pub type RomFnTableCode = [u8; 2];
@@ -36,6 +49,28 @@ fn rom_table_lookup<T>(table: *const u16, tag: RomFnTableCode) -> T {
     }
 }
 
+/// Look the address of a function up in the bootrom, for code which can't call the wrappers of
+/// this module as it runs while the flash is not accessible.
+pub(crate) fn rom_func_lookup(tag: RomFnTableCode) -> usize {
+    rom_table_lookup(FUNC_TABLE, tag)
+}
+
+/// Retrieve rom content from a table using a code, only looking it up once if the
+/// `rom-func-cache` feature is enabled, in which case the address found is kept in `cache`.
+fn rom_table_lookup_cached(cache: &AtomicUsize, table: *const u16, tag: RomFnTableCode) -> usize {
+    if cfg!(feature = "rom-func-cache") {
+        let cached = cache.load(Ordering::Relaxed);
+        if cached != 0 {
+            return cached;
+        }
+        let found = rom_table_lookup(table, tag);
+        cache.store(found, Ordering::Relaxed);
+        found
+    } else {
+        rom_table_lookup(table, tag)
+    }
+}
+
 /// To save space, the ROM likes to store memory pointers (which are 32-bit on
 /// the Cortex-M0+) using only the bottom 16-bits. The assumption is that the
 /// values they point at live in the first 64 KiB of ROM, and the ROM is mapped
@@ -59,7 +94,9 @@ macro_rules! rom_funcs {
         $(
             $(#[$outer])*
             pub fn $name($( $aname:$aty ),*) -> $ret{
-                let func:  extern "C" fn( $( $aty ),* ) -> $ret = rom_table_lookup(FUNC_TABLE, *$c);
+                static CACHE: AtomicUsize = AtomicUsize::new(0);
+                let func: extern "C" fn( $( $aty ),* ) -> $ret =
+                    unsafe { core::mem::transmute(rom_table_lookup_cached(&CACHE, FUNC_TABLE, *$c)) };
                 func($( $aname ),*)
             }
         )*
@@ -78,7 +115,9 @@ macro_rules! rom_funcs_unsafe {
         $(
             $(#[$outer])*
             pub unsafe fn $name($( $aname:$aty ),*) -> $ret{
-                let func:  extern "C" fn( $( $aty ),* ) -> $ret = rom_table_lookup(FUNC_TABLE, *$c);
+                static CACHE: AtomicUsize = AtomicUsize::new(0);
+                let func: extern "C" fn( $( $aty ),* ) -> $ret =
+                    core::mem::transmute(rom_table_lookup_cached(&CACHE, FUNC_TABLE, *$c));
                 func($( $aname ),*)
             }
         )*
@@ -113,7 +152,7 @@ rom_funcs! {
 
 rom_funcs_unsafe! {
     /// Sets n bytes start at ptr to the value c and returns ptr
-    b"MS" memset(ptr: *mut u8, c: u8, n: u8) -> *mut u8;
+    b"MS" memset(ptr: *mut u8, c: u8, n: u32) -> *mut u8;
 
     /// Sets n bytes start at ptr to the value c and returns ptr.
     ///
@@ -123,14 +162,14 @@ rom_funcs_unsafe! {
 
     /// Copies n bytes starting at src to dest and returns dest. The results are undefined if the
     /// regions overlap.
-    b"MC" memcpy(dest: *mut u8, src: *mut u8, n: u32) -> u8;
+    b"MC" memcpy(dest: *mut u8, src: *const u8, n: u32) -> *mut u8;
 
     /// Copies n bytes starting at src to dest and returns dest. The results are undefined if the
     /// regions overlap.
     ///
     /// Note this is a slightly more efficient variant of _memcpy that may only be
     /// used if dest and src are word aligned.
-    b"C4" memcpy44(dest: *mut u32, src: *mut u32, n: u32) -> *mut u8;
+    b"C4" memcpy44(dest: *mut u32, src: *const u32, n: u32) -> *mut u32;
 
     /// Restore all QSPI pad controls to their default state, and connect the SSI to the QSPI pads.
     b"IF" connect_internal_flash() -> ();
@@ -172,6 +211,40 @@ rom_funcs_unsafe! {
     b"WV" wait_for_vector() -> !;
 }
 
+/// Copy `src` into `dest` with the bootrom's [`memcpy`], or [`memcpy44`] when both are word
+/// aligned and hold whole words.
+///
+/// # Panics
+///
+/// Panics if `dest` and `src` have different lengths.
+pub fn copy(dest: &mut [u8], src: &[u8]) {
+    assert_eq!(dest.len(), src.len(), "The slices have different lengths");
+    let n = dest.len() as u32;
+    // Safety: both slices are valid for `n` bytes, and can't overlap as `dest` is borrowed
+    // mutably
+    unsafe {
+        if (dest.as_ptr() as u32 | src.as_ptr() as u32 | n) & 3 == 0 {
+            memcpy44(dest.as_mut_ptr() as *mut u32, src.as_ptr() as *const u32, n);
+        } else {
+            memcpy(dest.as_mut_ptr(), src.as_ptr(), n);
+        }
+    }
+}
+
+/// Set all bytes of `dest` to `value` with the bootrom's [`memset`], or [`memset4`] when it is
+/// word aligned and holds whole words.
+pub fn fill(dest: &mut [u8], value: u8) {
+    let n = dest.len() as u32;
+    // Safety: the slice is valid for `n` bytes
+    unsafe {
+        if (dest.as_ptr() as u32 | n) & 3 == 0 {
+            memset4(dest.as_mut_ptr() as *mut u32, value, n);
+        } else {
+            memset(dest.as_mut_ptr(), value, n);
+        }
+    }
+}
+
 unsafe fn convert_str(s: *const u8) -> &'static str {
     let mut end = s;
     while *end != 0 {
@@ -204,7 +277,8 @@ pub fn fplib_start() -> *const u8 {
 
 /// See Table 180 in the RP2040 datasheet for the contents of this table.
 pub fn soft_float_table() -> *const usize {
-    rom_table_lookup(DATA_TABLE, *b"SF")
+    static CACHE: AtomicUsize = AtomicUsize::new(0);
+    rom_table_lookup_cached(&CACHE, DATA_TABLE, *b"SF") as *const usize
 }
 
 /// The end address of the floating point library code and data.
@@ -214,7 +288,8 @@ pub fn fplib_end() -> *const u8 {
 
 /// This entry is only present in the V2 bootrom. See Table 182 in the RP2040 datasheet for the contents of this table.
 pub fn soft_double_table() -> *const usize {
-    rom_table_lookup(DATA_TABLE, *b"SD")
+    static CACHE: AtomicUsize = AtomicUsize::new(0);
+    rom_table_lookup_cached(&CACHE, DATA_TABLE, *b"SD") as *const usize
 }
 
 /// ROM functions using single-precision arithmetic (i.e. 'f32' in Rust terms)
@@ -524,3 +599,41 @@ pub mod double_funcs {
         0x7c double_to_float(v: f64) -> f32;
     }
 }
+
+/// Soft-float intrinsics of the compiler, running on the bootrom functions
+///
+/// Only the conversions which round like the compiler's are routed to the bootrom: its float to
+/// integer conversions round towards -Infinity, where Rust truncates.
+#[cfg(all(target_arch = "arm", feature = "rom-v2-intrinsics"))]
+mod intrinsics {
+    use super::{double_funcs, float_funcs};
+
+    macro_rules! intrinsics {
+        ($($intrinsic:ident => $module:ident::$func:ident ( $( $aname:ident : $aty:ty ),* ) -> $ret:ty;)*) => {
+            $(
+                #[no_mangle]
+                extern "C" fn $intrinsic($( $aname: $aty ),*) -> $ret {
+                    $module::$func()($( $aname ),*)
+                }
+            )*
+        }
+    }
+
+    intrinsics! {
+        __aeabi_fadd => float_funcs::fadd(a: f32, b: f32) -> f32;
+        __aeabi_fsub => float_funcs::fsub(a: f32, b: f32) -> f32;
+        __aeabi_fmul => float_funcs::fmul(a: f32, b: f32) -> f32;
+        __aeabi_fdiv => float_funcs::fdiv(a: f32, b: f32) -> f32;
+        __aeabi_i2f => float_funcs::int_to_float(v: i32) -> f32;
+        __aeabi_ui2f => float_funcs::uint_to_float(v: u32) -> f32;
+        __aeabi_f2d => float_funcs::float_to_double(v: f32) -> f64;
+
+        __aeabi_dadd => double_funcs::dadd(a: f64, b: f64) -> f64;
+        __aeabi_dsub => double_funcs::dsub(a: f64, b: f64) -> f64;
+        __aeabi_dmul => double_funcs::dmul(a: f64, b: f64) -> f64;
+        __aeabi_ddiv => double_funcs::ddiv(a: f64, b: f64) -> f64;
+        __aeabi_i2d => double_funcs::int_to_double(v: i32) -> f64;
+        __aeabi_ui2d => double_funcs::uint_to_double(v: u32) -> f64;
+        __aeabi_d2f => double_funcs::double_to_float(v: f64) -> f32;
+    }
+}
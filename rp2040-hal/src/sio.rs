@@ -19,6 +19,7 @@
 //! ```
 
 use super::*;
+use crate::interp::{Interp0, Interp1};
 use core::convert::Infallible;
 
 /// Marker struct for ownership of SIO gpio bank0
@@ -27,6 +28,20 @@ pub struct SioGpioBank0 {
 }
 
 /// Marker struct for ownership of SIO FIFO
+///
+/// The FIFO is made of two queues of eight 32-bit words, one from core 0 to core 1 and one the
+/// other way around. Each core writes to the queue towards the other core, and reads from the
+/// queue towards itself, through the same registers: the `SioFifo` of each core talks to the
+/// other core.
+///
+/// ```no_run
+/// use rp2040_hal::{pac, sio::Sio};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut sio = Sio::new(peripherals.SIO);
+/// // Send a request to the code running on core 1, and wait for its reply
+/// sio.fifo.write_blocking(42);
+/// let reply = sio.fifo.read_blocking();
+/// ```
 pub struct SioFifo {
     _private: (),
 }
@@ -60,12 +75,33 @@ pub struct Sio {
     pub hwdivider: HwDivider,
     /// Inter-core FIFO
     pub fifo: SioFifo,
-    // we can hand out other things here, for example:
-    // interp0
-    // interp1
+    /// Interpolator 0
+    pub interp0: Interp0,
+    /// Interpolator 1
+    pub interp1: Interp1,
+}
+
+/// One of the two cores of the RP2040
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoreId {
+    /// Core 0, which starts running the application after reset
+    Core0 = 0,
+    /// Core 1, which waits in the bootrom for code to run after reset
+    Core1 = 1,
 }
 
 impl Sio {
+    /// The core executing this.
+    pub fn core() -> CoreId {
+        // Safety: we're only reading from a register
+        let sio = unsafe { &*pac::SIO::ptr() };
+        if sio.cpuid.read().bits() == 0 {
+            CoreId::Core0
+        } else {
+            CoreId::Core1
+        }
+    }
+
     /// Create `Sio` from the PAC.
     pub fn new(sio: pac::SIO) -> Self {
         Self {
@@ -74,11 +110,22 @@ impl Sio {
             gpio_qspi: SioGpioQspi { _private: () },
             fifo: SioFifo { _private: () },
             hwdivider: HwDivider { _private: () },
+            interp0: Interp0::new(),
+            interp1: Interp1::new(),
         }
     }
 }
 
 impl SioFifo {
+    /// Get the end of the FIFO of the core executing this.
+    ///
+    /// # Safety
+    ///
+    /// Nothing else may use the FIFO of this core meanwhile.
+    pub(crate) unsafe fn steal() -> Self {
+        SioFifo { _private: () }
+    }
+
     /// Check if the inter-core FIFO has valid data for reading.
     ///
     /// Returning `true` means there is valid data, `false` means it is empty
@@ -103,6 +150,60 @@ impl SioFifo {
         sio.fifo_st.read().bits()
     }
 
+    /// Check if this core wrote to the FIFO while it was full (WOF), the value being lost.
+    ///
+    /// The flag stays set until [`clear_errors`](Self::clear_errors) is called.
+    pub fn write_overflowed(&self) -> bool {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.fifo_st.read().wof().bit_is_set()
+    }
+
+    /// Check if this core read from the FIFO while it was empty (ROE).
+    ///
+    /// The flag stays set until [`clear_errors`](Self::clear_errors) is called.
+    pub fn read_on_empty(&self) -> bool {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.fifo_st.read().roe().bit_is_set()
+    }
+
+    /// Clear the WOF and ROE flags of this core.
+    ///
+    /// These flags raise the FIFO interrupt of the core, so its handler must clear them.
+    pub fn clear_errors(&mut self) {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        // Write 1 to clear WOF (bit 2) and ROE (bit 3)
+        sio.fifo_st.write(|w| unsafe { w.bits(0b1100) });
+    }
+
+    /// The interrupt raised for the core executing this, when its end of the FIFO has data to
+    /// read, or one of its error flags is set.
+    ///
+    /// Each core has its own end of the FIFO and its own interrupt: `SIO_IRQ_PROC0` is only
+    /// routed to core 0, and `SIO_IRQ_PROC1` to core 1.
+    pub fn interrupt(&self) -> pac::Interrupt {
+        match Sio::core() {
+            CoreId::Core0 => pac::Interrupt::SIO_IRQ_PROC0,
+            CoreId::Core1 => pac::Interrupt::SIO_IRQ_PROC1,
+        }
+    }
+
+    /// Unmask the FIFO interrupt of the core executing this, in the NVIC of that core.
+    ///
+    /// Call it on each core which is to be interrupted when the other one writes to the FIFO.
+    ///
+    /// # Safety
+    ///
+    /// This can break mask-based critical sections, see
+    /// [`NVIC::unmask`](cortex_m::peripheral::NVIC::unmask).
+    pub unsafe fn unmask_interrupt(&mut self) {
+        cortex_m::peripheral::NVIC::unmask(self.interrupt());
+    }
+
+    /// Mask the FIFO interrupt of the core executing this, in the NVIC of that core.
+    pub fn mask_interrupt(&mut self) {
+        cortex_m::peripheral::NVIC::mask(self.interrupt());
+    }
+
     /// Write to the inter-core FIFO.
     ///
     /// You must ensure the FIFO has space by calling `is_write_ready`
@@ -126,6 +227,21 @@ impl SioFifo {
         }
     }
 
+    /// Write to the inter-core FIFO, or return `WouldBlock` if it is full.
+    pub fn try_write(&mut self, value: u32) -> nb::Result<(), Infallible> {
+        if self.is_write_ready() {
+            self.write(value);
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Read from the inter-core FIFO, or return `WouldBlock` if it is empty.
+    pub fn try_read(&mut self) -> nb::Result<u32, Infallible> {
+        self.read().ok_or(nb::Error::WouldBlock)
+    }
+
     /// Read from the FIFO until it is empty, throwing the contents away.
     pub fn drain(&mut self) {
         while self.read().is_some() {
@@ -165,17 +281,77 @@ impl SioFifo {
     }
 }
 
+/// A value for each core, of which each core only accesses its own
+///
+/// Drivers and loggers can keep their state in a `static` `CoreLocal`, without the cores racing
+/// for it.
+///
+/// ```no_run
+/// use core::cell::Cell;
+/// use rp2040_hal::sio::CoreLocal;
+///
+/// static INTERRUPTS: CoreLocal<Cell<u32>> = CoreLocal::new(Cell::new(0), Cell::new(0));
+///
+/// // In an interrupt handler, on either core
+/// INTERRUPTS.with(|count| count.set(count.get() + 1));
+/// ```
+pub struct CoreLocal<T> {
+    values: [T; 2],
+}
+
+// Safety: each core only accesses its own value, with its interrupts disabled unless `T` is
+// `Sync`
+unsafe impl<T: Send> Sync for CoreLocal<T> {}
+
+impl<T> CoreLocal<T> {
+    /// Create the values of core 0 and core 1.
+    pub const fn new(core0: T, core1: T) -> Self {
+        CoreLocal {
+            values: [core0, core1],
+        }
+    }
+
+    /// Call `f` with the value of the core executing this, with the interrupts of the core
+    /// disabled so that its interrupt handlers can't access the value meanwhile.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        cortex_m::interrupt::free(|_| f(&self.values[Sio::core() as usize]))
+    }
+
+    /// The value of the core executing this, which is safe to share with the interrupt handlers
+    /// of the core as it is `Sync`.
+    pub fn get(&self) -> &T
+    where
+        T: Sync,
+    {
+        &self.values[Sio::core() as usize]
+    }
+
+    /// The value of `core`, which is safe to access as this borrows the values mutably.
+    pub fn get_mut(&mut self, core: CoreId) -> &mut T {
+        &mut self.values[core as usize]
+    }
+
+    /// Return the values of core 0 and core 1.
+    pub fn into_inner(self) -> (T, T) {
+        let [core0, core1] = self.values;
+        (core0, core1)
+    }
+}
+
 impl HwDivider {
     /// Perform hardware unsigned divide/modulo operation
-    pub fn unsigned(&self, dividend: u32, divisor: u32) -> DivResult<u32> {
+    ///
+    /// The divider is shared by the code of a core and its interrupt handlers: an interrupt
+    /// handler using it must save its state first, see [`DividerState`].
+    pub fn div_rem_u32(&self, dividend: u32, divisor: u32) -> DivResult<u32> {
         let sio = unsafe { &(*pac::SIO::ptr()) };
         sio.div_udividend.write(|w| unsafe { w.bits(dividend) });
-
         sio.div_udivisor.write(|w| unsafe { w.bits(divisor) });
 
-        cortex_m::asm::delay(8);
+        // The result is ready 8 cycles after the divisor is written
+        wait_divider_ready(sio);
 
-        // Note: quotient must be read last
+        // Note: quotient must be read last, as it clears DIRTY
         let remainder = sio.div_remainder.read().bits();
         let quotient = sio.div_quotient.read().bits();
 
@@ -186,17 +362,20 @@ impl HwDivider {
     }
 
     /// Perform hardware signed divide/modulo operation
-    pub fn signed(&self, dividend: i32, divisor: i32) -> DivResult<i32> {
+    ///
+    /// The divider is shared by the code of a core and its interrupt handlers: an interrupt
+    /// handler using it must save its state first, see [`DividerState`].
+    pub fn div_rem_i32(&self, dividend: i32, divisor: i32) -> DivResult<i32> {
         let sio = unsafe { &(*pac::SIO::ptr()) };
         sio.div_sdividend
             .write(|w| unsafe { w.bits(dividend as u32) });
-
         sio.div_sdivisor
             .write(|w| unsafe { w.bits(divisor as u32) });
 
-        cortex_m::asm::delay(8);
+        // The result is ready 8 cycles after the divisor is written
+        wait_divider_ready(sio);
 
-        // Note: quotient must be read last
+        // Note: quotient must be read last, as it clears DIRTY
         let remainder = sio.div_remainder.read().bits() as i32;
         let quotient = sio.div_quotient.read().bits() as i32;
 
@@ -205,18 +384,215 @@ impl HwDivider {
             quotient,
         }
     }
+
+    /// Perform hardware unsigned divide/modulo operation
+    ///
+    /// This is the same as [`div_rem_u32`](Self::div_rem_u32).
+    pub fn unsigned(&self, dividend: u32, divisor: u32) -> DivResult<u32> {
+        self.div_rem_u32(dividend, divisor)
+    }
+
+    /// Perform hardware signed divide/modulo operation
+    ///
+    /// This is the same as [`div_rem_i32`](Self::div_rem_i32).
+    pub fn signed(&self, dividend: i32, divisor: i32) -> DivResult<i32> {
+        self.div_rem_i32(dividend, divisor)
+    }
+}
+
+fn wait_divider_ready(sio: &pac::sio::RegisterBlock) {
+    while sio.div_csr.read().ready().bit_is_clear() {
+        cortex_m::asm::nop();
+    }
+}
+
+/// Saved state of the hardware divider of a core
+///
+/// Each core has its own divider, which the interrupt handlers of the core share with the code
+/// they interrupt. An interrupt handler which divides, with [`HwDivider`] or the
+/// `divider-intrinsics` feature, must save the state of the divider beforehand, and restore it
+/// before returning, so that the result of the interrupted division isn't lost:
+///
+/// ```no_run
+/// use rp2040_hal::sio::DividerState;
+/// // In the interrupt handler
+/// let saved = DividerState::save();
+/// // Divide
+/// saved.restore();
+/// ```
+pub struct DividerState {
+    dividend: u32,
+    divisor: u32,
+    remainder: u32,
+    quotient: u32,
+}
+
+impl DividerState {
+    /// Save the state of the divider of the core executing this, waiting for the division in
+    /// progress to complete.
+    pub fn save() -> Self {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        wait_divider_ready(sio);
+        DividerState {
+            dividend: sio.div_udividend.read().bits(),
+            divisor: sio.div_udivisor.read().bits(),
+            remainder: sio.div_remainder.read().bits(),
+            quotient: sio.div_quotient.read().bits(),
+        }
+    }
+
+    /// Save the state of the divider only if it holds results which are yet to be read.
+    pub fn save_if_dirty() -> Option<Self> {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        if sio.div_csr.read().dirty().bit_is_set() {
+            Some(Self::save())
+        } else {
+            None
+        }
+    }
+
+    /// Restore the saved state of the divider.
+    pub fn restore(self) {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.div_udividend
+            .write(|w| unsafe { w.bits(self.dividend) });
+        sio.div_udivisor.write(|w| unsafe { w.bits(self.divisor) });
+        // Writing the results stops the division started by the operands, and sets READY and
+        // DIRTY, as the interrupted code expects
+        sio.div_remainder
+            .write(|w| unsafe { w.bits(self.remainder) });
+        sio.div_quotient.write(|w| unsafe { w.bits(self.quotient) });
+    }
+}
+
+/// Integer division intrinsics of the compiler, running on the hardware divider
+///
+/// They save the state of the divider when it is in use, so that they can be called from
+/// interrupt handlers.
+#[cfg(all(target_arch = "arm", feature = "divider-intrinsics"))]
+mod intrinsics {
+    use super::{DivResult, DividerState, HwDivider};
+
+    fn div_rem_u32(n: u32, d: u32) -> DivResult<u32> {
+        let saved = DividerState::save_if_dirty();
+        let result = HwDivider { _private: () }.div_rem_u32(n, d);
+        if let Some(saved) = saved {
+            saved.restore();
+        }
+        result
+    }
+
+    fn div_rem_i32(n: i32, d: i32) -> DivResult<i32> {
+        let saved = DividerState::save_if_dirty();
+        let result = HwDivider { _private: () }.div_rem_i32(n, d);
+        if let Some(saved) = saved {
+            saved.restore();
+        }
+        result
+    }
+
+    #[no_mangle]
+    extern "C" fn __aeabi_uidiv(n: u32, d: u32) -> u32 {
+        div_rem_u32(n, d).quotient
+    }
+
+    #[no_mangle]
+    extern "C" fn __aeabi_idiv(n: i32, d: i32) -> i32 {
+        div_rem_i32(n, d).quotient
+    }
+
+    /// The quotient is returned in r0 and the remainder in r1
+    #[no_mangle]
+    extern "C" fn __aeabi_uidivmod(n: u32, d: u32) -> u64 {
+        let result = div_rem_u32(n, d);
+        u64::from(result.quotient) | u64::from(result.remainder) << 32
+    }
+
+    /// The quotient is returned in r0 and the remainder in r1
+    #[no_mangle]
+    extern "C" fn __aeabi_idivmod(n: i32, d: i32) -> u64 {
+        let result = div_rem_i32(n, d);
+        u64::from(result.quotient as u32) | u64::from(result.remainder as u32) << 32
+    }
+
+    #[no_mangle]
+    extern "C" fn __udivsi3(n: u32, d: u32) -> u32 {
+        div_rem_u32(n, d).quotient
+    }
+
+    #[no_mangle]
+    extern "C" fn __divsi3(n: i32, d: i32) -> i32 {
+        div_rem_i32(n, d).quotient
+    }
+
+    #[no_mangle]
+    extern "C" fn __umodsi3(n: u32, d: u32) -> u32 {
+        div_rem_u32(n, d).remainder
+    }
+
+    #[no_mangle]
+    extern "C" fn __modsi3(n: i32, d: i32) -> i32 {
+        div_rem_i32(n, d).remainder
+    }
 }
 
-/// Trait for all the spinlock. See the documentation of e.g. [`Spinlock0`] for more information
-pub trait Spinlock: typelevel::Sealed + Sized {
+/// Hardware based spinlock.
+///
+/// You can claim this lock by calling either [`claim`], [`try_claim`] or [`claim_async`].
+/// This will automatically lock ALL spinlocks of type `Spinlock<N>`, on both cores.
+///
+/// When the obtained spinlock goes out of scope, it is automatically unlocked.
+///
+/// With the `critical-section-impl` feature, which is enabled by default, spinlock 31 is
+/// reserved for the [`critical-section`](critical_section) implementation of this crate, and
+/// `Spinlock<31>` is not available.
+///
+/// **warning**: These spinlocks are not re-entrant, meaning that the following code will cause a deadlock:
+///
+/// ```no_run
+/// use rp2040_hal::sio::Spinlock0;
+/// let lock_1 = Spinlock0::claim();
+/// let lock_2 = Spinlock0::claim(); // deadlock here
+/// ```
+///
+/// [`claim`]: #method.claim
+/// [`try_claim`]: #method.try_claim
+/// [`claim_async`]: #method.claim_async
+pub struct Spinlock<const N: usize>(core::marker::PhantomData<()>)
+where
+    Spinlock<N>: SpinlockValid;
+
+/// Marker trait of the spinlocks available to [`Spinlock`].
+pub trait SpinlockValid: typelevel::Sealed {}
+
+impl<const N: usize> Spinlock<N>
+where
+    Spinlock<N>: SpinlockValid,
+{
+    /// The register of this spinlock
+    fn register() -> *mut u32 {
+        // Safety: we only compute the address of a register
+        let sio = unsafe { &*pac::SIO::ptr() };
+        // The spinlock registers follow each other
+        unsafe { sio.spinlock0.as_ptr().add(N) }
+    }
+
     /// Try to claim the spinlock. Will return `Some(Self)` if the lock is obtained, and `None` if the lock is
     /// already in use somewhere else.
-    fn try_claim() -> Option<Self>;
+    pub fn try_claim() -> Option<Self> {
+        // Safety: reading the register claims the lock if it is free, which is what we want
+        let lock = unsafe { core::ptr::read_volatile(Self::register()) };
+        if lock > 0 {
+            Some(Self(core::marker::PhantomData))
+        } else {
+            None
+        }
+    }
 
     /// Claim the spinlock, will block the current thread until the lock is available.
     ///
     /// Note that calling this multiple times in a row will cause a deadlock
-    fn claim() -> Self {
+    pub fn claim() -> Self {
         loop {
             if let Some(result) = Self::try_claim() {
                 break result;
@@ -225,101 +601,90 @@ pub trait Spinlock: typelevel::Sealed + Sized {
     }
 
     /// Try to claim the spinlock. Will return `WouldBlock` until the spinlock is available.
-    fn claim_async() -> nb::Result<Self, Infallible> {
+    pub fn claim_async() -> nb::Result<Self, Infallible> {
         Self::try_claim().ok_or(nb::Error::WouldBlock)
     }
+
+    /// Release the spinlock, whoever holds it.
+    ///
+    /// # Safety
+    ///
+    /// The spinlock must not be held, other than by code which can't release it anymore, such as
+    /// code running on a core which was reset.
+    pub unsafe fn release() {
+        // Write (any value): release the lock
+        core::ptr::write_volatile(Self::register(), 1);
+    }
+}
+
+impl<const N: usize> Drop for Spinlock<N>
+where
+    Spinlock<N>: SpinlockValid,
+{
+    fn drop(&mut self) {
+        // Safety: At this point we should be the only one accessing this spinlock register
+        // so writing to this address is fine
+        unsafe { Self::release() }
+    }
 }
+
 macro_rules! impl_spinlock {
-    ($($spinlock_name:ident => $register:ident,)*) => {
+    ($($spinlock_name:ident => $n:literal,)*) => {
         $(
-            /// Hardware based spinlock.
-            ///
-            /// You can claim this lock by calling either [`claim`], [`try_claim`] or [`claim_async`].
-            /// This will automatically lock ALL spinlocks of type `
-            #[doc = stringify!($spinlock_name)]
-            /// `.
-            ///
-            /// When the obtained spinlock goes out of scope, it is automatically unlocked.
-            ///
-            /// **warning**: These spinlocks are not re-entrant, meaning that the following code will cause a deadlock:
-            ///
-            /// ```no_run
-            /// use rp2040_hal::sio::{Spinlock0, Spinlock};
-            /// let lock_1 = Spinlock0::claim();
-            /// let lock_2 = Spinlock0::claim(); // deadlock here
-            /// ```
-            ///
-            /// [`claim`]: #method.claim
-            /// [`try_claim`]: #method.try_claim
-            /// [`claim_async`]: #method.claim_async
-            pub struct $spinlock_name(core::marker::PhantomData<()>);
-
-            impl Spinlock for $spinlock_name {
-                fn try_claim() -> Option<$spinlock_name> {
-                    // Safety: We're only reading from this register
-                    let sio = unsafe { &*pac::SIO::ptr() };
-                    let lock = sio.$register.read().bits();
-                    if lock > 0 {
-                        Some(Self(core::marker::PhantomData))
-                    } else {
-                        None
-                    }
-                }
-            }
+            #[doc = concat!("Spinlock number ", stringify!($n))]
+            pub type $spinlock_name = Spinlock<$n>;
 
-            impl typelevel::Sealed for $spinlock_name {}
+            impl typelevel::Sealed for Spinlock<$n> {}
 
-            impl Drop for $spinlock_name {
-                fn drop(&mut self) {
-                    // Safety: At this point we should be the only one accessing this spinlock register
-                    // so writing to this address is fine
-                    let sio = unsafe { &*pac::SIO::ptr() };
-
-                    // Write (any value): release the lock
-                    sio.$register.write(|b| unsafe { b.bits(1) });
-                }
-            }
+            impl SpinlockValid for Spinlock<$n> {}
         )*
     }
 }
 
 impl_spinlock! {
-    Spinlock0 => spinlock0,
-    Spinlock1 => spinlock1,
-    Spinlock2 => spinlock2,
-    Spinlock3 => spinlock3,
-    Spinlock4 => spinlock4,
-    Spinlock5 => spinlock5,
-    Spinlock6 => spinlock6,
-    Spinlock7 => spinlock7,
-    Spinlock8 => spinlock8,
-    Spinlock9 => spinlock9,
-    Spinlock10 => spinlock10,
-    Spinlock11 => spinlock11,
-    Spinlock12 => spinlock12,
-    Spinlock13 => spinlock13,
-    Spinlock14 => spinlock14,
-    Spinlock15 => spinlock15,
-    Spinlock16 => spinlock16,
-    Spinlock17 => spinlock17,
-    Spinlock18 => spinlock18,
-    Spinlock19 => spinlock19,
-    Spinlock20 => spinlock20,
-    Spinlock21 => spinlock21,
-    Spinlock22 => spinlock22,
-    Spinlock23 => spinlock23,
-    Spinlock24 => spinlock24,
-    Spinlock25 => spinlock25,
-    Spinlock26 => spinlock26,
-    Spinlock27 => spinlock27,
-    Spinlock28 => spinlock28,
-    Spinlock29 => spinlock29,
-    Spinlock30 => spinlock30,
-    Spinlock31 => spinlock31,
+    Spinlock0 => 0,
+    Spinlock1 => 1,
+    Spinlock2 => 2,
+    Spinlock3 => 3,
+    Spinlock4 => 4,
+    Spinlock5 => 5,
+    Spinlock6 => 6,
+    Spinlock7 => 7,
+    Spinlock8 => 8,
+    Spinlock9 => 9,
+    Spinlock10 => 10,
+    Spinlock11 => 11,
+    Spinlock12 => 12,
+    Spinlock13 => 13,
+    Spinlock14 => 14,
+    Spinlock15 => 15,
+    Spinlock16 => 16,
+    Spinlock17 => 17,
+    Spinlock18 => 18,
+    Spinlock19 => 19,
+    Spinlock20 => 20,
+    Spinlock21 => 21,
+    Spinlock22 => 22,
+    Spinlock23 => 23,
+    Spinlock24 => 24,
+    Spinlock25 => 25,
+    Spinlock26 => 26,
+    Spinlock27 => 27,
+    Spinlock28 => 28,
+    Spinlock29 => 29,
+    Spinlock30 => 30,
+}
+
+// Spinlock 31 is taken by the `critical-section` implementation
+#[cfg(not(feature = "critical-section-impl"))]
+impl_spinlock! {
+    Spinlock31 => 31,
 }
 
 /// Returns the current state of the spinlocks. Each index corresponds to the associated spinlock, e.g. if index `5` is set to `true`, it means that [`Spinlock5`] is currently locked.
 ///
+/// With the `critical-section-impl` feature, index `31` tells whether a critical section is running on either core.
+///
 /// Note that spinlocks can be claimed or released at any point, so this function cannot guarantee the spinlock is actually available right after calling this function. This function is mainly intended for debugging.
 pub fn spinlock_state() -> [bool; 32] {
     // Safety: we're only reading from a register
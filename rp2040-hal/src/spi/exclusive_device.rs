@@ -0,0 +1,288 @@
+//! An SPI bus shared with a single device, with a GPIO chip select
+#[cfg(feature = "eh1_0_alpha")]
+use core::fmt::Debug;
+#[cfg(feature = "eh1_0_alpha")]
+use eh1_0_alpha::spi as eh1;
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::blocking::spi;
+use embedded_hal::digital::v2::OutputPin;
+
+#[cfg(feature = "eh1_0_alpha")]
+use super::SpiInfallible;
+use super::{Enabled, Spi, SpiDevice, ValidSpiPinout};
+
+/// An [`Spi`] bus with a single device on it, selected by a GPIO pin
+///
+/// Chip select is driven low for the duration of each operation, and only
+/// driven high again once the last bit has been clocked out, which a
+/// hardware chip select can't do across several words. This makes the
+/// wrapper usable wherever a device driver expects to own the bus and
+/// manage chip select itself.
+///
+/// With the `eh1_0_alpha` feature, the bus implements the blocking SPI traits
+/// of embedded-hal 1.0.0-alpha.6, and this wrapper implements them too, as
+/// well as `Transactional`, which runs several operations with the device
+/// selected throughout.
+pub struct ExclusiveDevice<D: SpiDevice, const DS: u8, P: ValidSpiPinout<D>, CS, DELAY> {
+    spi: Spi<Enabled, D, DS, P>,
+    cs: CS,
+    delay: DELAY,
+    /// Time from selecting the device to the first clock edge, in microseconds
+    cs_setup_us: u32,
+    /// Time from the last clock edge to deselecting the device, in
+    /// microseconds
+    cs_hold_us: u32,
+}
+
+impl<D, const DS: u8, P, CS, DELAY> ExclusiveDevice<D, DS, P, CS, DELAY>
+where
+    D: SpiDevice,
+    P: ValidSpiPinout<D>,
+    CS: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    /// Wrap a bus and the chip select pin of the device on it.
+    ///
+    /// The chip select pin is driven high, deselecting the device. `delay`
+    /// times the [chip select delays](Self::with_cs_delays), which are 0 by
+    /// default.
+    pub fn new(spi: Spi<Enabled, D, DS, P>, mut cs: CS, delay: DELAY) -> Result<Self, CS::Error> {
+        cs.set_high()?;
+        Ok(Self {
+            spi,
+            cs,
+            delay,
+            cs_setup_us: 0,
+            cs_hold_us: 0,
+        })
+    }
+
+    /// Wait `setup_us` microseconds after selecting the device, before the
+    /// first clock edge, and `hold_us` microseconds after the last clock
+    /// edge, before deselecting it, as the device requires.
+    pub fn with_cs_delays(self, setup_us: u32, hold_us: u32) -> Self {
+        Self {
+            cs_setup_us: setup_us,
+            cs_hold_us: hold_us,
+            ..self
+        }
+    }
+
+    /// Run `f` with the device selected.
+    ///
+    /// The device is deselected once `f` has returned and the bus has gone
+    /// idle.
+    pub fn transaction<R>(
+        &mut self,
+        f: impl FnOnce(&mut Spi<Enabled, D, DS, P>) -> R,
+    ) -> Result<R, CS::Error> {
+        self.cs.set_low()?;
+        self.delay.delay_us(self.cs_setup_us);
+        let result = f(&mut self.spi);
+        self.spi.flush();
+        self.delay.delay_us(self.cs_hold_us);
+        self.cs.set_high()?;
+        Ok(result)
+    }
+
+    /// Release the bus, the chip select pin and the delay.
+    pub fn release(self) -> (Spi<Enabled, D, DS, P>, CS, DELAY) {
+        (self.spi, self.cs, self.delay)
+    }
+}
+
+impl<W, D, const DS: u8, P, CS, DELAY> spi::Transfer<W> for ExclusiveDevice<D, DS, P, CS, DELAY>
+where
+    D: SpiDevice,
+    P: ValidSpiPinout<D>,
+    CS: OutputPin,
+    DELAY: DelayUs<u32>,
+    Spi<Enabled, D, DS, P>: spi::Transfer<W, Error = core::convert::Infallible>,
+{
+    type Error = CS::Error;
+
+    fn transfer<'w>(&mut self, words: &'w mut [W]) -> Result<&'w [W], Self::Error> {
+        self.transaction(|bus| match spi::Transfer::transfer(bus, words) {
+            Ok(words) => words,
+            Err(e) => match e {},
+        })
+    }
+}
+
+impl<W, D, const DS: u8, P, CS, DELAY> spi::Write<W> for ExclusiveDevice<D, DS, P, CS, DELAY>
+where
+    D: SpiDevice,
+    P: ValidSpiPinout<D>,
+    CS: OutputPin,
+    DELAY: DelayUs<u32>,
+    Spi<Enabled, D, DS, P>: spi::Write<W, Error = core::convert::Infallible>,
+{
+    type Error = CS::Error;
+
+    fn write(&mut self, words: &[W]) -> Result<(), Self::Error> {
+        self.transaction(|bus| {
+            let _ = spi::Write::write(bus, words);
+        })
+    }
+}
+
+impl<W, D, const DS: u8, P, CS, DELAY> spi::WriteIter<W> for ExclusiveDevice<D, DS, P, CS, DELAY>
+where
+    D: SpiDevice,
+    P: ValidSpiPinout<D>,
+    CS: OutputPin,
+    DELAY: DelayUs<u32>,
+    Spi<Enabled, D, DS, P>: spi::WriteIter<W, Error = core::convert::Infallible>,
+{
+    type Error = CS::Error;
+
+    fn write_iter<WI>(&mut self, words: WI) -> Result<(), Self::Error>
+    where
+        WI: IntoIterator<Item = W>,
+    {
+        self.transaction(|bus| {
+            let _ = spi::WriteIter::write_iter(bus, words);
+        })
+    }
+}
+
+/// Failure to drive the chip select pin of an [`ExclusiveDevice`], whose bus
+/// can't fail
+#[cfg(feature = "eh1_0_alpha")]
+#[derive(Debug)]
+pub struct ChipSelectError<E>(pub E);
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<E: Debug> eh1::Error for ChipSelectError<E> {
+    fn kind(&self) -> eh1::ErrorKind {
+        eh1::ErrorKind::Other
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<W: 'static, D, const DS: u8, P, CS, DELAY> eh1::blocking::Transactional<W>
+    for ExclusiveDevice<D, DS, P, CS, DELAY>
+where
+    D: SpiDevice,
+    P: ValidSpiPinout<D>,
+    CS: OutputPin,
+    CS::Error: Debug,
+    DELAY: DelayUs<u32>,
+    Spi<Enabled, D, DS, P>: eh1::blocking::Read<W, Error = SpiInfallible>
+        + eh1::blocking::Write<W, Error = SpiInfallible>
+        + eh1::blocking::Transfer<W, Error = SpiInfallible>
+        + eh1::blocking::TransferInplace<W, Error = SpiInfallible>,
+{
+    type Error = ChipSelectError<CS::Error>;
+
+    /// Run `operations` in order, with the device selected throughout.
+    fn exec<'a>(
+        &mut self,
+        operations: &mut [eh1::blocking::Operation<'a, W>],
+    ) -> Result<(), Self::Error> {
+        use eh1::blocking::Operation;
+
+        self.transaction(|bus| {
+            for operation in operations {
+                let result = match operation {
+                    Operation::Read(words) => eh1::blocking::Read::read(bus, words),
+                    Operation::Write(words) => eh1::blocking::Write::write(bus, words),
+                    Operation::Transfer(read, write) => {
+                        eh1::blocking::Transfer::transfer(bus, read, write)
+                    }
+                    Operation::TransferInplace(words) => {
+                        eh1::blocking::TransferInplace::transfer_inplace(bus, words)
+                    }
+                };
+                if let Err(e) = result {
+                    match e {}
+                }
+            }
+        })
+        .map_err(ChipSelectError)
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<W, D, const DS: u8, P, CS, DELAY> eh1::blocking::Read<W>
+    for ExclusiveDevice<D, DS, P, CS, DELAY>
+where
+    D: SpiDevice,
+    P: ValidSpiPinout<D>,
+    CS: OutputPin,
+    CS::Error: Debug,
+    DELAY: DelayUs<u32>,
+    Spi<Enabled, D, DS, P>: eh1::blocking::Read<W, Error = SpiInfallible>,
+{
+    type Error = ChipSelectError<CS::Error>;
+
+    fn read(&mut self, words: &mut [W]) -> Result<(), Self::Error> {
+        self.transaction(|bus| {
+            let _ = eh1::blocking::Read::read(bus, words);
+        })
+        .map_err(ChipSelectError)
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<W, D, const DS: u8, P, CS, DELAY> eh1::blocking::Write<W>
+    for ExclusiveDevice<D, DS, P, CS, DELAY>
+where
+    D: SpiDevice,
+    P: ValidSpiPinout<D>,
+    CS: OutputPin,
+    CS::Error: Debug,
+    DELAY: DelayUs<u32>,
+    Spi<Enabled, D, DS, P>: eh1::blocking::Write<W, Error = SpiInfallible>,
+{
+    type Error = ChipSelectError<CS::Error>;
+
+    fn write(&mut self, words: &[W]) -> Result<(), Self::Error> {
+        self.transaction(|bus| {
+            let _ = eh1::blocking::Write::write(bus, words);
+        })
+        .map_err(ChipSelectError)
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<W, D, const DS: u8, P, CS, DELAY> eh1::blocking::Transfer<W>
+    for ExclusiveDevice<D, DS, P, CS, DELAY>
+where
+    D: SpiDevice,
+    P: ValidSpiPinout<D>,
+    CS: OutputPin,
+    CS::Error: Debug,
+    DELAY: DelayUs<u32>,
+    Spi<Enabled, D, DS, P>: eh1::blocking::Transfer<W, Error = SpiInfallible>,
+{
+    type Error = ChipSelectError<CS::Error>;
+
+    fn transfer(&mut self, read: &mut [W], write: &[W]) -> Result<(), Self::Error> {
+        self.transaction(|bus| {
+            let _ = eh1::blocking::Transfer::transfer(bus, read, write);
+        })
+        .map_err(ChipSelectError)
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<W, D, const DS: u8, P, CS, DELAY> eh1::blocking::TransferInplace<W>
+    for ExclusiveDevice<D, DS, P, CS, DELAY>
+where
+    D: SpiDevice,
+    P: ValidSpiPinout<D>,
+    CS: OutputPin,
+    CS::Error: Debug,
+    DELAY: DelayUs<u32>,
+    Spi<Enabled, D, DS, P>: eh1::blocking::TransferInplace<W, Error = SpiInfallible>,
+{
+    type Error = ChipSelectError<CS::Error>;
+
+    fn transfer_inplace(&mut self, words: &mut [W]) -> Result<(), Self::Error> {
+        self.transaction(|bus| {
+            let _ = eh1::blocking::TransferInplace::transfer_inplace(bus, words);
+        })
+        .map_err(ChipSelectError)
+    }
+}
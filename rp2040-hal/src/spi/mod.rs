@@ -0,0 +1,860 @@
+//! Serial Peripheral Interface (SPI)
+//!
+//! See [Chapter 4 Section 4](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use embedded_hal::spi::MODE_0;
+//! use embedded_time::rate::*;
+//! use rp2040_hal::{spi::Spi, gpio::{Pins, FunctionSpi}, pac, sio::Sio};
+//!
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//!
+//! let sck = pins.gpio2.into_mode::<FunctionSpi>();
+//! let tx = pins.gpio3.into_mode::<FunctionSpi>();
+//!
+//! let spi = Spi::<_, _, 8, _>::new_with_pins(peripherals.SPI0, (tx, sck)).init(&mut peripherals.RESETS, 125_000_000u32.Hz(), 16_000_000u32.Hz(), &MODE_0);
+//! ```
+//!
+//! ## Frame size
+//!
+//! The `DS` parameter sets the number of bits per frame, from 4 to 16. Frames
+//! of up to 8 bits are sent and received as `u8`, and longer ones as `u16`:
+//!
+//! ```no_run
+//! # use embedded_hal::spi::MODE_0;
+//! # use embedded_time::rate::*;
+//! # use rp2040_hal::{spi::Spi, pac};
+//! use embedded_hal::blocking::spi::Write;
+//!
+//! # let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut spi = Spi::<_, _, 16>::new(peripherals.SPI0).init(&mut peripherals.RESETS, 125_000_000u32.Hz(), 16_000_000u32.Hz(), &MODE_0);
+//! spi.write(&[0x3FFFu16, 0x1234]).unwrap();
+//! ```
+
+use crate::atomic_register_access::{write_bitmask_clear, write_bitmask_set};
+use crate::dma::{bidirectional, Discard, ReadTarget, Repeat, SingleChannel, Word, WriteTarget};
+use crate::resets::SubsystemReset;
+use core::{convert::Infallible, marker::PhantomData, ops::Deref};
+#[cfg(feature = "eh1_0_alpha")]
+use eh1_0_alpha::spi as eh1;
+use embedded_dma::{ReadBuffer, WriteBuffer};
+use embedded_hal::blocking::spi;
+use embedded_hal::spi::{FullDuplex, Mode, Phase, Polarity};
+use embedded_time::rate::*;
+use pac::RESETS;
+
+mod pins;
+pub use pins::*;
+
+mod exclusive_device;
+#[cfg(feature = "eh1_0_alpha")]
+pub use exclusive_device::ChipSelectError;
+pub use exclusive_device::ExclusiveDevice;
+
+/// State of the SPI
+pub trait State {}
+
+/// Spi is disabled
+pub struct Disabled {
+    __private: (),
+}
+
+/// Spi is enabled
+pub struct Enabled {
+    __private: (),
+}
+
+impl State for Disabled {}
+impl State for Enabled {}
+
+/// Pac SPI device
+pub trait SpiDevice: Deref<Target = pac::spi0::RegisterBlock> + SubsystemReset {
+    /// The DREQ raised while the TX FIFO has room
+    const TX_DREQ: u8;
+    /// The DREQ raised while the RX FIFO contains data
+    const RX_DREQ: u8;
+}
+
+impl SpiDevice for pac::SPI0 {
+    const TX_DREQ: u8 = crate::dma::DREQ_SPI0_TX;
+    const RX_DREQ: u8 = crate::dma::DREQ_SPI0_RX;
+}
+impl SpiDevice for pac::SPI1 {
+    const TX_DREQ: u8 = crate::dma::DREQ_SPI1_TX;
+    const RX_DREQ: u8 = crate::dma::DREQ_SPI1_RX;
+}
+
+/// Frame format
+///
+/// `&Mode` converts into this, so the embedded-hal modes such as
+/// [`MODE_0`](embedded_hal::spi::MODE_0) can be passed wherever a frame format
+/// is expected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// Motorola SPI, in the given mode
+    MotorolaSpi(Mode),
+    /// Texas Instruments synchronous serial
+    ///
+    /// Each frame is marked by a one clock pulse on chip select, rather than
+    /// chip select being held low.
+    TexasInstruments,
+    /// National Semiconductor Microwire
+    ///
+    /// This is half duplex: the master sends an 8 bit control word, and the
+    /// slave answers with a `DS` bit word. As the master, the low 8 bits of
+    /// each word written are sent as the control word.
+    NationalMicrowire,
+}
+
+impl From<Mode> for FrameFormat {
+    fn from(mode: Mode) -> Self {
+        FrameFormat::MotorolaSpi(mode)
+    }
+}
+
+impl From<&Mode> for FrameFormat {
+    fn from(mode: &Mode) -> Self {
+        FrameFormat::MotorolaSpi(*mode)
+    }
+}
+
+/// Order in which the bits of each word are sent
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Most significant bit first, as the hardware does
+    MsbFirst,
+    /// Least significant bit first, reversing each word in software
+    LsbFirst,
+}
+
+/// SPI interrupt sources
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SpiInterrupt {
+    /// The RX FIFO is overrun, a word was received while it was full
+    RxOverrun,
+    /// The RX FIFO holds data, but nothing has been received for 32 bit
+    /// periods
+    RxTimeout,
+    /// The RX FIFO is at least half full
+    RxHalfFull,
+    /// The TX FIFO is at most half full
+    TxHalfEmpty,
+}
+
+impl SpiInterrupt {
+    fn mask(self) -> u32 {
+        match self {
+            SpiInterrupt::RxOverrun => 1 << 0,
+            SpiInterrupt::RxTimeout => 1 << 1,
+            SpiInterrupt::RxHalfFull => 1 << 2,
+            SpiInterrupt::TxHalfEmpty => 1 << 3,
+        }
+    }
+}
+
+/// Data size used in spi
+pub trait DataSize {}
+
+impl DataSize for u8 {}
+impl DataSize for u16 {}
+
+/// Spi
+pub struct Spi<S: State, D: SpiDevice, const DS: u8, P: ValidSpiPinout<D> = ()> {
+    device: D,
+    pins: P,
+    effective_baudrate: Hertz,
+    bit_order: BitOrder,
+    state: PhantomData<S>,
+}
+
+impl<S: State, D: SpiDevice, const DS: u8> Spi<S, D, DS> {
+    /// Releases the underlying device.
+    pub fn free(self) -> D {
+        self.device
+    }
+}
+
+impl<S: State, D: SpiDevice, const DS: u8, P: ValidSpiPinout<D>> Spi<S, D, DS, P> {
+    /// Fails the build when `DS` is not a frame size the hardware supports
+    const VALID_FRAME_SIZE: () = assert!(DS >= 4 && DS <= 16, "SPI frames are 4 to 16 bits");
+
+    fn transition<To: State>(self, _: To) -> Spi<To, D, DS, P> {
+        Spi {
+            device: self.device,
+            pins: self.pins,
+            effective_baudrate: self.effective_baudrate,
+            bit_order: self.bit_order,
+            state: PhantomData,
+        }
+    }
+
+    /// Releases the underlying device and pins.
+    pub fn release(self) -> (D, P) {
+        (self.device, self.pins)
+    }
+
+    /// The baud rate set by [`set_baudrate`](Self::set_baudrate) or
+    /// [`init`](Spi::init), which can be lower than the requested one because
+    /// of the dividers' resolution
+    pub fn effective_baudrate(&self) -> Hertz {
+        self.effective_baudrate
+    }
+
+    /// Set baudrate based on peripheral clock
+    ///
+    /// Typically the peripheral clock is set to 125_000_000
+    ///
+    /// When the SPI is enabled, this waits for the frame being transferred to
+    /// complete, then disables the SPI while the dividers are changed.
+    pub fn set_baudrate<F: Into<Hertz<u32>>, B: Into<Hertz<u32>>>(
+        &mut self,
+        peri_frequency: F,
+        baudrate: B,
+    ) -> Hertz {
+        let freq_in = peri_frequency.into().integer();
+        let baudrate = baudrate.into().integer();
+        let mut prescale: u8 = u8::MAX;
+        let mut postdiv: u8 = 0;
+
+        // Find smallest prescale value which puts output frequency in range of
+        // post-divide. Prescale is an even number from 2 to 254 inclusive.
+        for prescale_option in (2u32..=254).step_by(2) {
+            // We need to use an saturating_mul here because with a high baudrate certain invalid prescale
+            // values might not fit in u32. However we can be sure those values exeed the max sys_clk frequency
+            // So clamping a u32::MAX is fine here...
+            if freq_in < ((prescale_option + 2) * 256).saturating_mul(baudrate) {
+                prescale = prescale_option as u8;
+                break;
+            }
+        }
+
+        // We might not find a prescale value that lowers the clock freq enough, so we leave it at max
+        debug_assert_ne!(prescale, u8::MAX);
+
+        // Find largest post-divide which makes output <= baudrate. Post-divide is
+        // an integer in the range 0 to 255 inclusive.
+        for postdiv_option in (1..=255u8).rev() {
+            if freq_in / (prescale as u32 * postdiv_option as u32) > baudrate {
+                postdiv = postdiv_option;
+                break;
+            }
+        }
+
+        self.while_disabled(|spi| {
+            spi.device
+                .sspcpsr
+                .write(|w| unsafe { w.cpsdvsr().bits(prescale) });
+            spi.device
+                .sspcr0
+                .modify(|_, w| unsafe { w.scr().bits(postdiv) });
+        });
+
+        // Return the frequency we were able to achieve
+        self.effective_baudrate = (freq_in / (prescale as u32 * (1 + postdiv as u32))).Hz();
+        self.effective_baudrate
+    }
+
+    /// Set the bit order.
+    ///
+    /// The hardware only sends the most significant bit first, so for
+    /// [`BitOrder::LsbFirst`] the driver reverses the bits of each word
+    /// written and read. This doesn't apply to DMA transfers.
+    pub fn set_bit_order(&mut self, order: BitOrder) {
+        self.bit_order = order;
+    }
+
+    /// Set format and datasize
+    fn set_format(&mut self, data_bits: u8, format: FrameFormat) {
+        self.device.sspcr0.modify(|_, w| unsafe {
+            let w = w.dss().bits(data_bits - 1);
+            match format {
+                FrameFormat::MotorolaSpi(mode) => w
+                    .frf()
+                    .bits(0b00)
+                    .spo()
+                    .bit(mode.polarity == Polarity::IdleHigh)
+                    .sph()
+                    .bit(mode.phase == Phase::CaptureOnSecondTransition),
+                FrameFormat::TexasInstruments => w.frf().bits(0b01),
+                FrameFormat::NationalMicrowire => w.frf().bits(0b10),
+            }
+        });
+    }
+
+    /// Run `f` with the SPI disabled, as required to change its configuration
+    fn while_disabled<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let enabled = self.device.sspcr1.read().sse().bit_is_set();
+        if enabled {
+            // Let the frame being transferred complete
+            while self.device.sspsr.read().bsy().bit_is_set() {}
+            self.device.sspcr1.modify(|_, w| w.sse().clear_bit());
+        }
+
+        let result = f(self);
+
+        if enabled {
+            self.device.sspcr1.modify(|_, w| w.sse().set_bit());
+        }
+        result
+    }
+}
+
+impl<D: SpiDevice, const DS: u8> Spi<Disabled, D, DS> {
+    /// Create new spi device
+    ///
+    /// The pins must be set to [`FunctionSpi`](crate::gpio::FunctionSpi)
+    /// separately. Use [`new_with_pins`](Spi::new_with_pins) to have the
+    /// driver own them instead.
+    pub fn new(device: D) -> Spi<Disabled, D, DS> {
+        Spi::new_with_pins(device, ())
+    }
+}
+
+impl<D: SpiDevice, const DS: u8, P: ValidSpiPinout<D>> Spi<Disabled, D, DS, P> {
+    /// Create new spi device owning its pins
+    ///
+    /// The pins are checked at compile time to be routable to this SPI.
+    pub fn new_with_pins(device: D, pins: P) -> Spi<Disabled, D, DS, P> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID_FRAME_SIZE;
+        Spi {
+            device,
+            pins,
+            effective_baudrate: Hertz(0),
+            bit_order: BitOrder::MsbFirst,
+            state: PhantomData,
+        }
+    }
+
+    /// Initialize the SPI
+    pub fn init<F: Into<Hertz<u32>>, B: Into<Hertz<u32>>, M: Into<FrameFormat>>(
+        mut self,
+        resets: &mut RESETS,
+        peri_frequency: F,
+        baudrate: B,
+        format: M,
+    ) -> Spi<Enabled, D, DS, P> {
+        self.device.reset_bring_down(resets);
+        self.device.reset_bring_up(resets);
+
+        self.set_baudrate(peri_frequency, baudrate);
+        self.set_format(DS as u8, format.into());
+        // Always enable DREQ signals -- harmless if DMA is not listening
+        self.device
+            .sspdmacr
+            .modify(|_, w| w.txdmae().set_bit().rxdmae().set_bit());
+
+        // Finally enable the SPI
+        self.device.sspcr1.modify(|_, w| w.sse().set_bit());
+
+        self.transition(Enabled { __private: () })
+    }
+
+    /// Initialize the SPI as a slave
+    ///
+    /// The master drives the clock, which must be at most 1/12 of the
+    /// peripheral clock. Words to send must be in the TX FIFO before the
+    /// master clocks them out, otherwise the last word is sent again.
+    ///
+    /// In Motorola mode with [`Phase::CaptureOnFirstTransition`], the master
+    /// must deassert chip select between each word.
+    pub fn init_slave<M: Into<FrameFormat>>(
+        mut self,
+        resets: &mut RESETS,
+        format: M,
+    ) -> Spi<Enabled, D, DS, P> {
+        self.device.reset_bring_down(resets);
+        self.device.reset_bring_up(resets);
+
+        self.set_format(DS as u8, format.into());
+        // Always enable DREQ signals -- harmless if DMA is not listening
+        self.device
+            .sspdmacr
+            .modify(|_, w| w.txdmae().set_bit().rxdmae().set_bit());
+
+        // The mode can only be changed while the SPI is disabled
+        self.device.sspcr1.modify(|_, w| w.ms().set_bit());
+        self.device.sspcr1.modify(|_, w| w.sse().set_bit());
+
+        self.transition(Enabled { __private: () })
+    }
+}
+
+impl<D: SpiDevice, const DS: u8, P: ValidSpiPinout<D>> Spi<Enabled, D, DS, P> {
+    fn is_writable(&self) -> bool {
+        self.device.sspsr.read().tnf().bit_is_set()
+    }
+    fn is_readable(&self) -> bool {
+        self.device.sspsr.read().rne().bit_is_set()
+    }
+
+    /// Is the SPI sending or receiving a frame, or does the TX FIFO hold
+    /// data?
+    pub fn is_busy(&self) -> bool {
+        self.device.sspsr.read().bsy().bit_is_set()
+    }
+
+    /// Block until the last word written has been clocked out.
+    pub fn flush(&mut self) {
+        while self.is_busy() {}
+    }
+
+    /// Enable an interrupt source.
+    pub fn enable_interrupt(&mut self, interrupt: SpiInterrupt) {
+        // Safety: We only use the atomic alias of the register, so an
+        // interrupt handler masking a source meanwhile isn't undone.
+        unsafe { write_bitmask_set(self.device.sspimsc.as_ptr(), interrupt.mask()) }
+    }
+
+    /// Disable an interrupt source.
+    pub fn disable_interrupt(&mut self, interrupt: SpiInterrupt) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe { write_bitmask_clear(self.device.sspimsc.as_ptr(), interrupt.mask()) }
+    }
+
+    /// Is an enabled interrupt source asserting the SPI interrupt?
+    pub fn is_interrupt_pending(&self, interrupt: SpiInterrupt) -> bool {
+        self.device.sspmis.read().bits() & interrupt.mask() != 0
+    }
+
+    /// Clear an interrupt source.
+    ///
+    /// Only [`SpiInterrupt::RxOverrun`] and [`SpiInterrupt::RxTimeout`] are
+    /// cleared this way. The FIFO interrupts clear once the FIFO level has
+    /// moved past the half way mark.
+    pub fn clear_interrupt(&mut self, interrupt: SpiInterrupt) {
+        self.device
+            .sspicr
+            .write(|w| unsafe { w.bits(interrupt.mask()) });
+    }
+
+    /// The DREQ to pace a DMA channel writing to [`data_address`](Self::data_address)
+    pub fn tx_dreq(&self) -> u8 {
+        D::TX_DREQ
+    }
+
+    /// The DREQ to pace a DMA channel reading from [`data_address`](Self::data_address)
+    pub fn rx_dreq(&self) -> u8 {
+        D::RX_DREQ
+    }
+
+    /// Address of the data register, which a DMA channel reads received
+    /// words from or writes words to send to
+    ///
+    /// Each word sent also receives one, so a write-only transfer must still
+    /// drain the RX FIFO, or ignore the resulting overrun: see
+    /// [`write_dma`](Self::write_dma).
+    pub fn data_address(&self) -> u32 {
+        self.device.sspdr.as_ptr() as u32
+    }
+
+    /// Write a word to the TX FIFO, in the configured bit order
+    fn write_word(&self, word: u16) {
+        let word = self.reorder_bits(word);
+        self.device.sspdr.write(|w| unsafe { w.data().bits(word) });
+    }
+
+    /// Read a word from the RX FIFO, in the configured bit order
+    fn read_word(&self) -> u16 {
+        self.reorder_bits(self.device.sspdr.read().data().bits())
+    }
+
+    fn reorder_bits(&self, word: u16) -> u16 {
+        match self.bit_order {
+            BitOrder::MsbFirst => word,
+            BitOrder::LsbFirst => word.reverse_bits() >> (16 - DS),
+        }
+    }
+
+    /// Receive `count` words, sending zeros, and pass each to `store` with its
+    /// index
+    fn read_words(&mut self, count: usize, store: impl FnMut(usize, u16)) {
+        self.transfer_words(count, |_| 0, store);
+    }
+
+    /// Send `count` words, each returned by `load` from its index, and pass
+    /// each word received to `store` with its index
+    fn transfer_words(
+        &mut self,
+        count: usize,
+        mut load: impl FnMut(usize) -> u16,
+        mut store: impl FnMut(usize, u16),
+    ) {
+        let mut sent = 0;
+        let mut received = 0;
+
+        while received < count {
+            if sent < count && sent - received < FIFO_DEPTH && self.is_writable() {
+                self.write_word(load(sent));
+                sent += 1;
+            }
+
+            if self.is_readable() {
+                store(received, self.read_word());
+                received += 1;
+            }
+        }
+    }
+
+    /// Change the frame format, such as the SPI mode.
+    ///
+    /// This waits for the frame being transferred to complete, then disables
+    /// the SPI while the format is changed.
+    pub fn set_mode<M: Into<FrameFormat>>(&mut self, format: M) {
+        let format = format.into();
+        self.while_disabled(|spi| spi.set_format(DS, format));
+    }
+
+    /// Is the SPI configured as a slave?
+    pub fn is_slave(&self) -> bool {
+        self.device.sspcr1.read().ms().bit_is_set()
+    }
+
+    /// Enable or disable driving the TX pin, when the SPI is a slave.
+    ///
+    /// Disabling it lets several slaves receive a broadcast from the master
+    /// while only one of them answers.
+    pub fn set_slave_output_enabled(&mut self, enabled: bool) {
+        self.device.sspcr1.modify(|_, w| w.sod().bit(!enabled));
+    }
+
+    /// Disable the spi to reset its configuration
+    pub fn disable(self) -> Spi<Disabled, D, DS, P> {
+        self.device.sspcr1.modify(|_, w| w.sse().clear_bit());
+
+        self.transition(Disabled { __private: () })
+    }
+}
+
+/// Same as core::convert::Infallible, but implementing spi::Error
+///
+/// For eh 1.0.0-alpha.6, Infallible doesn't implement spi::Error,
+/// so use a locally defined type instead.
+/// This should be removed with the next release of e-h.
+/// (https://github.com/rust-embedded/embedded-hal/pull/328)
+#[cfg(feature = "eh1_0_alpha")]
+pub enum SpiInfallible {}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl core::fmt::Debug for SpiInfallible {
+    fn fmt(&self, _f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl eh1::Error for SpiInfallible {
+    fn kind(&self) -> eh1::ErrorKind {
+        match *self {}
+    }
+}
+
+/// Number of words each of the TX and RX FIFOs holds
+const FIFO_DEPTH: usize = 8;
+
+macro_rules! impl_write {
+    ($type:ident, [$($nr:expr),+]) => {
+
+        $(
+        impl<D: SpiDevice, P: ValidSpiPinout<D>> FullDuplex<$type> for Spi<Enabled, D, $nr, P> {
+            type Error = Infallible;
+
+            fn read(&mut self) -> Result<$type, nb::Error<Infallible>> {
+                if !self.is_readable() {
+                    return Err(nb::Error::WouldBlock);
+                }
+
+                Ok(self.read_word() as $type)
+            }
+            fn send(&mut self, word: $type) -> Result<(), nb::Error<Infallible>> {
+                // Write to TX FIFO whilst ignoring RX, then clean up afterward. When RX
+                // is full, PL022 inhibits RX pushes, and sets a sticky flag on
+                // push-on-full, but continues shifting. Safe if SSPIMSC_RORIM is not set.
+                if !self.is_writable() {
+                    return Err(nb::Error::WouldBlock);
+                }
+
+                self.write_word(word as u16);
+                Ok(())
+            }
+        }
+
+        impl<D: SpiDevice, P: ValidSpiPinout<D>> spi::Write<$type> for Spi<Enabled, D, $nr, P> {
+            type Error = Infallible;
+
+            fn write(&mut self, words: &[$type]) -> Result<(), Infallible> {
+                for word in words {
+                    while !self.is_writable() {}
+                    self.write_word(*word as u16);
+
+                    // Keep the RX FIFO from overflowing
+                    while self.is_readable() {
+                        let _ = self.device.sspdr.read();
+                    }
+                }
+
+                // Drain what was received while the last words were sent
+                self.flush();
+                while self.is_readable() {
+                    let _ = self.device.sspdr.read();
+                }
+                self.device.sspicr.write(|w| w.roric().set_bit());
+
+                Ok(())
+            }
+        }
+
+        impl<D: SpiDevice, P: ValidSpiPinout<D>> spi::Transfer<$type> for Spi<Enabled, D, $nr, P> {
+            type Error = Infallible;
+
+            fn transfer<'w>(&mut self, words: &'w mut [$type]) -> Result<&'w [$type], Infallible> {
+                let len = words.len();
+                let mut sent = 0;
+                let mut received = 0;
+
+                while received < len {
+                    // Keep the TX FIFO fed, but never further ahead than the
+                    // RX FIFO can absorb
+                    if sent < len && sent - received < FIFO_DEPTH && self.is_writable() {
+                        self.write_word(words[sent] as u16);
+                        sent += 1;
+                    }
+
+                    if self.is_readable() {
+                        words[received] = self.read_word() as $type;
+                        received += 1;
+                    }
+                }
+
+                Ok(words)
+            }
+        }
+
+        impl<D: SpiDevice, P: ValidSpiPinout<D>> spi::write_iter::Default<$type> for Spi<Enabled, D, $nr, P> {}
+
+        #[cfg(feature = "eh1_0_alpha")]
+        impl<D: SpiDevice, P: ValidSpiPinout<D>> eh1::blocking::Read<$type> for Spi<Enabled, D, $nr, P> {
+            type Error = SpiInfallible;
+
+            fn read(&mut self, words: &mut [$type]) -> Result<(), SpiInfallible> {
+                self.read_words(words.len(), |i, word| words[i] = word as $type);
+                Ok(())
+            }
+        }
+
+        #[cfg(feature = "eh1_0_alpha")]
+        impl<D: SpiDevice, P: ValidSpiPinout<D>> eh1::blocking::Write<$type> for Spi<Enabled, D, $nr, P> {
+            type Error = SpiInfallible;
+
+            fn write(&mut self, words: &[$type]) -> Result<(), SpiInfallible> {
+                let _ = spi::Write::write(self, words);
+                Ok(())
+            }
+        }
+
+        #[cfg(feature = "eh1_0_alpha")]
+        impl<D: SpiDevice, P: ValidSpiPinout<D>> eh1::blocking::Transfer<$type> for Spi<Enabled, D, $nr, P> {
+            type Error = SpiInfallible;
+
+            /// Send `write` while receiving into `read`, for as many words as
+            /// the longer of the two: zeros are sent past the end of `write`,
+            /// and the words received past the end of `read` are dropped.
+            fn transfer(&mut self, read: &mut [$type], write: &[$type]) -> Result<(), SpiInfallible> {
+                let count = read.len().max(write.len());
+                self.transfer_words(
+                    count,
+                    |i| write.get(i).map_or(0, |&word| word as u16),
+                    |i, word| {
+                        if let Some(slot) = read.get_mut(i) {
+                            *slot = word as $type;
+                        }
+                    },
+                );
+                Ok(())
+            }
+        }
+
+        #[cfg(feature = "eh1_0_alpha")]
+        impl<D: SpiDevice, P: ValidSpiPinout<D>> eh1::blocking::TransferInplace<$type> for Spi<Enabled, D, $nr, P> {
+            type Error = SpiInfallible;
+
+            fn transfer_inplace(&mut self, words: &mut [$type]) -> Result<(), SpiInfallible> {
+                let _ = spi::Transfer::transfer(self, words);
+                Ok(())
+            }
+        }
+
+        impl<D: SpiDevice, P: ValidSpiPinout<D>> Spi<Enabled, D, $nr, P> {
+            /// Send `write`, then receive into `read` while sending zeros.
+            ///
+            /// This suits devices which take a command and only then answer,
+            /// ignoring what they receive while answering.
+            pub fn write_then_read(&mut self, write: &[$type], read: &mut [$type]) {
+                let _ = spi::Write::write(self, write);
+                self.read_words(read.len(), |i, word| read[i] = word as $type);
+            }
+        }
+
+        impl<D: SpiDevice, P: ThreeWirePinout<D>> Spi<Enabled, D, $nr, P> {
+            /// Send `write`, then receive into `read`, on a 3-wire bus.
+            ///
+            /// The TX pin stops driving the data line while receiving, so the
+            /// device can drive it instead.
+            pub fn write_then_read_3wire(&mut self, write: &[$type], read: &mut [$type]) {
+                let _ = spi::Write::write(self, write);
+                self.pins.set_tx_output_enabled(false);
+                self.read_words(read.len(), |i, word| read[i] = word as $type);
+                self.flush();
+                self.pins.set_tx_output_enabled(true);
+            }
+        }
+
+        #[cfg(feature = "eh1_0_alpha")]
+        impl<D: SpiDevice, P: ValidSpiPinout<D>> eh1::nb::FullDuplex<$type> for Spi<Enabled, D, $nr, P> {
+            type Error = SpiInfallible;
+
+            fn read(&mut self) -> Result<$type, nb::Error<SpiInfallible>> {
+                if !self.is_readable() {
+                    return Err(nb::Error::WouldBlock);
+                }
+
+                Ok(self.read_word() as $type)
+            }
+            fn write(&mut self, word: $type) -> Result<(), nb::Error<SpiInfallible>> {
+                // Write to TX FIFO whilst ignoring RX, then clean up afterward. When RX
+                // is full, PL022 inhibits RX pushes, and sets a sticky flag on
+                // push-on-full, but continues shifting. Safe if SSPIMSC_RORIM is not set.
+                if !self.is_writable() {
+                    return Err(nb::Error::WouldBlock);
+                }
+
+                self.write_word(word as u16);
+                Ok(())
+            }
+        }
+
+    )+
+
+    };
+}
+
+impl_write!(u8, [4, 5, 6, 7, 8]);
+impl_write!(u16, [9, 10, 11, 12, 13, 14, 15, 16]);
+
+macro_rules! impl_dma_targets {
+    ($type:ident, [$($nr:expr),+]) => {
+
+    $(
+        /// Words are moved as they are on the wire: [`BitOrder::LsbFirst`] is not applied to
+        /// them.
+        // Safety: The data register stays valid for as long as the SPI is held by the transfer.
+        unsafe impl<D: SpiDevice, P: ValidSpiPinout<D>> ReadTarget for Spi<Enabled, D, $nr, P> {
+            type ReceivedWord = $type;
+
+            fn rx_treq(&self) -> Option<u8> {
+                Some(self.rx_dreq())
+            }
+
+            fn rx_address_count(&self) -> (u32, u32) {
+                (self.data_address(), u32::MAX)
+            }
+
+            fn rx_increment(&self) -> bool {
+                false
+            }
+        }
+
+        /// Words are moved as they are on the wire: [`BitOrder::LsbFirst`] is not applied to
+        /// them.
+        // Safety: The data register stays valid for as long as the SPI is held by the transfer.
+        unsafe impl<D: SpiDevice, P: ValidSpiPinout<D>> WriteTarget for Spi<Enabled, D, $nr, P> {
+            type TransmittedWord = $type;
+
+            fn tx_treq(&self) -> Option<u8> {
+                Some(self.tx_dreq())
+            }
+
+            fn tx_address_count(&mut self) -> (u32, u32) {
+                (self.data_address(), u32::MAX)
+            }
+
+            fn tx_increment(&self) -> bool {
+                false
+            }
+        }
+    )+
+
+    };
+}
+
+impl_dma_targets!(u8, [4, 5, 6, 7, 8]);
+impl_dma_targets!(u16, [9, 10, 11, 12, 13, 14, 15, 16]);
+
+/// DMA transfers owning the bus, on a pair of channels: the first one feeds the TX FIFO, and the
+/// second one drains the RX FIFO, so that the bus is clocked for each word read and never
+/// overruns.
+///
+/// Words are moved as they are on the wire: [`BitOrder::LsbFirst`] is not applied to them.
+impl<D: SpiDevice, const DS: u8, P: ValidSpiPinout<D>> Spi<Enabled, D, DS, P> {
+    /// Start sending `from` while receiving into `to`, through the DMA channels `ch`.
+    ///
+    /// As many words as the shortest of `from` and `to` holds are moved. The bus, the channels
+    /// and the buffers are given back once the transfer is done.
+    #[allow(clippy::type_complexity)]
+    pub fn transfer_dma<CH1, CH2, W, FROM, TO>(
+        self,
+        ch: (CH1, CH2),
+        from: FROM,
+        to: TO,
+    ) -> bidirectional::Transfer<CH1, CH2, FROM, Self, TO>
+    where
+        CH1: SingleChannel,
+        CH2: SingleChannel,
+        W: Word,
+        Self: ReadTarget<ReceivedWord = W> + WriteTarget<TransmittedWord = W>,
+        FROM: ReadBuffer<Word = W>,
+        TO: WriteBuffer<Word = W>,
+    {
+        bidirectional::Config::new(ch, from, self, to).start()
+    }
+
+    /// Start sending `from` through the DMA channels `ch`, dropping the words received.
+    #[allow(clippy::type_complexity)]
+    pub fn write_dma<CH1, CH2, W, FROM>(
+        self,
+        ch: (CH1, CH2),
+        from: FROM,
+    ) -> bidirectional::Transfer<CH1, CH2, FROM, Self, Discard<W>>
+    where
+        CH1: SingleChannel,
+        CH2: SingleChannel,
+        W: Word,
+        Self: ReadTarget<ReceivedWord = W> + WriteTarget<TransmittedWord = W>,
+        FROM: ReadBuffer<Word = W>,
+    {
+        bidirectional::Config::new(ch, from, self, Discard::new()).start()
+    }
+
+    /// Start receiving into `to` through the DMA channels `ch`, sending `fill` for each word,
+    /// such as `&0xff` for SD cards.
+    #[allow(clippy::type_complexity)]
+    pub fn read_dma<CH1, CH2, W, TO>(
+        self,
+        ch: (CH1, CH2),
+        fill: &'static W,
+        to: TO,
+    ) -> bidirectional::Transfer<CH1, CH2, Repeat<W>, Self, TO>
+    where
+        CH1: SingleChannel,
+        CH2: SingleChannel,
+        W: Word + 'static,
+        Self: ReadTarget<ReceivedWord = W> + WriteTarget<TransmittedWord = W>,
+        TO: WriteBuffer<Word = W>,
+    {
+        bidirectional::Config::new(ch, Repeat(fill), self, to).start()
+    }
+}
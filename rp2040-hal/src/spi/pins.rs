@@ -0,0 +1,195 @@
+//! Pins usable by the SPI peripherals
+use crate::gpio::pin::bank0::{
+    BankPinId, Gpio0, Gpio1, Gpio10, Gpio11, Gpio12, Gpio13, Gpio14, Gpio15, Gpio16, Gpio17,
+    Gpio18, Gpio19, Gpio2, Gpio20, Gpio21, Gpio22, Gpio23, Gpio24, Gpio25, Gpio26, Gpio27, Gpio28,
+    Gpio29, Gpio3, Gpio4, Gpio5, Gpio6, Gpio7, Gpio8, Gpio9,
+};
+use crate::gpio::pin::{FunctionSpi, Pin, PinId};
+use crate::gpio::OutputEnableOverride;
+use crate::typelevel::Sealed;
+use pac::{SPI0, SPI1};
+
+use super::SpiDevice;
+
+/// TX pin, also known as MOSI when the RP2040 is the master
+pub trait TxPin<D: SpiDevice>: Sealed {}
+
+/// RX pin, also known as MISO when the RP2040 is the master
+pub trait RxPin<D: SpiDevice>: Sealed {}
+
+/// Clock pin
+pub trait SckPin<D: SpiDevice>: Sealed {}
+
+/// Chip select pin
+pub trait CsPin<D: SpiDevice>: Sealed {}
+
+impl RxPin<SPI0> for Gpio0 {}
+impl CsPin<SPI0> for Gpio1 {}
+impl SckPin<SPI0> for Gpio2 {}
+impl TxPin<SPI0> for Gpio3 {}
+impl RxPin<SPI0> for Gpio4 {}
+impl CsPin<SPI0> for Gpio5 {}
+impl SckPin<SPI0> for Gpio6 {}
+impl TxPin<SPI0> for Gpio7 {}
+impl RxPin<SPI1> for Gpio8 {}
+impl CsPin<SPI1> for Gpio9 {}
+impl SckPin<SPI1> for Gpio10 {}
+impl TxPin<SPI1> for Gpio11 {}
+impl RxPin<SPI1> for Gpio12 {}
+impl CsPin<SPI1> for Gpio13 {}
+impl SckPin<SPI1> for Gpio14 {}
+impl TxPin<SPI1> for Gpio15 {}
+impl RxPin<SPI0> for Gpio16 {}
+impl CsPin<SPI0> for Gpio17 {}
+impl SckPin<SPI0> for Gpio18 {}
+impl TxPin<SPI0> for Gpio19 {}
+impl RxPin<SPI0> for Gpio20 {}
+impl CsPin<SPI0> for Gpio21 {}
+impl SckPin<SPI0> for Gpio22 {}
+impl TxPin<SPI0> for Gpio23 {}
+impl RxPin<SPI1> for Gpio24 {}
+impl CsPin<SPI1> for Gpio25 {}
+impl SckPin<SPI1> for Gpio26 {}
+impl TxPin<SPI1> for Gpio27 {}
+impl RxPin<SPI1> for Gpio28 {}
+impl CsPin<SPI1> for Gpio29 {}
+
+/// A set of pins a [`Spi`](super::Spi) can own
+///
+/// This is implemented for `()`, for when the pins are set up by the caller,
+/// and for tuples of pins in [`FunctionSpi`] mode: `(tx, sck)` for a bus
+/// that only transmits, `(tx, rx, sck)`, and `(tx, rx, sck, cs)` which hands
+/// chip select to the hardware.
+pub trait ValidSpiPinout<D: SpiDevice>: Sealed {}
+
+impl<D: SpiDevice> ValidSpiPinout<D> for () {}
+
+impl<TX, SCK> Sealed for (Pin<TX, FunctionSpi>, Pin<SCK, FunctionSpi>)
+where
+    TX: PinId + BankPinId,
+    SCK: PinId + BankPinId,
+{
+}
+
+impl<D, TX, SCK> ValidSpiPinout<D> for (Pin<TX, FunctionSpi>, Pin<SCK, FunctionSpi>)
+where
+    D: SpiDevice,
+    TX: PinId + BankPinId + TxPin<D>,
+    SCK: PinId + BankPinId + SckPin<D>,
+{
+}
+
+impl<TX, RX, SCK> Sealed
+    for (
+        Pin<TX, FunctionSpi>,
+        Pin<RX, FunctionSpi>,
+        Pin<SCK, FunctionSpi>,
+    )
+where
+    TX: PinId + BankPinId,
+    RX: PinId + BankPinId,
+    SCK: PinId + BankPinId,
+{
+}
+
+impl<D, TX, RX, SCK> ValidSpiPinout<D>
+    for (
+        Pin<TX, FunctionSpi>,
+        Pin<RX, FunctionSpi>,
+        Pin<SCK, FunctionSpi>,
+    )
+where
+    D: SpiDevice,
+    TX: PinId + BankPinId + TxPin<D>,
+    RX: PinId + BankPinId + RxPin<D>,
+    SCK: PinId + BankPinId + SckPin<D>,
+{
+}
+
+impl<TX, RX, SCK, CS> Sealed
+    for (
+        Pin<TX, FunctionSpi>,
+        Pin<RX, FunctionSpi>,
+        Pin<SCK, FunctionSpi>,
+        Pin<CS, FunctionSpi>,
+    )
+where
+    TX: PinId + BankPinId,
+    RX: PinId + BankPinId,
+    SCK: PinId + BankPinId,
+    CS: PinId + BankPinId,
+{
+}
+
+impl<D, TX, RX, SCK, CS> ValidSpiPinout<D>
+    for (
+        Pin<TX, FunctionSpi>,
+        Pin<RX, FunctionSpi>,
+        Pin<SCK, FunctionSpi>,
+        Pin<CS, FunctionSpi>,
+    )
+where
+    D: SpiDevice,
+    TX: PinId + BankPinId + TxPin<D>,
+    RX: PinId + BankPinId + RxPin<D>,
+    SCK: PinId + BankPinId + SckPin<D>,
+    CS: PinId + BankPinId + CsPin<D>,
+{
+}
+
+/// A pinout usable for a 3-wire bus, where the device's bidirectional data
+/// line is wired to both the TX and RX pins
+///
+/// A series resistor between the TX pin and the data line protects against
+/// both sides driving it at once.
+pub trait ThreeWirePinout<D: SpiDevice>: ValidSpiPinout<D> {
+    /// Enable or disable the TX pin's output driver
+    #[doc(hidden)]
+    fn set_tx_output_enabled(&mut self, enabled: bool);
+}
+
+fn tx_output_enable_override(enabled: bool) -> OutputEnableOverride {
+    if enabled {
+        OutputEnableOverride::DontInvert
+    } else {
+        OutputEnableOverride::Disable
+    }
+}
+
+impl<D, TX, RX, SCK> ThreeWirePinout<D>
+    for (
+        Pin<TX, FunctionSpi>,
+        Pin<RX, FunctionSpi>,
+        Pin<SCK, FunctionSpi>,
+    )
+where
+    D: SpiDevice,
+    TX: PinId + BankPinId + TxPin<D>,
+    RX: PinId + BankPinId + RxPin<D>,
+    SCK: PinId + BankPinId + SckPin<D>,
+{
+    fn set_tx_output_enabled(&mut self, enabled: bool) {
+        self.0
+            .set_output_enable_override(tx_output_enable_override(enabled));
+    }
+}
+
+impl<D, TX, RX, SCK, CS> ThreeWirePinout<D>
+    for (
+        Pin<TX, FunctionSpi>,
+        Pin<RX, FunctionSpi>,
+        Pin<SCK, FunctionSpi>,
+        Pin<CS, FunctionSpi>,
+    )
+where
+    D: SpiDevice,
+    TX: PinId + BankPinId + TxPin<D>,
+    RX: PinId + BankPinId + RxPin<D>,
+    SCK: PinId + BankPinId + SckPin<D>,
+    CS: PinId + BankPinId + CsPin<D>,
+{
+    fn set_tx_output_enabled(&mut self, enabled: bool) {
+        self.0
+            .set_output_enable_override(tx_output_enable_override(enabled));
+    }
+}
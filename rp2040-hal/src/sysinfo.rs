@@ -0,0 +1,129 @@
+//! Chip and bootrom identification
+//!
+//! The SYSINFO registers identify the RP2040 and its revision, and the bootrom header holds the
+//! version of the bootrom. The errata of the [RP2040 datasheet](https://datasheets.raspberrypi.org/rp2040/rp2040-datasheet.pdf)
+//! list the revisions they affect, so their workarounds can be applied only where needed.
+//!
+//! ## Usage
+//! ```no_run
+//! use rp2040_hal::sysinfo::{chip_revision, ChipRevision};
+//! if chip_revision() < ChipRevision::B2 {
+//!     // Work around an erratum fixed in B2
+//! }
+//! ```
+
+use crate::pac;
+
+/// Revision of the RP2040
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChipRevision {
+    /// RP2040-B0, with the V1 bootrom
+    B0,
+    /// RP2040-B1, with the V2 bootrom
+    B1,
+    /// RP2040-B2, with the V3 bootrom
+    B2,
+    /// A revision unknown to this crate, with the value of the REVISION field of CHIP_ID
+    Unknown(u8),
+}
+
+/// The identification of the chip, from the CHIP_ID register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChipId {
+    /// The JEDEC JEP-106 identification code of the manufacturer
+    pub manufacturer: u16,
+    /// The part number, 0x0002 for the RP2040
+    pub part: u16,
+    /// The revision of the chip
+    pub revision: ChipRevision,
+}
+
+/// The platform the chip runs on, from the PLATFORM register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    /// A manufactured chip
+    Asic,
+    /// An FPGA, as used during development of the chip
+    Fpga,
+}
+
+/// Read the identification of the chip.
+pub fn chip_id() -> ChipId {
+    // Safety: we're only reading from a register
+    let bits = unsafe { (*pac::SYSINFO::ptr()).chip_id.read().bits() };
+    ChipId {
+        manufacturer: (bits & 0xfff) as u16,
+        part: ((bits >> 12) & 0xffff) as u16,
+        revision: decode_revision((bits >> 28) as u8, rom_version),
+    }
+}
+
+/// Decode the REVISION field of CHIP_ID.
+///
+/// It reads 1 on both B0 and B1, which only differ by their bootrom, so `rom_version` is only
+/// called to tell them apart.
+fn decode_revision(revision: u8, rom_version: impl FnOnce() -> u8) -> ChipRevision {
+    match revision {
+        1 => match rom_version() {
+            1 => ChipRevision::B0,
+            _ => ChipRevision::B1,
+        },
+        2 => ChipRevision::B2,
+        revision => ChipRevision::Unknown(revision),
+    }
+}
+
+/// Read the revision of the chip.
+pub fn chip_revision() -> ChipRevision {
+    chip_id().revision
+}
+
+/// Read the platform the chip runs on.
+pub fn platform() -> Platform {
+    // Safety: we're only reading from a register
+    let sysinfo = unsafe { &*pac::SYSINFO::ptr() };
+    if sysinfo.platform.read().fpga().bit_is_set() {
+        Platform::Fpga
+    } else {
+        Platform::Asic
+    }
+}
+
+/// Read the git revision of the RTL of the chip.
+pub fn gitref() -> u32 {
+    // Safety: we're only reading from a register
+    unsafe { (*pac::SYSINFO::ptr()).gitref_rp2040.read().bits() }
+}
+
+/// Read the version of the bootrom, 1 for B0 chips, 2 for B1 chips and 3 for B2 chips.
+pub fn rom_version() -> u8 {
+    // The bootrom header starts with 'M', 'u', 1 at 0x10, followed by the version
+    const ROM_VERSION: *const u8 = 0x0000_0013 as _;
+    // Safety: the bootrom is always mapped, and read-only
+    unsafe { core::ptr::read_volatile(ROM_VERSION) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_revision_tells_b0_from_b1_by_the_bootrom() {
+        assert_eq!(decode_revision(1, || 1), ChipRevision::B0);
+        assert_eq!(decode_revision(1, || 2), ChipRevision::B1);
+    }
+
+    #[test]
+    fn decode_revision_b2() {
+        assert_eq!(
+            decode_revision(2, || panic!("bootrom read")),
+            ChipRevision::B2
+        );
+    }
+
+    #[test]
+    fn decode_revision_unknown() {
+        assert_eq!(decode_revision(0, || 1), ChipRevision::Unknown(0));
+        assert_eq!(decode_revision(3, || 3), ChipRevision::Unknown(3));
+    }
+}
@@ -0,0 +1,71 @@
+//! A [`defmt`] global logger writing to a UART
+//!
+//! Enabled with the `defmt-uart` feature. Hand a [`Writer`] to
+//! [`init_defmt_logger`] and `defmt` log frames are written to it, blocking
+//! until each frame has been queued. Messages logged before that are
+//! dropped.
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+use super::{
+    transmit_flushed, write_full_blocking, RegisterBlock, UartDevice, ValidUartPinout, Writer,
+};
+
+/// The UART to log to, or null before [`init_defmt_logger`] is called
+static UART: AtomicPtr<RegisterBlock> = AtomicPtr::new(ptr::null_mut());
+
+/// Set while a frame is being written, to catch reentrant use
+static TAKEN: AtomicBool = AtomicBool::new(false);
+static mut CS_RESTORE: u8 = 0;
+static mut ENCODER: defmt::Encoder = defmt::Encoder::new();
+
+/// Route `defmt` logs to `writer`
+///
+/// The writer is consumed, as the logger uses it for the rest of the
+/// program.
+pub fn init_defmt_logger<D: UartDevice, P: ValidUartPinout<D>>(writer: Writer<D, P>) {
+    UART.store(writer.device as *mut RegisterBlock, Ordering::Release);
+}
+
+fn do_write(bytes: &[u8]) {
+    let uart = UART.load(Ordering::Acquire);
+    if !uart.is_null() {
+        // Safe because the pointer was taken from a `Writer`, which points at
+        // a peripheral that lives forever.
+        write_full_blocking(unsafe { &*uart }, bytes);
+    }
+}
+
+#[defmt::global_logger]
+struct Logger;
+
+unsafe impl defmt::Logger for Logger {
+    fn acquire() {
+        let restore = unsafe { critical_section::acquire() };
+        if TAKEN.load(Ordering::Relaxed) {
+            panic!("defmt logger taken reentrantly")
+        }
+        TAKEN.store(true, Ordering::Relaxed);
+        unsafe {
+            CS_RESTORE = restore;
+            ENCODER.start_frame(do_write);
+        }
+    }
+
+    unsafe fn flush() {
+        let uart = UART.load(Ordering::Acquire);
+        if !uart.is_null() {
+            while transmit_flushed(&*uart).is_err() {}
+        }
+    }
+
+    unsafe fn release() {
+        ENCODER.end_frame(do_write);
+        TAKEN.store(false, Ordering::Relaxed);
+        critical_section::release(CS_RESTORE);
+    }
+
+    unsafe fn write(bytes: &[u8]) {
+        ENCODER.write(bytes, do_write);
+    }
+}
@@ -0,0 +1,236 @@
+use core::{
+    cell::RefCell,
+    convert::Infallible,
+    task::{Poll, Waker},
+};
+
+use cortex_m::interrupt::{self, Mutex};
+use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+
+use super::{
+    disable_interrupt, enable_interrupt, read_raw, write_raw, Enabled, ReadErrorType, Reader,
+    RegisterBlock, UartDevice, UartInterrupt, UartPeripheral, ValidUartPinout, Writer,
+};
+
+/// A task waiting on a UART
+type WakerSlot = Mutex<RefCell<Option<Waker>>>;
+
+/// The tasks waiting to receive on UART0 and UART1
+static RX_WAKERS: [WakerSlot; 2] = [
+    Mutex::new(RefCell::new(None)),
+    Mutex::new(RefCell::new(None)),
+];
+
+/// The tasks waiting to transmit on UART0 and UART1
+static TX_WAKERS: [WakerSlot; 2] = [
+    Mutex::new(RefCell::new(None)),
+    Mutex::new(RefCell::new(None)),
+];
+
+/// Wake the tasks waiting on UART0. Call this from the `UART0_IRQ` handler.
+///
+/// The async reads and writes sleep until the RX, RX timeout or TX interrupt
+/// fires, so the interrupt must be unmasked in the NVIC and its handler must
+/// call this.
+pub fn on_uart0_interrupt() {
+    on_interrupt(unsafe { &*pac::UART0::ptr() }, 0);
+}
+
+/// Wake the tasks waiting on UART1. Call this from the `UART1_IRQ` handler.
+///
+/// See [`on_uart0_interrupt`].
+pub fn on_uart1_interrupt() {
+    on_interrupt(unsafe { &*pac::UART1::ptr() }, 1);
+}
+
+fn on_interrupt(device: &RegisterBlock, index: usize) {
+    let pending = device.uartmis.read().bits();
+    // Stop the interrupts from firing again until the tasks are waiting again
+    if pending & (UartInterrupt::Rx.mask() | UartInterrupt::RxTimeout.mask()) != 0 {
+        disable_interrupt(device, UartInterrupt::Rx);
+        disable_interrupt(device, UartInterrupt::RxTimeout);
+        wake(&RX_WAKERS[index]);
+    }
+    if pending & UartInterrupt::Tx.mask() != 0 {
+        disable_interrupt(device, UartInterrupt::Tx);
+        wake(&TX_WAKERS[index]);
+    }
+}
+
+fn wake(slot: &WakerSlot) {
+    if let Some(waker) = interrupt::free(|cs| slot.borrow(cs).borrow_mut().take()) {
+        waker.wake();
+    }
+}
+
+fn index(device: &RegisterBlock) -> usize {
+    if device as *const RegisterBlock == pac::UART0::ptr() {
+        0
+    } else {
+        1
+    }
+}
+
+/// Wait until `f` is ready, sleeping until one of `interrupts` fires between
+/// checks.
+async fn wait_for<R>(
+    device: &RegisterBlock,
+    slot: &WakerSlot,
+    interrupts: &[UartInterrupt],
+    mut f: impl FnMut() -> Poll<R>,
+) -> R {
+    futures::future::poll_fn(|cx| {
+        if let Poll::Ready(result) = f() {
+            return Poll::Ready(result);
+        }
+
+        interrupt::free(|cs| slot.borrow(cs).replace(Some(cx.waker().clone())));
+        // The interrupt status is latched, so one fires right away if the
+        // condition was met in the meantime
+        for &interrupt in interrupts {
+            enable_interrupt(device, interrupt);
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+async fn read_async(device: &RegisterBlock, buffer: &mut [u8]) -> Result<usize, ReadErrorType> {
+    if buffer.is_empty() {
+        return Ok(0);
+    }
+
+    // The RX interrupt fires once the FIFO reaches its watermark, and the RX
+    // timeout once fewer bytes have been waiting for 32 bit periods
+    wait_for(
+        device,
+        &RX_WAKERS[index(device)],
+        &[UartInterrupt::Rx, UartInterrupt::RxTimeout],
+        || match read_raw(device, buffer) {
+            Ok(bytes_read) => Poll::Ready(Ok(bytes_read)),
+            Err(nb::Error::WouldBlock) => Poll::Pending,
+            Err(nb::Error::Other(e)) => Poll::Ready(Err(e.err_type)),
+        },
+    )
+    .await
+}
+
+async fn write_async(device: &RegisterBlock, data: &[u8]) -> usize {
+    if data.is_empty() {
+        return 0;
+    }
+
+    // The TX FIFO is full when this waits, so the TX interrupt fires once it
+    // drains down to its watermark
+    wait_for(
+        device,
+        &TX_WAKERS[index(device)],
+        &[UartInterrupt::Tx],
+        || match write_raw(device, data) {
+            Ok(rest) => Poll::Ready(data.len() - rest.len()),
+            Err(_) => Poll::Pending,
+        },
+    )
+    .await
+}
+
+async fn flush_async(device: &RegisterBlock) {
+    // The TX interrupt is only raised when the FIFO drains through its
+    // watermark, and the UART has no interrupt for the end of the
+    // transmission: sleep while the FIFO is above the watermark, then spin
+    // for the last characters.
+    wait_for(
+        device,
+        &TX_WAKERS[index(device)],
+        &[UartInterrupt::Tx],
+        || {
+            let flags = device.uartfr.read();
+            if flags.txfe().bit_is_set() || device.uartris.read().txris().bit_is_set() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        },
+    )
+    .await;
+
+    while device.uartfr.read().txfe().bit_is_clear() {}
+    while device.uartfr.read().busy().bit_is_set() {}
+}
+
+impl embedded_io_async::Error for ReadErrorType {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ReadErrorType::Overrun | ReadErrorType::Break => ErrorKind::Other,
+            ReadErrorType::Parity | ReadErrorType::Framing => ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> ErrorType for UartPeripheral<Enabled, D, P> {
+    type Error = ReadErrorType;
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> Read for UartPeripheral<Enabled, D, P> {
+    /// Read at least one byte, sleeping until the RX or RX timeout interrupt
+    /// fires.
+    ///
+    /// Returns the number of bytes read, which is as many as were in the RX
+    /// FIFO, up to the length of `buffer`.
+    async fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        read_async(&self.device, buffer).await
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> Write for UartPeripheral<Enabled, D, P> {
+    /// Write at least one byte, sleeping until the TX interrupt fires while
+    /// the TX FIFO is full.
+    ///
+    /// Unlike [`write_full_blocking`](Self::write_full_blocking), this does
+    /// not drive the RS-485 driver enable pin.
+    async fn write(&mut self, data: &[u8]) -> Result<usize, Self::Error> {
+        Ok(write_async(&self.device, data).await)
+    }
+
+    /// Wait until all the bytes written are transmitted.
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        flush_async(&self.device).await;
+        Ok(())
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> ErrorType for Reader<D, P> {
+    type Error = ReadErrorType;
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> Read for Reader<D, P> {
+    /// Read at least one byte, sleeping until the RX or RX timeout interrupt
+    /// fires.
+    ///
+    /// Returns the number of bytes read, which is as many as were in the RX
+    /// FIFO, up to the length of `buffer`.
+    async fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        read_async(&self.device, buffer).await
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> ErrorType for Writer<D, P> {
+    type Error = Infallible;
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> Write for Writer<D, P> {
+    /// Write at least one byte, sleeping until the TX interrupt fires while
+    /// the TX FIFO is full.
+    ///
+    /// Unlike [`write_full_blocking`](Self::write_full_blocking), this does
+    /// not drive the RS-485 driver enable pin.
+    async fn write(&mut self, data: &[u8]) -> Result<usize, Self::Error> {
+        Ok(write_async(self.device(), data).await)
+    }
+
+    /// Wait until all the bytes written are transmitted.
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        flush_async(self.device()).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,1292 @@
+//! Universal Asynchronous Receiver Transmitter (UART)
+//!
+//! See [Chapter 4 Section 2](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) of the datasheet for more details
+//!
+//! ## Usage
+//!
+//! See [examples/uart.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/uart.rs) for a more complete example
+//! ```no_run
+//! use rp2040_hal::{clocks::init_clocks_and_plls, gpio::{Pins, FunctionUart}, pac, sio::Sio, uart::{self, UartPeripheral}, watchdog::Watchdog};
+//!
+//! const XOSC_CRYSTAL_FREQ: u32 = 12_000_000; // Typically found in BSP crates
+//!
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+//! let mut clocks = init_clocks_and_plls(XOSC_CRYSTAL_FREQ, peripherals.XOSC, peripherals.CLOCKS, peripherals.PLL_SYS, peripherals.PLL_USB, &mut peripherals.RESETS, &mut watchdog).ok().unwrap();
+//!
+//! // Set up UART on GP0 and GP1 (Pico pins 1 and 2)
+//! let uart_pins = (
+//!     pins.gpio0.into_mode::<FunctionUart>(),
+//!     pins.gpio1.into_mode::<FunctionUart>(),
+//! );
+//!
+//! // Need to perform clock init before using UART or it will freeze.
+//! let uart = UartPeripheral::new(
+//!         peripherals.UART0,
+//!         uart_pins,
+//!         uart::common_configs::_9600_8_N_1,
+//!         &mut peripherals.RESETS,
+//!         clocks.peripheral_clock.into(),
+//!     ).unwrap();
+//!
+//! uart.write_full_blocking(b"Hello World!\r\n");
+//! ```
+//!
+//! The peripheral can be split into a [`Reader`] and a [`Writer`], e.g. to
+//! receive commands in an interrupt handler while logging from the main loop:
+//! ```no_run
+//! # use rp2040_hal::{gpio::{Pins, FunctionUart}, pac, sio::Sio, uart::{self, UartPeripheral}};
+//! # let mut peripherals = pac::Peripherals::take().unwrap();
+//! # let sio = Sio::new(peripherals.SIO);
+//! # let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! # let uart_pins = (pins.gpio0.into_mode::<FunctionUart>(), pins.gpio1.into_mode::<FunctionUart>());
+//! # let uart = UartPeripheral::new(peripherals.UART0, uart_pins, uart::common_configs::_9600_8_N_1, &mut peripherals.RESETS, embedded_time::rate::Hertz(125_000_000)).unwrap();
+//! let (mut reader, writer) = uart.split();
+//! reader.enable_rx_interrupt();
+//! writer.write_full_blocking(b"Ready\r\n");
+//! ```
+//!
+//! Other formats can be set up with [`UartConfig::new`]:
+//! ```no_run
+//! use embedded_time::rate::Baud;
+//! use rp2040_hal::uart::{DataBits, Parity, StopBits, UartConfig};
+//! let config = UartConfig::new(Baud(4800), DataBits::Seven, Some(Parity::Even), StopBits::Two);
+//! ```
+
+use core::cell::Cell;
+use core::convert::Infallible;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::Deref;
+use embedded_time::fixed_point::FixedPoint;
+use embedded_time::rate::Baud;
+use embedded_time::rate::Hertz;
+
+#[cfg(feature = "eh1_0_alpha")]
+use eh1_0_alpha::serial::nb as eh1;
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::serial::{Read, Write};
+
+use nb::Error::{Other, WouldBlock};
+
+use crate::pac::{
+    uart0::{uartlcr_h::W as UART_LCR_H_Writer, RegisterBlock},
+    UART0, UART1,
+};
+
+use crate::atomic_register_access::{write_bitmask_clear, write_bitmask_set};
+use crate::dma::{single_buffer, ReadTarget, SingleChannel, WriteTarget};
+use crate::gpio::{DynPin, Pin, PinId, PushPullOutput};
+use crate::resets::SubsystemReset;
+use embedded_dma::{ReadBuffer, WriteBuffer};
+
+mod pins;
+pub use pins::*;
+
+mod reader;
+pub use reader::Reader;
+
+mod writer;
+pub use writer::Writer;
+
+#[cfg(feature = "embassy-traits")]
+mod embassy_support;
+#[cfg(feature = "embassy-traits")]
+pub use embassy_support::{on_uart0_interrupt, on_uart1_interrupt};
+
+#[cfg(feature = "defmt-uart")]
+mod defmt_logger;
+#[cfg(feature = "defmt-uart")]
+pub use defmt_logger::init_defmt_logger;
+
+/// Error type for UART operations.
+#[derive(Debug)]
+pub enum Error {
+    /// Bad argument : when things overflow, ...
+    BadArgument,
+}
+
+/// When there's a read error.
+pub struct ReadError<'err> {
+    /// The type of error
+    pub err_type: ReadErrorType,
+
+    /// Reference to the data that was read but eventually discared because of the error.
+    pub discared: &'err [u8],
+}
+
+/// Possible types of read errors. See Chapter 4, Section 2 §8 - Table 436: "UARTDR Register"
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReadErrorType {
+    /// Triggered when the FIFO (or shift-register) is overflowed.
+    Overrun,
+
+    /// Triggered when a break is received
+    Break,
+
+    /// Triggered when there is a parity mismatch between what's received and our settings.
+    Parity,
+
+    /// Triggered when the received character didn't have a valid stop bit.
+    Framing,
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl eh1_0_alpha::serial::Error for ReadErrorType {
+    fn kind(&self) -> eh1_0_alpha::serial::ErrorKind {
+        match self {
+            ReadErrorType::Overrun => eh1_0_alpha::serial::ErrorKind::Overrun,
+            ReadErrorType::Break => eh1_0_alpha::serial::ErrorKind::Other,
+            ReadErrorType::Parity => eh1_0_alpha::serial::ErrorKind::Parity,
+            ReadErrorType::Framing => eh1_0_alpha::serial::ErrorKind::FrameFormat,
+        }
+    }
+}
+
+/// State of the UART Peripheral.
+pub trait State {}
+
+/// Trait to handle both underlying devices (UART0 & UART1)
+pub trait UartDevice: Deref<Target = RegisterBlock> + SubsystemReset {
+    /// The DREQ raised while the TX FIFO has room
+    const TX_DREQ: u8;
+    /// The DREQ raised while the RX FIFO contains data
+    const RX_DREQ: u8;
+}
+
+impl UartDevice for UART0 {
+    const TX_DREQ: u8 = crate::dma::DREQ_UART0_TX;
+    const RX_DREQ: u8 = crate::dma::DREQ_UART0_RX;
+}
+impl UartDevice for UART1 {
+    const TX_DREQ: u8 = crate::dma::DREQ_UART1_TX;
+    const RX_DREQ: u8 = crate::dma::DREQ_UART1_RX;
+}
+
+/// UART is enabled.
+pub struct Enabled;
+
+/// UART is disabled.
+pub struct Disabled;
+
+impl State for Enabled {}
+impl State for Disabled {}
+
+/// Data bits
+pub enum DataBits {
+    /// 5 bits
+    Five,
+    /// 6 bits
+    Six,
+    /// 7 bits
+    Seven,
+    /// 8 bits
+    Eight,
+}
+
+/// Stop bits
+pub enum StopBits {
+    /// 1 bit
+    One,
+
+    /// 2 bits
+    Two,
+}
+
+/// Parity
+/// The "none" state of parity is represented with the Option type (None).
+pub enum Parity {
+    /// Odd parity
+    Odd,
+
+    /// Even parity
+    Even,
+
+    /// Stick parity, the parity bit is always 1
+    Mark,
+
+    /// Stick parity, the parity bit is always 0
+    ///
+    /// This is the receive configuration for 9 bit multiprocessor
+    /// communication, see [`UartPeripheral::write_9bit`].
+    Space,
+}
+
+/// FIFO level at which the RX or TX interrupt is asserted
+///
+/// The RX interrupt is asserted when the RX FIFO fills to this level, and the
+/// TX interrupt when the TX FIFO drains to it. Each FIFO holds 32 entries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FifoWatermark {
+    /// 1/8 full, 4 entries
+    Bytes4,
+    /// 1/4 full, 8 entries
+    Bytes8,
+    /// 1/2 full, 16 entries
+    Bytes16,
+    /// 3/4 full, 24 entries
+    Bytes24,
+    /// 7/8 full, 28 entries
+    Bytes28,
+}
+
+impl FifoWatermark {
+    fn bits(self) -> u8 {
+        match self {
+            FifoWatermark::Bytes4 => 0b000,
+            FifoWatermark::Bytes8 => 0b001,
+            FifoWatermark::Bytes16 => 0b010,
+            FifoWatermark::Bytes24 => 0b011,
+            FifoWatermark::Bytes28 => 0b100,
+        }
+    }
+}
+
+/// UART interrupt sources
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UartInterrupt {
+    /// The RX FIFO has reached its [watermark](FifoWatermark)
+    Rx,
+    /// The TX FIFO has drained to its [watermark](FifoWatermark)
+    Tx,
+    /// The RX FIFO is not empty, and no more data has arrived for 32 bit
+    /// periods
+    RxTimeout,
+    /// A character was received without a valid stop bit
+    Framing,
+    /// A character was received with the wrong parity
+    Parity,
+    /// A break condition was received
+    Break,
+    /// A character was received while the RX FIFO was full
+    Overrun,
+}
+
+impl UartInterrupt {
+    fn mask(self) -> u32 {
+        match self {
+            UartInterrupt::Rx => 1 << 4,
+            UartInterrupt::Tx => 1 << 5,
+            UartInterrupt::RxTimeout => 1 << 6,
+            UartInterrupt::Framing => 1 << 7,
+            UartInterrupt::Parity => 1 << 8,
+            UartInterrupt::Break => 1 << 9,
+            UartInterrupt::Overrun => 1 << 10,
+        }
+    }
+}
+
+/// A struct holding the configuration for an UART device.
+pub struct UartConfig {
+    baudrate: Baud,
+    data_bits: DataBits,
+    stop_bits: StopBits,
+    parity: Option<Parity>,
+}
+
+impl UartConfig {
+    /// Create a new configuration, for when none of the
+    /// [`common_configs`] fit
+    pub const fn new(
+        baudrate: Baud,
+        data_bits: DataBits,
+        parity: Option<Parity>,
+        stop_bits: StopBits,
+    ) -> UartConfig {
+        UartConfig {
+            baudrate,
+            data_bits,
+            stop_bits,
+            parity,
+        }
+    }
+}
+
+/// Common configurations for UART.
+pub mod common_configs {
+    use super::{DataBits, StopBits, UartConfig};
+    use embedded_time::rate::Baud;
+
+    /// 9600 baud, 8 data bits, no parity, 1 stop bit
+    pub const _9600_8_N_1: UartConfig = UartConfig {
+        baudrate: Baud(9600),
+        data_bits: DataBits::Eight,
+        stop_bits: StopBits::One,
+        parity: None,
+    };
+
+    /// 19200 baud, 8 data bits, no parity, 1 stop bit
+    pub const _19200_8_N_1: UartConfig = UartConfig {
+        baudrate: Baud(19200),
+        data_bits: DataBits::Eight,
+        stop_bits: StopBits::One,
+        parity: None,
+    };
+
+    /// 38400 baud, 8 data bits, no parity, 1 stop bit
+    pub const _38400_8_N_1: UartConfig = UartConfig {
+        baudrate: Baud(38400),
+        data_bits: DataBits::Eight,
+        stop_bits: StopBits::One,
+        parity: None,
+    };
+
+    /// 57600 baud, 8 data bits, no parity, 1 stop bit
+    pub const _57600_8_N_1: UartConfig = UartConfig {
+        baudrate: Baud(57600),
+        data_bits: DataBits::Eight,
+        stop_bits: StopBits::One,
+        parity: None,
+    };
+
+    /// 115200 baud, 8 data bits, no parity, 1 stop bit
+    pub const _115200_8_N_1: UartConfig = UartConfig {
+        baudrate: Baud(115200),
+        data_bits: DataBits::Eight,
+        stop_bits: StopBits::One,
+        parity: None,
+    };
+}
+
+/// An UART Peripheral based on an underlying UART device.
+///
+/// `P` is the set of pins owned by the peripheral, see [`ValidUartPinout`].
+pub struct UartPeripheral<S: State, D: UartDevice, P: ValidUartPinout<D> = ()> {
+    device: D,
+    _state: S,
+    config: UartConfig,
+    effective_baudrate: Baud,
+    pins: P,
+    de_pin: Cell<Option<DynPin>>,
+}
+
+impl<S: State, D: UartDevice, P: ValidUartPinout<D>> UartPeripheral<S, D, P> {
+    fn transition<To: State>(self, state: To) -> UartPeripheral<To, D, P> {
+        UartPeripheral {
+            device: self.device,
+            config: self.config,
+            effective_baudrate: self.effective_baudrate,
+            pins: self.pins,
+            de_pin: self.de_pin,
+            _state: state,
+        }
+    }
+
+    /// Releases the underlying device and pins.
+    pub fn release(self) -> (D, P) {
+        (self.device, self.pins)
+    }
+}
+
+impl<S: State, D: UartDevice> UartPeripheral<S, D, ()> {
+    /// Releases the underlying device.
+    pub fn free(self) -> D {
+        self.device
+    }
+}
+
+impl<D: UartDevice> UartPeripheral<Disabled, D, ()> {
+    /// Enables the provided UART device with the given configuration.
+    ///
+    /// The TX and RX pins must be put in [`FunctionUart`](crate::gpio::FunctionUart)
+    /// mode separately. Use [`new`](UartPeripheral::new) to have the peripheral
+    /// own its pins instead.
+    pub fn enable(
+        device: D,
+        resets: &mut pac::RESETS,
+        config: UartConfig,
+        frequency: Hertz,
+    ) -> Result<UartPeripheral<Enabled, D>, Error> {
+        Self::new(device, (), config, resets, frequency)
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> UartPeripheral<Disabled, D, P> {
+    /// Enables the provided UART device with the given configuration, taking
+    /// ownership of its pins.
+    ///
+    /// `pins` is usually a `(tx, rx)` tuple of pins in
+    /// [`FunctionUart`](crate::gpio::FunctionUart) mode. `frequency` is the
+    /// frequency of the peripheral clock, which the baud rate is derived from.
+    pub fn new(
+        device: D,
+        pins: P,
+        config: UartConfig,
+        resets: &mut pac::RESETS,
+        frequency: Hertz,
+    ) -> Result<UartPeripheral<Enabled, D, P>, Error> {
+        device.reset_bring_up(resets);
+
+        let effective_baudrate = configure_baudrate(&device, &config.baudrate, &frequency)?;
+
+        device.uartlcr_h.write(|w| {
+            w.fen().set_bit();
+            set_format(w, &config.data_bits, &config.stop_bits, &config.parity);
+            w
+        });
+
+        // Enable the UART, both TX and RX, with flow control if the pins
+        // for it are owned
+        device.uartcr.write(|w| {
+            w.uarten().set_bit();
+            w.txe().set_bit();
+            w.rxe().set_bit();
+            w.ctsen().bit(P::CTS);
+            w.rtsen().bit(P::RTS);
+            w
+        });
+
+        device.uartdmacr.write(|w| {
+            w.txdmae().set_bit();
+            w.rxdmae().set_bit();
+            w
+        });
+
+        Ok(UartPeripheral {
+            device,
+            config,
+            effective_baudrate,
+            pins,
+            de_pin: Cell::new(None),
+            _state: Enabled,
+        })
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> UartPeripheral<Enabled, D, P> {
+    /// Disable this UART Peripheral, falling back to the Disabled state.
+    pub fn disable(self) -> UartPeripheral<Disabled, D, P> {
+        // Disable the UART, both TX and RX
+        self.device.uartcr.write(|w| {
+            w.uarten().clear_bit();
+            w.txe().clear_bit();
+            w.rxe().clear_bit();
+            w
+        });
+
+        self.transition(Disabled)
+    }
+
+    /// Split the peripheral into its receiving and transmitting halves.
+    ///
+    /// The RS-485 driver enable pin, if any, goes with the writer.
+    ///
+    /// This lets the receiving side live in an interrupt handler while the
+    /// transmitting side is used elsewhere. The halves can be put back
+    /// together with [`Reader::join`].
+    pub fn split(self) -> (Reader<D, P>, Writer<D, P>) {
+        let writer = Writer {
+            device: &*self.device as *const RegisterBlock,
+            de_pin: self.de_pin,
+            _device: PhantomData,
+        };
+        let reader = Reader {
+            device: self.device,
+            pins: self.pins,
+            config: self.config,
+            effective_baudrate: self.effective_baudrate,
+        };
+        (reader, writer)
+    }
+
+    pub(crate) fn transmit_flushed(&self) -> nb::Result<(), Infallible> {
+        transmit_flushed(&self.device)
+    }
+
+    /// Writes bytes to the UART.
+    /// This function writes as long as it can. As soon that the FIFO is full, if :
+    /// - 0 bytes were written, a WouldBlock Error is returned
+    /// - some bytes were written, it is deemed to be a success
+    /// Upon success, the remaining slice is returned.
+    pub fn write_raw<'d>(&self, data: &'d [u8]) -> nb::Result<&'d [u8], Infallible> {
+        write_raw(&self.device, data)
+    }
+
+    /// Reads bytes from the UART.
+    /// This function reads as long as it can. As soon that the FIFO is empty, if :
+    /// - 0 bytes were read, a WouldBlock Error is returned
+    /// - some bytes were read, it is deemed to be a success
+    /// Upon success, it will return how many bytes were read.
+    pub fn read_raw<'b>(&self, buffer: &'b mut [u8]) -> nb::Result<usize, ReadError<'b>> {
+        read_raw(&self.device, buffer)
+    }
+
+    /// Writes bytes to the UART.
+    /// This function blocks until the full buffer has been sent.
+    pub fn write_full_blocking(&self, data: &[u8]) {
+        with_de_pin(&self.de_pin, &self.device, || {
+            write_full_blocking(&self.device, data)
+        })
+    }
+
+    /// Receive a frame, returning its length once the line goes idle.
+    ///
+    /// This blocks until at least one byte has been received and the line
+    /// has then been idle for 32 bit periods, as signalled by the RX timeout.
+    /// See [`read_until_idle_nb`](Self::read_until_idle_nb) for details.
+    pub fn read_until_idle(&self, buffer: &mut [u8]) -> Result<usize, ReadErrorType> {
+        let mut received = 0;
+        nb::block!(read_until_idle(&self.device, buffer, &mut received))
+    }
+
+    /// Receive a frame, returning its length once the line goes idle.
+    ///
+    /// `received` tracks how much of `buffer` has been filled so far, and must
+    /// start at zero. Call this with the same buffer and counter until it
+    /// stops returning `WouldBlock`, which also resets the counter. It must
+    /// be called often enough that the RX FIFO does not overflow: the FIFO is
+    /// drained each time it reaches the RX [`FifoWatermark`].
+    ///
+    /// If the frame is longer than `buffer`, the rest of it is left in the
+    /// FIFO and a full buffer is returned.
+    pub fn read_until_idle_nb(
+        &self,
+        buffer: &mut [u8],
+        received: &mut usize,
+    ) -> nb::Result<usize, ReadErrorType> {
+        read_until_idle(&self.device, buffer, received)
+    }
+
+    /// Set a pin to drive the driver enable of an RS-485 transceiver.
+    ///
+    /// The pin is driven high for the duration of the blocking writes,
+    /// [`write_full_blocking`](Self::write_full_blocking) and
+    /// [`write_9bit`](Self::write_9bit), and low otherwise. The non-blocking
+    /// writes don't drive it.
+    pub fn set_rs485_de_pin<I: PinId>(&mut self, pin: Pin<I, PushPullOutput>) {
+        let mut pin: DynPin = pin.into();
+        let _ = pin.set_low();
+        self.de_pin.set(Some(pin));
+    }
+
+    /// Stop driving the RS-485 driver enable pin, and return it.
+    pub fn take_rs485_de_pin(&mut self) -> Option<DynPin> {
+        self.de_pin.take()
+    }
+
+    /// Write bytes with the 9th bit set to `address`, blocking until done.
+    ///
+    /// Multi-drop protocols use the 9th bit to mark address bytes. This is
+    /// implemented with stick parity, so the UART should be configured with
+    /// [`Parity::Space`], which is the setting restored afterwards and the
+    /// one needed by [`read_9bit`](Self::read_9bit).
+    pub fn write_9bit(&self, data: &[u8], address: bool) {
+        with_de_pin(&self.de_pin, &self.device, || {
+            write_9bit(&self.device, data, address)
+        })
+    }
+
+    /// Read a byte and its 9th bit.
+    ///
+    /// The UART must be configured with [`Parity::Space`], so that the 9th
+    /// bit being set shows up as a parity error.
+    pub fn read_9bit(&self) -> nb::Result<(u8, bool), ReadErrorType> {
+        read_9bit(&self.device)
+    }
+
+    /// Change the baud rate, returning the baud rate actually achieved.
+    ///
+    /// Any data in the TX FIFO is sent at the old baud rate first. The UART
+    /// is disabled while the divider is changed, so data arriving meanwhile
+    /// is lost. `frequency` is the frequency of the peripheral clock.
+    pub fn set_baudrate(&mut self, baudrate: Baud, frequency: Hertz) -> Result<Baud, Error> {
+        // Check the arguments before touching the hardware
+        calculate_baudrate_dividers(&baudrate, &frequency)?;
+
+        while self.device.uartfr.read().txfe().bit_is_clear()
+            || self.device.uartfr.read().busy().bit_is_set()
+        {}
+        let cr = self.device.uartcr.read().bits();
+        self.device.uartcr.modify(|_, w| w.uarten().clear_bit());
+
+        let result = configure_baudrate(&self.device, &baudrate, &frequency);
+
+        self.device.uartcr.write(|w| unsafe { w.bits(cr) });
+
+        let effective_baudrate = result?;
+        self.config.baudrate = baudrate;
+        self.effective_baudrate = effective_baudrate;
+        Ok(effective_baudrate)
+    }
+
+    /// The baud rate actually achieved, which can differ slightly from the
+    /// requested one because of the divider's resolution.
+    pub fn effective_baudrate(&self) -> Baud {
+        self.effective_baudrate
+    }
+
+    /// Reads bytes from the UART.
+    /// This function blocks until the full buffer has been received.
+    pub fn read_full_blocking(&self, buffer: &mut [u8]) -> Result<(), ReadErrorType> {
+        read_full_blocking(&self.device, buffer)
+    }
+
+    /// The DREQ to pace a DMA channel writing to [`data_address`](Self::data_address)
+    pub fn tx_dreq(&self) -> u8 {
+        D::TX_DREQ
+    }
+
+    /// The DREQ to pace a DMA channel reading from [`data_address`](Self::data_address)
+    pub fn rx_dreq(&self) -> u8 {
+        D::RX_DREQ
+    }
+
+    /// Address of the data register, which a DMA channel reads received
+    /// bytes from or writes bytes to send to
+    ///
+    /// Transfers must be byte sized. Errors flagged in the upper bits of the
+    /// register are not seen by byte reads, so check
+    /// [`UartInterrupt`]s to detect them.
+    pub fn data_address(&self) -> u32 {
+        self.device.uartdr.as_ptr() as u32
+    }
+
+    /// Start sending `buffer` through the DMA channel `ch`, paced by the TX
+    /// DREQ.
+    ///
+    /// The UART, the channel and the buffer are given back once the transfer
+    /// is done.
+    pub fn write_dma<CH, B>(self, ch: CH, buffer: B) -> single_buffer::Transfer<CH, B, Self>
+    where
+        CH: SingleChannel,
+        B: ReadBuffer<Word = u8>,
+    {
+        single_buffer::Config::new(ch, buffer, self).start()
+    }
+
+    /// Start receiving into `buffer` through the DMA channel `ch`, paced by
+    /// the RX DREQ, until it is full.
+    ///
+    /// The UART, the channel and the buffer are given back once the transfer
+    /// is done.
+    pub fn read_dma<CH, B>(self, ch: CH, buffer: B) -> single_buffer::Transfer<CH, Self, B>
+    where
+        CH: SingleChannel,
+        B: WriteBuffer<Word = u8>,
+    {
+        single_buffer::Config::new(ch, self, buffer).start()
+    }
+
+    /// Start transmitting a break, holding TX low.
+    ///
+    /// Any data still in the TX FIFO is sent first. The break continues until
+    /// [`lowlevel_break_stop`](Self::lowlevel_break_stop) is called.
+    pub fn lowlevel_break_start(&mut self) {
+        break_start(&self.device)
+    }
+
+    /// Stop transmitting a break.
+    pub fn lowlevel_break_stop(&mut self) {
+        break_stop(&self.device)
+    }
+
+    /// Transmit a break for `duration_us` microseconds.
+    ///
+    /// Any data still in the TX FIFO is sent first.
+    pub fn send_break<T: DelayUs<u32>>(&mut self, delay: &mut T, duration_us: u32) {
+        send_break(&self.device, delay, duration_us)
+    }
+
+    /// Enable the break interrupt, raised when a break is received.
+    pub fn enable_break_interrupt(&mut self) {
+        self.enable_interrupt(UartInterrupt::Break);
+    }
+
+    /// Disable the break interrupt.
+    pub fn disable_break_interrupt(&mut self) {
+        self.disable_interrupt(UartInterrupt::Break);
+    }
+
+    /// Enable or disable loopback, which internally connects TX to RX.
+    pub fn enable_loopback(&mut self, enable: bool) {
+        self.device.uartcr.modify(|_, w| w.lbe().bit(enable));
+    }
+
+    /// Check the UART by sending a pattern in loopback and reading it back.
+    ///
+    /// Anything waiting in the RX FIFO is discarded first, and the loopback
+    /// setting is restored afterwards. Returns whether the pattern was
+    /// received intact.
+    pub fn self_test(&mut self) -> bool {
+        const PATTERN: [u8; 4] = [0x55, 0xAA, 0x00, 0xFF];
+
+        let mask = match self.config.data_bits {
+            DataBits::Five => 0x1F,
+            DataBits::Six => 0x3F,
+            DataBits::Seven => 0x7F,
+            DataBits::Eight => 0xFF,
+        };
+
+        let loopback = self.device.uartcr.read().lbe().bit_is_set();
+        self.enable_loopback(true);
+
+        while uart_is_readable(&self.device) {
+            let _ = self.device.uartdr.read();
+        }
+
+        write_full_blocking(&self.device, &PATTERN);
+        // The last byte has been received by the time its stop bit is sent
+        while self.device.uartfr.read().busy().bit_is_set() {}
+
+        let mut received = [0; PATTERN.len()];
+        let passed = match read_raw(&self.device, &mut received) {
+            Ok(bytes_read) if bytes_read == PATTERN.len() => PATTERN
+                .iter()
+                .zip(received.iter())
+                .all(|(sent, received)| sent & mask == *received),
+            _ => false,
+        };
+
+        self.enable_loopback(loopback);
+        passed
+    }
+
+    /// Enable or disable hardware flow control.
+    ///
+    /// This is set up automatically when the peripheral owns CTS and RTS
+    /// pins. Use this when the pins are set up separately.
+    pub fn set_hardware_flow_control(&mut self, cts: bool, rts: bool) {
+        self.device
+            .uartcr
+            .modify(|_, w| w.ctsen().bit(cts).rtsen().bit(rts));
+    }
+
+    /// Set the FIFO levels at which the RX and TX interrupts are asserted.
+    pub fn set_fifo_watermarks(&mut self, rx: FifoWatermark, tx: FifoWatermark) {
+        self.device
+            .uartifls
+            .write(|w| unsafe { w.rxiflsel().bits(rx.bits()).txiflsel().bits(tx.bits()) });
+    }
+
+    /// Enable the receive interrupts.
+    ///
+    /// This enables both the RX watermark and the RX timeout interrupts, so a
+    /// message shorter than the watermark still raises an interrupt.
+    pub fn enable_rx_interrupt(&mut self) {
+        self.enable_interrupt(UartInterrupt::Rx);
+        self.enable_interrupt(UartInterrupt::RxTimeout);
+    }
+
+    /// Disable the receive interrupts.
+    pub fn disable_rx_interrupt(&mut self) {
+        self.disable_interrupt(UartInterrupt::Rx);
+        self.disable_interrupt(UartInterrupt::RxTimeout);
+    }
+
+    /// Enable the transmit interrupt.
+    pub fn enable_tx_interrupt(&mut self) {
+        self.enable_interrupt(UartInterrupt::Tx);
+    }
+
+    /// Disable the transmit interrupt.
+    pub fn disable_tx_interrupt(&mut self) {
+        self.disable_interrupt(UartInterrupt::Tx);
+    }
+
+    /// Enable an interrupt source.
+    pub fn enable_interrupt(&mut self, interrupt: UartInterrupt) {
+        enable_interrupt(&self.device, interrupt);
+    }
+
+    /// Disable an interrupt source.
+    pub fn disable_interrupt(&mut self, interrupt: UartInterrupt) {
+        disable_interrupt(&self.device, interrupt);
+    }
+
+    /// Is an enabled interrupt source asserting the UART interrupt?
+    pub fn is_interrupt_pending(&self, interrupt: UartInterrupt) -> bool {
+        self.device.uartmis.read().bits() & interrupt.mask() != 0
+    }
+
+    /// Clear an interrupt source.
+    ///
+    /// The RX and TX interrupts are also cleared by reading or writing the
+    /// FIFO past the watermark.
+    pub fn clear_interrupt(&mut self, interrupt: UartInterrupt) {
+        clear_interrupt(&self.device, interrupt);
+    }
+}
+
+fn transmit_flushed(device: &RegisterBlock) -> nb::Result<(), Infallible> {
+    if device.uartfr.read().txfe().bit_is_set() {
+        Ok(())
+    } else {
+        Err(WouldBlock)
+    }
+}
+
+fn uart_is_writable(device: &RegisterBlock) -> bool {
+    device.uartfr.read().txff().bit_is_clear()
+}
+
+fn uart_is_readable(device: &RegisterBlock) -> bool {
+    device.uartfr.read().rxfe().bit_is_clear()
+}
+
+fn write_raw<'d>(device: &RegisterBlock, data: &'d [u8]) -> nb::Result<&'d [u8], Infallible> {
+    let mut bytes_written = 0;
+
+    for c in data {
+        if !uart_is_writable(device) {
+            if bytes_written == 0 {
+                return Err(WouldBlock);
+            } else {
+                return Ok(&data[bytes_written..]);
+            }
+        }
+
+        device.uartdr.write(|w| unsafe {
+            w.data().bits(*c);
+            w
+        });
+
+        bytes_written += 1;
+    }
+    Ok(&data[bytes_written..])
+}
+
+fn read_raw<'b>(device: &RegisterBlock, buffer: &'b mut [u8]) -> nb::Result<usize, ReadError<'b>> {
+    let mut bytes_read = 0;
+
+    Ok(loop {
+        if !uart_is_readable(device) {
+            if bytes_read == 0 {
+                return Err(WouldBlock);
+            } else {
+                break bytes_read;
+            }
+        }
+
+        if bytes_read < buffer.len() {
+            let mut error: Option<ReadErrorType> = None;
+
+            let read = device.uartdr.read();
+
+            if read.oe().bit_is_set() {
+                error = Some(ReadErrorType::Overrun);
+            }
+
+            if read.be().bit_is_set() {
+                error = Some(ReadErrorType::Break);
+            }
+
+            if read.pe().bit_is_set() {
+                error = Some(ReadErrorType::Parity);
+            }
+
+            if read.fe().bit_is_set() {
+                error = Some(ReadErrorType::Framing);
+            }
+
+            if let Some(err_type) = error {
+                return Err(Other(ReadError {
+                    err_type,
+                    discared: buffer,
+                }));
+            }
+
+            buffer[bytes_read] = read.data().bits();
+            bytes_read += 1;
+        } else {
+            break bytes_read;
+        }
+    })
+}
+
+fn write_full_blocking(device: &RegisterBlock, data: &[u8]) {
+    let mut temp = data;
+
+    while !temp.is_empty() {
+        temp = match write_raw(device, temp) {
+            Ok(remaining) => remaining,
+            Err(WouldBlock) => continue,
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+fn read_full_blocking(device: &RegisterBlock, buffer: &mut [u8]) -> Result<(), ReadErrorType> {
+    let mut offset = 0;
+
+    while offset != buffer.len() {
+        offset += match read_raw(device, &mut buffer[offset..]) {
+            Ok(bytes_read) => bytes_read,
+            Err(e) => match e {
+                Other(inner) => return Err(inner.err_type),
+                WouldBlock => continue,
+            },
+        }
+    }
+
+    Ok(())
+}
+
+fn read_until_idle(
+    device: &RegisterBlock,
+    buffer: &mut [u8],
+    received: &mut usize,
+) -> nb::Result<usize, ReadErrorType> {
+    let ris = device.uartris.read();
+    let result = if ris.rtris().bit_is_set() {
+        // The line has been idle with data in the FIFO, so the rest of the
+        // frame is in there
+        let end = buffer.len();
+        read_into(device, buffer, received, end).map(|()| {
+            clear_interrupt(device, UartInterrupt::RxTimeout);
+            Some(*received)
+        })
+    } else if ris.rxris().bit_is_set() {
+        // Make room, but leave a byte behind as the timeout only runs while
+        // the FIFO holds data. The RX interrupt being set guarantees there
+        // are at least as many bytes as the watermark.
+        let watermark = match device.uartifls.read().rxiflsel().bits() {
+            0b000 => 4,
+            0b001 => 8,
+            0b010 => 16,
+            0b011 => 24,
+            _ => 28,
+        };
+        let end = core::cmp::min(*received + watermark - 1, buffer.len());
+        read_into(device, buffer, received, end).map(|()| None)
+    } else {
+        Ok(None)
+    };
+
+    match result {
+        Ok(Some(len)) => {
+            *received = 0;
+            Ok(len)
+        }
+        Ok(None) => Err(WouldBlock),
+        Err(e) => {
+            *received = 0;
+            Err(Other(e))
+        }
+    }
+}
+
+/// Read into `buffer[*received..end]` whatever is in the RX FIFO.
+fn read_into(
+    device: &RegisterBlock,
+    buffer: &mut [u8],
+    received: &mut usize,
+    end: usize,
+) -> Result<(), ReadErrorType> {
+    match read_raw(device, &mut buffer[*received..end]) {
+        Ok(bytes_read) => *received += bytes_read,
+        Err(WouldBlock) => {}
+        Err(Other(e)) => return Err(e.err_type),
+    }
+    Ok(())
+}
+
+/// Run `f`, driving the RS-485 driver enable pin, if any, high until the
+/// transmission is complete.
+fn with_de_pin<F: FnOnce()>(de_pin: &Cell<Option<DynPin>>, device: &RegisterBlock, f: F) {
+    match de_pin.take() {
+        Some(mut pin) => {
+            let _ = pin.set_high();
+            f();
+            while device.uartfr.read().busy().bit_is_set() {}
+            let _ = pin.set_low();
+            de_pin.set(Some(pin));
+        }
+        None => f(),
+    }
+}
+
+fn write_9bit(device: &RegisterBlock, data: &[u8], address: bool) {
+    // The parity setting must not change while a character is in flight
+    while device.uartfr.read().busy().bit_is_set() {}
+    // Stick parity sends the inverse of EPS as the parity bit
+    device
+        .uartlcr_h
+        .modify(|_, w| w.pen().set_bit().sps().set_bit().eps().bit(!address));
+    write_full_blocking(device, data);
+    while device.uartfr.read().busy().bit_is_set() {}
+    device.uartlcr_h.modify(|_, w| w.eps().set_bit());
+}
+
+fn read_9bit(device: &RegisterBlock) -> nb::Result<(u8, bool), ReadErrorType> {
+    if !uart_is_readable(device) {
+        return Err(WouldBlock);
+    }
+
+    let read = device.uartdr.read();
+    if read.oe().bit_is_set() {
+        Err(Other(ReadErrorType::Overrun))
+    } else if read.be().bit_is_set() {
+        Err(Other(ReadErrorType::Break))
+    } else if read.fe().bit_is_set() {
+        Err(Other(ReadErrorType::Framing))
+    } else {
+        Ok((read.data().bits(), read.pe().bit_is_set()))
+    }
+}
+
+fn break_start(device: &RegisterBlock) {
+    // Let the last character go out first, or it would be cut short
+    while device.uartfr.read().busy().bit_is_set() {}
+    device.uartlcr_h.modify(|_, w| w.brk().set_bit());
+}
+
+fn break_stop(device: &RegisterBlock) {
+    device.uartlcr_h.modify(|_, w| w.brk().clear_bit());
+}
+
+fn send_break<T: DelayUs<u32>>(device: &RegisterBlock, delay: &mut T, duration_us: u32) {
+    break_start(device);
+    delay.delay_us(duration_us);
+    break_stop(device);
+}
+
+// The interrupt mask is shared by the reader and the writer, which may run in different contexts,
+// so it is only updated through the atomic set and clear aliases.
+fn enable_interrupt(device: &RegisterBlock, interrupt: UartInterrupt) {
+    unsafe { write_bitmask_set(device.uartimsc.as_ptr(), interrupt.mask()) }
+}
+
+fn disable_interrupt(device: &RegisterBlock, interrupt: UartInterrupt) {
+    unsafe { write_bitmask_clear(device.uartimsc.as_ptr(), interrupt.mask()) }
+}
+
+fn clear_interrupt(device: &RegisterBlock, interrupt: UartInterrupt) {
+    device
+        .uarticr
+        .write(|w| unsafe { w.bits(interrupt.mask()) });
+}
+
+/// The PL011 (PrimeCell UART) supports a fractional baud rate divider
+/// From the wanted baudrate, we calculate the divider's two parts: integer and fractional parts.
+/// Code inspired from the C SDK.
+fn calculate_baudrate_dividers(
+    wanted_baudrate: &Baud,
+    frequency: &Hertz,
+) -> Result<(u16, u16), Error> {
+    // See Chapter 4, Section 2 §7.1 from the datasheet for an explanation of how baudrate is
+    // calculated
+    let baudrate_div = frequency
+        .integer()
+        .checked_mul(8)
+        .and_then(|r| r.checked_div(wanted_baudrate.integer()))
+        .ok_or(Error::BadArgument)?;
+
+    Ok(match (baudrate_div >> 7, ((baudrate_div & 0x7F) + 1) / 2) {
+        (0, _) => (1, 0),
+
+        (int_part, _) if int_part >= 65535 => (65535, 0),
+
+        (int_part, frac_part) => (int_part as u16, frac_part as u16),
+    })
+}
+
+/// Baudrate configuration. Code loosely inspired from the C SDK.
+fn configure_baudrate(
+    device: &RegisterBlock,
+    wanted_baudrate: &Baud,
+    frequency: &Hertz,
+) -> Result<Baud, Error> {
+    let (baud_div_int, baud_div_frac) = calculate_baudrate_dividers(wanted_baudrate, frequency)?;
+
+    // First we load the integer part of the divider.
+    device.uartibrd.write(|w| unsafe {
+        w.baud_divint().bits(baud_div_int as u16);
+        w
+    });
+
+    // Then we load the fractional part of the divider.
+    device.uartfbrd.write(|w| unsafe {
+        w.baud_divfrac().bits(baud_div_frac as u8);
+        w
+    });
+
+    // PL011 needs a (dummy) line control register write to latch in the
+    // divisors. We don't want to actually change LCR contents here.
+    device.uartlcr_h.modify(|_, w| w);
+
+    Ok(Baud(
+        (4 * frequency.integer()) / (64 * baud_div_int + baud_div_frac) as u32,
+    ))
+}
+
+/// Format configuration. Code loosely inspired from the C SDK.
+fn set_format<'w>(
+    w: &'w mut UART_LCR_H_Writer,
+    data_bits: &DataBits,
+    stop_bits: &StopBits,
+    parity: &Option<Parity>,
+) -> &'w mut UART_LCR_H_Writer {
+    match parity {
+        Some(p) => {
+            w.pen().set_bit();
+            match p {
+                Parity::Odd => w.sps().clear_bit().eps().clear_bit(),
+                Parity::Even => w.sps().clear_bit().eps().set_bit(),
+                Parity::Mark => w.sps().set_bit().eps().clear_bit(),
+                Parity::Space => w.sps().set_bit().eps().set_bit(),
+            };
+        }
+        None => {
+            w.pen().bit(false);
+            w.sps().clear_bit();
+        }
+    };
+
+    unsafe {
+        w.wlen().bits(match data_bits {
+            DataBits::Five => 0b00,
+            DataBits::Six => 0b01,
+            DataBits::Seven => 0b10,
+            DataBits::Eight => 0b11,
+        })
+    };
+
+    match stop_bits {
+        StopBits::One => w.stp2().clear_bit(),
+        StopBits::Two => w.stp2().set_bit(),
+    };
+
+    w
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> Read<u8> for UartPeripheral<Enabled, D, P> {
+    type Error = ReadErrorType;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let byte: &mut [u8] = &mut [0; 1];
+
+        match self.read_raw(byte) {
+            Ok(_) => Ok(byte[0]),
+            Err(e) => match e {
+                Other(inner) => Err(Other(inner.err_type)),
+                WouldBlock => Err(WouldBlock),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<D: UartDevice, P: ValidUartPinout<D>> eh1::Read<u8> for UartPeripheral<Enabled, D, P> {
+    type Error = ReadErrorType;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let byte: &mut [u8] = &mut [0; 1];
+
+        match self.read_raw(byte) {
+            Ok(_) => Ok(byte[0]),
+            Err(e) => match e {
+                Other(inner) => Err(Other(inner.err_type)),
+                WouldBlock => Err(WouldBlock),
+            },
+        }
+    }
+}
+
+/// Same as core::convert::Infallible, but implementing spi::Error
+///
+/// For eh 1.0.0-alpha.6, Infallible doesn't implement spi::Error,
+/// so use a locally defined type instead.
+/// This should be removed with the next release of e-h.
+/// (https://github.com/rust-embedded/embedded-hal/pull/328)
+#[cfg(feature = "eh1_0_alpha")]
+pub enum SerialInfallible {}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl core::fmt::Debug for SerialInfallible {
+    fn fmt(&self, _f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl eh1_0_alpha::serial::Error for SerialInfallible {
+    fn kind(&self) -> eh1_0_alpha::serial::ErrorKind {
+        match *self {}
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> Write<u8> for UartPeripheral<Enabled, D, P> {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        if self.write_raw(&[word]).is_err() {
+            Err(WouldBlock)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.transmit_flushed()
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<D: UartDevice, P: ValidUartPinout<D>> eh1::Write<u8> for UartPeripheral<Enabled, D, P> {
+    type Error = SerialInfallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        if self.write_raw(&[word]).is_err() {
+            Err(WouldBlock)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.transmit_flushed().map_err(|e| match e {
+            WouldBlock => WouldBlock,
+            Other(v) => match v {},
+        })
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_hal::blocking::serial::write::Default<u8>
+    for UartPeripheral<Enabled, D, P>
+{
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> fmt::Write for UartPeripheral<Enabled, D, P> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.bytes()
+            .try_for_each(|c| nb::block!(self.write(c)))
+            .map_err(|_| fmt::Error)
+    }
+}
+
+// Safety: The data register stays valid for as long as the UART is held by the transfer.
+unsafe impl<D: UartDevice, P: ValidUartPinout<D>> ReadTarget for UartPeripheral<Enabled, D, P> {
+    type ReceivedWord = u8;
+
+    fn rx_treq(&self) -> Option<u8> {
+        Some(self.rx_dreq())
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.data_address(), u32::MAX)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
+}
+
+// Safety: The data register stays valid for as long as the UART is held by the transfer.
+unsafe impl<D: UartDevice, P: ValidUartPinout<D>> WriteTarget for UartPeripheral<Enabled, D, P> {
+    type TransmittedWord = u8;
+
+    fn tx_treq(&self) -> Option<u8> {
+        Some(self.tx_dreq())
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        (self.data_address(), u32::MAX)
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,124 @@
+//! Pins usable by the UART peripherals
+use crate::gpio::pin::bank0::{
+    BankPinId, Gpio0, Gpio1, Gpio10, Gpio11, Gpio12, Gpio13, Gpio14, Gpio15, Gpio16, Gpio17,
+    Gpio18, Gpio19, Gpio2, Gpio20, Gpio21, Gpio22, Gpio23, Gpio24, Gpio25, Gpio26, Gpio27, Gpio28,
+    Gpio29, Gpio3, Gpio4, Gpio5, Gpio6, Gpio7, Gpio8, Gpio9,
+};
+use crate::gpio::pin::{FunctionUart, Pin, PinId};
+use crate::typelevel::Sealed;
+use pac::{UART0, UART1};
+
+use super::UartDevice;
+
+/// TX pin
+pub trait TxPin<D: UartDevice>: Sealed {}
+
+/// RX pin
+pub trait RxPin<D: UartDevice>: Sealed {}
+
+/// CTS pin, an input which gates transmission when flow control is enabled
+pub trait CtsPin<D: UartDevice>: Sealed {}
+
+/// RTS pin, an output which signals that the RX FIFO has room when flow
+/// control is enabled
+pub trait RtsPin<D: UartDevice>: Sealed {}
+
+impl TxPin<UART0> for Gpio0 {}
+impl RxPin<UART0> for Gpio1 {}
+impl TxPin<UART1> for Gpio4 {}
+impl RxPin<UART1> for Gpio5 {}
+impl TxPin<UART1> for Gpio8 {}
+impl RxPin<UART1> for Gpio9 {}
+impl TxPin<UART0> for Gpio12 {}
+impl RxPin<UART0> for Gpio13 {}
+impl TxPin<UART0> for Gpio16 {}
+impl RxPin<UART0> for Gpio17 {}
+impl TxPin<UART1> for Gpio20 {}
+impl RxPin<UART1> for Gpio21 {}
+impl TxPin<UART1> for Gpio24 {}
+impl RxPin<UART1> for Gpio25 {}
+impl TxPin<UART0> for Gpio28 {}
+impl RxPin<UART0> for Gpio29 {}
+
+impl CtsPin<UART0> for Gpio2 {}
+impl RtsPin<UART0> for Gpio3 {}
+impl CtsPin<UART1> for Gpio6 {}
+impl RtsPin<UART1> for Gpio7 {}
+impl CtsPin<UART1> for Gpio10 {}
+impl RtsPin<UART1> for Gpio11 {}
+impl CtsPin<UART0> for Gpio14 {}
+impl RtsPin<UART0> for Gpio15 {}
+impl CtsPin<UART0> for Gpio18 {}
+impl RtsPin<UART0> for Gpio19 {}
+impl CtsPin<UART1> for Gpio22 {}
+impl RtsPin<UART1> for Gpio23 {}
+impl CtsPin<UART1> for Gpio26 {}
+impl RtsPin<UART1> for Gpio27 {}
+
+/// A set of pins a [`UartPeripheral`](super::UartPeripheral) can own
+///
+/// This is implemented for `()`, for when the pins are set up by the caller,
+/// for a `(tx, rx)` tuple of pins in [`FunctionUart`] mode, and for a
+/// `(tx, rx, cts, rts)` tuple, which enables hardware flow control.
+pub trait ValidUartPinout<D: UartDevice>: Sealed {
+    /// Whether the pinout includes a CTS pin
+    const CTS: bool;
+    /// Whether the pinout includes an RTS pin
+    const RTS: bool;
+}
+
+impl Sealed for () {}
+impl<D: UartDevice> ValidUartPinout<D> for () {
+    const CTS: bool = false;
+    const RTS: bool = false;
+}
+
+impl<TX, RX> Sealed for (Pin<TX, FunctionUart>, Pin<RX, FunctionUart>)
+where
+    TX: PinId + BankPinId,
+    RX: PinId + BankPinId,
+{
+}
+
+impl<D, TX, RX> ValidUartPinout<D> for (Pin<TX, FunctionUart>, Pin<RX, FunctionUart>)
+where
+    D: UartDevice,
+    TX: PinId + BankPinId + TxPin<D>,
+    RX: PinId + BankPinId + RxPin<D>,
+{
+    const CTS: bool = false;
+    const RTS: bool = false;
+}
+
+impl<TX, RX, CTS, RTS> Sealed
+    for (
+        Pin<TX, FunctionUart>,
+        Pin<RX, FunctionUart>,
+        Pin<CTS, FunctionUart>,
+        Pin<RTS, FunctionUart>,
+    )
+where
+    TX: PinId + BankPinId,
+    RX: PinId + BankPinId,
+    CTS: PinId + BankPinId,
+    RTS: PinId + BankPinId,
+{
+}
+
+impl<D, TX, RX, CTS, RTS> ValidUartPinout<D>
+    for (
+        Pin<TX, FunctionUart>,
+        Pin<RX, FunctionUart>,
+        Pin<CTS, FunctionUart>,
+        Pin<RTS, FunctionUart>,
+    )
+where
+    D: UartDevice,
+    TX: PinId + BankPinId + TxPin<D>,
+    RX: PinId + BankPinId + RxPin<D>,
+    CTS: PinId + BankPinId + CtsPin<D>,
+    RTS: PinId + BankPinId + RtsPin<D>,
+{
+    const CTS: bool = true;
+    const RTS: bool = true;
+}
@@ -0,0 +1,188 @@
+//! Receiving half of a split UART
+use crate::dma::{single_buffer, ReadTarget, SingleChannel};
+use embedded_dma::WriteBuffer;
+use embedded_hal::serial::Read;
+use embedded_time::rate::Baud;
+use nb::Error::{Other, WouldBlock};
+
+#[cfg(feature = "eh1_0_alpha")]
+use eh1_0_alpha::serial::nb as eh1;
+
+use super::{
+    disable_interrupt, enable_interrupt, read_9bit, read_full_blocking, read_raw, read_until_idle,
+    Enabled, ReadError, ReadErrorType, UartConfig, UartDevice, UartInterrupt, UartPeripheral,
+    ValidUartPinout, Writer,
+};
+
+/// The receiving half of a [`UartPeripheral`], created by
+/// [`split`](UartPeripheral::split)
+///
+/// The reader owns the UART device and pins, so the configuration is kept
+/// here until the two halves are [joined](Reader::join) again.
+pub struct Reader<D: UartDevice, P: ValidUartPinout<D>> {
+    pub(super) device: D,
+    pub(super) pins: P,
+    pub(super) config: UartConfig,
+    pub(super) effective_baudrate: Baud,
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> Reader<D, P> {
+    /// Reads bytes from the UART.
+    /// This function reads as long as it can. As soon that the FIFO is empty, if :
+    /// - 0 bytes were read, a WouldBlock Error is returned
+    /// - some bytes were read, it is deemed to be a success
+    /// Upon success, it will return how many bytes were read.
+    pub fn read_raw<'b>(&self, buffer: &'b mut [u8]) -> nb::Result<usize, ReadError<'b>> {
+        read_raw(&self.device, buffer)
+    }
+
+    /// Reads bytes from the UART.
+    /// This function blocks until the full buffer has been received.
+    pub fn read_full_blocking(&self, buffer: &mut [u8]) -> Result<(), ReadErrorType> {
+        read_full_blocking(&self.device, buffer)
+    }
+
+    /// Receive a frame, returning its length once the line goes idle.
+    ///
+    /// This blocks until at least one byte has been received and the line
+    /// has then been idle for 32 bit periods, as signalled by the RX timeout.
+    /// See [`read_until_idle_nb`](Self::read_until_idle_nb) for details.
+    pub fn read_until_idle(&self, buffer: &mut [u8]) -> Result<usize, ReadErrorType> {
+        let mut received = 0;
+        nb::block!(read_until_idle(&self.device, buffer, &mut received))
+    }
+
+    /// Receive a frame, returning its length once the line goes idle.
+    ///
+    /// `received` tracks how much of `buffer` has been filled so far, and must
+    /// start at zero. Call this with the same buffer and counter until it
+    /// stops returning `WouldBlock`, which also resets the counter. It must
+    /// be called often enough that the RX FIFO does not overflow: the FIFO is
+    /// drained each time it reaches the RX [`FifoWatermark`](super::FifoWatermark).
+    ///
+    /// If the frame is longer than `buffer`, the rest of it is left in the
+    /// FIFO and a full buffer is returned.
+    pub fn read_until_idle_nb(
+        &self,
+        buffer: &mut [u8],
+        received: &mut usize,
+    ) -> nb::Result<usize, ReadErrorType> {
+        read_until_idle(&self.device, buffer, received)
+    }
+
+    /// Enable the receive interrupts.
+    ///
+    /// This enables both the RX watermark and the RX timeout interrupts, so a
+    /// message shorter than the watermark still raises an interrupt.
+    pub fn enable_rx_interrupt(&mut self) {
+        enable_interrupt(&self.device, UartInterrupt::Rx);
+        enable_interrupt(&self.device, UartInterrupt::RxTimeout);
+    }
+
+    /// Disable the receive interrupts.
+    pub fn disable_rx_interrupt(&mut self) {
+        disable_interrupt(&self.device, UartInterrupt::Rx);
+        disable_interrupt(&self.device, UartInterrupt::RxTimeout);
+    }
+
+    /// The DREQ to pace a DMA channel reading from [`data_address`](Self::data_address)
+    pub fn rx_dreq(&self) -> u8 {
+        D::RX_DREQ
+    }
+
+    /// Address of the data register, which a DMA channel reads received
+    /// bytes from
+    pub fn data_address(&self) -> u32 {
+        self.device.uartdr.as_ptr() as u32
+    }
+
+    /// Start receiving into `buffer` through the DMA channel `ch`, paced by
+    /// the RX DREQ, until it is full.
+    pub fn read_dma<CH, B>(self, ch: CH, buffer: B) -> single_buffer::Transfer<CH, Self, B>
+    where
+        CH: SingleChannel,
+        B: WriteBuffer<Word = u8>,
+    {
+        single_buffer::Config::new(ch, self, buffer).start()
+    }
+
+    /// Read a byte and its 9th bit.
+    ///
+    /// The UART must be configured with [`Parity::Space`](super::Parity::Space),
+    /// so that the 9th bit being set shows up as a parity error.
+    pub fn read_9bit(&self) -> nb::Result<(u8, bool), ReadErrorType> {
+        read_9bit(&self.device)
+    }
+
+    /// Enable the break interrupt, raised when a break is received.
+    pub fn enable_break_interrupt(&mut self) {
+        enable_interrupt(&self.device, UartInterrupt::Break);
+    }
+
+    /// Disable the break interrupt.
+    pub fn disable_break_interrupt(&mut self) {
+        disable_interrupt(&self.device, UartInterrupt::Break);
+    }
+
+    /// Join the reader and the writer back into a [`UartPeripheral`].
+    pub fn join(self, writer: Writer<D, P>) -> UartPeripheral<Enabled, D, P> {
+        UartPeripheral {
+            device: self.device,
+            config: self.config,
+            effective_baudrate: self.effective_baudrate,
+            pins: self.pins,
+            de_pin: writer.de_pin,
+            _state: Enabled,
+        }
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> Read<u8> for Reader<D, P> {
+    type Error = ReadErrorType;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let byte: &mut [u8] = &mut [0; 1];
+
+        match self.read_raw(byte) {
+            Ok(_) => Ok(byte[0]),
+            Err(e) => match e {
+                Other(inner) => Err(Other(inner.err_type)),
+                WouldBlock => Err(WouldBlock),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<D: UartDevice, P: ValidUartPinout<D>> eh1::Read<u8> for Reader<D, P> {
+    type Error = ReadErrorType;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let byte: &mut [u8] = &mut [0; 1];
+
+        match self.read_raw(byte) {
+            Ok(_) => Ok(byte[0]),
+            Err(e) => match e {
+                Other(inner) => Err(Other(inner.err_type)),
+                WouldBlock => Err(WouldBlock),
+            },
+        }
+    }
+}
+
+// Safety: The data register stays valid for as long as the UART is held by the transfer.
+unsafe impl<D: UartDevice, P: ValidUartPinout<D>> ReadTarget for Reader<D, P> {
+    type ReceivedWord = u8;
+
+    fn rx_treq(&self) -> Option<u8> {
+        Some(self.rx_dreq())
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.data_address(), u32::MAX)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,191 @@
+//! Transmitting half of a split UART
+use core::cell::Cell;
+use core::convert::Infallible;
+use core::fmt;
+use core::marker::PhantomData;
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::serial::Write;
+
+use crate::dma::{single_buffer, SingleChannel, WriteTarget};
+use crate::gpio::DynPin;
+use embedded_dma::ReadBuffer;
+use nb::Error::WouldBlock;
+
+#[cfg(feature = "eh1_0_alpha")]
+use eh1_0_alpha::serial::nb as eh1;
+
+#[cfg(feature = "eh1_0_alpha")]
+use super::SerialInfallible;
+use super::{
+    break_start, break_stop, disable_interrupt, enable_interrupt, send_break, transmit_flushed,
+    with_de_pin, write_9bit, write_full_blocking, write_raw, RegisterBlock, UartDevice,
+    UartInterrupt, ValidUartPinout,
+};
+
+/// The transmitting half of a [`UartPeripheral`](super::UartPeripheral),
+/// created by [`split`](super::UartPeripheral::split)
+pub struct Writer<D: UartDevice, P: ValidUartPinout<D>> {
+    pub(super) device: *const RegisterBlock,
+    pub(super) de_pin: Cell<Option<DynPin>>,
+    pub(super) _device: PhantomData<(D, P)>,
+}
+
+// Safe because the registers the writer shares with the reader are only
+// updated atomically: the interrupt mask through its atomic set and clear
+// aliases, and the interrupt clear register, whose bits are independent. The
+// writer is the only half modifying the line control register, and the data
+// register has separate TX and RX FIFOs behind it.
+unsafe impl<D: UartDevice + Send, P: ValidUartPinout<D> + Send> Send for Writer<D, P> {}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> Writer<D, P> {
+    pub(super) fn device(&self) -> &RegisterBlock {
+        // Safe because the pointer was made from a `UartDevice`, which points
+        // at a peripheral that lives forever.
+        unsafe { &*self.device }
+    }
+
+    /// Writes bytes to the UART.
+    /// This function writes as long as it can. As soon that the FIFO is full, if :
+    /// - 0 bytes were written, a WouldBlock Error is returned
+    /// - some bytes were written, it is deemed to be a success
+    /// Upon success, the remaining slice is returned.
+    pub fn write_raw<'d>(&self, data: &'d [u8]) -> nb::Result<&'d [u8], Infallible> {
+        write_raw(self.device(), data)
+    }
+
+    /// Writes bytes to the UART.
+    /// This function blocks until the full buffer has been sent.
+    pub fn write_full_blocking(&self, data: &[u8]) {
+        with_de_pin(&self.de_pin, self.device(), || {
+            write_full_blocking(self.device(), data)
+        })
+    }
+
+    /// Write bytes with the 9th bit set to `address`, blocking until done.
+    ///
+    /// See [`UartPeripheral::write_9bit`](super::UartPeripheral::write_9bit).
+    pub fn write_9bit(&self, data: &[u8], address: bool) {
+        with_de_pin(&self.de_pin, self.device(), || {
+            write_9bit(self.device(), data, address)
+        })
+    }
+
+    /// The DREQ to pace a DMA channel writing to [`data_address`](Self::data_address)
+    pub fn tx_dreq(&self) -> u8 {
+        D::TX_DREQ
+    }
+
+    /// Address of the data register, which a DMA channel writes bytes to
+    /// send to
+    pub fn data_address(&self) -> u32 {
+        self.device().uartdr.as_ptr() as u32
+    }
+
+    /// Start sending `buffer` through the DMA channel `ch`, paced by the TX
+    /// DREQ.
+    ///
+    /// Unlike [`write_full_blocking`](Self::write_full_blocking), this does
+    /// not drive the RS-485 driver enable pin.
+    pub fn write_dma<CH, B>(self, ch: CH, buffer: B) -> single_buffer::Transfer<CH, B, Self>
+    where
+        CH: SingleChannel,
+        B: ReadBuffer<Word = u8>,
+    {
+        single_buffer::Config::new(ch, buffer, self).start()
+    }
+
+    /// Start transmitting a break, holding TX low.
+    ///
+    /// Any data still in the TX FIFO is sent first. The break continues until
+    /// [`lowlevel_break_stop`](Self::lowlevel_break_stop) is called.
+    pub fn lowlevel_break_start(&mut self) {
+        break_start(self.device())
+    }
+
+    /// Stop transmitting a break.
+    pub fn lowlevel_break_stop(&mut self) {
+        break_stop(self.device())
+    }
+
+    /// Transmit a break for `duration_us` microseconds.
+    ///
+    /// Any data still in the TX FIFO is sent first.
+    pub fn send_break<T: DelayUs<u32>>(&mut self, delay: &mut T, duration_us: u32) {
+        send_break(self.device(), delay, duration_us)
+    }
+
+    /// Enable the transmit interrupt.
+    pub fn enable_tx_interrupt(&mut self) {
+        enable_interrupt(self.device(), UartInterrupt::Tx);
+    }
+
+    /// Disable the transmit interrupt.
+    pub fn disable_tx_interrupt(&mut self) {
+        disable_interrupt(self.device(), UartInterrupt::Tx);
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> Write<u8> for Writer<D, P> {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        if self.write_raw(&[word]).is_err() {
+            Err(WouldBlock)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        transmit_flushed(self.device())
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl<D: UartDevice, P: ValidUartPinout<D>> eh1::Write<u8> for Writer<D, P> {
+    type Error = SerialInfallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        if self.write_raw(&[word]).is_err() {
+            Err(WouldBlock)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        transmit_flushed(self.device()).map_err(|e| match e {
+            WouldBlock => WouldBlock,
+            nb::Error::Other(v) => match v {},
+        })
+    }
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_hal::blocking::serial::write::Default<u8>
+    for Writer<D, P>
+{
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> fmt::Write for Writer<D, P> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_full_blocking(s.as_bytes());
+        Ok(())
+    }
+}
+
+// Safety: The data register stays valid for as long as the UART is held by the transfer.
+unsafe impl<D: UartDevice, P: ValidUartPinout<D>> WriteTarget for Writer<D, P> {
+    type TransmittedWord = u8;
+
+    fn tx_treq(&self) -> Option<u8> {
+        Some(self.tx_dreq())
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        (self.data_address(), u32::MAX)
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,670 @@
+//! USB host
+//!
+//! [`UsbHost`] runs the USB controller as a host, to which a keyboard, a MIDI controller or any
+//! other USB device can be attached, without a hub.
+//!
+//! The controller generates the Start Of Frame packets, and runs one transfer at a time on its
+//! EPX endpoint, which takes the control and bulk transfers. Interrupt IN endpoints are polled
+//! by the controller itself at their interval, through [`InterruptPipe`]s.
+//!
+//! Enumeration is up to the firmware: wait for a device to [connect](UsbHost::speed),
+//! [reset](UsbHost::reset_bus) it, then read its descriptors and set its address and
+//! configuration with control transfers.
+//!
+//! ## Usage
+//! ```no_run
+//! use rp2040_hal::{clocks::init_clocks_and_plls, pac, usb::host::UsbHost, watchdog::Watchdog};
+//!
+//! const XOSC_CRYSTAL_FREQ: u32 = 12_000_000; // Typically found in BSP crates
+//!
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! let mut watchdog = Watchdog::new(pac.WATCHDOG);
+//! let clocks = init_clocks_and_plls(
+//!     XOSC_CRYSTAL_FREQ,
+//!     pac.XOSC,
+//!     pac.CLOCKS,
+//!     pac.PLL_SYS,
+//!     pac.PLL_USB,
+//!     &mut pac.RESETS,
+//!     &mut watchdog
+//! ).ok().unwrap();
+//!
+//! let mut host = UsbHost::new(pac.USBCTRL_REGS, pac.USBCTRL_DPRAM, clocks.usb_clock, &mut pac.RESETS);
+//! while host.speed().is_none() {}
+//! host.reset_bus();
+//! // Wait for the reset and the recovery of the device, then read its device descriptor
+//! let get_descriptor = [0x80, 6, 0, 1, 0, 0, 18, 0];
+//! let mut descriptor = [0; 18];
+//! let len = host.control_in(0, 8, &get_descriptor, &mut descriptor).unwrap();
+//! ```
+
+use core::ptr;
+
+use crate::clocks::UsbClock;
+use crate::pac::usbctrl_dpram::EP_BUFFER_CONTROL;
+use crate::pac::RESETS;
+use crate::pac::USBCTRL_DPRAM;
+use crate::pac::USBCTRL_REGS;
+use crate::resets::SubsystemReset;
+
+use super::{DPRAM_BUFFERS_OFFSET, DPRAM_SIZE};
+
+/// Offset of the EPX control register in the DPRAM, in host mode
+const EPX_CONTROL_OFFSET: usize = 0x100;
+/// Offset of the EPX buffer, the first of the endpoint buffers
+const EPX_BUFFER_OFFSET: u16 = DPRAM_BUFFERS_OFFSET;
+/// Size of the EPX buffer and of the buffer of each interrupt endpoint
+const BUFFER_SIZE: u16 = 64;
+/// Number of interrupt endpoints polled by the controller
+const INTERRUPT_ENDPOINTS: u8 = 15;
+
+/// Bits of the endpoint control registers, in the DPRAM
+const EP_CONTROL_ENABLE: u32 = 1 << 31;
+const EP_CONTROL_INTERRUPT_PER_BUFF: u32 = 1 << 29;
+const EP_CONTROL_TYPE_CONTROL: u32 = 0;
+const EP_CONTROL_TYPE_BULK: u32 = 2 << 26;
+const EP_CONTROL_TYPE_INTERRUPT: u32 = 3 << 26;
+
+/// Speed of the device attached to the host
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speed {
+    /// Low speed, 1.5 Mbps
+    Low,
+    /// Full speed, 12 Mbps
+    Full,
+}
+
+/// Failure of a USB host transfer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The device was disconnected.
+    Disconnected,
+    /// The device stalled the endpoint, rejecting the request.
+    Stall,
+    /// The device didn't answer.
+    Timeout,
+    /// The device answered with the wrong DATA0/DATA1 packet.
+    DataSequence,
+    /// A packet was corrupted on the bus: bad CRC or bit stuffing.
+    Corrupted,
+    /// The device sent more data than requested.
+    Overflow,
+    /// All interrupt endpoints of the controller are in use.
+    NoInterruptEndpoint,
+}
+
+/// Direction and PID of one EPX transaction
+#[derive(Clone, Copy)]
+enum Packet<'a> {
+    Setup(&'a [u8; 8]),
+    In { toggle: bool, len: u16 },
+    Out { toggle: bool, data: &'a [u8] },
+}
+
+/// An endpoint of a device, for bulk transfers
+///
+/// The pipe keeps track of the DATA0/DATA1 toggle of the endpoint.
+#[derive(Debug)]
+pub struct BulkPipe {
+    address: u8,
+    endpoint: u8,
+    max_packet_size: u16,
+    toggle: bool,
+}
+
+impl BulkPipe {
+    /// Create a pipe to the bulk endpoint `endpoint` of the device at `address`, which takes
+    /// packets of up to `max_packet_size` bytes, as set in its endpoint descriptor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_packet_size` is over 64 bytes.
+    pub fn new(address: u8, endpoint: u8, max_packet_size: u16) -> Self {
+        assert!(max_packet_size <= BUFFER_SIZE);
+        BulkPipe {
+            address,
+            endpoint,
+            max_packet_size,
+            toggle: false,
+        }
+    }
+}
+
+/// An interrupt IN endpoint of a device, polled by the controller
+#[derive(Debug)]
+pub struct InterruptPipe {
+    /// Interrupt endpoint of the controller, from 1 to 15
+    index: u8,
+    max_packet_size: u16,
+    toggle: bool,
+    /// Whether the controller writes the status of the next packet to the upper half of the
+    /// buffer control register, see [`move_buffer_status`]
+    upper_half: bool,
+}
+
+/// The USB controller, as a host
+pub struct UsbHost {
+    ctrl_reg: USBCTRL_REGS,
+    ctrl_dpram: USBCTRL_DPRAM,
+    /// Interrupt endpoints of the controller in use, by bit
+    interrupt_endpoints: u16,
+}
+
+impl UsbHost {
+    /// Bring up the USB controller as a host.
+    pub fn new(
+        ctrl_reg: USBCTRL_REGS,
+        ctrl_dpram: USBCTRL_DPRAM,
+        _pll: UsbClock,
+        resets: &mut RESETS,
+    ) -> Self {
+        ctrl_reg.reset_bring_down(resets);
+        ctrl_reg.reset_bring_up(resets);
+
+        Self::from_parts(ctrl_reg, ctrl_dpram)
+    }
+
+    /// Bring up the USB controller as a host, whatever its previous state.
+    pub(super) fn from_parts(ctrl_reg: USBCTRL_REGS, ctrl_dpram: USBCTRL_DPRAM) -> Self {
+        unsafe {
+            let raw_ctrl_pdram = core::slice::from_raw_parts_mut(
+                USBCTRL_DPRAM::ptr() as *mut u32,
+                usize::from(DPRAM_BUFFERS_OFFSET) / 4,
+            );
+            raw_ctrl_pdram.fill(0);
+        }
+
+        ctrl_reg.usb_muxing.modify(|_, w| {
+            w.to_phy().set_bit();
+            w.softcon().set_bit()
+        });
+        // The host provides VBUS
+        ctrl_reg.usb_pwr.modify(|_, w| {
+            w.vbus_detect().set_bit();
+            w.vbus_detect_override_en().set_bit()
+        });
+        ctrl_reg.main_ctrl.modify(|_, w| {
+            w.sim_timing().clear_bit();
+            w.host_ndevice().set_bit();
+            w.controller_en().set_bit()
+        });
+        // Pull the data lines down, and keep the device from suspending once it is connected
+        ctrl_reg.sie_ctrl.write(|w| {
+            w.pulldown_en().set_bit();
+            w.sof_en().set_bit();
+            w.keep_alive_en().set_bit();
+            w.ep0_int_1buf().set_bit()
+        });
+        // Transfers are polled, and all state is cleared
+        ctrl_reg.inte.reset();
+        ctrl_reg.int_ep_ctrl.reset();
+        ctrl_reg.addr_endp.reset();
+        ctrl_reg
+            .buff_status
+            .write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        let status = ctrl_reg.sie_status.read().bits();
+        ctrl_reg.sie_status.write(|w| unsafe { w.bits(status) });
+
+        Self {
+            ctrl_reg,
+            ctrl_dpram,
+            interrupt_endpoints: 0,
+        }
+    }
+
+    /// Stop the controller, and return the USB peripherals.
+    pub fn free(self) -> (USBCTRL_REGS, USBCTRL_DPRAM) {
+        self.ctrl_reg.main_ctrl.reset();
+        self.ctrl_reg.sie_ctrl.reset();
+        (self.ctrl_reg, self.ctrl_dpram)
+    }
+
+    /// The speed of the device attached to the host, if any.
+    pub fn speed(&self) -> Option<Speed> {
+        match self.ctrl_reg.sie_status.read().speed().bits() {
+            1 => Some(Speed::Low),
+            2 => Some(Speed::Full),
+            _ => None,
+        }
+    }
+
+    /// Start resetting the bus, which brings the device to its default state, at address 0.
+    ///
+    /// The controller drives the reset, after which the device needs 10 ms to recover before
+    /// the first request.
+    pub fn reset_bus(&mut self) {
+        self.ctrl_reg
+            .sie_ctrl
+            .modify(|_, w| w.reset_bus().set_bit());
+    }
+
+    /// The number of the frame, incremented by the controller every millisecond.
+    pub fn frame_number(&self) -> u16 {
+        self.ctrl_reg.sof_rd.read().count().bits()
+    }
+
+    /// Run a control transfer reading up to `buf.len()` bytes from the device at `address`,
+    /// with `setup` as the setup packet, and return the number of bytes read.
+    ///
+    /// `max_packet_size` is the maximum packet size of the endpoint 0 of the device, 8 bytes
+    /// until read from its device descriptor.
+    pub fn control_in(
+        &mut self,
+        address: u8,
+        max_packet_size: u16,
+        setup: &[u8; 8],
+        buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.transaction(address, 0, EP_CONTROL_TYPE_CONTROL, Packet::Setup(setup))?;
+        let mut toggle = true;
+        let len = self.read_packets(
+            address,
+            0,
+            EP_CONTROL_TYPE_CONTROL,
+            max_packet_size,
+            &mut toggle,
+            buf,
+        )?;
+        // Status stage
+        self.transaction(
+            address,
+            0,
+            EP_CONTROL_TYPE_CONTROL,
+            Packet::Out {
+                toggle: true,
+                data: &[],
+            },
+        )?;
+        Ok(len)
+    }
+
+    /// Run a control transfer writing `data` to the device at `address`, with `setup` as the
+    /// setup packet.
+    ///
+    /// `max_packet_size` is the maximum packet size of the endpoint 0 of the device, 8 bytes
+    /// until read from its device descriptor.
+    pub fn control_out(
+        &mut self,
+        address: u8,
+        max_packet_size: u16,
+        setup: &[u8; 8],
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.transaction(address, 0, EP_CONTROL_TYPE_CONTROL, Packet::Setup(setup))?;
+        let mut toggle = true;
+        if !data.is_empty() {
+            self.write_packets(
+                address,
+                0,
+                EP_CONTROL_TYPE_CONTROL,
+                max_packet_size,
+                &mut toggle,
+                data,
+                false,
+            )?;
+        }
+        // Status stage
+        self.transaction(
+            address,
+            0,
+            EP_CONTROL_TYPE_CONTROL,
+            Packet::In {
+                toggle: true,
+                len: 0,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Read up to `buf.len()` bytes from a bulk IN endpoint, and return the number of bytes
+    /// read.
+    ///
+    /// This blocks for as long as the device has no data to send.
+    pub fn bulk_in(&mut self, pipe: &mut BulkPipe, buf: &mut [u8]) -> Result<usize, Error> {
+        self.read_packets(
+            pipe.address,
+            pipe.endpoint,
+            EP_CONTROL_TYPE_BULK,
+            pipe.max_packet_size,
+            &mut pipe.toggle,
+            buf,
+        )
+    }
+
+    /// Write `data` to a bulk OUT endpoint.
+    ///
+    /// A transfer of a multiple of the maximum packet size is ended by a zero length packet.
+    pub fn bulk_out(&mut self, pipe: &mut BulkPipe, data: &[u8]) -> Result<(), Error> {
+        self.write_packets(
+            pipe.address,
+            pipe.endpoint,
+            EP_CONTROL_TYPE_BULK,
+            pipe.max_packet_size,
+            &mut pipe.toggle,
+            data,
+            true,
+        )
+    }
+
+    /// Have the controller poll the interrupt IN endpoint `endpoint` of the device at `address`
+    /// every `interval` milliseconds, as set in its endpoint descriptor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_packet_size` is over 64 bytes, or if `interval` is 0.
+    pub fn open_interrupt_in(
+        &mut self,
+        address: u8,
+        endpoint: u8,
+        max_packet_size: u16,
+        interval: u8,
+    ) -> Result<InterruptPipe, Error> {
+        assert!(max_packet_size <= BUFFER_SIZE);
+        assert!(interval > 0);
+        let index = (1..=INTERRUPT_ENDPOINTS)
+            .find(|i| self.interrupt_endpoints & (1 << i) == 0)
+            .ok_or(Error::NoInterruptEndpoint)?;
+        self.interrupt_endpoints |= 1 << index;
+
+        let pipe = InterruptPipe {
+            index,
+            max_packet_size,
+            toggle: false,
+            upper_half: false,
+        };
+        unsafe {
+            // ADDR_ENDPx registers follow ADDR_ENDP, the direction being IN
+            let addr_endp = (USBCTRL_REGS::ptr() as *mut u32).add(usize::from(index));
+            ptr::write_volatile(
+                addr_endp,
+                u32::from(address & 0x7f) | u32::from(endpoint & 0xf) << 16,
+            );
+            // The control register of interrupt endpoint x takes the place of the one of EPx IN
+            // in device mode
+            let buffer = EPX_BUFFER_OFFSET + u16::from(index) * BUFFER_SIZE;
+            self.ctrl_dpram.ep_control[usize::from(index - 1) * 2].write(|w| {
+                w.bits(
+                    EP_CONTROL_ENABLE
+                        | EP_CONTROL_INTERRUPT_PER_BUFF
+                        | EP_CONTROL_TYPE_INTERRUPT
+                        | u32::from(interval - 1) << 16
+                        | u32::from(buffer),
+                )
+            });
+        }
+        self.arm_interrupt(&pipe);
+        self.ctrl_reg
+            .int_ep_ctrl
+            .modify(|r, w| unsafe { w.bits(r.bits() | 1 << index) });
+        Ok(pipe)
+    }
+
+    /// Stop polling an interrupt endpoint.
+    pub fn close_interrupt(&mut self, pipe: InterruptPipe) {
+        let mask = 1 << pipe.index;
+        self.ctrl_reg
+            .int_ep_ctrl
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+        self.ctrl_dpram.ep_control[usize::from(pipe.index - 1) * 2].reset();
+        self.ctrl_dpram.ep_buffer_control[usize::from(pipe.index) * 2].reset();
+        self.ctrl_reg
+            .buff_status
+            .write(|w| unsafe { w.bits(1 << (pipe.index * 2)) });
+        self.interrupt_endpoints &= !mask;
+    }
+
+    /// Copy the last packet received on an interrupt endpoint into `buf`, and return its length,
+    /// or [`WouldBlock`](nb::Error::WouldBlock) if the device sent nothing new.
+    pub fn read_interrupt(
+        &mut self,
+        pipe: &mut InterruptPipe,
+        buf: &mut [u8],
+    ) -> nb::Result<usize, Error> {
+        if self.speed().is_none() {
+            return Err(nb::Error::Other(Error::Disconnected));
+        }
+        let mask = 1 << (pipe.index * 2);
+        if self.ctrl_reg.buff_status.read().bits() & mask == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.ctrl_reg.buff_status.write(|w| unsafe { w.bits(mask) });
+
+        // BUFF_CPU_SHOULD_HANDLE is not valid for the interrupt endpoints, so the buffer the
+        // controller selected is tracked here
+        let buf_control = &self.ctrl_dpram.ep_buffer_control[usize::from(pipe.index) * 2];
+        move_buffer_status(buf_control, pipe.upper_half);
+        pipe.upper_half = !pipe.upper_half;
+        let len = buf_control.read().length_0().bits();
+        let len = usize::from(len);
+        let buffer = EPX_BUFFER_OFFSET + u16::from(pipe.index) * BUFFER_SIZE;
+        let result = if len > buf.len() {
+            Err(nb::Error::Other(Error::Overflow))
+        } else {
+            buf[..len].copy_from_slice(&dpram_buffer(buffer)[..len]);
+            Ok(len)
+        };
+        pipe.toggle = !pipe.toggle;
+        self.arm_interrupt(pipe);
+        result
+    }
+
+    fn arm_interrupt(&self, pipe: &InterruptPipe) {
+        self.ctrl_dpram.ep_buffer_control[usize::from(pipe.index) * 2].write(|w| unsafe {
+            w.length_0().bits(pipe.max_packet_size);
+            w.pid_0().bit(pipe.toggle);
+            w.last_0().set_bit();
+            w.available_0().set_bit()
+        });
+    }
+
+    /// Read packets into `buf` until it is full or a short packet ends the transfer.
+    fn read_packets(
+        &mut self,
+        address: u8,
+        endpoint: u8,
+        ep_type: u32,
+        max_packet_size: u16,
+        toggle: &mut bool,
+        buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mut read = 0;
+        loop {
+            let len = self.transaction(
+                address,
+                endpoint,
+                ep_type,
+                Packet::In {
+                    toggle: *toggle,
+                    len: max_packet_size,
+                },
+            )?;
+            *toggle = !*toggle;
+            if read + len > buf.len() {
+                return Err(Error::Overflow);
+            }
+            buf[read..read + len].copy_from_slice(&dpram_buffer(EPX_BUFFER_OFFSET)[..len]);
+            read += len;
+            if len < usize::from(max_packet_size) || read == buf.len() {
+                return Ok(read);
+            }
+        }
+    }
+
+    /// Write `data` in packets, ending with a zero length packet if `zlp` is set and the last
+    /// packet is full.
+    #[allow(clippy::too_many_arguments)]
+    fn write_packets(
+        &mut self,
+        address: u8,
+        endpoint: u8,
+        ep_type: u32,
+        max_packet_size: u16,
+        toggle: &mut bool,
+        data: &[u8],
+        zlp: bool,
+    ) -> Result<(), Error> {
+        let mut chunks = data.chunks(usize::from(max_packet_size));
+        let mut last_len = usize::from(max_packet_size);
+        for chunk in &mut chunks {
+            self.transaction(
+                address,
+                endpoint,
+                ep_type,
+                Packet::Out {
+                    toggle: *toggle,
+                    data: chunk,
+                },
+            )?;
+            *toggle = !*toggle;
+            last_len = chunk.len();
+        }
+        if zlp && last_len == usize::from(max_packet_size) {
+            self.transaction(
+                address,
+                endpoint,
+                ep_type,
+                Packet::Out {
+                    toggle: *toggle,
+                    data: &[],
+                },
+            )?;
+            *toggle = !*toggle;
+        }
+        Ok(())
+    }
+
+    /// Run one transaction on EPX, and return the number of bytes received.
+    fn transaction(
+        &mut self,
+        address: u8,
+        endpoint: u8,
+        ep_type: u32,
+        packet: Packet,
+    ) -> Result<usize, Error> {
+        if self.speed().is_none() {
+            return Err(Error::Disconnected);
+        }
+
+        self.ctrl_reg.addr_endp.write(|w| unsafe {
+            w.address().bits(address & 0x7f);
+            w.endpoint().bits(endpoint & 0xf)
+        });
+        unsafe {
+            let epx_control = (USBCTRL_DPRAM::ptr() as *mut u8).add(EPX_CONTROL_OFFSET);
+            ptr::write_volatile(
+                epx_control as *mut u32,
+                EP_CONTROL_ENABLE
+                    | EP_CONTROL_INTERRUPT_PER_BUFF
+                    | ep_type
+                    | u32::from(EPX_BUFFER_OFFSET),
+            );
+        }
+
+        let buf_control = &self.ctrl_dpram.ep_buffer_control[0];
+        match packet {
+            Packet::Setup(setup) => {
+                self.ctrl_dpram.setup_packet_low.write(|w| unsafe {
+                    w.bits(u32::from_le_bytes([setup[0], setup[1], setup[2], setup[3]]))
+                });
+                self.ctrl_dpram.setup_packet_high.write(|w| unsafe {
+                    w.bits(u32::from_le_bytes([setup[4], setup[5], setup[6], setup[7]]))
+                });
+            }
+            Packet::In { toggle, len } => {
+                buf_control.write(|w| unsafe {
+                    w.length_0().bits(len);
+                    w.pid_0().bit(toggle);
+                    w.last_0().set_bit();
+                    w.available_0().set_bit()
+                });
+            }
+            Packet::Out { toggle, data } => {
+                dpram_buffer(EPX_BUFFER_OFFSET)[..data.len()].copy_from_slice(data);
+                buf_control.write(|w| unsafe {
+                    w.length_0().bits(data.len() as u16);
+                    w.pid_0().bit(toggle);
+                    w.full_0().set_bit();
+                    w.last_0().set_bit();
+                    w.available_0().set_bit()
+                });
+            }
+        }
+
+        self.ctrl_reg.sie_ctrl.modify(|_, w| {
+            w.send_setup().bit(matches!(packet, Packet::Setup(_)));
+            w.receive_data().bit(matches!(packet, Packet::In { .. }));
+            w.send_data().bit(matches!(packet, Packet::Out { .. }))
+        });
+        // The controller runs from clk_usb: START_TRANS must be set once the other bits have
+        // settled
+        cortex_m::asm::delay(12);
+        self.ctrl_reg
+            .sie_ctrl
+            .modify(|_, w| w.start_trans().set_bit());
+
+        let result = loop {
+            let status = self.ctrl_reg.sie_status.read();
+            if status.trans_complete().bit_is_set() {
+                break Ok(());
+            } else if status.stall_rec().bit_is_set() {
+                break Err(Error::Stall);
+            } else if status.rx_timeout().bit_is_set() {
+                break Err(Error::Timeout);
+            } else if status.data_seq_error().bit_is_set() {
+                break Err(Error::DataSequence);
+            } else if status.crc_error().bit_is_set() || status.bit_stuff_error().bit_is_set() {
+                break Err(Error::Corrupted);
+            } else if status.rx_overflow().bit_is_set() {
+                break Err(Error::Overflow);
+            } else if status.speed().bits() == 0 {
+                break Err(Error::Disconnected);
+            }
+        };
+        // The controller selects the buffers of EPX in turn, as for the interrupt endpoints
+        let upper_half = self.ctrl_reg.buff_cpu_should_handle.read().bits() & 1 != 0;
+        // Clear the status flags and the buffer status of EPX
+        let status = self.ctrl_reg.sie_status.read().bits();
+        self.ctrl_reg
+            .sie_status
+            .write(|w| unsafe { w.bits(status) });
+        self.ctrl_reg.buff_status.write(|w| unsafe { w.bits(1) });
+        if result.is_err() {
+            self.ctrl_reg
+                .sie_ctrl
+                .modify(|_, w| w.stop_trans().set_bit());
+        }
+        result?;
+
+        move_buffer_status(buf_control, upper_half);
+        Ok(match packet {
+            Packet::In { .. } => usize::from(buf_control.read().length_0().bits()),
+            _ => 0,
+        })
+    }
+}
+
+/// Move the status of a packet from the upper half of a buffer control register to its lower half,
+/// if the controller wrote it there.
+///
+/// Erratum RP2040-E4: in host mode, the controller toggles its buffer selection after each packet
+/// even when the endpoint is single buffered, and writes the status of every other packet to the
+/// half of buffer 1. The buffer itself is the one of buffer 0.
+fn move_buffer_status(buf_control: &EP_BUFFER_CONTROL, upper_half: bool) {
+    if upper_half {
+        let bits = buf_control.read().bits();
+        buf_control.write(|w| unsafe { w.bits(bits >> 16) });
+    }
+}
+
+fn dpram_buffer(offset: u16) -> &'static mut [u8] {
+    debug_assert!(offset + BUFFER_SIZE <= DPRAM_SIZE);
+    // Safety: The buffers are only accessed by the controller while a transaction runs, or an
+    // interrupt endpoint is armed, and by the CPU otherwise.
+    unsafe {
+        core::slice::from_raw_parts_mut(
+            (USBCTRL_DPRAM::ptr() as *mut u8).add(usize::from(offset)),
+            usize::from(BUFFER_SIZE),
+        )
+    }
+}
@@ -0,0 +1,1304 @@
+//! Universal Serial Bus (USB)
+// See [Chapter 4 Section 1](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+//!
+//! [`UsbBus`] runs the USB controller as a device, and implements the [`usb_device`] bus, so that
+//! class crates such as `usbd-serial` or `usbd-hid` work on top of it.
+//!
+//! Endpoint 0 is the control endpoint, with its buffers in the dedicated area of the USB DPRAM.
+//! The other 15 endpoint numbers take bulk, interrupt or isochronous endpoints in each direction,
+//! their buffers being allocated from the rest of the DPRAM in 64 bytes units. Bulk, interrupt
+//! and control endpoints take packets of up to 64 bytes, and isochronous endpoints of up to 1023
+//! bytes.
+//!
+//! The controller can also run as a host instead: see [`host`].
+//!
+//! Bulk and isochronous endpoints are double-buffered as long as the DPRAM has room for both of
+//! their buffers: the controller moves a packet in one buffer while the CPU handles the other
+//! one, which lets bulk transfers approach the 12 Mbps of the bus, and isochronous endpoints
+//! prepare the packet of the next frame while the one of the current frame is moved. The second
+//! buffer of an isochronous endpoint is 128, 256, 512 or 1024 bytes after the first one,
+//! whichever is the first above its maximum packet size. A USB audio device with a 192 bytes
+//! isochronous endpoint, for 48 kHz stereo 16 bits samples, thus takes 448 bytes of DPRAM.
+//!
+//! When the endpoints of a composite device don't fit, [`last_alloc_error`] tells which one
+//! failed and why, and [`UsbBus::endpoint_dpram`] how much DPRAM each of the others takes.
+//!
+//! ## Usage
+//!
+//! Initialize the Usb Bus forcing the VBUS detection.
+//! ```no_run
+//! use rp2040_hal::{clocks::init_clocks_and_plls, pac, sio::Sio, usb::UsbBus, watchdog::Watchdog};
+//! use usb_device::class_prelude::UsbBusAllocator;
+//!
+//! const XOSC_CRYSTAL_FREQ: u32 = 12_000_000; // Typically found in BSP crates
+//!
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(pac.SIO);
+//! let mut watchdog = Watchdog::new(pac.WATCHDOG);
+//! let mut clocks = init_clocks_and_plls(
+//!     XOSC_CRYSTAL_FREQ,
+//!     pac.XOSC,
+//!     pac.CLOCKS,
+//!     pac.PLL_SYS,
+//!     pac.PLL_USB,
+//!     &mut pac.RESETS,
+//!     &mut watchdog
+//! ).ok().unwrap();
+//!
+//! let usb_bus = UsbBusAllocator::new(UsbBus::new(
+//!         pac.USBCTRL_REGS,
+//!         pac.USBCTRL_DPRAM,
+//!         clocks.usb_clock,
+//!         true,
+//!         &mut pac.RESETS,
+//!     ));
+//! // Use the usb_bus as usual.
+//! ```
+//!
+//! ## Self-powered devices
+//!
+//! The controller only connects to the host once it senses VBUS. Devices without VBUS wired to a
+//! VBUS detect pin, typically self-powered or battery-powered ones, have to tell it whether the
+//! host is there: see [`UsbBus::set_vbus_detect`]. They can also stay disconnected until ready
+//! with [`UsbBus::set_pullup`], and report whether they are currently powered by the bus with
+//! `UsbDevice::set_self_powered` of [`usb_device`].
+//!
+//! ## Suspend and resume
+//!
+//! The host suspends the bus when it has no use for the device, which then must draw less than
+//! 2.5 mA. [`UsbBus::set_suspend_hook`] and [`UsbBus::set_resume_hook`] let the firmware slow
+//! down its clocks and power down peripherals while suspended, and restore them on resume. They
+//! run from `UsbDevice::poll`, which has to be called on the USB interrupt for the device to
+//! notice the resume. Once allowed by the host, the device can also wake it up with
+//! [`UsbBus::remote_wakeup`].
+//!
+//! See [pico_usb_serial.rs](https://github.com/rp-rs/rp-hal/tree/main/boards/pico/examples/pico_usb_serial.rs) for more complete examples
+//!
+//!
+//! ## Dual role
+//!
+//! The controller can leave the device role for the host role at runtime, and come back to it,
+//! for instance to act as a device while plugged into a computer, and as a host while a
+//! peripheral is attached. [`UsbBus::switch_to_host`] disconnects the device, and brings the
+//! controller up as a host, pulling the data lines down. The [`host::UsbHost`] driver is then
+//! reached through [`UsbBus::with_host`], while the device stack sees an idle bus.
+//! [`UsbBus::switch_to_device`] brings the device role back, pulling D+ up so that the host
+//! enumerates it again.
+//!
+//! ## Compliance testing
+//!
+//! Electrical compliance tests need the data lines held in a given state, which
+//! [`UsbBus::force_line_state`] does regardless of the controller, until released. The
+//! controller's own timings can also be shortened with [`UsbBus::set_sim_timing`].
+//!
+//! ## Enumeration on B0 and B1 chips
+//!
+//! Devices built on the B0 and B1 revisions of the RP2040 may fail to leave the reset state on a
+//! busy bus, such as behind a hub (erratum RP2040-E5). On these revisions, [`UsbBus`] applies
+//! the workaround of the errata on each bus reset: it waits for the end of the reset, blocking
+//! in `UsbDevice::poll` for up to the 10 ms the host drives it, then forces the J state into the
+//! controller until it reports the device connected, about 800 µs. This borrows GPIO 15 meanwhile:
+//! its input is forced high, and its output disabled, before its configuration is restored.
+//!
+//! ## Enumeration issue with small EP0 max packet size
+//!
+//! During enumeration Windows hosts send a `StatusOut` after the `DataIn` packet of the first
+//! `Get Descriptor` resquest even if the `DataIn` isn't completed (typically when the `max_packet_size_ep0`
+//! is less than 18bytes). The next request request is a `Set Address` that expect a `StatusIn`.
+//!
+//! The issue is that by the time the previous `DataIn` packet is acknoledged and the `StatusOut`
+//! followed by `Setup` are received, the usb stack may have already prepared the next `DataIn` payload
+//! in the EP0 IN mailbox resulting in the payload being transmitted to the host instead of the
+//! `StatusIn` for the `Set Address` request as expected by the host.
+//!
+//! To avoid that issue, the EP0 In mailbox should be invalidated between the `Setup` packet and the
+//! next `StatusIn` initiated by the host. The workaround implemented clears the available bit of the
+//! EP0 In endpoint's buffer to stop the device from sending the data instead of the status packet.
+//! This workaround has the caveat that the poll function must be called between those two which
+//! are only separated by a few microseconds.
+//!
+//! If the required timing cannot be met, using an maximum packet size of the endpoint 0 above 18bytes
+//! (e.g. `.max_packet_size_ep0(64)`) should avoid that issue.
+
+pub mod host;
+
+use core::cell::{Cell, RefCell};
+use core::ptr;
+
+use crate::clocks::UsbClock;
+use crate::pac::usbctrl_dpram::EP_BUFFER_CONTROL;
+use crate::pac::RESETS;
+use crate::pac::USBCTRL_DPRAM;
+use crate::pac::USBCTRL_REGS;
+use crate::resets::SubsystemReset;
+use crate::sysinfo::{self, ChipRevision};
+
+use host::UsbHost;
+
+use cortex_m::interrupt::{self, Mutex};
+
+use usb_device::{
+    bus::{PollResult, UsbBus as UsbBusTrait},
+    endpoint::{EndpointAddress, EndpointType},
+    Result as UsbResult, UsbDirection, UsbError,
+};
+
+/// Offset of the first endpoint buffer in the DPRAM, following the endpoint control registers
+/// and the EP0 buffers
+const DPRAM_BUFFERS_OFFSET: u16 = 0x180;
+/// Size of the DPRAM
+const DPRAM_SIZE: u16 = 4096;
+
+fn ep_addr_to_ep_buf_ctrl_idx(ep_addr: EndpointAddress) -> usize {
+    ep_addr.index() * 2 + (if ep_addr.is_in() { 0 } else { 1 })
+}
+fn buffer_stride(ep_type: EndpointType, max_packet_size: u16) -> u16 {
+    if ep_type == EndpointType::Isochronous {
+        max_packet_size.next_power_of_two().max(128)
+    } else {
+        64
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    ep_type: EndpointType,
+    max_packet_size: u16,
+    buffer_offset: u16,
+    /// Whether the endpoint has a second buffer, `buffer_stride` bytes after the first one
+    double_buffered: bool,
+    /// The buffer the CPU handles next, when double-buffered: buffer 1 if set
+    next_buffer: bool,
+    /// The PID of the next packet the CPU arms a buffer for, DATA1 if set
+    ///
+    /// It is tracked apart from the buffers, as the host resets it to DATA0 without resetting
+    /// the bus, for instance when it clears a halt.
+    data_pid: bool,
+}
+impl Endpoint {
+    /// The offset of the second buffer from the first one: 64 bytes, or for isochronous
+    /// endpoints, the first power of 2 from 128 to 1024 bytes above their maximum packet size.
+    fn buffer_stride(&self) -> u16 {
+        buffer_stride(self.ep_type, self.max_packet_size)
+    }
+
+    /// Take the PID of the next packet, DATA1 if set, toggling it but for isochronous packets
+    /// which are all DATA0.
+    fn take_data_pid(&mut self) -> bool {
+        let pid = self.data_pid;
+        self.data_pid = !pid && self.ep_type != EndpointType::Isochronous;
+        pid
+    }
+
+    /// The PID following `pid` on a single-buffered endpoint.
+    fn next_pid(&self, pid: bool) -> bool {
+        !pid && self.ep_type != EndpointType::Isochronous
+    }
+
+    unsafe fn get_buf_parts(&self, buffer: bool) -> (*mut u8, usize) {
+        const DPRAM_BASE: *mut u8 = USBCTRL_DPRAM::ptr() as *mut u8;
+        if self.ep_type == EndpointType::Control {
+            (DPRAM_BASE.offset(0x100), self.max_packet_size as usize)
+        } else {
+            let offset = DPRAM_BUFFERS_OFFSET
+                + self.buffer_offset * 64
+                + u16::from(buffer) * self.buffer_stride();
+            (
+                DPRAM_BASE.offset(offset as isize),
+                self.max_packet_size as usize,
+            )
+        }
+    }
+
+    /// The number of bytes of DPRAM taken by the buffers of the endpoint.
+    fn dpram_size(&self) -> u16 {
+        let size = (self.max_packet_size + 63) / 64 * 64;
+        if self.ep_type == EndpointType::Control {
+            // In the dedicated area
+            0
+        } else if self.double_buffered {
+            self.buffer_stride() + size
+        } else {
+            size
+        }
+    }
+
+    fn get_buf(&self, buffer: bool) -> &'static [u8] {
+        unsafe {
+            let (base, len) = self.get_buf_parts(buffer);
+            core::slice::from_raw_parts(base as *const _, len)
+        }
+    }
+    fn get_buf_mut(&self, buffer: bool) -> &'static mut [u8] {
+        unsafe {
+            let (base, len) = self.get_buf_parts(buffer);
+            core::slice::from_raw_parts_mut(base, len)
+        }
+    }
+}
+
+struct Inner {
+    ctrl_reg: USBCTRL_REGS,
+    ctrl_dpram: USBCTRL_DPRAM,
+    in_endpoints: [Option<Endpoint>; 16],
+    out_endpoints: [Option<Endpoint>; 16],
+    next_offset: u16,
+    read_setup: bool,
+    suspend_hook: Option<fn()>,
+    resume_hook: Option<fn()>,
+    /// Whether the controller is in the host role, instead of the device one
+    host_role: bool,
+    /// The host driver, while in the host role and not in use
+    host: Option<UsbHost>,
+    /// The VBUS detection of the device role, kept while in the host role
+    device_usb_pwr: u32,
+}
+impl Inner {
+    fn new(ctrl_reg: USBCTRL_REGS, ctrl_dpram: USBCTRL_DPRAM) -> Self {
+        Self {
+            ctrl_reg,
+            ctrl_dpram,
+            in_endpoints: Default::default(),
+            out_endpoints: Default::default(),
+            next_offset: 0,
+            read_setup: false,
+            suspend_hook: None,
+            resume_hook: None,
+            host_role: false,
+            host: None,
+            device_usb_pwr: 0,
+        }
+    }
+
+    /// Raise interrupts on device events, and connect to the host.
+    fn enable_device(&self) {
+        // Enable interrupt generation when a buffer is done, when the bus is reset,
+        // suspended or resumed, and when a setup packet is received
+        // this should be sufficient for device mode, will need more for host.
+        self.ctrl_reg.inte.modify(|_, w| {
+            w.buff_status()
+                .set_bit()
+                .bus_reset()
+                .set_bit()
+                .dev_suspend()
+                .set_bit()
+                .dev_resume_from_host()
+                .set_bit()
+                .setup_req()
+                .set_bit()
+        });
+
+        // enable pull up to let the host know we exist.
+        self.ctrl_reg
+            .sie_ctrl
+            .modify(|_, w| w.pullup_en().set_bit());
+    }
+
+    fn ep_allocate(
+        &mut self,
+        ep_addr: Option<EndpointAddress>,
+        ep_dir: UsbDirection,
+        ep_type: EndpointType,
+        max_packet_size: u16,
+    ) -> Result<EndpointAddress, AllocError> {
+        let ep_addr = ep_addr
+            .or_else(|| {
+                let eps = if ep_dir == UsbDirection::In {
+                    self.in_endpoints.iter()
+                } else {
+                    self.out_endpoints.iter()
+                };
+                // find free end point
+                let mut iter = eps.enumerate();
+                // reserve ep0 for the control endpoint
+                if ep_type != EndpointType::Control {
+                    iter.next();
+                }
+                iter.find(|(_, ep)| ep.is_none())
+                    .map(|(index, _)| EndpointAddress::from_parts(index, ep_dir))
+            })
+            .ok_or(AllocError::NoFreeEndpoint(ep_dir))?;
+
+        let is_ep0 = ep_addr.index() == 0;
+        let is_ctrl_ep = ep_type == EndpointType::Control;
+        if !(is_ep0 ^ !is_ctrl_ep) {
+            return Err(AllocError::InvalidType(ep_addr, ep_type));
+        }
+
+        let eps = if ep_addr.is_in() {
+            &mut self.in_endpoints
+        } else {
+            &mut self.out_endpoints
+        };
+        let maybe_ep = eps
+            .get_mut(ep_addr.index())
+            .ok_or(AllocError::NoSuchEndpoint(ep_addr))?;
+        if maybe_ep.is_some() {
+            return Err(AllocError::EndpointTaken(ep_addr));
+        }
+
+        // Validate buffer size. From datasheet (4.1.2.5):
+        // Data Buffers are typically 64 bytes long as this is the max normal packet size for most FS packets.
+        // For Isochronous endpoints a maximum buffer size of 1023 bytes is supported.
+        // For other packet types the maximum size is 64 bytes per buffer.
+        let limit = if ep_type == EndpointType::Isochronous {
+            1023
+        } else {
+            64
+        };
+        if max_packet_size > limit {
+            return Err(AllocError::PacketTooLarge {
+                max_packet_size,
+                limit,
+            });
+        }
+
+        if ep_addr.index() == 0 {
+            *maybe_ep = Some(Endpoint {
+                ep_type,
+                max_packet_size,
+                buffer_offset: 0, // not used on CTRL ep
+                double_buffered: false,
+                next_buffer: false,
+                data_pid: false,
+            });
+        } else {
+            // size in 64bytes units.
+            // NOTE: the compiler is smart enough to recognize /64 as a 6bit right shift so let's
+            // keep the division here for the sake of clarity
+            let aligned_sized = (max_packet_size + 63) / 64;
+            let available = (DPRAM_SIZE - DPRAM_BUFFERS_OFFSET) / 64 - self.next_offset;
+            if aligned_sized > available {
+                return Err(AllocError::OutOfMemory {
+                    requested: aligned_sized * 64,
+                    available: available * 64,
+                });
+            }
+            // Bulk and isochronous endpoints take a second buffer if there is room for it
+            let double_sized = buffer_stride(ep_type, max_packet_size) / 64 + aligned_sized;
+            let double_buffered = matches!(ep_type, EndpointType::Bulk | EndpointType::Isochronous)
+                && double_sized <= available;
+
+            let buffer_offset = self.next_offset;
+            self.next_offset += if double_buffered {
+                double_sized
+            } else {
+                aligned_sized
+            };
+
+            *maybe_ep = Some(Endpoint {
+                ep_type,
+                max_packet_size,
+                buffer_offset,
+                double_buffered,
+                next_buffer: false,
+                data_pid: false,
+            });
+        }
+        Ok(ep_addr)
+    }
+
+    fn ep_reset_all(&mut self) {
+        self.ctrl_reg
+            .sie_ctrl
+            .modify(|_, w| w.ep0_int_1buf().set_bit());
+        // expect ctrl ep to receive on DATA first
+        self.ctrl_dpram.ep_buffer_control[0].write(|w| w.pid_0().set_bit());
+        self.ctrl_dpram.ep_buffer_control[1].write(|w| {
+            w.available_0().set_bit();
+            w.pid_0().set_bit()
+        });
+
+        for (index, ep) in itertools::interleave(
+            self.in_endpoints.iter_mut().skip(1),  // skip control endpoint
+            self.out_endpoints.iter_mut().skip(1), // skip control endpoint
+        )
+        .enumerate()
+        .filter_map(|(i, ep)| ep.as_mut().map(|ep| (i, ep)))
+        {
+            use pac::usbctrl_dpram::ep_control::ENDPOINT_TYPE_A;
+            let ep_type = match ep.ep_type {
+                EndpointType::Bulk => ENDPOINT_TYPE_A::BULK,
+                EndpointType::Isochronous => ENDPOINT_TYPE_A::ISOCHRONOUS,
+                EndpointType::Control => ENDPOINT_TYPE_A::CONTROL,
+                EndpointType::Interrupt => ENDPOINT_TYPE_A::INTERRUPT,
+            };
+            // configure
+            // ep 0 in&out are not part of index (skipped before enumeration)
+            self.ctrl_dpram.ep_control[index].modify(|_, w| unsafe {
+                w.endpoint_type().variant(ep_type);
+                w.interrupt_per_buff().set_bit();
+                w.double_buffered().bit(ep.double_buffered);
+                w.enable().set_bit();
+                w.buffer_address()
+                    .bits(DPRAM_BUFFERS_OFFSET + (ep.buffer_offset << 6))
+            });
+            // reset OUT ep and prepare IN ep to accept data
+            let buf_control = &self.ctrl_dpram.ep_buffer_control[index + 2];
+            reset_buffers(buf_control, ep, (index & 1) == 1);
+        }
+    }
+
+    /// Reset the data toggle of the endpoints but the control ones, or only of `ep_addr`,
+    /// dropping the packets armed in their buffers.
+    ///
+    /// The host expects DATA0 next from and to these endpoints after SET_CONFIGURATION, and after
+    /// it clears the halt of an endpoint.
+    fn reset_data_toggles(&mut self, ep_addr: Option<EndpointAddress>) {
+        let ctrl_dpram = &self.ctrl_dpram;
+        let in_eps = self
+            .in_endpoints
+            .iter_mut()
+            .map(|ep| (ep, UsbDirection::In));
+        let out_eps = self
+            .out_endpoints
+            .iter_mut()
+            .map(|ep| (ep, UsbDirection::Out));
+        for (index, (ep, dir)) in in_eps.enumerate().chain(out_eps.enumerate()) {
+            let ep = match ep {
+                Some(ep) if ep.ep_type != EndpointType::Control => ep,
+                _ => continue,
+            };
+            let addr = EndpointAddress::from_parts(index, dir);
+            if ep_addr.map_or(true, |ep_addr| ep_addr == addr) {
+                let buf_control = &ctrl_dpram.ep_buffer_control[ep_addr_to_ep_buf_ctrl_idx(addr)];
+                reset_buffers(buf_control, ep, dir == UsbDirection::Out);
+            }
+        }
+    }
+
+    fn ep_write(&mut self, ep_addr: EndpointAddress, buf: &[u8]) -> UsbResult<usize> {
+        let index = ep_addr.index();
+        let ep = self
+            .in_endpoints
+            .get_mut(index)
+            .map(Option::as_mut)
+            .flatten()
+            .ok_or(UsbError::InvalidEndpoint)?;
+
+        let buf_control = &self.ctrl_dpram.ep_buffer_control[index * 2];
+        let buffer = ep.next_buffer;
+        let buf_control_val = buf_control.read();
+        let available = if buffer {
+            buf_control_val.available_1().bit_is_set()
+        } else {
+            buf_control_val.available_0().bit_is_set()
+        };
+        if available {
+            return Err(UsbError::WouldBlock);
+        }
+
+        let ep_buf = ep.get_buf_mut(buffer);
+        if ep_buf.len() < buf.len() {
+            return Err(UsbError::BufferOverflow);
+        }
+        ep_buf[..buf.len()].copy_from_slice(buf);
+
+        if ep.ep_type == EndpointType::Control {
+            buf_control.modify(|r, w| unsafe {
+                w.available_0().set_bit();
+                w.length_0().bits(buf.len() as u16);
+                w.full_0().set_bit();
+                w.pid_0().bit(ep.next_pid(r.pid_0().bit()))
+            });
+        } else {
+            let pid = ep.take_data_pid();
+            arm_buffer(buf_control, buffer, buf.len() as u16, true, pid);
+        }
+        ep.next_buffer = ep.double_buffered && !buffer;
+
+        Ok(buf.len())
+    }
+
+    fn ep_read(&mut self, ep_addr: EndpointAddress, buf: &mut [u8]) -> UsbResult<usize> {
+        let index = ep_addr.index();
+        let ep = self
+            .out_endpoints
+            .get_mut(index)
+            .map(Option::as_mut)
+            .flatten()
+            .ok_or(UsbError::InvalidEndpoint)?;
+
+        let buf_control = &self.ctrl_dpram.ep_buffer_control[index * 2 + 1];
+        let buf_control_val = buf_control.read();
+
+        let process_setup = index == 0 && self.read_setup;
+        let (ep_buf, len) = if process_setup {
+            // assume we want to read the setup request
+
+            // Next packet will be on DATA1 so clear pid_0 so it gets flipped by next buf config
+            self.ctrl_dpram.ep_buffer_control[0].modify(|_, w| w.pid_0().clear_bit());
+            // the OUT packet will be either data or a status zlp
+            // clear setup request flag
+            self.ctrl_reg.sie_status.write(|w| w.setup_rec().set_bit());
+            (
+                unsafe { core::slice::from_raw_parts(USBCTRL_DPRAM::ptr() as *const u8, 8) },
+                8,
+            )
+        } else {
+            let (full, len) = if ep.next_buffer {
+                (
+                    buf_control_val.full_1().bit_is_set(),
+                    buf_control_val.length_1().bits(),
+                )
+            } else {
+                (
+                    buf_control_val.full_0().bit_is_set(),
+                    buf_control_val.length_0().bits(),
+                )
+            };
+            if !full {
+                return Err(UsbError::WouldBlock);
+            }
+            (ep.get_buf(ep.next_buffer), usize::from(len))
+        };
+
+        if len > buf.len() {
+            return Err(UsbError::BufferOverflow);
+        }
+
+        buf[..len].copy_from_slice(&ep_buf[..len]);
+
+        if process_setup {
+            self.read_setup = false;
+
+            // clear any out standing out flag e.g. in case a zlp got discarded
+            self.ctrl_reg.buff_status.write(|w| unsafe { w.bits(2) });
+
+            let data_length = u16::from(buf[6]) | (u16::from(buf[7]) << 8);
+            let is_in_request = (buf[0] & 0x80) == 0x80;
+            let expect_data_or_zlp = is_in_request || data_length != 0;
+            buf_control.modify(|_, w| unsafe {
+                // enable if and only if a dataphase is expected.
+                w.available_0().bit(expect_data_or_zlp);
+                w.length_0().bits(ep.max_packet_size);
+                w.full_0().clear_bit();
+                w.pid_0().set_bit()
+            });
+        } else {
+            if ep.ep_type == EndpointType::Control {
+                buf_control.modify(|r, w| unsafe {
+                    w.available_0().set_bit();
+                    w.length_0().bits(ep.max_packet_size);
+                    w.full_0().clear_bit();
+                    w.pid_0().bit(ep.next_pid(r.pid_0().bit()))
+                });
+            } else {
+                // Both halves of a double-buffered endpoint are armed, so the packet of this one
+                // follows the one of the other half
+                let pid = ep.take_data_pid();
+                arm_buffer(buf_control, ep.next_buffer, ep.max_packet_size, false, pid);
+            }
+            ep.next_buffer = ep.double_buffered && !ep.next_buffer;
+            // Clear OUT flag once it is read.
+            self.ctrl_reg
+                .buff_status
+                .write(|w| unsafe { w.bits(1 << (index * 2 + 1)) });
+        }
+
+        if process_setup {
+            let request_type = buf[0];
+            let request = buf[1];
+            let value = u16::from(buf[2]) | (u16::from(buf[3]) << 8);
+            match (request_type, request, value) {
+                // SET_CONFIGURATION
+                (0x00, 9, _) => self.reset_data_toggles(None),
+                // CLEAR_FEATURE(ENDPOINT_HALT)
+                (0x02, 1, 0) => self.reset_data_toggles(Some(EndpointAddress::from(buf[4]))),
+                _ => {}
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+/// Reset the data toggle and the buffers of an endpoint other than a control one, arming the
+/// buffers of an OUT endpoint.
+fn reset_buffers(buf_control: &EP_BUFFER_CONTROL, ep: &mut Endpoint, is_out: bool) {
+    ep.next_buffer = false;
+    ep.data_pid = false;
+    if ep.double_buffered {
+        // Start the controller on buffer 0 again
+        let iso_offset = (ep.buffer_stride() / 128).trailing_zeros() as u8;
+        buf_control.write(|w| unsafe {
+            w.reset().set_bit();
+            w.double_buffer_iso_offset().bits(iso_offset)
+        });
+        if is_out {
+            for &buffer in &[false, true] {
+                let pid = ep.take_data_pid();
+                arm_buffer(buf_control, buffer, ep.max_packet_size, false, pid);
+            }
+        }
+    } else {
+        buf_control.write(|w| unsafe { w.bits(0) });
+        if is_out {
+            let pid = ep.take_data_pid();
+            arm_buffer(buf_control, false, ep.max_packet_size, false, pid);
+        }
+    }
+}
+
+/// Arm `buffer` of an endpoint, buffer 1 if set, to send or receive a packet of `len` bytes with
+/// the given PID, DATA1 if set.
+///
+/// This only writes the 16-bit half of the buffer control register of the buffer, so that it
+/// doesn't undo the controller updating the other half meanwhile.
+fn arm_buffer(buf_control: &EP_BUFFER_CONTROL, buffer: bool, len: u16, full: bool, pid: bool) {
+    // Bits of each half of the register
+    const FULL: u16 = 1 << 15;
+    const PID: u16 = 1 << 13;
+    const AVAILABLE: u16 = 1 << 10;
+    const LENGTH: u16 = 0x3ff;
+    // STALL in the half of buffer 0, and DOUBLE_BUFFER_ISO_OFFSET in the half of buffer 1
+    let kept = if buffer { 0x3 << 11 } else { 1 << 11 };
+
+    let half = (buf_control.as_ptr() as *mut u16).wrapping_add(usize::from(buffer));
+    // Safety: the half belongs to the endpoint, which the bus owns
+    unsafe {
+        let mut value = ptr::read_volatile(half) & kept | len & LENGTH;
+        if full {
+            value |= FULL;
+        }
+        if pid {
+            value |= PID;
+        }
+        ptr::write_volatile(half, value);
+        // The controller runs on a slower clock than the processor: make sure it sees the rest of
+        // the buffer control before it is made available
+        cortex_m::asm::delay(12);
+        ptr::write_volatile(half, value | AVAILABLE);
+    }
+}
+
+/// Why an endpoint could not be allocated
+///
+/// Classes usually panic when their endpoints can't be allocated: the reason is then kept for
+/// [`last_alloc_error`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AllocError {
+    /// All endpoint numbers are taken in the direction.
+    NoFreeEndpoint(UsbDirection),
+    /// The endpoint number requested is above 15.
+    NoSuchEndpoint(EndpointAddress),
+    /// The endpoint requested is already allocated.
+    EndpointTaken(EndpointAddress),
+    /// Endpoint 0 must be the control endpoint, and the control endpoint must be endpoint 0.
+    InvalidType(EndpointAddress, EndpointType),
+    /// The maximum packet size requested is over the limit of the type of the endpoint: 1023
+    /// bytes for isochronous endpoints, and 64 bytes for the others.
+    PacketTooLarge {
+        /// The maximum packet size requested
+        max_packet_size: u16,
+        /// The largest packet size allowed
+        limit: u16,
+    },
+    /// The DPRAM has no room left for the buffer of the endpoint.
+    OutOfMemory {
+        /// The size of the buffer, in bytes
+        requested: u16,
+        /// The free DPRAM, in bytes
+        available: u16,
+    },
+}
+
+impl AllocError {
+    fn usb_error(self) -> UsbError {
+        match self {
+            AllocError::NoFreeEndpoint(_) | AllocError::NoSuchEndpoint(_) => {
+                UsbError::EndpointOverflow
+            }
+            AllocError::EndpointTaken(_) => UsbError::InvalidEndpoint,
+            AllocError::InvalidType(..) | AllocError::PacketTooLarge { .. } => {
+                UsbError::Unsupported
+            }
+            AllocError::OutOfMemory { .. } => UsbError::EndpointMemoryOverflow,
+        }
+    }
+}
+
+static LAST_ALLOC_ERROR: Mutex<Cell<Option<AllocError>>> = Mutex::new(Cell::new(None));
+
+/// Why the last endpoint allocation failed, if any did.
+///
+/// `usb-device` only reports a generic [`UsbError`] when an endpoint can't be allocated, and
+/// classes usually panic on it: this tells, for instance from the panic handler, which endpoint
+/// was rejected and why.
+pub fn last_alloc_error() -> Option<AllocError> {
+    interrupt::free(|cs| LAST_ALLOC_ERROR.borrow(cs).get())
+}
+
+/// Full speed state of the data lines
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineState {
+    /// D+ high and D- low, the idle state of a full speed bus
+    J,
+    /// D+ low and D- high
+    K,
+    /// Both lines low, single ended 0
+    Se0,
+}
+
+/// Source of the VBUS detection of the controller
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VbusDetect {
+    /// VBUS is sensed on a pin in the [`FunctionUsbAux`](crate::gpio::FunctionUsbAux) function
+    /// with the `VBUS_DET` role: GPIO 1, 4, 7, ... 28.
+    Pin,
+    /// VBUS is reported present by software, whatever the pins say.
+    Present,
+    /// VBUS is reported absent by software, whatever the pins say.
+    Absent,
+}
+
+/// Usb bus
+pub struct UsbBus {
+    inner: Mutex<RefCell<Inner>>,
+    /// Whether the chip needs the workaround of erratum RP2040-E5
+    e5_workaround: bool,
+}
+
+impl UsbBus {
+    /// Create new usb bus struct and bring up usb as device.
+    ///
+    /// VBUS is reported present if `force_vbus_detect_bit` is set, or else sensed on a pin: see
+    /// [`set_vbus_detect`](Self::set_vbus_detect).
+    pub fn new(
+        ctrl_reg: USBCTRL_REGS,
+        ctrl_dpram: USBCTRL_DPRAM,
+        _pll: UsbClock,
+        force_vbus_detect_bit: bool,
+        resets: &mut RESETS,
+    ) -> Self {
+        ctrl_reg.reset_bring_down(resets);
+        ctrl_reg.reset_bring_up(resets);
+
+        unsafe {
+            let raw_ctrl_reg =
+                core::slice::from_raw_parts_mut(USBCTRL_REGS::ptr() as *mut u32, 1 + 0x98 / 4);
+            raw_ctrl_reg.fill(0);
+
+            let raw_ctrl_pdram =
+                core::slice::from_raw_parts_mut(USBCTRL_DPRAM::ptr() as *mut u32, 1 + 0xfc / 4);
+            raw_ctrl_pdram.fill(0);
+        }
+
+        ctrl_reg.usb_muxing.modify(|_, w| {
+            w.to_phy().set_bit();
+            w.softcon().set_bit()
+        });
+
+        if force_vbus_detect_bit {
+            ctrl_reg.usb_pwr.modify(|_, w| {
+                w.vbus_detect().set_bit();
+                w.vbus_detect_override_en().set_bit()
+            });
+        }
+        ctrl_reg.main_ctrl.modify(|_, w| {
+            w.sim_timing().clear_bit();
+            w.host_ndevice().clear_bit();
+            w.controller_en().set_bit()
+        });
+
+        Self {
+            inner: Mutex::new(RefCell::new(Inner::new(ctrl_reg, ctrl_dpram))),
+            // The erratum is fixed from the B2 revision on
+            e5_workaround: matches!(
+                sysinfo::chip_revision(),
+                ChipRevision::B0 | ChipRevision::B1
+            ),
+        }
+    }
+
+    /// Set how the controller detects VBUS, and so the presence of the host.
+    ///
+    /// Switching between [`VbusDetect::Present`] and [`VbusDetect::Absent`] connects and
+    /// disconnects the device, for instance as a battery-powered device senses VBUS through an
+    /// ADC or a GPIO not able to detect it.
+    pub fn set_vbus_detect(&self, detect: VbusDetect) {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            inner.ctrl_reg.usb_pwr.modify(|_, w| {
+                w.vbus_detect().bit(detect == VbusDetect::Present);
+                w.vbus_detect_override_en().bit(detect != VbusDetect::Pin)
+            });
+        })
+    }
+
+    /// Whether the controller currently detects VBUS.
+    pub fn vbus_detected(&self) -> bool {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            inner
+                .ctrl_reg
+                .sie_status
+                .read()
+                .vbus_detected()
+                .bit_is_set()
+        })
+    }
+
+    /// Enable or disable the pull-up on D+, which tells the host a device is connected.
+    ///
+    /// The pull-up is enabled when the device is enabled: disabling it afterwards disconnects the
+    /// device from the host until it is enabled again.
+    pub fn set_pullup(&self, enable: bool) {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            inner
+                .ctrl_reg
+                .sie_ctrl
+                .modify(|_, w| w.pullup_en().bit(enable));
+        })
+    }
+
+    /// The number of bytes of DPRAM taken by the buffers of the endpoints, out of the 3712 bytes
+    /// available to them.
+    ///
+    /// The buffers of endpoint 0 have their own area, and are not counted.
+    pub fn dpram_allocated(&self) -> u16 {
+        interrupt::free(|cs| self.inner.borrow(cs).borrow().next_offset * 64)
+    }
+
+    /// The number of bytes of DPRAM taken by the buffers of an endpoint, if it is allocated.
+    pub fn endpoint_dpram(&self, ep_addr: EndpointAddress) -> Option<u16> {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow();
+            let eps = if ep_addr.is_in() {
+                &inner.in_endpoints
+            } else {
+                &inner.out_endpoints
+            };
+            eps.get(ep_addr.index())?.as_ref().map(Endpoint::dpram_size)
+        })
+    }
+
+    /// Leave the device role for the host role, disconnecting from the host.
+    ///
+    /// Until switched back to the device role, the device stack sees an idle bus, and
+    /// transfers of its endpoints fail.
+    pub fn switch_to_host(&self) {
+        interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            if inner.host_role {
+                return;
+            }
+            inner.device_usb_pwr = inner.ctrl_reg.usb_pwr.read().bits();
+            inner
+                .ctrl_reg
+                .sie_ctrl
+                .modify(|_, w| w.pullup_en().clear_bit());
+
+            // Safety: The bus leaves the registers to the host driver until it gets it back in
+            // `switch_to_device`.
+            let (ctrl_reg, ctrl_dpram) =
+                unsafe { (ptr::read(&inner.ctrl_reg), ptr::read(&inner.ctrl_dpram)) };
+            inner.host = Some(UsbHost::from_parts(ctrl_reg, ctrl_dpram));
+            inner.host_role = true;
+        })
+    }
+
+    /// Run `f` with the host driver, if the controller is in the host role.
+    ///
+    /// Interrupts are not disabled while `f` runs: it must not be called from an interrupt
+    /// handler which may have interrupted another call.
+    pub fn with_host<R>(&self, f: impl FnOnce(&mut UsbHost) -> R) -> Option<R> {
+        let mut host = interrupt::free(|cs| self.inner.borrow(cs).borrow_mut().host.take())?;
+        let result = f(&mut host);
+        interrupt::free(|cs| self.inner.borrow(cs).borrow_mut().host = Some(host));
+        Some(result)
+    }
+
+    /// Leave the host role for the device role, connecting to the host again.
+    ///
+    /// The host then resets the bus and enumerates the device anew.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within [`with_host`](Self::with_host).
+    pub fn switch_to_device(&self) {
+        interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            if !inner.host_role {
+                return;
+            }
+            let host = inner
+                .host
+                .take()
+                .expect("switch_to_device called from with_host");
+            // The registers of the host driver are those of the bus
+            core::mem::forget(host);
+            inner.host_role = false;
+
+            let ctrl_reg = &inner.ctrl_reg;
+            ctrl_reg.int_ep_ctrl.reset();
+            ctrl_reg.addr_endp.reset();
+            ctrl_reg
+                .usb_pwr
+                .write(|w| unsafe { w.bits(inner.device_usb_pwr) });
+            ctrl_reg.sie_ctrl.write(|w| w.ep0_int_1buf().set_bit());
+            ctrl_reg
+                .main_ctrl
+                .modify(|_, w| w.host_ndevice().clear_bit());
+            ctrl_reg
+                .buff_status
+                .write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+            let status = ctrl_reg.sie_status.read().bits();
+            ctrl_reg.sie_status.write(|w| unsafe { w.bits(status) });
+
+            inner.enable_device();
+        })
+    }
+
+    /// Drive the data lines into `state`, overriding the controller, or give them back to the
+    /// controller with `None`.
+    pub fn force_line_state(&self, state: Option<LineState>) {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            let ctrl_reg = &inner.ctrl_reg;
+            match state {
+                Some(state) => {
+                    ctrl_reg.usbphy_direct.modify(|_, w| {
+                        w.tx_diffmode().clear_bit();
+                        w.tx_dp().bit(state == LineState::J);
+                        w.tx_dm().bit(state == LineState::K);
+                        w.tx_dp_oe().set_bit();
+                        w.tx_dm_oe().set_bit()
+                    });
+                    ctrl_reg.usbphy_direct_override.modify(|_, w| {
+                        w.tx_diffmode_override_en().set_bit();
+                        w.tx_dp_override_en().set_bit();
+                        w.tx_dm_override_en().set_bit();
+                        w.tx_dp_oe_override_en().set_bit();
+                        w.tx_dm_oe_override_en().set_bit()
+                    });
+                }
+                None => {
+                    ctrl_reg.usbphy_direct_override.modify(|_, w| {
+                        w.tx_diffmode_override_en().clear_bit();
+                        w.tx_dp_override_en().clear_bit();
+                        w.tx_dm_override_en().clear_bit();
+                        w.tx_dp_oe_override_en().clear_bit();
+                        w.tx_dm_oe_override_en().clear_bit()
+                    });
+                }
+            }
+        })
+    }
+
+    /// Enable or disable the reduced timings of the controller, meant for simulation: the
+    /// device then won't work with a real host, but its timeouts are shortened for tests.
+    pub fn set_sim_timing(&self, enable: bool) {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            inner
+                .ctrl_reg
+                .main_ctrl
+                .modify(|_, w| w.sim_timing().bit(enable));
+        })
+    }
+
+    /// Set the function called when the host suspends the bus, before the device enters the
+    /// suspended state.
+    pub fn set_suspend_hook(&self, hook: fn()) {
+        interrupt::free(|cs| {
+            self.inner.borrow(cs).borrow_mut().suspend_hook = Some(hook);
+        })
+    }
+
+    /// Set the function called when the host resumes the bus, before the device leaves the
+    /// suspended state.
+    pub fn set_resume_hook(&self, hook: fn()) {
+        interrupt::free(|cs| {
+            self.inner.borrow(cs).borrow_mut().resume_hook = Some(hook);
+        })
+    }
+
+    /// Signal the host to resume the bus, while it is suspended.
+    ///
+    /// The host has to allow it beforehand: see `UsbDevice::remote_wakeup_enabled` of
+    /// [`usb_device`].
+    pub fn remote_wakeup(&self) {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            inner.ctrl_reg.sie_ctrl.modify(|_, w| w.resume().set_bit());
+        })
+    }
+
+    /// The number of the last frame started by the host, from the last Start Of Frame packet.
+    pub fn frame_number(&self) -> u16 {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            inner.ctrl_reg.sof_rd.read().count().bits()
+        })
+    }
+}
+
+/// Bring the controller out of the reset state, which it may miss if the bus isn't idle once
+/// the reset ends (erratum RP2040-E5).
+///
+/// The controller needs 800 µs of J state after a reset to become connected: the J state is
+/// forced into it through the digital pads, with GPIO 15 holding D+ high.
+fn rp2040_e5_workaround() {
+    // Line states of SIE_STATUS
+    const SE0: u8 = 0;
+    const J: u8 = 1;
+    const DP_PIN: usize = 15;
+    // Function of GPIO 15 connecting it to D+ of the controller
+    const USB_MUXING_FUNCSEL: u8 = 8;
+
+    // Safety: The USB registers are only accessed by the bus, which is in the middle of a reset,
+    // and GPIO 15 is restored as it was found.
+    let ctrl_reg = unsafe { &*USBCTRL_REGS::ptr() };
+    let io = unsafe { &(*pac::IO_BANK0::ptr()).gpio[DP_PIN] };
+
+    // Wait for the end of the reset
+    while ctrl_reg.sie_status.read().line_state().bits() == SE0 {}
+
+    let gpio_ctrl = io.gpio_ctrl.read().bits();
+    // Keep the pin from driving anything, and feed a high D+, so that the controller sees the J
+    // state once connected to the digital pads
+    io.gpio_ctrl.modify(|_, w| unsafe {
+        w.oeover().bits(2);
+        w.inover().bits(3);
+        w.funcsel().bits(USB_MUXING_FUNCSEL)
+    });
+    // Keep the pull-up of D+ enabled while the PHY is not connected to the controller, so that
+    // the host doesn't see a disconnect
+    ctrl_reg
+        .usbphy_direct
+        .modify(|_, w| w.dp_pullup_en().set_bit());
+    ctrl_reg
+        .usbphy_direct_override
+        .modify(|_, w| w.dp_pullup_en_override_en().set_bit());
+    ctrl_reg.usb_muxing.write(|w| {
+        w.to_digital_pad().set_bit();
+        w.softcon().set_bit()
+    });
+    while ctrl_reg.sie_status.read().line_state().bits() != J {}
+    while ctrl_reg.sie_status.read().connected().bit_is_clear() {}
+
+    // Back to the PHY
+    ctrl_reg.usb_muxing.write(|w| {
+        w.to_phy().set_bit();
+        w.softcon().set_bit()
+    });
+    ctrl_reg
+        .usbphy_direct_override
+        .modify(|_, w| w.dp_pullup_en_override_en().clear_bit());
+    ctrl_reg
+        .usbphy_direct
+        .modify(|_, w| w.dp_pullup_en().clear_bit());
+    io.gpio_ctrl.write(|w| unsafe { w.bits(gpio_ctrl) });
+}
+
+impl UsbBusTrait for UsbBus {
+    fn alloc_ep(
+        &mut self,
+        ep_dir: UsbDirection,
+        ep_addr: Option<EndpointAddress>,
+        ep_type: EndpointType,
+        max_packet_size: u16,
+        _interval: u8,
+    ) -> UsbResult<EndpointAddress> {
+        interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+
+            inner
+                .ep_allocate(ep_addr, ep_dir, ep_type, max_packet_size)
+                .map_err(|error| {
+                    LAST_ALLOC_ERROR.borrow(cs).set(Some(error));
+                    error.usb_error()
+                })
+        })
+    }
+
+    fn enable(&mut self) {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            // at this stage ep's are expected to be in their reset state
+            // TODO: is it worth having a debug_assert for that here?
+
+            inner.enable_device();
+        })
+    }
+    fn reset(&self) {
+        interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+
+            // clear reset flag
+            inner.ctrl_reg.sie_status.write(|w| w.bus_reset().set_bit());
+            inner
+                .ctrl_reg
+                .buff_status
+                .write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+
+            // reset all endpoints
+            inner.ep_reset_all();
+
+            // Reset address register
+            inner.ctrl_reg.addr_endp.reset();
+            // TODO: reset all endpoints & buffer statuses
+        });
+        if self.e5_workaround {
+            rp2040_e5_workaround();
+        }
+    }
+    fn set_device_address(&self, addr: u8) {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            inner
+                .ctrl_reg
+                .addr_endp
+                .modify(|_, w| unsafe { w.address().bits(addr & 0x3F) });
+            // reset ep0
+            inner.ctrl_dpram.ep_buffer_control[0].modify(|_, w| w.pid_0().set_bit());
+            inner.ctrl_dpram.ep_buffer_control[1].modify(|_, w| w.pid_0().set_bit());
+        })
+    }
+    fn write(&self, ep_addr: EndpointAddress, buf: &[u8]) -> UsbResult<usize> {
+        interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            if inner.host_role {
+                return Err(UsbError::InvalidState);
+            }
+            inner.ep_write(ep_addr, buf)
+        })
+    }
+    fn read(&self, ep_addr: EndpointAddress, buf: &mut [u8]) -> UsbResult<usize> {
+        interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            if inner.host_role {
+                return Err(UsbError::InvalidState);
+            }
+            inner.ep_read(ep_addr, buf)
+        })
+    }
+    fn set_stalled(&self, ep_addr: EndpointAddress, stalled: bool) {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+
+            if ep_addr.index() == 0 {
+                inner.ctrl_reg.ep_stall_arm.modify(|_, w| {
+                    if ep_addr.is_in() {
+                        w.ep0_in().bit(stalled)
+                    } else {
+                        w.ep0_out().bit(stalled)
+                    }
+                });
+            }
+
+            let index = ep_addr_to_ep_buf_ctrl_idx(ep_addr);
+            inner.ctrl_dpram.ep_buffer_control[index].modify(|_, w| w.stall().bit(stalled));
+        })
+    }
+    fn is_stalled(&self, ep_addr: EndpointAddress) -> bool {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow_mut();
+            let index = ep_addr_to_ep_buf_ctrl_idx(ep_addr);
+            inner.ctrl_dpram.ep_buffer_control[index]
+                .read()
+                .stall()
+                .bit_is_set()
+        })
+    }
+    fn suspend(&self) {
+        // The controller suspends on its own, leaving the rest of the chip to the firmware
+        let hook = interrupt::free(|cs| self.inner.borrow(cs).borrow().suspend_hook);
+        if let Some(hook) = hook {
+            hook();
+        }
+    }
+    fn resume(&self) {
+        let hook = interrupt::free(|cs| self.inner.borrow(cs).borrow().resume_hook);
+        if let Some(hook) = hook {
+            hook();
+        }
+    }
+    fn poll(&self) -> PollResult {
+        interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            if inner.host_role {
+                return PollResult::None;
+            }
+
+            // check for bus reset
+            let sie_status = inner.ctrl_reg.sie_status.read();
+            if sie_status.bus_reset().bit_is_set() {
+                return PollResult::Reset;
+            }
+
+            // check for resume request
+            if sie_status.resume().bit_is_set() {
+                inner.ctrl_reg.sie_status.write(|w| w.resume().set_bit());
+                return PollResult::Resume;
+            }
+
+            // check for suspend request
+            if sie_status.suspended().bit_is_set() {
+                inner.ctrl_reg.sie_status.write(|w| w.suspended().set_bit());
+                return PollResult::Suspend;
+            }
+
+            let (mut ep_out, mut ep_in_complete, mut ep_setup): (u16, u16, u16) = (0, 0, 0);
+
+            let buff_status = inner.ctrl_reg.buff_status.read().bits();
+            if buff_status != 0 {
+                // IN Complete shall only be reported once.
+                inner
+                    .ctrl_reg
+                    .buff_status
+                    .write(|w| unsafe { w.bits(0x5555_5555) });
+
+                for i in 0..32u32 {
+                    let mask = 1 << i;
+                    if (buff_status & mask) == mask {
+                        if (i & 1) == 0 {
+                            ep_in_complete |= 1 << (i / 2);
+                        } else {
+                            ep_out |= 1 << (i / 2);
+                        }
+                    }
+                }
+            }
+            // The OUT flag of a double-buffered endpoint may have been cleared while its other
+            // buffer was already full
+            for (index, ep) in inner.out_endpoints.iter().enumerate().skip(1) {
+                if let Some(ep) = ep.as_ref().filter(|ep| ep.double_buffered) {
+                    let buf_control_val = inner.ctrl_dpram.ep_buffer_control[index * 2 + 1].read();
+                    let full = if ep.next_buffer {
+                        buf_control_val.full_1().bit_is_set()
+                    } else {
+                        buf_control_val.full_0().bit_is_set()
+                    };
+                    if full {
+                        ep_out |= 1 << index;
+                    }
+                }
+            }
+
+            // check for setup request
+            // Only report setup if OUT has been cleared.
+            if sie_status.setup_rec().bit_is_set() {
+                // Small max_packet_size_ep0 Work-Around
+                inner.ctrl_dpram.ep_buffer_control[0].modify(|_, w| w.available_0().clear_bit());
+
+                ep_setup |= 1;
+                inner.read_setup = true;
+            }
+
+            if let (0, 0, 0) = (ep_out, ep_in_complete, ep_setup) {
+                return PollResult::None;
+            }
+            PollResult::Data {
+                ep_out,
+                ep_in_complete,
+                ep_setup,
+            }
+        })
+    }
+
+    const QUIRK_SET_ADDRESS_BEFORE_STATUS: bool = false;
+}